@@ -0,0 +1,1043 @@
+//! Multi-user mode (`[accounts]` in the config file): local accounts, each
+//! with a watchlist of its own. Signed-in users see their watchlist on
+//! `/dashboard`, add and remove repos there, and get a private iCalendar
+//! feed of its releases at `/feed/{token}/calendar.ics`. Their repos are
+//! refreshed in the background like watched ones.
+//!
+//! Accounts are created by the operator at `/admin/accounts/{name}`, or by
+//! visitors at `/signup` with `signup = true`, and kept in accounts.json
+//! with PBKDF2 password hashes. Users can also sign in through OpenID
+//! Connect providers (see `oidc`). Sessions live in memory, so a restart
+//! signs everyone out; the session cookie is `SameSite=Lax`, which keeps
+//! other sites from posting forms on a user's behalf. Password sign-ins are
+//! limited per client and per username, and sign-ups per client, before any
+//! password is hashed.
+
+use crate::{
+    AppState, RepoPath,
+    admin::require_admin,
+    cache::CacheManager,
+    calendar,
+    config::{AccountsConfig, WatchEntry},
+    error::{ApiError, AppError, ErrorCode},
+    format_html, oidc, provider,
+    quota::ClientIp,
+    shedding,
+    signing::json_response,
+};
+use anyhow::Result;
+use axum::{
+    Form, Json,
+    extract::{Path, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use openssl::{hash::MessageDigest, memcmp, pkcs5::pbkdf2_hmac, rand::rand_bytes};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+};
+
+/// Name of the session cookie
+const COOKIE: &str = "checkup_session";
+/// PBKDF2-HMAC-SHA256 rounds of new password hashes
+const PBKDF2_ITERATIONS: usize = 600_000;
+const MIN_PASSWORD_LEN: usize = 8;
+const MAX_USERNAME_LEN: usize = 32;
+/// Length of the window `max_attempts` applies to
+const ATTEMPT_WINDOW_SECS: i64 = 900;
+/// Cap on tracked clients and usernames; windows that already ended are
+/// pruned past this.
+const MAX_TRACKED_ATTEMPTS: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub username: String,
+    /// "pbkdf2-sha256${iterations}${salt}${hash}", salt and hash in hex;
+    /// None for accounts that only sign in through OpenID Connect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password_hash: Option<String>,
+    /// Linked OpenID Connect logins, as "{provider}:{subject}"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    identities: Vec<String>,
+    /// Secret of the account's feed URL
+    feed_token: String,
+    #[serde(default)]
+    pub watchlist: Vec<WatchEntry>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Account {
+    /// Whether a login through the provider named `name` is linked.
+    pub fn has_identity_from(&self, name: &str) -> bool {
+        self.identities
+            .iter()
+            .any(|i| i.split_once(':').is_some_and(|(n, _)| n == name))
+    }
+}
+
+/// An account without its secrets, as listed at `/admin/accounts`.
+#[derive(Debug, Serialize)]
+struct AccountSummary<'a> {
+    username: &'a str,
+    admin: bool,
+    identities: &'a [String],
+    watched: usize,
+    created_at: DateTime<Utc>,
+}
+
+struct Session {
+    username: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// What became of a repo added to a watchlist.
+pub enum WatchOutcome {
+    Added,
+    /// On the watchlist already
+    Duplicate,
+    /// The account watches `max_watched` repos already
+    Full,
+}
+
+/// Lowercase hex of `bytes`.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// 32 random bytes as hex.
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    rand_bytes(&mut bytes)?;
+    Ok(hex(&bytes))
+}
+
+fn derive(password: &str, salt: &[u8], iterations: usize) -> Result<[u8; 32]> {
+    let mut hash = [0u8; 32];
+    pbkdf2_hmac(
+        password.as_bytes(),
+        salt,
+        iterations,
+        MessageDigest::sha256(),
+        &mut hash,
+    )?;
+    Ok(hash)
+}
+
+fn hash_password(password: &str) -> Result<String> {
+    let mut salt = [0u8; 16];
+    rand_bytes(&mut salt)?;
+    let hash = derive(password, &salt, PBKDF2_ITERATIONS)?;
+    Ok(format!(
+        "pbkdf2-sha256${}${}${}",
+        PBKDF2_ITERATIONS,
+        hex(&salt),
+        hex(&hash)
+    ))
+}
+
+fn verify_password(password: &str, stored: &str) -> bool {
+    let mut parts = stored.split('$');
+    let (Some("pbkdf2-sha256"), Some(iterations), Some(salt), Some(hash), None) = (
+        parts.next(),
+        parts.next().and_then(|i| i.parse().ok()),
+        parts.next().and_then(unhex),
+        parts.next().and_then(unhex),
+        parts.next(),
+    ) else {
+        return false;
+    };
+    derive(password, &salt, iterations)
+        .is_ok_and(|derived| hash.len() == derived.len() && memcmp::eq(&derived, &hash))
+}
+
+/// Usernames are 1 to 32 lowercase letters, digits, `.`, `_` and `-`,
+/// starting with a letter or digit.
+fn check_username(username: &str) -> Result<()> {
+    let valid = !username.is_empty()
+        && username.len() <= MAX_USERNAME_LEN
+        && username
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && username
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c));
+    if !valid {
+        anyhow::bail!(
+            "Invalid username '{}': use up to {} lowercase letters, digits, '.', '_' and '-'",
+            username,
+            MAX_USERNAME_LEN
+        );
+    }
+    Ok(())
+}
+
+/// A valid username made from what a sign-in provider calls the user,
+/// e.g. "Jane.Doe@example.com" becomes "jane.doe".
+fn username_from(hint: &str) -> String {
+    let hint = hint.split('@').next().unwrap_or_default();
+    let name: String = hint
+        .chars()
+        .map(|c| c.to_ascii_lowercase())
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .skip_while(|c| !c.is_ascii_alphanumeric())
+        .take(MAX_USERNAME_LEN - 3)
+        .collect();
+    if name.is_empty() {
+        String::from("user")
+    } else {
+        name
+    }
+}
+
+fn check_password(password: &str) -> Result<()> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        anyhow::bail!("Passwords need at least {} characters", MIN_PASSWORD_LEN);
+    }
+    Ok(())
+}
+
+/// The repo route of a watchlist entry, e.g. "github/owner/repo", and the
+/// repo it names.
+fn resolve(route: &str) -> Result<(String, RepoPath)> {
+    let (prefix, repo) = provider::parse_route(route)?;
+    let route = provider::repo_route(prefix, &repo)
+        .trim_start_matches('/')
+        .to_string();
+    Ok((route, repo))
+}
+
+pub struct Accounts {
+    config: AccountsConfig,
+    entries: RwLock<Vec<Account>>,
+    /// By session token
+    sessions: DashMap<String, Session>,
+    /// Password attempts in the current window, by "login:{client}",
+    /// "user:{username}" or "signup:{client}"
+    attempts: DashMap<String, (DateTime<Utc>, u32)>,
+    cache: CacheManager,
+    /// Whether cookies are limited to HTTPS, as when the public URL is
+    secure_cookies: bool,
+    public_url: String,
+    pub oidc: oidc::Providers,
+}
+
+impl Accounts {
+    /// None unless `[accounts] enabled`; fails on an unreadable
+    /// accounts.json.
+    pub fn load(
+        config: &AccountsConfig,
+        cache: CacheManager,
+        public_url: &str,
+    ) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let entries: Vec<Account> = cache.read_accounts()?;
+        Ok(Some(Self {
+            oidc: oidc::Providers::new(&config.oidc, public_url)?,
+            config: config.clone(),
+            entries: RwLock::new(entries),
+            sessions: DashMap::new(),
+            attempts: DashMap::new(),
+            cache,
+            secure_cookies: public_url.starts_with("https://"),
+            public_url: public_url.to_string(),
+        }))
+    }
+
+    pub fn signup_enabled(&self) -> bool {
+        self.config.signup
+    }
+
+    /// Whether cookies get the `Secure` attribute.
+    pub fn secure_cookies(&self) -> bool {
+        self.secure_cookies
+    }
+
+    /// Whether `[accounts] admins` names the account or one of its logins.
+    pub fn is_admin(&self, account: &Account) -> bool {
+        self.config
+            .admins
+            .iter()
+            .any(|a| *a == account.username || account.identities.contains(a))
+    }
+
+    /// Whether `[accounts] admins` names the login, which then gets an
+    /// account on its first sign-in even without `signup`.
+    pub fn is_admin_identity(&self, identity: &str) -> bool {
+        self.config.admins.iter().any(|a| a == identity)
+    }
+
+    pub fn has_admins(&self) -> bool {
+        !self.config.admins.is_empty()
+    }
+
+    /// Names that can be taken by signing up: not admins', nor existing.
+    fn available(&self, entries: &[Account], username: &str) -> bool {
+        !self.config.admins.iter().any(|a| a == username)
+            && !entries.iter().any(|a| a.username == username)
+    }
+
+    fn get(&self, username: &str) -> Option<Account> {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .find(|a| a.username == username)
+            .cloned()
+    }
+
+    /// Create an account. Returns false, leaving it as it is, when the name
+    /// is taken or reserved for an admin.
+    fn create(&self, username: &str, password: &str) -> Result<bool> {
+        check_username(username)?;
+        check_password(password)?;
+        let password_hash = hash_password(password)?;
+        let mut entries = self.entries.write().unwrap();
+        if !self.available(&entries, username) {
+            return Ok(false);
+        }
+        entries.push(Account {
+            username: username.to_string(),
+            password_hash: Some(password_hash),
+            identities: Vec::new(),
+            feed_token: new_token()?,
+            watchlist: Vec::new(),
+            created_at: Utc::now(),
+        });
+        self.cache.write_accounts(&*entries)?;
+        Ok(true)
+    }
+
+    /// Create an account or change its password and linked logins, signing
+    /// it out everywhere. Logins linked here are unlinked from other
+    /// accounts. Returns whether it was created.
+    fn update(
+        &self,
+        username: &str,
+        password: Option<&str>,
+        identities: Option<&[String]>,
+    ) -> Result<bool> {
+        check_username(username)?;
+        let password_hash = password
+            .map(|p| check_password(p).and_then(|()| hash_password(p)))
+            .transpose()?;
+        for identity in identities.unwrap_or_default() {
+            self.oidc.check_identity(identity)?;
+        }
+        let mut entries = self.entries.write().unwrap();
+        let created = !entries.iter().any(|a| a.username == username);
+        if created {
+            if password_hash.is_none() && identities.is_none_or(|i| i.is_empty()) {
+                anyhow::bail!("New accounts need a password or identities");
+            }
+            entries.push(Account {
+                username: username.to_string(),
+                password_hash: None,
+                identities: Vec::new(),
+                feed_token: new_token()?,
+                watchlist: Vec::new(),
+                created_at: Utc::now(),
+            });
+        }
+        for account in entries.iter_mut() {
+            if account.username == username {
+                if let Some(hash) = &password_hash {
+                    account.password_hash = Some(hash.clone());
+                }
+                if let Some(identities) = identities {
+                    account.identities = identities.to_vec();
+                }
+            } else if let Some(identities) = identities {
+                account.identities.retain(|i| !identities.contains(i));
+            }
+        }
+        self.cache.write_accounts(&*entries)?;
+        self.sessions.retain(|_, s| s.username != username);
+        Ok(created)
+    }
+
+    fn remove(&self, username: &str) -> Result<bool> {
+        let mut entries = self.entries.write().unwrap();
+        let Some(index) = entries.iter().position(|a| a.username == username) else {
+            return Ok(false);
+        };
+        entries.remove(index);
+        self.cache.write_accounts(&*entries)?;
+        self.sessions.retain(|_, s| s.username != username);
+        Ok(true)
+    }
+
+    /// Count a password attempt against each of `keys`, or refuse it once
+    /// one has used up `max_attempts`, with the time it may try again.
+    fn count_attempt(&self, keys: &[String]) -> Result<(), DateTime<Utc>> {
+        let now = Utc::now();
+        let window = Duration::seconds(ATTEMPT_WINDOW_SECS);
+        if self.attempts.len() >= MAX_TRACKED_ATTEMPTS {
+            self.attempts.retain(|_, (start, _)| now - *start < window);
+        }
+        for key in keys {
+            if let Some(entry) = self.attempts.get(key)
+                && let (start, used) = *entry.value()
+                && now - start < window
+                && used >= self.config.max_attempts
+            {
+                return Err(start + window);
+            }
+        }
+        for key in keys {
+            let mut entry = self.attempts.entry(key.clone()).or_insert((now, 0));
+            let (start, used) = entry.value_mut();
+            if now - *start >= window {
+                *start = now;
+                *used = 0;
+            }
+            *used += 1;
+        }
+        Ok(())
+    }
+
+    /// The account, if the password is right. Takes a while by design.
+    fn authenticate(&self, username: &str, password: &str) -> Option<Account> {
+        let account = self.get(username);
+        let Some(stored) = account.as_ref().and_then(|a| a.password_hash.as_deref()) else {
+            // Hash for unknown names too, so timing doesn't tell which exist
+            let _ = derive(password, &[0; 16], PBKDF2_ITERATIONS);
+            return None;
+        };
+        let valid = verify_password(password, stored);
+        account.filter(|_| valid)
+    }
+
+    /// The account an OpenID Connect login is linked to.
+    pub fn by_identity(&self, identity: &str) -> Option<Account> {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .find(|a| a.identities.iter().any(|i| i == identity))
+            .cloned()
+    }
+
+    /// Link an OpenID Connect login to an account, unlinking it from any
+    /// other.
+    pub fn link_identity(&self, username: &str, identity: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        if !entries.iter().any(|a| a.username == username) {
+            anyhow::bail!("No account '{}'", username);
+        }
+        for account in entries.iter_mut() {
+            account.identities.retain(|i| i != identity);
+            if account.username == username {
+                account.identities.push(identity.to_string());
+            }
+        }
+        self.cache.write_accounts(&*entries)?;
+        Ok(())
+    }
+
+    /// Create an account without a password for an OpenID Connect login,
+    /// named after `hint` with a number appended if that is taken. Returns
+    /// the name.
+    pub fn create_for_identity(&self, hint: &str, identity: &str) -> Result<String> {
+        let base = username_from(hint);
+        let mut entries = self.entries.write().unwrap();
+        let username = std::iter::once(base.clone())
+            .chain((2..1000).map(|n| format!("{}-{}", base, n)))
+            .find(|name| self.available(&entries, name))
+            .ok_or_else(|| anyhow::anyhow!("No free username for '{}'", base))?;
+        entries.push(Account {
+            username: username.clone(),
+            password_hash: None,
+            identities: vec![identity.to_string()],
+            feed_token: new_token()?,
+            watchlist: Vec::new(),
+            created_at: Utc::now(),
+        });
+        self.cache.write_accounts(&*entries)?;
+        Ok(username)
+    }
+
+    /// Sign `username` in, returning the session cookie to set.
+    pub fn start_session(&self, username: &str) -> Result<HeaderValue> {
+        let now = Utc::now();
+        self.sessions.retain(|_, s| s.expires_at > now);
+        let token = new_token()?;
+        let max_age = Duration::days(self.config.session_days.max(1));
+        self.sessions.insert(
+            token.clone(),
+            Session {
+                username: username.to_string(),
+                expires_at: now + max_age,
+            },
+        );
+        let secure = if self.secure_cookies { "; Secure" } else { "" };
+        Ok(HeaderValue::from_str(&format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+            COOKIE,
+            token,
+            max_age.num_seconds(),
+            secure
+        ))?)
+    }
+
+    fn session_token(headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .find_map(|c| c.trim().strip_prefix(COOKIE)?.strip_prefix('='))
+    }
+
+    /// The signed-in user of a request.
+    pub fn user(&self, headers: &HeaderMap) -> Option<Account> {
+        let token = Self::session_token(headers)?;
+        let username = {
+            let session = self.sessions.get(token)?;
+            if session.expires_at <= Utc::now() {
+                drop(session);
+                self.sessions.remove(token);
+                return None;
+            }
+            session.username.clone()
+        };
+        self.get(&username)
+    }
+
+    fn end_session(&self, headers: &HeaderMap) {
+        if let Some(token) = Self::session_token(headers) {
+            self.sessions.remove(token);
+        }
+    }
+
+    /// Add `route` to the account's watchlist.
+    fn watch(&self, username: &str, route: &str) -> Result<WatchOutcome> {
+        let mut entries = self.entries.write().unwrap();
+        let Some(account) = entries.iter_mut().find(|a| a.username == username) else {
+            anyhow::bail!("No account '{}'", username);
+        };
+        if account.watchlist.iter().any(|e| e.repo == route) {
+            return Ok(WatchOutcome::Duplicate);
+        }
+        if account.watchlist.len() >= self.config.max_watched {
+            return Ok(WatchOutcome::Full);
+        }
+        account.watchlist.push(WatchEntry {
+            repo: route.to_string(),
+            interval_hours: None,
+            category: None,
+        });
+        self.cache.write_accounts(&*entries)?;
+        Ok(WatchOutcome::Added)
+    }
+
+    fn unwatch(&self, username: &str, route: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap();
+        let Some(account) = entries.iter_mut().find(|a| a.username == username) else {
+            return Ok(());
+        };
+        let watched = account.watchlist.len();
+        account.watchlist.retain(|e| e.repo != route);
+        if account.watchlist.len() < watched {
+            self.cache.write_accounts(&*entries)?;
+        }
+        Ok(())
+    }
+
+    fn by_feed_token(&self, token: &str) -> Option<Account> {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .find(|a| {
+                a.feed_token.len() == token.len()
+                    && memcmp::eq(a.feed_token.as_bytes(), token.as_bytes())
+            })
+            .cloned()
+    }
+
+    /// URL of the account's release feed.
+    pub fn feed_url(&self, account: &Account) -> String {
+        format!(
+            "{}/feed/{}/calendar.ics",
+            self.public_url, account.feed_token
+        )
+    }
+
+    /// Repos on any account's watchlist.
+    fn routes(&self) -> Vec<String> {
+        let entries = self.entries.read().unwrap();
+        let routes: BTreeSet<&str> = entries
+            .iter()
+            .flat_map(|a| &a.watchlist)
+            .map(|e| e.repo.as_str())
+            .collect();
+        routes.into_iter().map(String::from).collect()
+    }
+}
+
+/// The repos on an account's watchlist, with their routes, e.g.
+/// "/github/owner/repo".
+pub fn watched_repos(account: &Account) -> Vec<(String, RepoPath)> {
+    account
+        .watchlist
+        .iter()
+        .filter_map(|e| resolve(&e.repo).ok())
+        .map(|(route, repo)| (format!("/{}", route), repo))
+        .collect()
+}
+
+/// Repos on users' watchlists, for the refresher to keep fresh like watched
+/// ones.
+pub fn watch_entries(state: &AppState) -> Vec<WatchEntry> {
+    let Some(accounts) = &state.accounts else {
+        return Vec::new();
+    };
+    accounts
+        .routes()
+        .into_iter()
+        .map(|repo| WatchEntry {
+            repo,
+            interval_hours: None,
+            category: None,
+        })
+        .collect()
+}
+
+pub fn page(status: StatusCode, title: &str, message: &str) -> Response {
+    (
+        status,
+        Html(format_html::format_notice_html(title, message)),
+    )
+        .into_response()
+}
+
+pub fn disabled() -> Response {
+    page(
+        StatusCode::NOT_FOUND,
+        "Not Found",
+        "Accounts are not enabled on this instance.",
+    )
+}
+
+fn sign_in_form(status: StatusCode, accounts: &Accounts, error: Option<&str>) -> Response {
+    (
+        status,
+        Html(format_html::format_sign_in_html(
+            "/login",
+            "Sign In",
+            error,
+            accounts.signup_enabled(),
+            &accounts.oidc.buttons(),
+        )),
+    )
+        .into_response()
+}
+
+/// Redirect to `to`, setting `cookie`.
+pub fn redirect_with_cookie(to: &str, cookie: HeaderValue) -> Response {
+    let mut response = Redirect::to(to).into_response();
+    response.headers_mut().insert(header::SET_COOKIE, cookie);
+    response
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CredentialsForm {
+    username: String,
+    password: String,
+}
+
+/// `GET /login`
+pub async fn login_page(State(state): State<Arc<AppState>>) -> Response {
+    match &state.accounts {
+        Some(accounts) => sign_in_form(StatusCode::OK, accounts, None),
+        None => disabled(),
+    }
+}
+
+/// The form error for a refused attempt, asking the client to retry at
+/// `retry_at`.
+fn too_many_attempts(retry_at: DateTime<Utc>) -> String {
+    shedding::set_retry_after((retry_at - Utc::now()).num_seconds().max(1) as u64);
+    format!(
+        "Too many attempts; try again after {}.",
+        retry_at.format("%H:%M:%S UTC")
+    )
+}
+
+/// `POST /login` with `username` and `password` form fields; signed-in
+/// users are sent to `/dashboard`. Attempts are limited per client and per
+/// username.
+pub async fn login_handler(
+    State(state): State<Arc<AppState>>,
+    client: ClientIp,
+    Form(form): Form<CredentialsForm>,
+) -> Response {
+    let Some(accounts) = state.accounts.clone() else {
+        return disabled();
+    };
+    let username = form.username.trim().to_ascii_lowercase();
+    if let Err(retry_at) =
+        accounts.count_attempt(&[format!("login:{}", client.0), format!("user:{}", username)])
+    {
+        return sign_in_form(
+            StatusCode::TOO_MANY_REQUESTS,
+            &accounts,
+            Some(&too_many_attempts(retry_at)),
+        );
+    }
+    let authenticated = {
+        let accounts = accounts.clone();
+        let username = username.clone();
+        tokio::task::spawn_blocking(move || accounts.authenticate(&username, &form.password))
+            .await
+            .ok()
+            .flatten()
+    };
+    if authenticated.is_none() {
+        return sign_in_form(
+            StatusCode::UNAUTHORIZED,
+            &accounts,
+            Some("Wrong username or password."),
+        );
+    }
+    match accounts.start_session(&username) {
+        Ok(cookie) => redirect_with_cookie("/dashboard", cookie),
+        Err(e) => AppError::internal(e).into_response(),
+    }
+}
+
+/// `POST /logout`
+pub async fn logout_handler(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let Some(accounts) = &state.accounts else {
+        return disabled();
+    };
+    accounts.end_session(&headers);
+    let mut response = Redirect::to("/").into_response();
+    if let Ok(cookie) = HeaderValue::from_str(&format!(
+        "{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax",
+        COOKIE
+    )) {
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+    response
+}
+
+/// `GET /signup`
+pub async fn signup_page(State(state): State<Arc<AppState>>) -> Response {
+    match &state.accounts {
+        Some(accounts) if accounts.signup_enabled() => Html(format_html::format_sign_in_html(
+            "/signup",
+            "Create Account",
+            None,
+            false,
+            &[],
+        ))
+        .into_response(),
+        _ => disabled(),
+    }
+}
+
+/// `POST /signup` with `username` and `password` form fields. Attempts are
+/// limited per client, which keeps one client from creating accounts in
+/// bulk.
+pub async fn signup_handler(
+    State(state): State<Arc<AppState>>,
+    client: ClientIp,
+    Form(form): Form<CredentialsForm>,
+) -> Response {
+    let Some(accounts) = state.accounts.clone().filter(|a| a.signup_enabled()) else {
+        return disabled();
+    };
+    let username = form.username.trim().to_ascii_lowercase();
+    let signup_form = |status, error: &str| {
+        (
+            status,
+            Html(format_html::format_sign_in_html(
+                "/signup",
+                "Create Account",
+                Some(error),
+                false,
+                &[],
+            )),
+        )
+            .into_response()
+    };
+    if let Err(retry_at) = accounts.count_attempt(&[format!("signup:{}", client.0)]) {
+        return signup_form(StatusCode::TOO_MANY_REQUESTS, &too_many_attempts(retry_at));
+    }
+    let created = {
+        let accounts = accounts.clone();
+        let username = username.clone();
+        tokio::task::spawn_blocking(move || accounts.create(&username, &form.password)).await
+    };
+    match created {
+        Ok(Ok(true)) => match accounts.start_session(&username) {
+            Ok(cookie) => redirect_with_cookie("/dashboard", cookie),
+            Err(e) => AppError::internal(e).into_response(),
+        },
+        Ok(Ok(false)) => signup_form(StatusCode::CONFLICT, "That username is taken."),
+        Ok(Err(e)) => signup_form(StatusCode::BAD_REQUEST, &e.to_string()),
+        Err(e) => AppError::internal(e).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchForm {
+    /// Repo route, e.g. "github/owner/repo"
+    repo: String,
+}
+
+/// `POST /dashboard/watch` with a `repo` form field: add the repo to the
+/// signed-in user's watchlist. Repos not cached yet count against the fetch
+/// quota, since the refresher fetches them.
+pub async fn watch_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    client: ClientIp,
+    Form(form): Form<WatchForm>,
+) -> Response {
+    let Some(accounts) = &state.accounts else {
+        return disabled();
+    };
+    let Some(account) = accounts.user(&headers) else {
+        return Redirect::to("/login").into_response();
+    };
+    let Ok((route, repo)) = resolve(form.repo.trim().trim_matches('/')) else {
+        return page(
+            StatusCode::BAD_REQUEST,
+            "Invalid Repository",
+            &format!("'{}' is not a repository route.", form.repo),
+        );
+    };
+    let cached = matches!(
+        state
+            .cache
+            .read_timestamp(&repo.host, &repo.owner, &repo.repo),
+        Ok(Some(_))
+    );
+    if !cached && let Err(e) = state.fetch_quota.acquire(client) {
+        return e.into_response();
+    }
+    match accounts.watch(&account.username, &route) {
+        Ok(WatchOutcome::Added | WatchOutcome::Duplicate) => {
+            Redirect::to("/dashboard").into_response()
+        }
+        Ok(WatchOutcome::Full) => page(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Watchlist Full",
+            &format!(
+                "Accounts can watch up to {} repositories; remove one first.",
+                accounts.config.max_watched
+            ),
+        ),
+        Err(e) => AppError::internal(e).into_response(),
+    }
+}
+
+/// `POST /dashboard/unwatch` with a `repo` form field.
+pub async fn unwatch_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(form): Form<WatchForm>,
+) -> Response {
+    let Some(accounts) = &state.accounts else {
+        return disabled();
+    };
+    let Some(account) = accounts.user(&headers) else {
+        return Redirect::to("/login").into_response();
+    };
+    match accounts.unwatch(&account.username, form.repo.trim().trim_matches('/')) {
+        Ok(()) => Redirect::to("/dashboard").into_response(),
+        Err(e) => AppError::internal(e).into_response(),
+    }
+}
+
+/// `GET /feed/{token}/calendar.ics`: releases of an account's watchlist.
+pub async fn feed_ics(State(state): State<Arc<AppState>>, Path(token): Path<String>) -> Response {
+    let Some(account) = state
+        .accounts
+        .as_ref()
+        .and_then(|accounts| accounts.by_feed_token(&token))
+    else {
+        return AppError::new(StatusCode::NOT_FOUND, "Unknown feed").into_response();
+    };
+    let releases = calendar::releases_of(&state, &watched_repos(&account));
+    calendar::ics_response(calendar::format_ics(
+        &format!("Releases watched by {}", account.username),
+        &releases,
+    ))
+}
+
+fn accounts_api(state: &AppState) -> Result<Arc<Accounts>, ApiError> {
+    state.accounts.clone().ok_or_else(|| {
+        AppError::new(StatusCode::NOT_FOUND, "Accounts are not enabled")
+            .with_code(ErrorCode::Disabled)
+            .into()
+    })
+}
+
+/// `GET /admin/accounts`
+pub async fn list_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let accounts = accounts_api(&state)?;
+    let entries = accounts.entries.read().unwrap();
+    let summaries: Vec<AccountSummary> = entries
+        .iter()
+        .map(|a| AccountSummary {
+            username: &a.username,
+            admin: accounts.is_admin(a),
+            identities: &a.identities,
+            watched: a.watchlist.len(),
+            created_at: a.created_at,
+        })
+        .collect();
+    let body = serde_json::to_string_pretty(&summaries).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountBody {
+    password: Option<String>,
+    /// Replace the linked OpenID Connect logins
+    identities: Option<Vec<String>>,
+}
+
+/// `PUT /admin/accounts/{username}` with `{"password": "...", "identities":
+/// ["github:1234"]}`, either optional: create the account, or change it.
+pub async fn put_handler(
+    Path(username): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<AccountBody>,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let accounts = accounts_api(&state)?;
+    let created = tokio::task::spawn_blocking(move || {
+        accounts.update(
+            &username,
+            body.password.as_deref(),
+            body.identities.as_deref(),
+        )
+    })
+    .await
+    .map_err(AppError::internal)?
+    .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok(json_response(&state, status, String::from("{}")))
+}
+
+/// `DELETE /admin/accounts/{username}`
+pub async fn delete_handler(
+    Path(username): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let accounts = accounts_api(&state)?;
+    if !accounts.remove(&username).map_err(AppError::internal)? {
+        return Err(
+            AppError::new(StatusCode::NOT_FOUND, format!("No account '{}'", username)).into(),
+        );
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwords_verify_against_their_hash_only() {
+        let stored = hash_password("correct horse").unwrap();
+        assert!(stored.starts_with("pbkdf2-sha256$600000$"));
+        assert!(verify_password("correct horse", &stored));
+        assert!(!verify_password("correct hors", &stored));
+        assert_ne!(stored, hash_password("correct horse").unwrap());
+        assert!(!verify_password("correct horse", "pbkdf2-sha256$1$00$00"));
+        assert!(!verify_password("correct horse", "plain$correct horse"));
+    }
+
+    #[test]
+    fn usernames_are_limited_to_safe_characters() {
+        for name in ["alice", "a.b_c-d", "42", &"a".repeat(32)] {
+            assert!(check_username(name).is_ok(), "{name}");
+        }
+        for name in [
+            "",
+            "Alice",
+            "-a",
+            ".a",
+            "a b",
+            "a/b",
+            "<b>",
+            &"a".repeat(33),
+        ] {
+            assert!(check_username(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn attempts_are_limited_per_key() {
+        let dir = std::env::temp_dir().join("checkup-attempts-test");
+        let config = AccountsConfig {
+            enabled: true,
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let cache = CacheManager::new(dir.clone(), dir, 24);
+        let accounts = Accounts::load(&config, cache, "http://localhost")
+            .unwrap()
+            .unwrap();
+        let keys = |client: &str, user: &str| [format!("login:{client}"), format!("user:{user}")];
+        assert!(accounts.count_attempt(&keys("a", "alice")).is_ok());
+        assert!(accounts.count_attempt(&keys("a", "bob")).is_ok());
+        // The client is used up, and refused attempts count against no one
+        assert!(accounts.count_attempt(&keys("a", "carol")).is_err());
+        assert!(accounts.count_attempt(&keys("b", "carol")).is_ok());
+        assert!(accounts.count_attempt(&keys("c", "alice")).is_ok());
+        let retry_at = accounts.count_attempt(&keys("d", "alice")).unwrap_err();
+        assert!(retry_at > Utc::now());
+        assert!(accounts.count_attempt(&keys("d", "dave")).is_ok());
+    }
+
+    #[test]
+    fn provider_usernames_become_valid_ones() {
+        assert_eq!(username_from("Jane.Doe@example.com"), "jane.doe");
+        assert_eq!(username_from("-Ünïcode user"), "n-code-user");
+        assert_eq!(username_from("__"), "user");
+        assert_eq!(username_from(&"x".repeat(40)).len(), MAX_USERNAME_LEN - 3);
+        for hint in ["Jane.Doe@example.com", "-Ünïcode user", "", "@"] {
+            assert!(check_username(&username_from(hint)).is_ok(), "{hint}");
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Operator-only endpoints, authenticated with the `--admin-token` bearer token.
+
+use crate::{
+    AppState,
+    error::{ApiError, AppError, ErrorCode},
+    signing::json_response,
+    stats::RepoRequests,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::{fmt::Write, sync::Arc};
+use subtle::ConstantTimeEq;
+
+/// Repos listed by disk usage in `/admin/stats`
+const LARGEST_REPOS: usize = 20;
+
+/// Reject the request unless it carries `Authorization: Bearer <admin token>`,
+/// or the session cookie of one of `[accounts] admins`.
+pub fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    let admins = state.accounts.as_deref().filter(|a| a.has_admins());
+    if state.admin_token.is_none() && admins.is_none() {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            "Admin API is disabled (start with --admin-token or set [accounts] admins)".to_string(),
+        )
+        .with_code(ErrorCode::Disabled));
+    }
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    // Compared in constant time, so response times don't reveal how much
+    // of a guess was right
+    let valid_token = match (provided, &state.admin_token) {
+        (Some(p), Some(token)) => bool::from(p.as_bytes().ct_eq(token.as_bytes())),
+        _ => false,
+    };
+    // The session cookie is SameSite=Lax, so other sites can't send it
+    // along with requests that change anything
+    let admin_session = || {
+        admins.is_some_and(|accounts| {
+            accounts
+                .user(headers)
+                .is_some_and(|a| accounts.is_admin(&a))
+        })
+    };
+    if !valid_token && !admin_session() {
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid admin token".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct AdminStats {
+    total_requests: u64,
+    pending_repos: usize,
+    failed_repos: usize,
+    popular: Vec<RepoRequests>,
+    cache_bytes: u64,
+    cache_files: usize,
+    largest_repos: Vec<RepoDiskUsage>,
+}
+
+#[derive(Serialize)]
+struct RepoDiskUsage {
+    /// Cache directory of the repo, e.g. "github.com/owner/repo"
+    repo: String,
+    bytes: u64,
+}
+
+pub async fn stats_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+
+    let stats = AdminStats {
+        total_requests: state.stats.total(),
+        pending_repos: state.fetches.pending_count(),
+        failed_repos: state.fetches.failed_count(),
+        popular: state.stats.top(100),
+        cache_bytes: state.cache.disk_usage.total_bytes(),
+        cache_files: state.cache.disk_usage.file_count(),
+        largest_repos: state
+            .cache
+            .disk_usage
+            .by_repo()
+            .into_iter()
+            .take(LARGEST_REPOS)
+            .map(|(repo, bytes)| RepoDiskUsage { repo, bytes })
+            .collect(),
+    };
+    let body = serde_json::to_string_pretty(&stats).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
+
+/// `/metrics`: Prometheus text exposition of cache disk usage (total and per
+/// repo) and request and fetch counters.
+pub async fn metrics_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+
+    let usage = &state.cache.disk_usage;
+    let mut body = String::new();
+    let mut gauge = |name: &str, help: &str, value: u64| {
+        let _ = write!(
+            body,
+            "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"
+        );
+    };
+    gauge(
+        "checkup_cache_bytes",
+        "Bytes used by the cache directory",
+        usage.total_bytes(),
+    );
+    gauge(
+        "checkup_cache_files",
+        "Files in the cache directory",
+        usage.file_count() as u64,
+    );
+    gauge(
+        "checkup_pending_fetches",
+        "Repos being fetched in the background",
+        state.fetches.pending_count() as u64,
+    );
+    gauge(
+        "checkup_upstream_in_flight",
+        "Upstream requests in flight",
+        state.upstream.in_flight() as u64,
+    );
+    gauge(
+        "checkup_failed_repos",
+        "Repos whose last fetch failed",
+        state.fetches.failed_count() as u64,
+    );
+
+    body.push_str("# HELP checkup_requests_total Requests to repo pages\n");
+    body.push_str("# TYPE checkup_requests_total counter\n");
+    let _ = writeln!(body, "checkup_requests_total {}", state.stats.total());
+
+    body.push_str("# HELP checkup_cache_repo_bytes Bytes cached per repo\n");
+    body.push_str("# TYPE checkup_cache_repo_bytes gauge\n");
+    for (repo, bytes) in usage.by_repo() {
+        let _ = writeln!(
+            body,
+            "checkup_cache_repo_bytes{{repo=\"{}\"}} {}",
+            escape_label(&repo),
+            bytes
+        );
+    }
+
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    )
+        .into_response())
+}
+
+/// Escape a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,203 @@
+//! Archived repos, for projects that are gone or no longer worth the
+//! fetching: archiving a repo at `/admin/archive/{...}` keeps it served from
+//! its last cached snapshot, which no longer expires, with a banner on its
+//! pages. Background refreshes and federation polls leave it alone, so no
+//! more release emails go out for it. Unarchiving makes the snapshot expire
+//! as usual again, so the next request refetches it if it is stale.
+//!
+//! Archived repos are kept in archived.json.
+
+use crate::{
+    AppState,
+    admin::require_admin,
+    cache::CacheManager,
+    error::{ApiError, AppError},
+    format_html::{escape_html, insert_banner},
+    provider,
+    provider::CachedReleases,
+    routes,
+    signing::json_response,
+};
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedRepo {
+    /// Repo route as used in URLs, e.g. "github/owner/repo"
+    pub route: String,
+    pub archived_at: DateTime<Utc>,
+    /// Shown in the banner, e.g. why it was archived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+pub struct Archive {
+    /// By cache key
+    archived: DashMap<String, ArchivedRepo>,
+    cache: CacheManager,
+}
+
+impl Archive {
+    /// Fails on an unreadable archived.json.
+    pub fn load(cache: CacheManager) -> Result<Self> {
+        let archived = DashMap::new();
+        for entry in cache.read_archived::<Vec<ArchivedRepo>>()? {
+            let (_, repo) = provider::parse_route(&entry.route)?;
+            cache.set_archived(&repo.cache_key(), true);
+            archived.insert(repo.cache_key(), entry);
+        }
+        Ok(Self { archived, cache })
+    }
+
+    pub fn get(&self, cache_key: &str) -> Option<ArchivedRepo> {
+        self.archived.get(cache_key).map(|a| a.clone())
+    }
+
+    /// Every archived repo, sorted by route.
+    pub fn all(&self) -> Vec<ArchivedRepo> {
+        let mut all: Vec<ArchivedRepo> = self.archived.iter().map(|a| a.value().clone()).collect();
+        all.sort_by(|a, b| a.route.cmp(&b.route));
+        all
+    }
+
+    fn archive(&self, cache_key: String, entry: ArchivedRepo) -> Result<()> {
+        self.cache.set_archived(&cache_key, true);
+        self.archived.insert(cache_key, entry);
+        self.save()
+    }
+
+    fn unarchive(&self, cache_key: &str) -> Result<bool> {
+        let removed = self.archived.remove(cache_key).is_some();
+        if removed {
+            self.cache.set_archived(cache_key, false);
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self) -> Result<()> {
+        self.cache.write_archived(&self.all())
+    }
+}
+
+/// `GET /admin/archive` (admin only): every archived repo.
+pub async fn list_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let body = serde_json::to_string_pretty(&state.archive.all()).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
+
+/// `PUT /admin/archive/{provider}/{...}` (admin only), with an optional note
+/// for visitors as the request body. Only cached repos can be archived.
+pub async fn archive_handler(
+    Path(route): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    note: String,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let (_, repo) = provider::parse_route(&route).map_err(|e| AppError::from_route(&e))?;
+    let cached = state
+        .cache
+        .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?;
+    if cached.is_none() {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("{} is not cached, so there is nothing to archive", route),
+        )
+        .into());
+    }
+
+    let cache_key = repo.cache_key();
+    let archived_at = state
+        .archive
+        .get(&cache_key)
+        .map_or_else(Utc::now, |a| a.archived_at);
+    let entry = ArchivedRepo {
+        route: route.trim_matches('/').to_string(),
+        archived_at,
+        note: Some(note.trim().to_string()).filter(|n| !n.is_empty()),
+    };
+    state
+        .archive
+        .archive(cache_key, entry.clone())
+        .map_err(AppError::internal)?;
+
+    let body = serde_json::to_string_pretty(&entry).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
+
+/// `DELETE /admin/archive/{provider}/{...}` (admin only).
+pub async fn unarchive_handler(
+    Path(route): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let (_, repo) = provider::parse_route(&route).map_err(|e| AppError::from_route(&e))?;
+    let removed = state
+        .archive
+        .unarchive(&repo.cache_key())
+        .map_err(AppError::internal)?;
+    if !removed {
+        return Err(
+            AppError::new(StatusCode::NOT_FOUND, format!("{} is not archived", route)).into(),
+        );
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// The archived repo a page URL path belongs to, if any.
+fn archived_for_path(state: &AppState, path: &str) -> Option<ArchivedRepo> {
+    let (prefix, rest) = path.trim_matches('/').split_once('/')?;
+    let route = routes::parse(prefix, rest);
+    let (_, repo) = provider::parse_route(&format!("{}/{}", prefix, route.repo)).ok()?;
+    state.archive.get(&repo.cache_key())
+}
+
+/// Put the archive banner at the top of archived repos' HTML pages.
+pub async fn banner(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let archived = archived_for_path(&state, request.uri().path());
+    let response = next.run(request).await;
+    let Some(archived) = archived else {
+        return response;
+    };
+    let html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if !html {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let banner = format!(
+        r#"<div style="margin: 0 0 20px 0; padding: 12px 16px; background: #f1f3f5; border: 1px solid #adb5bd; border-radius: 6px; color: #495057;"><strong>Archived</strong> on {}, no longer refreshed: releases are as of then.{}</div>"#,
+        archived.archived_at.format("%Y-%m-%d"),
+        archived
+            .note
+            .map(|n| format!(" {}", escape_html(&n)))
+            .unwrap_or_default()
+    );
+    let page = insert_banner(&String::from_utf8_lossy(&bytes), &banner);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(page))
+}
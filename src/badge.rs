@@ -0,0 +1,87 @@
+//! `/{provider}/{...}/badge.svg`: a shields-style badge with the latest
+//! release's version. With `?compare=<version>` it shows whether that version
+//! is current ("up to date") or how far behind it is ("1.2.3 → 1.4.0"), for
+//! dashboards tracking deployment drift.
+
+use crate::{
+    AppState, RepoPath, cache_control::RouteKind, error::AppError, format_html::escape_html,
+    provider::Release, versions,
+};
+use axum::{
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use std::cmp::Ordering;
+
+const BLUE: &str = "#007ec6";
+const GREEN: &str = "#4c1";
+const YELLOW: &str = "#dfb317";
+const RED: &str = "#e05d44";
+
+/// Badge for a repo's releases (newest first). Drafts and prereleases are
+/// skipped unless there is nothing else.
+pub fn badge_response(
+    state: &AppState,
+    repo: &RepoPath,
+    releases: &[Release],
+    compare: Option<&str>,
+) -> Result<Response, AppError> {
+    let latest = releases
+        .iter()
+        .find(|r| !r.draft && !r.prerelease)
+        .or_else(|| releases.first())
+        .ok_or(AppError::new(
+            StatusCode::NOT_FOUND,
+            "No releases found".to_string(),
+        ))?;
+    let rules = state.tag_rules.for_repo(&repo.cache_key());
+    let latest_version = latest
+        .version
+        .clone()
+        .unwrap_or_else(|| rules.normalize(&latest.tag_name));
+
+    let (message, color) = match compare.filter(|c| !c.trim().is_empty()) {
+        None => (latest_version, BLUE),
+        Some(current) => {
+            let current = rules.normalize(current);
+            match versions::compare_versions(&current, &latest_version) {
+                Ordering::Less => {
+                    let major_behind = versions::major_version(&current)
+                        != versions::major_version(&latest_version);
+                    let color = if major_behind { RED } else { YELLOW };
+                    (format!("{} → {}", current, latest_version), color)
+                }
+                _ => ("up to date".to_string(), GREEN),
+            }
+        }
+    };
+
+    let svg = render_badge("release", &message, color);
+    Ok(state.cache_policy.apply(
+        RouteKind::Badge,
+        ([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response(),
+    ))
+}
+
+/// Approximate rendered width of `text` in 11px Verdana.
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+fn render_badge(label: &str, message: &str, color: &str) -> String {
+    let label_width = text_width(label);
+    let message_width = text_width(message);
+    let width = label_width + message_width;
+    let (label, message) = (escape_html(label), escape_html(message));
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+<title>{label}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="14">{label}</text><text x="{message_x}" y="14">{message}</text></g>
+</svg>"##,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
@@ -0,0 +1,237 @@
+//! `checkup bench`: replays a mix of traffic against a running instance and
+//! reports latency percentiles and cache hit ratios, so regressions in the
+//! cache or rendering path show before a release.
+//!
+//! Repos are requested with the skew of real traffic: the list is taken as
+//! ordered by popularity, and the repo at rank `n` is picked with weight
+//! `1/n`. Each request is one of the routes visitors and tools hit most,
+//! see [`KINDS`]. Hits and misses are read from the `X-Checkup-Cache` header
+//! of release pages and `/+json`.
+
+use crate::{VERSION, cache_control::CACHE_STATUS_HEADER};
+use anyhow::{Context, Result};
+use futures_util::{StreamExt, stream};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct BenchArgs {
+    /// Base URL of the instance to benchmark
+    #[arg(long, value_name = "URL")]
+    against: String,
+
+    /// File with one repo route per line (e.g. "github/owner/repo"), most popular first
+    #[arg(long, value_name = "FILE")]
+    repos: PathBuf,
+
+    /// Requests to send
+    #[arg(long, default_value = "1000")]
+    requests: usize,
+
+    /// Requests in flight at once
+    #[arg(long, default_value = "16")]
+    concurrency: usize,
+
+    /// Seed of the request mix, for runs that can be compared
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+/// Routes requested of each repo, with their share of the traffic in percent
+const KINDS: [(&str, u32); 4] = [
+    ("page", 60),
+    ("+json", 25),
+    ("badge.svg", 10),
+    ("latest", 5),
+];
+
+struct Sample {
+    kind: &'static str,
+    status: Option<u16>,
+    latency: Duration,
+    /// From `X-Checkup-Cache`, where sent
+    hit: Option<bool>,
+}
+
+/// Repo routes of a list file, without blank lines and `#` comments.
+fn read_repos(path: &PathBuf) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let repos: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_matches('/').to_string())
+        .collect();
+    if repos.is_empty() {
+        anyhow::bail!("No repos in {:?}", path);
+    }
+    Ok(repos)
+}
+
+/// Index of a repo picked with weight `1/rank`.
+fn pick_repo(rng: &mut fastrand::Rng, weights: &[f64], total: f64) -> usize {
+    let mut at = rng.f64() * total;
+    for (i, weight) in weights.iter().enumerate() {
+        if at < *weight {
+            return i;
+        }
+        at -= weight;
+    }
+    weights.len() - 1
+}
+
+fn pick_kind(rng: &mut fastrand::Rng) -> &'static str {
+    let mut at = rng.u32(0..100);
+    for (kind, share) in KINDS {
+        if at < share {
+            return kind;
+        }
+        at -= share;
+    }
+    KINDS[0].0
+}
+
+fn url(base: &str, repo: &str, kind: &str) -> String {
+    match kind {
+        "page" => format!("{}/{}", base, repo),
+        "latest" => format!("{}/{}/latest.tar.gz", base, repo),
+        suffix => format!("{}/{}/{}", base, repo, suffix),
+    }
+}
+
+async fn send(client: &reqwest::Client, url: String, kind: &'static str) -> Sample {
+    let started = Instant::now();
+    let response = client.get(&url).send().await;
+    let (status, hit) = match response {
+        Ok(response) => {
+            let hit = response
+                .headers()
+                .get(CACHE_STATUS_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v == "hit");
+            let status = response.status().as_u16();
+            // Latency includes reading the whole body
+            let _ = response.bytes().await;
+            (Some(status), hit)
+        }
+        Err(_) => (None, None),
+    };
+    Sample {
+        kind,
+        status,
+        latency: started.elapsed(),
+        hit,
+    }
+}
+
+/// The latency below which `p` percent of `sorted` fall.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn millis(d: Duration) -> String {
+    format!("{:.1}", d.as_secs_f64() * 1000.0)
+}
+
+fn report(samples: &[Sample], elapsed: Duration) {
+    println!(
+        "{} requests in {:.2}s ({:.1} req/s)\n",
+        samples.len(),
+        elapsed.as_secs_f64(),
+        samples.len() as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "{:<10} {:>6} {:>9} {:>9} {:>9} {:>9} {:>10}",
+        "route", "count", "p50 ms", "p90 ms", "p99 ms", "max ms", "cache hit"
+    );
+    let kinds = KINDS.iter().map(|(kind, _)| Some(*kind)).chain([None]);
+    for kind in kinds {
+        let of_kind: Vec<&Sample> = samples
+            .iter()
+            .filter(|s| kind.is_none_or(|k| s.kind == k))
+            .collect();
+        if of_kind.is_empty() {
+            continue;
+        }
+        let mut latencies: Vec<Duration> = of_kind.iter().map(|s| s.latency).collect();
+        latencies.sort();
+        let reported: Vec<bool> = of_kind.iter().filter_map(|s| s.hit).collect();
+        let hit_ratio = if reported.is_empty() {
+            "-".to_string()
+        } else {
+            let hits = reported.iter().filter(|hit| **hit).count();
+            format!("{:.1}%", hits as f64 * 100.0 / reported.len() as f64)
+        };
+        println!(
+            "{:<10} {:>6} {:>9} {:>9} {:>9} {:>9} {:>10}",
+            kind.unwrap_or("all"),
+            of_kind.len(),
+            millis(percentile(&latencies, 50.0)),
+            millis(percentile(&latencies, 90.0)),
+            millis(percentile(&latencies, 99.0)),
+            millis(*latencies.last().unwrap()),
+            hit_ratio
+        );
+    }
+
+    let mut statuses: BTreeMap<String, usize> = BTreeMap::new();
+    for sample in samples {
+        let status = sample
+            .status
+            .map_or_else(|| "error".to_string(), |s| s.to_string());
+        *statuses.entry(status).or_default() += 1;
+    }
+    let statuses: Vec<String> = statuses
+        .iter()
+        .map(|(status, count)| format!("{} × {}", status, count))
+        .collect();
+    println!("\nStatus: {}", statuses.join(", "));
+}
+
+pub async fn run(args: BenchArgs) -> Result<()> {
+    let base = args.against.trim_end_matches('/');
+    let repos = read_repos(&args.repos)?;
+    let client = reqwest::Client::builder()
+        .user_agent(format!("checkup-bench/{}", VERSION))
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(60))
+        .build()?;
+
+    let mut rng = args
+        .seed
+        .map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed);
+    let weights: Vec<f64> = (1..=repos.len()).map(|rank| 1.0 / rank as f64).collect();
+    let total: f64 = weights.iter().sum();
+    let plan: Vec<(String, &'static str)> = (0..args.requests)
+        .map(|_| {
+            let repo = &repos[pick_repo(&mut rng, &weights, total)];
+            let kind = pick_kind(&mut rng);
+            (url(base, repo, kind), kind)
+        })
+        .collect();
+
+    println!(
+        "Sending {} requests for {} repos to {} ({} at once)",
+        plan.len(),
+        repos.len(),
+        base,
+        args.concurrency.max(1)
+    );
+    let started = Instant::now();
+    let samples: Vec<Sample> = stream::iter(plan)
+        .map(|(url, kind)| send(&client, url, kind))
+        .buffer_unordered(args.concurrency.max(1))
+        .collect()
+        .await;
+    let elapsed = started.elapsed();
+    if samples.is_empty() {
+        return Ok(());
+    }
+    report(&samples, elapsed);
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//! The operator's branding on every HTML page (`[branding]` in the config
+//! file): the instance name in page titles, a footer with its logo, privacy
+//! policy, terms, contact and further links, and an announcement banner at
+//! the top. Public instances use it to say who runs them and on what terms.
+
+use crate::{
+    AppState,
+    config::BrandingConfig,
+    format_html::{escape_html, insert_banner, insert_footer},
+};
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+/// Name pages carry in their titles unless the instance has its own
+const DEFAULT_NAME: &str = "Checkup";
+
+/// Link targets allowed in the footer; no `javascript:` and the like
+const LINK_SCHEMES: [&str; 4] = ["https://", "http://", "mailto:", "/"];
+
+/// The branding, rendered once at startup.
+#[derive(Debug, Default)]
+pub struct Branding {
+    name: Option<String>,
+    footer: Option<String>,
+    announcement: Option<String>,
+}
+
+fn check_link(url: &str) -> Result<()> {
+    if !LINK_SCHEMES.iter().any(|scheme| url.starts_with(scheme)) {
+        anyhow::bail!(
+            "Invalid [branding] link {:?}: use an http(s) or mailto: URL, or a path",
+            url
+        );
+    }
+    Ok(())
+}
+
+impl Branding {
+    pub fn from_config(config: &BrandingConfig) -> Result<Self> {
+        let name = config.name.clone().filter(|n| !n.trim().is_empty());
+
+        let mut links: Vec<(String, &str)> = Vec::new();
+        if let Some(url) = &config.privacy_url {
+            links.push((url.clone(), "Privacy policy"));
+        }
+        if let Some(url) = &config.terms_url {
+            links.push((url.clone(), "Terms of use"));
+        }
+        if let Some(contact) = &config.contact {
+            // A bare address is mailed to
+            let url = if contact.contains('@') && !contact.contains(':') {
+                format!("mailto:{}", contact)
+            } else {
+                contact.clone()
+            };
+            links.push((url, "Contact"));
+        }
+        for link in &config.links {
+            links.push((link.url.clone(), &link.label));
+        }
+        for (url, _) in &links {
+            check_link(url)?;
+        }
+        if let Some(logo) = &config.logo_url {
+            check_link(logo)?;
+        }
+
+        let mut footer_parts: Vec<String> = Vec::new();
+        if let Some(logo) = &config.logo_url {
+            footer_parts.push(format!(
+                r#"<img src="{}" alt="" style="height: 20px; vertical-align: middle; margin-right: 6px;">"#,
+                escape_html(logo)
+            ));
+        }
+        if let Some(name) = &name {
+            footer_parts.push(format!("<strong>{}</strong>", escape_html(name)));
+        }
+        let links_html = links
+            .iter()
+            .map(|(url, label)| {
+                format!(
+                    r#"<a href="{}" style="color: #586069;">{}</a>"#,
+                    escape_html(url),
+                    escape_html(label)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" &middot; ");
+        if !links_html.is_empty() {
+            footer_parts.push(links_html);
+        }
+        let footer = (!footer_parts.is_empty()).then(|| {
+            format!(
+                r#"<footer style="margin: 40px 0 0 0; padding: 16px 0; border-top: 1px solid #e1e4e8; color: #586069; font-size: 0.9em; text-align: center;">{}</footer>"#,
+                footer_parts.join(" ")
+            )
+        });
+
+        let announcement = config
+            .announcement
+            .as_deref()
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(|a| {
+                format!(
+                    r#"<div style="margin: 0 0 20px 0; padding: 12px 16px; background: #e8f4fd; border: 1px solid #79b8ff; border-radius: 6px; color: #032f62;">{}</div>"#,
+                    escape_html(a)
+                )
+            });
+
+        Ok(Self {
+            name,
+            footer,
+            announcement,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.name.is_none() && self.footer.is_none() && self.announcement.is_none()
+    }
+
+    /// Brand a rendered page.
+    fn apply(&self, page: &str) -> String {
+        let mut page = match &self.name {
+            Some(name) => retitle(page, name),
+            None => page.to_string(),
+        };
+        if let Some(announcement) = &self.announcement {
+            page = insert_banner(&page, announcement);
+        }
+        if let Some(footer) = &self.footer {
+            page = insert_footer(&page, footer);
+        }
+        page
+    }
+}
+
+/// Put `name` in the page's title in place of "Checkup", or after it on
+/// pages whose title doesn't name the instance.
+fn retitle(page: &str, name: &str) -> String {
+    let (Some(start), Some(end)) = (page.find("<title>"), page.find("</title>")) else {
+        return page.to_string();
+    };
+    let start = start + "<title>".len();
+    if end < start {
+        return page.to_string();
+    }
+    let title = &page[start..end];
+    let name = escape_html(name);
+    let title = if title.contains(DEFAULT_NAME) {
+        title.replace(DEFAULT_NAME, &name)
+    } else {
+        format!("{} - {}", title, name)
+    };
+    format!("{}{}{}", &page[..start], title, &page[end..])
+}
+
+/// Brand HTML pages.
+pub async fn decorate(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if state.branding.is_empty() {
+        return response;
+    }
+    let html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if !html {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let page = state.branding.apply(&String::from_utf8_lossy(&bytes));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(page))
+}
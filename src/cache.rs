@@ -1,22 +1,63 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Utc};
+use dashmap::{DashMap, DashSet};
 use serde::de::DeserializeOwned;
-use std::{fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 #[derive(Clone)]
 pub struct CacheManager {
     pub cache_dir: PathBuf,
+    /// Directory of registered state (watchlist, deployments,
+    /// subscriptions), the cache directory unless `--state-dir` is given
+    pub state_dir: PathBuf,
     pub cache_duration: Duration,
+    pub disk_usage: Arc<DiskUsage>,
+    /// Set in maintenance mode: nothing expires, so cached repos are served
+    /// however old they are
+    frozen: Arc<AtomicBool>,
+    /// Cache keys of archived repos (`archive.rs`), whose cache never
+    /// expires
+    archived: Arc<DashSet<String>>,
+    /// Per cache key, serializes the read-modify-writes of a repo's
+    /// hashes.json, cids.json and cached releases
+    repo_writes: Arc<DashMap<String, Arc<Mutex<()>>>>,
 }
 
 impl CacheManager {
-    pub fn new(cache_dir: PathBuf, cache_hours: i64) -> Self {
+    /// Scans the cache directory once to learn its disk usage.
+    pub fn new(cache_dir: PathBuf, state_dir: PathBuf, cache_hours: i64) -> Self {
         Self {
+            disk_usage: Arc::new(DiskUsage::scan(&cache_dir)),
             cache_dir,
+            state_dir,
             cache_duration: Duration::hours(cache_hours),
+            frozen: Arc::new(AtomicBool::new(false)),
+            archived: Arc::new(DashSet::new()),
+            repo_writes: Arc::new(DashMap::new()),
         }
     }
 
+    /// Write a cache file, keeping track of its size.
+    /// Write through a temporary file renamed into place, so a file being
+    /// read or streamed is never seen half-written.
+    fn write_file(&self, path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+        let contents = contents.as_ref();
+        let name = path.file_name().context("Cache file without a name")?;
+        let temp = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, path)?;
+        self.disk_usage.record(path, contents.len() as u64);
+        Ok(())
+    }
+
     pub fn get_repo_dir(&self, host: &str, owner: &str, repo: &str) -> PathBuf {
         self.cache_dir
             .join("repo")
@@ -25,6 +66,25 @@ impl CacheManager {
             .join(repo)
     }
 
+    /// Directory of generated asset deltas (`deltas.rs`)
+    pub fn deltas_dir(&self, host: &str, owner: &str, repo: &str) -> PathBuf {
+        self.get_repo_dir(host, owner, repo).join("deltas")
+    }
+
+    /// Count a file written outside of this manager towards the disk usage.
+    pub fn track_file(&self, path: &Path) {
+        if let Ok(metadata) = fs::metadata(path) {
+            self.disk_usage.record(path, metadata.len());
+        }
+    }
+
+    /// Remove a file from the cache and from the disk usage.
+    pub fn remove_file(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path)?;
+        self.disk_usage.files.remove(path);
+        Ok(())
+    }
+
     pub fn read_timestamp(
         &self,
         host: &str,
@@ -47,12 +107,22 @@ impl CacheManager {
     }
 
     pub fn write_timestamp(&self, host: &str, owner: &str, repo: &str) -> Result<()> {
+        self.write_timestamp_at(host, owner, repo, Utc::now())
+    }
+
+    /// Mark the cache as fetched at `at`, for releases fetched elsewhere.
+    pub fn write_timestamp_at(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
         let repo_dir = self.get_repo_dir(host, owner, repo);
         fs::create_dir_all(&repo_dir)?;
 
         let current_file = repo_dir.join(".current");
-        let timestamp = Utc::now().to_rfc3339();
-        fs::write(&current_file, timestamp)?;
+        self.write_file(&current_file, at.to_rfc3339())?;
 
         Ok(())
     }
@@ -100,36 +170,619 @@ impl CacheManager {
 
         let json_file = repo_dir.join("cache.json");
         let content = serde_json::to_string_pretty(data)?;
-        fs::write(&json_file, content)?;
+        self.write_file(&json_file, content)?;
 
         Ok(())
     }
 
-    pub fn read_html(&self, host: &str, owner: &str, repo: &str) -> Result<Option<String>> {
+    /// index.html opened for streaming, so large pages aren't read into
+    /// memory whole.
+    pub async fn open_html(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<tokio::fs::File>> {
+        let html_file = self.get_repo_dir(host, owner, repo).join("index.html");
+
+        match tokio::fs::File::open(&html_file).await {
+            Ok(file) => Ok(Some(file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("Failed to open index.html file"),
+        }
+    }
+
+    pub fn write_html(&self, host: &str, owner: &str, repo: &str, html: &str) -> Result<()> {
         let repo_dir = self.get_repo_dir(host, owner, repo);
+        fs::create_dir_all(&repo_dir)?;
+
         let html_file = repo_dir.join("index.html");
+        self.write_file(&html_file, html)?;
+
+        Ok(())
+    }
+
+    pub fn read_tags<T: DeserializeOwned>(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<T>> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        let tags_file = repo_dir.join("tags.json");
 
-        if !html_file.exists() {
+        if !tags_file.exists() {
             return Ok(None);
         }
 
-        let content = fs::read_to_string(&html_file).context("Failed to read index.html file")?;
+        let content = fs::read_to_string(&tags_file).context("Failed to read tags.json file")?;
+        let data: T = serde_json::from_str(&content).context("Failed to parse tags.json file")?;
 
-        Ok(Some(content))
+        Ok(Some(data))
     }
 
-    pub fn write_html(&self, host: &str, owner: &str, repo: &str, html: &str) -> Result<()> {
+    pub fn write_tags<T: serde::Serialize>(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        data: &T,
+    ) -> Result<()> {
         let repo_dir = self.get_repo_dir(host, owner, repo);
         fs::create_dir_all(&repo_dir)?;
 
-        let html_file = repo_dir.join("index.html");
-        fs::write(&html_file, html)?;
+        let tags_file = repo_dir.join("tags.json");
+        let content = serde_json::to_string_pretty(data)?;
+        self.write_file(&tags_file, content)?;
+
+        Ok(())
+    }
+
+    pub fn read_latest<T: DeserializeOwned>(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<T>> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        let latest_file = repo_dir.join("latest.json");
+
+        if !latest_file.exists() {
+            return Ok(None);
+        }
+
+        let content =
+            fs::read_to_string(&latest_file).context("Failed to read latest.json file")?;
+        let data: T = serde_json::from_str(&content).context("Failed to parse latest.json file")?;
+
+        Ok(Some(data))
+    }
+
+    pub fn write_latest<T: serde::Serialize>(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        data: &T,
+    ) -> Result<()> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        fs::create_dir_all(&repo_dir)?;
+
+        let latest_file = repo_dir.join("latest.json");
+        let content = serde_json::to_string_pretty(data)?;
+        self.write_file(&latest_file, content)?;
+
+        Ok(())
+    }
+
+    /// SHA-256 hashes recorded by the download proxy, by asset URL.
+    pub fn read_hashes(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<BTreeMap<String, String>> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        let hashes_file = repo_dir.join("hashes.json");
+
+        if !hashes_file.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let content =
+            fs::read_to_string(&hashes_file).context("Failed to read hashes.json file")?;
+        let data = serde_json::from_str(&content).context("Failed to parse hashes.json file")?;
+
+        Ok(data)
+    }
+
+    pub fn write_hashes(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        hashes: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        fs::create_dir_all(&repo_dir)?;
+
+        let hashes_file = repo_dir.join("hashes.json");
+        let content = serde_json::to_string_pretty(hashes)?;
+        self.write_file(&hashes_file, content)?;
+
+        Ok(())
+    }
+
+    /// IPFS CIDs of assets pinned after proxy downloads, by asset URL.
+    pub fn read_cids(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<BTreeMap<String, String>> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        let cids_file = repo_dir.join("cids.json");
+
+        if !cids_file.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let content = fs::read_to_string(&cids_file).context("Failed to read cids.json file")?;
+        let data = serde_json::from_str(&content).context("Failed to parse cids.json file")?;
+
+        Ok(data)
+    }
+
+    pub fn write_cids(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        cids: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        fs::create_dir_all(&repo_dir)?;
+
+        let cids_file = repo_dir.join("cids.json");
+        let content = serde_json::to_string_pretty(cids)?;
+        self.write_file(&cids_file, content)?;
+
+        Ok(())
+    }
+
+    /// The CIDs in every repo's cids.json.
+    pub fn read_all_cids(&self) -> HashSet<String> {
+        let mut files = Vec::new();
+        collect_files(&self.cache_dir.join("repo"), "cids.json", &mut files);
+        files
+            .iter()
+            .filter_map(|file| fs::read_to_string(file).ok())
+            .filter_map(|content| serde_json::from_str::<BTreeMap<String, String>>(&content).ok())
+            .flat_map(BTreeMap::into_values)
+            .collect()
+    }
+
+    /// Full release notes of releases whose notes were truncated, by tag.
+    pub fn read_notes(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<BTreeMap<String, String>> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        let notes_file = repo_dir.join("notes.json");
+
+        if !notes_file.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let content = fs::read_to_string(&notes_file).context("Failed to read notes.json file")?;
+        let data = serde_json::from_str(&content).context("Failed to parse notes.json file")?;
+
+        Ok(data)
+    }
+
+    pub fn write_notes(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        notes: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        fs::create_dir_all(&repo_dir)?;
+
+        let notes_file = repo_dir.join("notes.json");
+        let content = serde_json::to_string_pretty(notes)?;
+        self.write_file(&notes_file, content)?;
+
+        Ok(())
+    }
+
+    /// SBOM summaries by tag.
+    pub fn read_sboms<T: DeserializeOwned + Default>(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<T> {
+        let sboms_file = self.get_repo_dir(host, owner, repo).join("sboms.json");
+
+        if !sboms_file.exists() {
+            return Ok(T::default());
+        }
+
+        let content = fs::read_to_string(&sboms_file).context("Failed to read sboms.json file")?;
+        let data = serde_json::from_str(&content).context("Failed to parse sboms.json file")?;
+
+        Ok(data)
+    }
+
+    pub fn write_sboms<T: serde::Serialize>(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        sboms: &T,
+    ) -> Result<()> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        fs::create_dir_all(&repo_dir)?;
+
+        let content = serde_json::to_string_pretty(sboms)?;
+        self.write_file(&repo_dir.join("sboms.json"), content)?;
+
+        Ok(())
+    }
+
+    /// Translated release notes by language and tag.
+    pub fn read_translations<T: DeserializeOwned + Default>(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<T> {
+        let translations_file = self
+            .get_repo_dir(host, owner, repo)
+            .join("translations.json");
+
+        if !translations_file.exists() {
+            return Ok(T::default());
+        }
+
+        let content = fs::read_to_string(&translations_file)
+            .context("Failed to read translations.json file")?;
+        let data =
+            serde_json::from_str(&content).context("Failed to parse translations.json file")?;
+
+        Ok(data)
+    }
+
+    pub fn write_translations<T: serde::Serialize>(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        translations: &T,
+    ) -> Result<()> {
+        let repo_dir = self.get_repo_dir(host, owner, repo);
+        fs::create_dir_all(&repo_dir)?;
+
+        let content = serde_json::to_string_pretty(translations)?;
+        self.write_file(&repo_dir.join("translations.json"), content)?;
+
+        Ok(())
+    }
+
+    fn catalog_file(&self, provider: &str, host: &str) -> PathBuf {
+        self.cache_dir
+            .join("catalog")
+            .join(provider)
+            .join(format!("{}.json", host))
+    }
+
+    pub fn read_catalog<T: DeserializeOwned>(
+        &self,
+        provider: &str,
+        host: &str,
+    ) -> Result<Option<T>> {
+        let catalog_file = self.catalog_file(provider, host);
+
+        if !catalog_file.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&catalog_file).context("Failed to read catalog file")?;
+        let data: T = serde_json::from_str(&content).context("Failed to parse catalog file")?;
+
+        Ok(Some(data))
+    }
+
+    pub fn write_catalog<T: serde::Serialize>(
+        &self,
+        provider: &str,
+        host: &str,
+        data: &T,
+    ) -> Result<()> {
+        let catalog_file = self.catalog_file(provider, host);
+        if let Some(dir) = catalog_file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let content = serde_json::to_string_pretty(data)?;
+        self.write_file(&catalog_file, content)?;
+
+        Ok(())
+    }
+
+    /// Deployed versions registered through the API.
+    pub fn read_deployments<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let deployments_file = self.state_dir.join("deployments.json");
+
+        if !deployments_file.exists() {
+            return Ok(T::default());
+        }
+
+        let content = fs::read_to_string(&deployments_file)
+            .context("Failed to read deployments.json file")?;
+        serde_json::from_str(&content).context("Failed to parse deployments.json file")
+    }
+
+    pub fn write_deployments<T: serde::Serialize>(&self, deployments: &T) -> Result<()> {
+        fs::create_dir_all(&self.state_dir)?;
+        let content = serde_json::to_string_pretty(deployments)?;
+        self.write_file(&self.state_dir.join("deployments.json"), content)?;
+
+        Ok(())
+    }
+
+    /// Local accounts and their watchlists.
+    pub fn read_accounts<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let accounts_file = self.state_dir.join("accounts.json");
+
+        if !accounts_file.exists() {
+            return Ok(T::default());
+        }
+
+        let content =
+            fs::read_to_string(&accounts_file).context("Failed to read accounts.json file")?;
+        serde_json::from_str(&content).context("Failed to parse accounts.json file")
+    }
+
+    pub fn write_accounts<T: serde::Serialize>(&self, accounts: &T) -> Result<()> {
+        fs::create_dir_all(&self.state_dir)?;
+        let content = serde_json::to_string_pretty(accounts)?;
+        self.write_file(&self.state_dir.join("accounts.json"), content)?;
+
+        Ok(())
+    }
+
+    /// Email subscriptions to repo pages, confirmed or pending.
+    pub fn read_subscriptions<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let subscriptions_file = self.state_dir.join("subscriptions.json");
+
+        if !subscriptions_file.exists() {
+            return Ok(T::default());
+        }
+
+        let content = fs::read_to_string(&subscriptions_file)
+            .context("Failed to read subscriptions.json file")?;
+        serde_json::from_str(&content).context("Failed to parse subscriptions.json file")
+    }
+
+    pub fn write_subscriptions<T: serde::Serialize>(&self, subscriptions: &T) -> Result<()> {
+        fs::create_dir_all(&self.state_dir)?;
+        let content = serde_json::to_string_pretty(subscriptions)?;
+        self.write_file(&self.state_dir.join("subscriptions.json"), content)?;
+
+        Ok(())
+    }
+
+    /// Repos archived at `/admin/archive/`.
+    pub fn read_archived<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let archived_file = self.state_dir.join("archived.json");
+
+        if !archived_file.exists() {
+            return Ok(T::default());
+        }
+
+        let content =
+            fs::read_to_string(&archived_file).context("Failed to read archived.json file")?;
+        serde_json::from_str(&content).context("Failed to parse archived.json file")
+    }
+
+    pub fn write_archived<T: serde::Serialize>(&self, archived: &T) -> Result<()> {
+        fs::create_dir_all(&self.state_dir)?;
+        let content = serde_json::to_string_pretty(archived)?;
+        self.write_file(&self.state_dir.join("archived.json"), content)?;
 
         Ok(())
     }
 
+    /// Running and failed background fetches (`fetches.rs`).
+    pub fn read_fetches<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let fetches_file = self.state_dir.join("fetches.json");
+
+        if !fetches_file.exists() {
+            return Ok(T::default());
+        }
+
+        let content =
+            fs::read_to_string(&fetches_file).context("Failed to read fetches.json file")?;
+        serde_json::from_str(&content).context("Failed to parse fetches.json file")
+    }
+
+    pub fn write_fetches<T: serde::Serialize>(&self, fetches: &T) -> Result<()> {
+        fs::create_dir_all(&self.state_dir)?;
+        let content = serde_json::to_string_pretty(fetches)?;
+        self.write_file(&self.state_dir.join("fetches.json"), content)?;
+
+        Ok(())
+    }
+
+    /// Watched repos added through `/api/v2/import`.
+    pub fn read_watchlist<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let watchlist_file = self.state_dir.join("watchlist.json");
+
+        if !watchlist_file.exists() {
+            return Ok(T::default());
+        }
+
+        let content =
+            fs::read_to_string(&watchlist_file).context("Failed to read watchlist.json file")?;
+        serde_json::from_str(&content).context("Failed to parse watchlist.json file")
+    }
+
+    pub fn write_watchlist<T: serde::Serialize>(&self, watchlist: &T) -> Result<()> {
+        fs::create_dir_all(&self.state_dir)?;
+        let content = serde_json::to_string_pretty(watchlist)?;
+        self.write_file(&self.state_dir.join("watchlist.json"), content)?;
+
+        Ok(())
+    }
+
+    /// Every repo's cache.json that parses as `T`, e.g. for searching.
+    pub fn read_all_json<T: DeserializeOwned>(&self) -> Vec<T> {
+        let mut files = Vec::new();
+        collect_files(&self.cache_dir.join("repo"), "cache.json", &mut files);
+        files
+            .iter()
+            .filter_map(|file| fs::read_to_string(file).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect()
+    }
+
     pub fn is_expired(&self, cached_at: DateTime<Utc>) -> bool {
         let now = Utc::now();
-        now - cached_at > self.cache_duration
+        !self.frozen.load(Ordering::Relaxed) && now - cached_at > self.cache_duration
+    }
+
+    pub fn set_frozen(&self, frozen: bool) {
+        self.frozen.store(frozen, Ordering::Relaxed);
+    }
+
+    /// Whether a repo's cache from `cached_at` is due for a refetch; never
+    /// for archived repos.
+    pub fn is_repo_expired(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        cached_at: DateTime<Utc>,
+    ) -> bool {
+        !self.is_archived(&format!("{}/{}/{}", host, owner, repo)) && self.is_expired(cached_at)
+    }
+
+    pub fn is_archived(&self, cache_key: &str) -> bool {
+        self.archived.contains(cache_key)
+    }
+
+    pub fn set_archived(&self, cache_key: &str, archived: bool) {
+        if archived {
+            self.archived.insert(cache_key.to_string());
+        } else {
+            self.archived.remove(cache_key);
+        }
+    }
+
+    /// Run `update` while no other update of the repo's recorded hashes,
+    /// CIDs or cached releases runs, so none of them is lost. It does file
+    /// I/O; call it from a blocking thread.
+    pub fn with_repo_lock<T>(&self, cache_key: &str, update: impl FnOnce() -> T) -> T {
+        let lock = self
+            .repo_writes
+            .entry(cache_key.to_string())
+            .or_default()
+            .clone();
+        let _guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        update()
+    }
+}
+
+/// Sizes of the files in the cache directory, found by one scan at startup
+/// and updated on every write, so disk usage can be reported without walking
+/// the tree each time.
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    /// Cache directory the paths are below
+    root: PathBuf,
+    files: DashMap<PathBuf, u64>,
+}
+
+impl DiskUsage {
+    fn scan(root: &Path) -> Self {
+        let usage = Self {
+            root: root.to_path_buf(),
+            files: DashMap::new(),
+        };
+        let mut files = Vec::new();
+        collect_all_files(root, &mut files);
+        for file in files {
+            if let Ok(metadata) = fs::metadata(&file) {
+                usage.files.insert(file, metadata.len());
+            }
+        }
+        usage
+    }
+
+    fn record(&self, path: &Path, bytes: u64) {
+        self.files.insert(path.to_path_buf(), bytes);
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| *f.value()).sum()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Bytes used by each cached repo, keyed by its directory below `repo/`
+    /// (e.g. "github.com/owner/repo"), largest first.
+    pub fn by_repo(&self) -> Vec<(String, u64)> {
+        let repo_root = self.root.join("repo");
+        let mut repos: HashMap<String, u64> = HashMap::new();
+        for file in self.files.iter() {
+            let Some(dir) = file
+                .key()
+                .parent()
+                .and_then(|dir| dir.strip_prefix(&repo_root).ok())
+            else {
+                continue;
+            };
+            *repos.entry(dir.to_string_lossy().into_owned()).or_default() += *file.value();
+        }
+        let mut repos: Vec<(String, u64)> = repos.into_iter().collect();
+        repos.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        repos
+    }
+}
+
+fn collect_all_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_all_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// Recursively collect files named `name` below `dir`; cgit repo paths can
+/// be nested arbitrarily deep.
+fn collect_files(dir: &Path, name: &str, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, name, files);
+        } else if path.file_name().is_some_and(|n| n == name) {
+            files.push(path);
+        }
     }
 }
@@ -0,0 +1,77 @@
+//! `Cache-Control` headers per response type, so a CDN in front of checkup
+//! can cache release pages, JSON, latest redirects and badges for different
+//! durations.
+
+use crate::config::CacheControlConfig;
+use anyhow::{Context, Result};
+use axum::{
+    http::{HeaderValue, header},
+    response::Response,
+};
+
+/// Whether a release page or `/+json` was served from the cache ("hit") or
+/// needed a fetch from upstream first ("miss"), as counted by `checkup bench`
+pub const CACHE_STATUS_HEADER: &str = "X-Checkup-Cache";
+
+#[derive(Debug, Clone, Copy)]
+pub enum RouteKind {
+    /// Rendered release pages
+    Html,
+    /// `/+json` and `/+check`
+    Json,
+    /// `latest*` redirects
+    Latest,
+    /// `badge.svg`
+    Badge,
+    /// Processing and fetch-error pages, which must never be cached
+    Transient,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CachePolicy {
+    html: Option<HeaderValue>,
+    json: Option<HeaderValue>,
+    latest: Option<HeaderValue>,
+    badge: Option<HeaderValue>,
+}
+
+impl CachePolicy {
+    pub fn from_config(config: &CacheControlConfig) -> Result<Self> {
+        let parse = |name: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(HeaderValue::from_str)
+                .transpose()
+                .with_context(|| format!("Invalid cache_control.{} value", name))
+        };
+        Ok(Self {
+            html: parse("html", &config.html)?,
+            json: parse("json", &config.json)?,
+            latest: parse("latest", &config.latest)?,
+            badge: parse("badge", &config.badge)?,
+        })
+    }
+
+    /// Set the configured `Cache-Control` for `kind`; responses of unconfigured
+    /// kinds are left as they are.
+    pub fn apply(&self, kind: RouteKind, mut response: Response) -> Response {
+        let value = match kind {
+            RouteKind::Html => self.html.clone(),
+            RouteKind::Json => self.json.clone(),
+            RouteKind::Latest => self.latest.clone(),
+            RouteKind::Badge => self.badge.clone(),
+            RouteKind::Transient => Some(HeaderValue::from_static("no-store")),
+        };
+        if let Some(value) = value {
+            response.headers_mut().insert(header::CACHE_CONTROL, value);
+        }
+        response
+    }
+}
+
+/// Mark `response` as served from the cache or not.
+pub fn with_cache_status(mut response: Response, hit: bool) -> Response {
+    let value = HeaderValue::from_static(if hit { "hit" } else { "miss" });
+    response.headers_mut().insert(CACHE_STATUS_HEADER, value);
+    response
+}
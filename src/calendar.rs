@@ -0,0 +1,218 @@
+//! `/calendar`: releases of the watched repos on a month grid, and all of
+//! them as an iCalendar feed at `/calendar.ics` that calendar apps can
+//! subscribe to. Both are built from the cached releases.
+//!
+//! `/{provider}/{...}/releases.ics` is the feed of a single repo, so the
+//! histories of individual projects can be overlaid in calendar clients.
+
+use crate::{
+    AppState, RepoPath,
+    error::AppError,
+    format_html,
+    provider::{CachedReleases, Release},
+};
+use axum::{
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use std::{fmt::Write, sync::Arc};
+
+/// A release as shown in calendars.
+pub struct CalendarRelease {
+    /// Route of the repo page, e.g. "/github/owner/repo"
+    pub route: String,
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub published_at: DateTime<Utc>,
+    /// When a rolling tag's assets were last rebuilt
+    pub updated_at: Option<DateTime<Utc>>,
+    pub html_url: String,
+    pub prerelease: bool,
+    pub body: Option<String>,
+}
+
+/// Every cached release of the watched repos, drafts left out.
+fn watched_releases(state: &AppState) -> Vec<CalendarRelease> {
+    let repos: Vec<(String, RepoPath)> = state
+        .refresher
+        .repos()
+        .into_iter()
+        .map(|watched| (watched.route, watched.repo))
+        .collect();
+    releases_of(state, &repos)
+}
+
+/// Every cached release of `repos`, given with their routes, drafts left
+/// out; oldest first.
+pub fn releases_of(state: &AppState, repos: &[(String, RepoPath)]) -> Vec<CalendarRelease> {
+    let mut releases = Vec::new();
+    for (route, repo) in repos {
+        let Ok(Some(cached)) =
+            state
+                .cache
+                .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        else {
+            continue;
+        };
+        releases.extend(cached.releases.into_iter().filter(|r| !r.draft).map(|r| {
+            CalendarRelease {
+                route: route.clone(),
+                tag_name: r.tag_name,
+                name: r.name,
+                published_at: r.published_at,
+                updated_at: r.updated_at,
+                html_url: r.html_url,
+                prerelease: r.prerelease,
+                body: r.body,
+            }
+        }));
+    }
+    releases.sort_by_key(|r| r.published_at);
+    releases
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    /// Month to show, e.g. "2024-03"; defaults to the current month
+    month: Option<String>,
+}
+
+pub async fn calendar_page(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CalendarQuery>,
+) -> Result<Html<String>, AppError> {
+    let month = match query.month.as_deref() {
+        Some(month) => {
+            NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").map_err(|_| {
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid month '{}', expected YYYY-MM", month),
+                )
+            })?
+        }
+        None => Utc::now().date_naive().with_day(1).unwrap_or_default(),
+    };
+    let releases: Vec<CalendarRelease> = watched_releases(&state)
+        .into_iter()
+        .filter(|r| {
+            let date = r.published_at.date_naive();
+            date.year() == month.year() && date.month() == month.month()
+        })
+        .collect();
+    Ok(Html(format_html::format_calendar_html(month, &releases)))
+}
+
+pub async fn calendar_ics(State(state): State<Arc<AppState>>) -> Response {
+    ics_response(format_ics("Watched releases", &watched_releases(&state)))
+}
+
+/// `releases.ics` of the repo at `route`, e.g. "/github/owner/repo", from
+/// its releases; drafts are left out.
+pub fn repo_ics_response(route: &str, releases: &[Release]) -> Response {
+    let releases: Vec<CalendarRelease> = releases
+        .iter()
+        .filter(|r| !r.draft)
+        .map(|r| CalendarRelease {
+            route: route.to_string(),
+            tag_name: r.tag_name.clone(),
+            name: r.name.clone(),
+            published_at: r.published_at,
+            updated_at: r.updated_at,
+            html_url: r.html_url.clone(),
+            prerelease: r.prerelease,
+            body: r.body.clone(),
+        })
+        .collect();
+    ics_response(format_ics(route.trim_start_matches('/'), &releases))
+}
+
+pub fn ics_response(ics: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        ics,
+    )
+        .into_response()
+}
+
+/// An iCalendar (RFC 5545) calendar named `name`, with each release as an
+/// all-day event on the day it was published and its notes as description.
+/// Rebuilds of rolling tags bump the event's DTSTAMP and LAST-MODIFIED, so
+/// calendar clients pick the change up.
+pub fn format_ics(name: &str, releases: &[CalendarRelease]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//checkup//releases//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+    for r in releases {
+        let repo = r.route.trim_start_matches('/');
+        let day = r.published_at.date_naive();
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}/{}@checkup", repo, r.tag_name));
+        lines.push(format!(
+            "DTSTAMP:{}",
+            r.updated_at
+                .unwrap_or(r.published_at)
+                .format("%Y%m%dT%H%M%SZ")
+        ));
+        if let Some(updated_at) = r.updated_at {
+            lines.push(format!(
+                "LAST-MODIFIED:{}",
+                updated_at.format("%Y%m%dT%H%M%SZ")
+            ));
+        }
+        lines.push(format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")));
+        lines.push(format!(
+            "DTEND;VALUE=DATE:{}",
+            (day + Duration::days(1)).format("%Y%m%d")
+        ));
+        let title = r.name.as_deref().unwrap_or(&r.tag_name);
+        let prerelease = if r.prerelease { " (pre-release)" } else { "" };
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape_text(&format!("{} {}{}", repo, title, prerelease))
+        ));
+        lines.push(format!("URL:{}", r.html_url));
+        if let Some(body) = r.body.as_deref().filter(|b| !b.trim().is_empty()) {
+            lines.push(format!("DESCRIPTION:{}", escape_text(body.trim())));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in &lines {
+        let _ = write!(ics, "{}\r\n", fold_line(line));
+    }
+    ics
+}
+
+/// Escape backslashes, separators and newlines in a TEXT value.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line into lines of at most 75 octets, continuation lines
+/// starting with a space; never splits a UTF-8 character.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
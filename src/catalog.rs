@@ -0,0 +1,89 @@
+//! Repository lists of a whole forge instance, e.g. `/cgit/{host}`, so users
+//! can find the right repo path. Lists are cached per host with the same
+//! expiry as releases and shown in pages.
+
+use crate::{
+    AppState, cache_control::RouteKind, error::AppError, format_html, quota::ClientIp, shedding,
+};
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// Repos listed per page
+pub const CATALOG_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogRepo {
+    /// Route of the repo's release page, e.g. "/cgit/git.kernel.org/pub/scm/git/git.git"
+    pub route: String,
+    /// Repo path as shown by the instance
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedCatalog {
+    pub repos: Vec<CatalogRepo>,
+    pub cached_at: DateTime<Utc>,
+}
+
+/// Answer a catalog page from the catalog cache, fetching the list with
+/// `fetch` when it is missing or expired.
+pub async fn catalog_response(
+    state: &AppState,
+    prefix: &str,
+    host: &str,
+    page: usize,
+    client: ClientIp,
+    fetch: impl Future<Output = anyhow::Result<Vec<CatalogRepo>>>,
+) -> Result<Response, AppError> {
+    let cached = match state
+        .cache
+        .read_catalog::<CachedCatalog>(prefix, host)
+        .map_err(AppError::internal)?
+    {
+        Some(cached) if !state.cache.is_expired(cached.cached_at) => cached,
+        _ => {
+            shedding::admit_fetch(state, client)?;
+            let repos = state
+                .upstream
+                .track(host, fetch)
+                .await
+                .map_err(|e| AppError::from_upstream(&e))?;
+            let cached = CachedCatalog {
+                repos,
+                cached_at: Utc::now(),
+            };
+            let _ = state.cache.write_catalog(prefix, host, &cached);
+            cached
+        }
+    };
+
+    let pages = format_html::page_count(cached.repos.len(), CATALOG_PAGE_SIZE);
+    if page > pages {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("Page {} does not exist ({} pages)", page, pages),
+        ));
+    }
+    let shown = cached
+        .repos
+        .iter()
+        .skip((page - 1) * CATALOG_PAGE_SIZE)
+        .take(CATALOG_PAGE_SIZE);
+    let html = format_html::format_catalog_html(
+        host,
+        shown,
+        cached.repos.len(),
+        page,
+        pages,
+        cached.cached_at,
+    );
+    Ok(state
+        .cache_policy
+        .apply(RouteKind::Html, Html(html).into_response()))
+}
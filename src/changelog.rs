@@ -0,0 +1,195 @@
+//! Structured sections extracted from release notes.
+//!
+//! Understands Keep-a-Changelog style headings ("### Added", "**Bug Fixes**",
+//! "Breaking changes:") and conventional-commit bullets ("feat(api)!: ...").
+//! Anything that fits neither is left out; the raw notes stay in `body`.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Changelog {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deprecated: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub security: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breaking: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Added,
+    Changed,
+    Fixed,
+    Deprecated,
+    Removed,
+    Security,
+    Breaking,
+}
+
+impl Changelog {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.changed.is_empty()
+            && self.fixed.is_empty()
+            && self.deprecated.is_empty()
+            && self.removed.is_empty()
+            && self.security.is_empty()
+            && self.breaking.is_empty()
+    }
+
+    pub fn has_breaking(&self) -> bool {
+        !self.breaking.is_empty()
+    }
+
+    pub fn has_security(&self) -> bool {
+        !self.security.is_empty()
+    }
+
+    fn push(&mut self, section: Section, entry: &str) {
+        let list = match section {
+            Section::Added => &mut self.added,
+            Section::Changed => &mut self.changed,
+            Section::Fixed => &mut self.fixed,
+            Section::Deprecated => &mut self.deprecated,
+            Section::Removed => &mut self.removed,
+            Section::Security => &mut self.security,
+            Section::Breaking => &mut self.breaking,
+        };
+        if !list.iter().any(|e| e == entry) {
+            list.push(entry.to_string());
+        }
+    }
+}
+
+/// Map a heading's text to a section, e.g. "🐛 Bug Fixes" -> Fixed.
+fn heading_section(text: &str) -> Option<Section> {
+    let text = text
+        .trim_start_matches(|c: char| !c.is_alphabetic())
+        .trim_end_matches(|c: char| c == ':' || c.is_whitespace())
+        .to_lowercase();
+    let starts = |prefixes: &[&str]| prefixes.iter().any(|p| text.starts_with(p));
+
+    if starts(&["breaking"]) {
+        Some(Section::Breaking)
+    } else if starts(&["security"]) {
+        Some(Section::Security)
+    } else if starts(&["added", "new", "feature", "enhancement"]) {
+        Some(Section::Added)
+    } else if starts(&["fixed", "fix", "bug"]) {
+        Some(Section::Fixed)
+    } else if starts(&["changed", "change", "improvement", "performance"]) {
+        Some(Section::Changed)
+    } else if starts(&["deprecated", "deprecation"]) {
+        Some(Section::Deprecated)
+    } else if starts(&["removed", "removal"]) {
+        Some(Section::Removed)
+    } else {
+        None
+    }
+}
+
+/// Heading text if the line is a Markdown (`## Foo`) or bold (`**Foo**`) heading.
+fn heading_text(line: &str) -> Option<&str> {
+    if let Some(text) = line.strip_prefix('#') {
+        return Some(text.trim_start_matches('#').trim());
+    }
+    line.strip_prefix("**")
+        .and_then(|l| l.trim_end_matches(':').strip_suffix("**"))
+        .map(str::trim)
+}
+
+/// Conventional-commit classification, e.g. "feat(cli)!: drop --foo" -> Breaking.
+fn conventional_section(entry: &str) -> Option<(Section, &str)> {
+    let (prefix, description) = entry.split_once(": ")?;
+    let breaking = prefix.ends_with('!');
+    let kind = prefix.trim_end_matches('!');
+    let kind = kind.split_once('(').map_or(kind, |(k, _)| k);
+    if !kind.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+    let section = match kind {
+        _ if breaking => Section::Breaking,
+        "feat" => Section::Added,
+        "fix" => Section::Fixed,
+        "perf" | "refactor" => Section::Changed,
+        "security" | "sec" => Section::Security,
+        "deprecate" => Section::Deprecated,
+        _ => return None,
+    };
+    Some((section, description.trim()))
+}
+
+/// Parse release notes into changelog sections.
+pub fn parse(notes: &str) -> Changelog {
+    let mut changelog = Changelog::default();
+    let mut current: Option<Section> = None;
+
+    for line in notes.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let bullet = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|b| line.strip_prefix(b))
+            .or_else(|| {
+                let (num, rest) = line.split_once(". ")?;
+                (!num.is_empty() && num.chars().all(|c| c.is_ascii_digit())).then_some(rest)
+            });
+
+        let Some(entry) = bullet.map(str::trim).filter(|e| !e.is_empty()) else {
+            if let Some(rest) = line
+                .strip_prefix("BREAKING CHANGE:")
+                .or_else(|| line.strip_prefix("BREAKING CHANGES:"))
+                .or_else(|| line.strip_prefix("BREAKING:"))
+                && !rest.trim().is_empty()
+            {
+                changelog.push(Section::Breaking, rest.trim());
+            } else if let Some(text) = heading_text(line) {
+                // Unknown headings end the previous section
+                current = heading_section(text);
+            } else if line.len() <= 40
+                && line.ends_with(':')
+                && let Some(section) = heading_section(line)
+            {
+                // Plain "Bug fixes:" style labels
+                current = Some(section);
+            }
+            continue;
+        };
+
+        let conventional = conventional_section(entry);
+        let (section, entry) = match (current, conventional) {
+            (Some(section), _) => (Some(section), entry),
+            (None, Some((section, description))) => (Some(section), description),
+            (None, None) => (None, entry),
+        };
+        if let Some(section) = section {
+            changelog.push(section, entry);
+        }
+
+        // Call-outs inside other sections
+        if matches!(conventional, Some((Section::Breaking, _)))
+            || entry.contains("BREAKING")
+            || entry.to_lowercase().contains("breaking change")
+        {
+            changelog.push(Section::Breaking, entry);
+        }
+        if entry.contains("CVE-") || entry.contains("GHSA-") {
+            changelog.push(Section::Security, entry);
+        }
+    }
+
+    changelog
+}
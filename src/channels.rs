@@ -0,0 +1,233 @@
+//! Release channels: `/{provider}/{...}/channel/{stable|beta|nightly}` lists
+//! only the releases of one channel, with `/+json` and `/releases.ics` feeds
+//! of them, and `?channel=` picks `latest*` redirects from it. Releases are
+//! put into a channel by the repo's `channels` tag patterns when it has them,
+//! else by their version's prerelease identifiers: "nightly", "dev",
+//! "snapshot" and the like are nightlies, any other identifier ("rc.1",
+//! "beta.2") or a release marked as prerelease upstream is beta.
+
+use crate::{
+    AppState, RepoPath,
+    cache_control::RouteKind,
+    calendar,
+    config::RepoConfig,
+    error::AppError,
+    format_html,
+    provider::{self, CachedReleases, Release, ReleaseQuery},
+    signing, versions,
+};
+use anyhow::{Context, Result};
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use regex::Regex;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl Channel {
+    /// In the order configured patterns are tried
+    const ALL: [Channel; 3] = [Channel::Nightly, Channel::Beta, Channel::Stable];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+            Channel::Nightly => "nightly",
+        }
+    }
+}
+
+/// What a channel route serves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelFeed {
+    Page,
+    /// `/+json`
+    Json,
+    /// `/releases.ics`
+    Calendar,
+}
+
+/// Prerelease identifiers of nightly builds; matched at the start of words,
+/// so "dev" covers "devel" too
+const NIGHTLY_WORDS: [&str; 6] = ["nightly", "dev", "snapshot", "canary", "daily", "edge"];
+
+/// The channel of a release by its version alone: the prerelease part of
+/// versions, or the whole tag when it isn't one ("nightly-2024-05-01").
+fn builtin_channel(release: &Release) -> Channel {
+    let version = release.version.as_deref().unwrap_or(&release.tag_name);
+    let label = match versions::prerelease(version) {
+        Some(pre) => Some(pre),
+        None if versions::major_version(version).is_none() => Some(release.tag_name.as_str()),
+        None => None,
+    };
+    let nightly = label.is_some_and(|label| {
+        label.split(|c: char| !c.is_ascii_alphabetic()).any(|word| {
+            let word = word.to_ascii_lowercase();
+            NIGHTLY_WORDS.iter().any(|n| word.starts_with(n))
+        })
+    });
+    if nightly {
+        Channel::Nightly
+    } else if release.prerelease || versions::prerelease(version).is_some() {
+        Channel::Beta
+    } else {
+        Channel::Stable
+    }
+}
+
+/// Channel tag patterns of every repo configured with some, by cache key.
+#[derive(Debug, Default)]
+pub struct ChannelRules {
+    by_repo: HashMap<String, Vec<(Channel, Regex)>>,
+}
+
+impl ChannelRules {
+    /// Fails on routes that don't name a repo, unknown channels and invalid
+    /// patterns.
+    pub fn from_config(repos: &[RepoConfig]) -> Result<Self> {
+        let mut by_repo = HashMap::new();
+        for entry in repos.iter().filter(|e| !e.channels.is_empty()) {
+            let (_, repo) = provider::parse_route(&entry.repo)?;
+            let mut patterns = Vec::new();
+            for (name, pattern) in &entry.channels {
+                let channel = Channel::from_name(name).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown channel {:?} for {}; use stable, beta or nightly",
+                        name,
+                        entry.repo
+                    )
+                })?;
+                let regex = Regex::new(pattern).with_context(|| {
+                    format!("Invalid {} channel pattern for {}", name, entry.repo)
+                })?;
+                patterns.push((channel, regex));
+            }
+            patterns.sort_by_key(|(channel, _)| Channel::ALL.iter().position(|c| c == channel));
+            by_repo.insert(repo.cache_key(), patterns);
+        }
+        Ok(Self { by_repo })
+    }
+
+    /// The release's channel: the first of the repo's patterns matching its
+    /// tag, nightly before beta before stable, else the built-in one.
+    pub fn classify(&self, cache_key: &str, release: &Release) -> Channel {
+        self.by_repo
+            .get(cache_key)
+            .and_then(|patterns| {
+                patterns
+                    .iter()
+                    .find(|(_, regex)| regex.is_match(&release.tag_name))
+            })
+            .map_or_else(|| builtin_channel(release), |(channel, _)| *channel)
+    }
+
+    pub fn channel_releases(
+        &self,
+        cache_key: &str,
+        channel: Channel,
+        releases: Vec<Release>,
+    ) -> Vec<Release> {
+        releases
+            .into_iter()
+            .filter(|r| self.classify(cache_key, r) == channel)
+            .collect()
+    }
+}
+
+/// The releases of `?channel=`, or all of them without it.
+pub fn query_releases(
+    state: &AppState,
+    repo: &RepoPath,
+    releases: Vec<Release>,
+    channel: Option<&str>,
+) -> Result<Vec<Release>, AppError> {
+    let Some(name) = channel else {
+        return Ok(releases);
+    };
+    let channel = Channel::from_name(name).ok_or(AppError::new(
+        StatusCode::BAD_REQUEST,
+        format!("Unknown channel '{}'; use stable, beta or nightly", name),
+    ))?;
+    Ok(state
+        .channel_rules
+        .channel_releases(&repo.cache_key(), channel, releases))
+}
+
+/// Serve a channel route with `releases`, already narrowed to `?component=`.
+/// An empty channel is still a page, so it can be bookmarked before its
+/// first release.
+pub fn channel_response(
+    state: &AppState,
+    repo: &RepoPath,
+    route_prefix: &str,
+    releases: Vec<Release>,
+    channel: Channel,
+    feed: ChannelFeed,
+    query: &ReleaseQuery,
+) -> Result<Response, AppError> {
+    let cache_key = repo.cache_key();
+    let releases = state
+        .channel_rules
+        .channel_releases(&cache_key, channel, releases);
+    let cached_at = state
+        .cache
+        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?;
+
+    match feed {
+        ChannelFeed::Page => {
+            let page = query.page.unwrap_or(1).max(1);
+            if page > format_html::page_count(releases.len(), state.render.page_size) {
+                return Err(AppError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("Page {} not found", page),
+                ));
+            }
+            let filter = format_html::PageFilter {
+                component: query.component.as_deref(),
+                lang: None,
+                channel: Some(channel),
+            };
+            let html = format_html::format_filtered_releases_html(
+                &releases,
+                &cache_key,
+                route_prefix,
+                cached_at,
+                &state.render,
+                page,
+                &filter,
+            );
+            Ok(state
+                .cache_policy
+                .apply(RouteKind::Html, Html(html).into_response()))
+        }
+        ChannelFeed::Json => {
+            let cached = CachedReleases {
+                trusted_publisher: state.publishers.trusted(&cache_key, &releases),
+                releases,
+                cached_at: cached_at.unwrap_or_else(chrono::Utc::now),
+                repo_path: cache_key,
+                provider: Some(route_prefix.to_string()),
+            };
+            let body = serde_json::to_string_pretty(&cached).map_err(AppError::internal)?;
+            let response = signing::json_response(state, StatusCode::OK, body);
+            Ok(state.cache_policy.apply(RouteKind::Json, response))
+        }
+        // Events keep the UIDs they have in the repo's own calendar
+        ChannelFeed::Calendar => Ok(calendar::repo_ics_response(
+            &provider::repo_route(route_prefix, repo),
+            &releases,
+        )),
+    }
+}
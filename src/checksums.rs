@@ -0,0 +1,121 @@
+//! Asset hashes from checksum files published alongside release assets
+//! (`SHA256SUMS`, `checksums.txt`, `app.tar.gz.sha256`, ...).
+
+use crate::{
+    debug,
+    provider::{Asset, Release, sri_from_sha256},
+};
+use reqwest::Client;
+use std::collections::HashMap;
+
+/// Checksum files larger than this are not hash lists and are skipped.
+const MAX_CHECKSUM_FILE_SIZE: u64 = 1024 * 1024;
+/// Upper bound on checksum files fetched per release.
+const MAX_CHECKSUM_FILES: usize = 10;
+
+fn is_checksum_file(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    lower.ends_with(".sha256")
+        || lower.ends_with(".sha256sum")
+        || lower.contains("sha256sums")
+        || lower.contains("checksums")
+}
+
+pub fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Drop asset hashes that aren't hex SHA-256 digests, as a federation peer
+/// may send, and derive the SRI values anew from the others.
+pub fn drop_invalid_hashes(releases: &mut [Release]) {
+    for asset in releases.iter_mut().flat_map(|r| &mut r.assets) {
+        asset.sha256 = asset
+            .sha256
+            .take()
+            .filter(|hash| is_sha256_hex(hash))
+            .map(|hash| hash.to_lowercase());
+        asset.sri = asset.sha256.as_deref().and_then(sri_from_sha256);
+    }
+}
+
+/// Parse checksum file content into filename -> hex digest.
+/// Supports GNU (`<hash>  file`, `<hash> *file`), BSD (`SHA256 (file) = <hash>`)
+/// and bare-hash sidecars, whose filename is taken from `sidecar_for`.
+fn parse_checksums(content: &str, sidecar_for: Option<&str>) -> HashMap<String, String> {
+    let mut hashes = HashMap::new();
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(rest) = line.strip_prefix("SHA256 (")
+            && let Some((file, hash)) = rest.split_once(") = ")
+        {
+            if is_sha256_hex(hash.trim()) {
+                hashes.insert(file.to_string(), hash.trim().to_lowercase());
+            }
+            continue;
+        }
+
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let hash = parts.next().unwrap_or("");
+        if !is_sha256_hex(hash) {
+            continue;
+        }
+        let file = parts.next().map(|f| f.trim().trim_start_matches('*'));
+        match (file, sidecar_for) {
+            (Some(file), _) if !file.is_empty() => {
+                // Entries may carry a path prefix ("./dist/app.tar.gz")
+                let file = file.rsplit('/').next().unwrap_or(file);
+                hashes.insert(file.to_string(), hash.to_lowercase());
+            }
+            (_, Some(target)) => {
+                hashes.insert(target.to_string(), hash.to_lowercase());
+            }
+            _ => {}
+        }
+    }
+
+    hashes
+}
+
+/// Fill `sha256`/`sri` for assets covered by checksum files in the same release.
+/// Fetch or parse failures are ignored; hashes stay unknown in that case.
+pub async fn fill_from_checksum_files(client: &Client, assets: &mut [Asset]) {
+    let checksum_files: Vec<(String, String, u64)> = assets
+        .iter()
+        .filter(|a| is_checksum_file(&a.name))
+        .take(MAX_CHECKSUM_FILES)
+        .map(|a| (a.name.clone(), a.url.clone(), a.size))
+        .collect();
+
+    let mut hashes = HashMap::new();
+    for (name, url, size) in checksum_files {
+        if size > MAX_CHECKSUM_FILE_SIZE {
+            continue;
+        }
+        debug::request("GET", &url);
+        let Ok(response) = client.get(&url).send().await else {
+            continue;
+        };
+        debug::response(&response);
+        if !response.status().is_success() {
+            continue;
+        }
+        let Ok(content) = response.text().await else {
+            continue;
+        };
+        let sidecar_for = name
+            .strip_suffix(".sha256")
+            .or_else(|| name.strip_suffix(".sha256sum"));
+        let parsed = parse_checksums(&content, sidecar_for);
+        debug::parsed(&format!("checksums in {}", name), parsed.len());
+        hashes.extend(parsed);
+    }
+
+    for asset in assets.iter_mut() {
+        if asset.sha256.is_none()
+            && let Some(hash) = hashes.get(&asset.name)
+        {
+            asset.sri = sri_from_sha256(hash);
+            asset.sha256 = Some(hash.clone());
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! `/compare-mirrors?a=<repo>&b=<repo>`: whether two mirrors of a project,
+//! possibly on different forges, carry the same tags and assets.
+
+use crate::{
+    AppState, RepoPath,
+    error::AppError,
+    format_html,
+    provider::{self, CachedReleases, Release},
+    quota::ClientIp,
+    shedding,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Html,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Debug, Deserialize)]
+pub struct CompareQuery {
+    a: Option<String>,
+    b: Option<String>,
+}
+
+pub struct MirrorComparison {
+    /// Routes of the two mirrors, e.g. "/github/owner/repo"
+    pub a: String,
+    pub b: String,
+    /// Newest first
+    pub tags: Vec<TagComparison>,
+}
+
+pub struct TagComparison {
+    pub tag: String,
+    pub published_at: DateTime<Utc>,
+    pub status: TagStatus,
+}
+
+pub enum TagStatus {
+    Same,
+    OnlyA,
+    OnlyB,
+    /// Present on both, with the asset discrepancies found
+    Differs(Vec<String>),
+}
+
+pub async fn compare_mirrors(
+    Query(query): Query<CompareQuery>,
+    client: ClientIp,
+    State(state): State<Arc<AppState>>,
+) -> Result<Html<String>, AppError> {
+    let (Some(a), Some(b)) = (query.a, query.b) else {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Use /compare-mirrors?a=<repo>&b=<repo>, e.g. a=github/owner/repo&b=codeberg.org/owner/repo",
+        ));
+    };
+    let (prefix_a, repo_a) = resolve_mirror(&a)?;
+    let (prefix_b, repo_b) = resolve_mirror(&b)?;
+
+    let releases_a = load_releases(&state, prefix_a, &repo_a, client).await?;
+    let releases_b = load_releases(&state, prefix_b, &repo_b, client).await?;
+
+    let comparison = MirrorComparison {
+        a: provider::repo_route(prefix_a, &repo_a),
+        b: provider::repo_route(prefix_b, &repo_b),
+        tags: compare(&releases_a, &releases_b),
+    };
+    Ok(Html(format_html::format_compare_html(&comparison)))
+}
+
+/// Accept anything `provider::resolve_repo` does, e.g. "github/owner/repo"
+/// or "codeberg.org/owner/repo".
+fn resolve_mirror(mirror: &str) -> Result<(&'static str, RepoPath), AppError> {
+    provider::resolve_repo(mirror)
+        .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// Cached releases of a mirror, fetched first when missing or expired.
+async fn load_releases(
+    state: &Arc<AppState>,
+    prefix: &str,
+    repo: &RepoPath,
+    client: ClientIp,
+) -> Result<Vec<Release>, AppError> {
+    let read = || {
+        state
+            .cache
+            .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+            .map_err(AppError::internal)
+    };
+
+    if let Some(cached) = read()?
+        && !state
+            .cache
+            .is_repo_expired(&repo.host, &repo.owner, &repo.repo, cached.cached_at)
+    {
+        return Ok(cached.releases);
+    }
+
+    shedding::admit_fetch(state, client)?;
+    provider::fetch_and_cache(state, prefix, repo)
+        .await
+        .map_err(|e| AppError::from_upstream(&e))?;
+
+    read()?.map(|cached| cached.releases).ok_or_else(|| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to read cached releases",
+        )
+    })
+}
+
+/// Tags are matched ignoring a leading "v"; drafts are left out.
+fn compare(a: &[Release], b: &[Release]) -> Vec<TagComparison> {
+    let key = |tag: &str| tag.strip_prefix('v').unwrap_or(tag).to_string();
+    let mut b_by_tag: HashMap<String, &Release> = b
+        .iter()
+        .filter(|r| !r.draft)
+        .map(|r| (key(&r.tag_name), r))
+        .collect();
+
+    let mut tags: Vec<TagComparison> = a
+        .iter()
+        .filter(|r| !r.draft)
+        .map(|release| {
+            let status = match b_by_tag.remove(&key(&release.tag_name)) {
+                None => TagStatus::OnlyA,
+                Some(other) => match asset_differences(release, other) {
+                    diffs if diffs.is_empty() => TagStatus::Same,
+                    diffs => TagStatus::Differs(diffs),
+                },
+            };
+            TagComparison {
+                tag: release.tag_name.clone(),
+                published_at: release.published_at,
+                status,
+            }
+        })
+        .collect();
+
+    tags.extend(b_by_tag.into_values().map(|release| TagComparison {
+        tag: release.tag_name.clone(),
+        published_at: release.published_at,
+        status: TagStatus::OnlyB,
+    }));
+    tags.sort_by_key(|t| std::cmp::Reverse(t.published_at));
+    tags
+}
+
+/// Assets missing on either side, and sizes or hashes that disagree where
+/// both mirrors know them.
+fn asset_differences(a: &Release, b: &Release) -> Vec<String> {
+    let mut diffs = Vec::new();
+
+    for asset in &a.assets {
+        let Some(other) = b.assets.iter().find(|o| o.name == asset.name) else {
+            diffs.push(format!("{} only on A", asset.name));
+            continue;
+        };
+        if asset.size > 0 && other.size > 0 && asset.size != other.size {
+            diffs.push(format!(
+                "{}: size {} on A, {} on B",
+                asset.name, asset.size, other.size
+            ));
+        }
+        if let (Some(x), Some(y)) = (&asset.sha256, &other.sha256)
+            && x != y
+        {
+            diffs.push(format!("{}: SHA-256 differs", asset.name));
+        }
+    }
+    for asset in &b.assets {
+        if !a.assets.iter().any(|o| o.name == asset.name) {
+            diffs.push(format!("{} only on B", asset.name));
+        }
+    }
+
+    diffs
+}
@@ -0,0 +1,709 @@
+//! Optional TOML configuration file (`--config`).
+//!
+//! ```toml
+//! [refresh]
+//! interval_hours = 24    # default for watched repos (defaults to --cache-hours)
+//! jitter_secs = 300      # random delay added before each refresh
+//! max_concurrent = 2     # refreshes running at once
+//!
+//! [cache_control]
+//! html = "public, max-age=300, s-maxage=3600"
+//! json = "public, max-age=300"
+//! latest = "public, max-age=60, s-maxage=600"
+//! badge = "public, max-age=300"
+//!
+//! [freshness]
+//! fresh_days = 90        # latest release younger than this: green
+//! stale_days = 365       # older than this: red; in between: yellow
+//!
+//! [notes]
+//! max_bytes = 65536      # of release notes kept on pages; the rest at /{tag}/notes
+//!
+//! [assets]
+//! hide = ["*.sha256", "*.sig", "*.sbom.json"]  # shown under "Other files"
+//! keep_releases = 3      # newest releases whose assets are pinned and patched
+//!
+//! [latest]
+//! extensions = [".AppImage.zsync"]  # kept whole, before the built-in ones
+//! version_pattern = '^v?[0-9]+\.[0-9]+(\.|$)'  # name segment taken for the version
+//! separators = ["-", "_"]  # the first one in the name splits it
+//!
+//! [catalog]
+//! forgejo_hosts = ["codeberg.org"]  # instances listed at /forgejo/{host}
+//!
+//! [providers.github]
+//! page_size = 100        # releases requested per API page
+//! max_pages = 10         # pages fetched, 4 at a time
+//! max_releases = 50      # releases kept per repo
+//! max_assets = 200       # assets kept per release
+//! timeout_secs = 20      # per upstream request
+//!
+//! [providers.cgit]
+//! timeout_secs = 60
+//! max_html_bytes = 4000000  # of the refs/tags page parsed
+//!
+//! [translate]
+//! url = "http://localhost:5000"  # LibreTranslate-compatible backend for ?lang=
+//! api_key = "..."
+//! source = "en"          # language of the notes; defaults to "auto"
+//! languages = ["de", "fr"]  # allowed ?lang= values; empty allows any
+//!
+//! [branding]
+//! name = "Example Releases"  # in page titles and the footer
+//! logo_url = "/static/logo.svg"
+//! privacy_url = "https://example.com/privacy"
+//! terms_url = "https://example.com/terms"
+//! contact = "ops@example.com"  # or a URL
+//! links = [{ label = "Status", url = "https://status.example.com" }]
+//! announcement = "Moving to releases.example.com on June 1."  # on every page
+//!
+//! [accounts]
+//! enabled = true         # local accounts with a watchlist each
+//! signup = true          # visitors may create accounts at /signup
+//! session_days = 30
+//! max_watched = 100      # repos per account
+//! admins = ["alice", "github:583231"]  # accounts or logins let into the admin API
+//!
+//! [[accounts.oidc]]
+//! name = "github"        # sign in at /login/oidc/github
+//! provider = "github"    # or "gitlab", or "generic" (the default)
+//! client_id = "..."
+//! client_secret = "..."
+//!
+//! [[accounts.oidc]]
+//! name = "sso"
+//! issuer = "https://sso.example.com/realms/main"  # discovered from here
+//! label = "Example SSO"  # on the sign-in button
+//! client_id = "checkup"
+//! client_secret = "..."
+//!
+//! [credentials."git.example.com"]
+//! token = "..."          # sent with API requests to this host
+//! scheme = "bearer"      # or "token", or "private-token" (GitLab)
+//!
+//! [[watch]]
+//! repo = "github/BurntSushi/ripgrep"
+//! interval_hours = 6
+//! category = "CLI tools"  # groups the weekly report
+//!
+//! [[watch]]
+//! repo = "forgejo/codeberg.org/forgejo/forgejo"
+//!
+//! [[repo]]
+//! repo = "github/owner/project"
+//! strip_prefixes = ["release-", "project-"]  # tried in order
+//! version_pattern = '^project_(?P<version>[0-9_]+)$'
+//! components = { cli = "cli-v", lib = "lib/" }  # tag prefix per ?component=
+//! hide_assets = ["*.pem"]  # in addition to [assets] hide
+//! keep_releases = 10       # instead of [assets] keep_releases
+//! deployed = "1.2.0"       # version in use, for drift on the dashboard
+//! images = ["ghcr.io/owner/project:{version}"]  # shown as `docker pull` commands
+//! channels = { nightly = '^nightly-', beta = '-preview' }  # tags of /channel/{name}
+//! signing_keys = ["RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3"]  # trusted publisher
+//! display_name = "Project"  # with description, icon and homepage, shown on pages
+//!
+//! [[rewrite]]
+//! pattern = '^https://github\.com/([^/]+)/([^/]+)/releases/download/(.*)$'
+//! replace = 'https://nexus.internal/repository/github/$1/$2/$3'
+//! ```
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub refresh: RefreshConfig,
+    #[serde(default)]
+    pub cache_control: CacheControlConfig,
+    #[serde(default)]
+    pub freshness: FreshnessConfig,
+    #[serde(default)]
+    pub notes: NotesConfig,
+    #[serde(default)]
+    pub deltas: DeltasConfig,
+    #[serde(default)]
+    pub ipfs: IpfsConfig,
+    #[serde(default)]
+    pub email: EmailConfig,
+    #[serde(default)]
+    pub federation: FederationConfig,
+    #[serde(default)]
+    pub assets: AssetsConfig,
+    #[serde(default)]
+    pub latest: LatestConfig,
+    #[serde(default)]
+    pub catalog: CatalogConfig,
+    #[serde(default)]
+    pub providers: ProvidersConfig,
+    #[serde(default)]
+    pub translate: TranslateConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub branding: BrandingConfig,
+    #[serde(default)]
+    pub accounts: AccountsConfig,
+    /// API credentials by host, e.g. `[credentials."git.example.com"]`
+    #[serde(default)]
+    pub credentials: HashMap<String, HostCredentials>,
+    /// Repos kept fresh by the background refresher
+    #[serde(default)]
+    pub watch: Vec<WatchEntry>,
+    /// Per-repo settings
+    #[serde(default)]
+    pub repo: Vec<RepoConfig>,
+    /// Asset URL rewrites, tried in order
+    #[serde(default)]
+    pub rewrite: Vec<RewriteConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RefreshConfig {
+    pub interval_hours: Option<i64>,
+    #[serde(default)]
+    pub jitter_secs: u64,
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        Self {
+            interval_hours: None,
+            jitter_secs: 0,
+            max_concurrent: default_max_concurrent(),
+        }
+    }
+}
+
+fn default_max_concurrent() -> usize {
+    2
+}
+
+/// `Cache-Control` values per response type; unset types get no header.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheControlConfig {
+    pub html: Option<String>,
+    pub json: Option<String>,
+    pub latest: Option<String>,
+    pub badge: Option<String>,
+}
+
+/// Age thresholds, in days, for the release freshness badges.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FreshnessConfig {
+    #[serde(default = "default_fresh_days")]
+    pub fresh_days: i64,
+    #[serde(default = "default_stale_days")]
+    pub stale_days: i64,
+}
+
+impl Default for FreshnessConfig {
+    fn default() -> Self {
+        Self {
+            fresh_days: default_fresh_days(),
+            stale_days: default_stale_days(),
+        }
+    }
+}
+
+fn default_fresh_days() -> i64 {
+    90
+}
+
+fn default_stale_days() -> i64 {
+    365
+}
+
+/// Size of release notes kept in cache.json and on release pages; longer
+/// notes are truncated and served whole by `/{tag}/notes`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotesConfig {
+    /// In bytes; 0 keeps all notes whole
+    #[serde(default = "default_max_notes_bytes")]
+    pub max_bytes: usize,
+}
+
+impl Default for NotesConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: default_max_notes_bytes(),
+        }
+    }
+}
+
+fn default_max_notes_bytes() -> usize {
+    crate::notes::DEFAULT_MAX_BYTES
+}
+
+/// How `/delta/...` patches between release assets are made, for repos
+/// with `deltas = true`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeltasConfig {
+    /// zstd binary, by path or looked up in `PATH`
+    #[serde(default = "default_zstd_command")]
+    pub command: String,
+    /// zstd compression level, 1 to 22
+    #[serde(default = "default_delta_level")]
+    pub level: u8,
+    /// Larger assets get no deltas
+    #[serde(default = "default_delta_max_asset_bytes")]
+    pub max_asset_bytes: u64,
+}
+
+impl Default for DeltasConfig {
+    fn default() -> Self {
+        Self {
+            command: default_zstd_command(),
+            level: default_delta_level(),
+            max_asset_bytes: default_delta_max_asset_bytes(),
+        }
+    }
+}
+
+fn default_zstd_command() -> String {
+    "zstd".to_string()
+}
+
+fn default_delta_level() -> u8 {
+    19
+}
+
+fn default_delta_max_asset_bytes() -> u64 {
+    512 * 1024 * 1024
+}
+
+/// Pinning of assets downloaded through the proxy to an IPFS node.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IpfsConfig {
+    /// Kubo RPC API of the node, e.g. "http://127.0.0.1:5001"; pinning is
+    /// off without it
+    pub api: Option<String>,
+    /// Gateway pinned assets are linked through on release pages
+    #[serde(default = "default_ipfs_gateway")]
+    pub gateway: String,
+}
+
+impl Default for IpfsConfig {
+    fn default() -> Self {
+        Self {
+            api: None,
+            gateway: default_ipfs_gateway(),
+        }
+    }
+}
+
+fn default_ipfs_gateway() -> String {
+    "https://ipfs.io".to_string()
+}
+
+/// Release emails to visitors subscribed to a repo page.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    /// Sender address, e.g. "Checkup <releases@example.com>"; subscriptions
+    /// are off without it
+    pub from: Option<String>,
+    /// sendmail-compatible binary mail is handed to
+    #[serde(default = "default_sendmail")]
+    pub sendmail: String,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            from: None,
+            sendmail: default_sendmail(),
+        }
+    }
+}
+
+fn default_sendmail() -> String {
+    "/usr/sbin/sendmail".to_string()
+}
+
+/// Local accounts, each with a watchlist and dashboard of its own.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Whether visitors may create accounts; otherwise only the admin API
+    /// does
+    #[serde(default)]
+    pub signup: bool,
+    /// How long a sign-in lasts
+    #[serde(default = "default_session_days")]
+    pub session_days: i64,
+    /// Repos one account may watch
+    #[serde(default = "default_max_watched")]
+    pub max_watched: usize,
+    /// Password sign-ins one client, and one username, may attempt per 15
+    /// minutes; also the sign-ups one client may attempt in that time
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Accounts, or OpenID Connect logins as "{provider}:{subject}", whose
+    /// sessions are let into the admin API like the admin token. These
+    /// names can't be taken at `/signup` or by a first login.
+    #[serde(default)]
+    pub admins: Vec<String>,
+    /// Sign-in providers, offered on `/login`
+    #[serde(default)]
+    pub oidc: Vec<OidcConfig>,
+}
+
+impl Default for AccountsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            signup: false,
+            session_days: default_session_days(),
+            max_watched: default_max_watched(),
+            max_attempts: default_max_attempts(),
+            admins: Vec::new(),
+            oidc: Vec::new(),
+        }
+    }
+}
+
+fn default_session_days() -> i64 {
+    30
+}
+
+fn default_max_watched() -> usize {
+    100
+}
+
+fn default_max_attempts() -> u32 {
+    10
+}
+
+/// An OpenID Connect provider, or GitHub's OAuth, users can sign in with.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// Identifies the provider in its URLs, `/login/oidc/{name}`, and in
+    /// the logins linked to accounts
+    pub name: String,
+    #[serde(default)]
+    pub provider: OidcProvider,
+    /// Issuer URL, whose `/.well-known/openid-configuration` is read;
+    /// required for generic providers, "https://gitlab.com" by default
+    /// for GitLab
+    pub issuer: Option<String>,
+    /// Button text on `/login`; the provider's name by default
+    pub label: Option<String>,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+// Keeps the client secret out of logs
+impl std::fmt::Debug for OidcConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcConfig")
+            .field("name", &self.name)
+            .field("provider", &self.provider)
+            .field("issuer", &self.issuer)
+            .field("client_id", &self.client_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Kind of sign-in provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OidcProvider {
+    /// Any OpenID Connect provider, configured through discovery
+    #[default]
+    Generic,
+    /// GitHub's OAuth apps, which don't speak OpenID Connect
+    Github,
+    /// OpenID Connect with GitLab's defaults
+    Gitlab,
+}
+
+/// Other instances whose watched repos are taken into this one's cache.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FederationConfig {
+    /// Base URLs of the instances, e.g. "https://releases.example.org"
+    #[serde(default)]
+    pub peers: Vec<String>,
+    /// How often each peer's `/federation/+json` is polled
+    #[serde(default = "default_federation_interval")]
+    pub interval_minutes: u64,
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            peers: Vec::new(),
+            interval_minutes: default_federation_interval(),
+        }
+    }
+}
+
+fn default_federation_interval() -> u64 {
+    15
+}
+
+/// Asset name globs collapsed into "Other files" on release pages, and
+/// how many releases' assets are stored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetsConfig {
+    #[serde(default)]
+    pub hide: Vec<String>,
+    /// Newest releases of a repo whose assets are pinned to IPFS and have
+    /// delta patches; older ones' are removed after each fetch. All when
+    /// unset
+    pub keep_releases: Option<usize>,
+}
+
+/// How asset names map to `latest` names in latest links; unset keys keep
+/// the built-in grammar.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LatestConfig {
+    /// Extensions kept whole, e.g. ".AppImage.zsync"
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Regex matched against each segment of a name to find the version
+    pub version_pattern: Option<String>,
+    /// Separators of name segments, in order of preference
+    pub separators: Option<Vec<String>>,
+}
+
+/// Forge instances whose repository list may be shown.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogConfig {
+    #[serde(default)]
+    pub forgejo_hosts: Vec<String>,
+}
+
+/// Request shaping per provider.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProvidersConfig {
+    #[serde(default)]
+    pub github: ProviderLimits,
+    #[serde(default)]
+    pub gitlab: ProviderLimits,
+    #[serde(default)]
+    pub forgejo: ProviderLimits,
+    #[serde(default)]
+    pub sourcehut: ProviderLimits,
+    #[serde(default)]
+    pub launchpad: ProviderLimits,
+    #[serde(default)]
+    pub snap: ProviderLimits,
+    #[serde(default)]
+    pub huggingface: ProviderLimits,
+    #[serde(default)]
+    pub pagure: ProviderLimits,
+    #[serde(default)]
+    pub gitiles: ProviderLimits,
+    #[serde(default)]
+    pub hg: ProviderLimits,
+    #[serde(default)]
+    pub cgit: ProviderLimits,
+    #[serde(default)]
+    pub autoindex: ProviderLimits,
+}
+
+/// Limits on what is requested from a provider and kept of its answers;
+/// unset limits leave the upstream defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderLimits {
+    /// Releases requested per API page (`per_page`, or `limit` on Forgejo)
+    pub page_size: Option<u32>,
+    /// Newest releases kept per repo
+    pub max_releases: Option<usize>,
+    /// Pages fetched at most of GitHub releases, and of GitHub, GitLab and
+    /// Forgejo tags, which page by `Link` header
+    pub max_pages: Option<usize>,
+    /// Assets kept per release
+    pub max_assets: Option<usize>,
+    /// Timeout of each upstream request
+    pub timeout_secs: Option<u64>,
+    /// Bytes of an HTML page parsed at most (cgit)
+    pub max_html_bytes: Option<usize>,
+}
+
+/// Token sent with API requests to one GitLab or Forgejo host.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostCredentials {
+    pub token: String,
+    #[serde(default)]
+    pub scheme: AuthScheme,
+}
+
+// Keeps the token out of logs
+impl std::fmt::Debug for HostCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostCredentials")
+            .field("scheme", &self.scheme)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How a token is sent.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthScheme {
+    /// `Authorization: Bearer {token}`; GitLab and Forgejo both accept it
+    #[default]
+    Bearer,
+    /// `Authorization: token {token}`, as in Gitea's documentation
+    Token,
+    /// GitLab's `PRIVATE-TOKEN: {token}` header
+    PrivateToken,
+}
+
+/// Backend translating release notes for `?lang=`; disabled without `url`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TranslateConfig {
+    /// Base URL of a LibreTranslate-compatible API
+    pub url: Option<String>,
+    pub api_key: Option<String>,
+    /// Language the notes are written in; "auto" when unset
+    pub source: Option<String>,
+    /// Target languages allowed in `?lang=`; any when empty
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+/// Opt-in reporting of aggregate usage statistics; nothing is sent without
+/// `endpoint`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// URL the statistics are POSTed to as JSON
+    pub endpoint: Option<String>,
+    #[serde(default = "default_telemetry_interval")]
+    pub interval_hours: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            interval_hours: default_telemetry_interval(),
+        }
+    }
+}
+
+fn default_telemetry_interval() -> u64 {
+    24
+}
+
+/// Who runs the instance and on what terms, shown on every HTML page.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrandingConfig {
+    /// Instance name, in page titles and the footer instead of "Checkup"
+    pub name: Option<String>,
+    /// Image shown in the footer, next to the name
+    pub logo_url: Option<String>,
+    pub privacy_url: Option<String>,
+    pub terms_url: Option<String>,
+    /// How to reach the operator: an email address or a URL
+    pub contact: Option<String>,
+    /// More footer links, in order
+    #[serde(default)]
+    pub links: Vec<FooterLink>,
+    /// Banner at the top of every page
+    pub announcement: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FooterLink {
+    pub label: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchEntry {
+    /// Repo route as used in URLs, e.g. "github/owner/repo"
+    pub repo: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_hours: Option<i64>,
+    /// Section of the weekly report the repo is listed under
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Repo route as used in URLs, e.g. "github/owner/repo"
+    pub repo: String,
+    /// Tag prefixes stripped to get the version, e.g. "release-"
+    #[serde(default)]
+    pub strip_prefixes: Vec<String>,
+    /// Regex matching the version in tags, in a `version` group or the first group
+    pub version_pattern: Option<String>,
+    /// Tag prefix of each monorepo component, e.g. cli = "cli-v"
+    #[serde(default)]
+    pub components: HashMap<String, String>,
+    /// Asset name globs hidden on this repo's pages, e.g. "*.pem"
+    #[serde(default)]
+    pub hide_assets: Vec<String>,
+    /// Overrides `[assets] keep_releases` for this repo
+    pub keep_releases: Option<usize>,
+    /// Version currently deployed, compared with the latest release
+    pub deployed: Option<String>,
+    /// Container images published with releases, e.g. "ghcr.io/owner/project:{version}"
+    #[serde(default)]
+    pub images: Vec<String>,
+    /// Tag regex of each release channel, e.g. nightly = "^nightly-"
+    #[serde(default)]
+    pub channels: HashMap<String, String>,
+    /// Serve binary deltas between consecutive releases' assets at `/delta/...`
+    #[serde(default)]
+    pub deltas: bool,
+    /// Keys releases are expected to be signed with: minisign public keys or
+    /// "ssh-ed25519 ..." lines
+    #[serde(default)]
+    pub signing_keys: Vec<String>,
+    /// Name shown instead of the repo path on its page and the dashboard
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    /// URL of an image shown next to the name
+    pub icon: Option<String>,
+    /// Project website, linked from the repo's page
+    pub homepage: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteConfig {
+    /// Regex matched against asset URLs
+    pub pattern: String,
+    /// Replacement URL, with `$1` or `${name}` for captured groups
+    pub replace: String,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {:?}", path))?;
+        toml::from_str(&content).with_context(|| format!("Invalid config file {:?}", path))
+    }
+}
@@ -0,0 +1,157 @@
+//! `/dashboard`: latest cached release of every watched repo, and of those
+//! taken from federation peers, installable as a Progressive Web App
+//! (manifest, icons and a service worker for offline use). Signed-in users
+//! (see `accounts.rs`) see their own watchlist instead.
+
+use crate::{
+    AppState, RepoPath, accounts, display::RepoDisplay, drift, format_html, oidc,
+    provider::CachedReleases,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, header},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+pub struct DashboardEntry {
+    /// Route of the repo page, e.g. "/github/owner/repo"
+    pub route: String,
+    pub latest_tag: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub cached_at: Option<DateTime<Utc>>,
+    pub pending: bool,
+    pub error: Option<String>,
+    /// How far the deployed version is behind, when one is set
+    pub drift: Option<drift::Drift>,
+    /// Federation peer the repo is taken from, for repos not watched here
+    pub peer: Option<String>,
+    /// Name, description and icon set for the repo
+    pub display: Option<RepoDisplay>,
+}
+
+fn entry(state: &AppState, route: &str, repo: &RepoPath, peer: Option<&str>) -> DashboardEntry {
+    let cache_key = repo.cache_key();
+    let cached = state
+        .cache
+        .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        .ok()
+        .flatten();
+    let latest = cached
+        .as_ref()
+        .and_then(|c| c.releases.iter().find(|r| !r.draft));
+    DashboardEntry {
+        route: route.to_string(),
+        latest_tag: latest.map(|r| r.tag_name.clone()),
+        published_at: latest.map(|r| r.published_at),
+        cached_at: cached.as_ref().map(|c| c.cached_at),
+        pending: state.fetches.is_pending(&cache_key),
+        error: state.fetches.failure(&cache_key),
+        drift: state
+            .deployments
+            .get(&cache_key)
+            .map(|d| drift::drift(state, &d)),
+        peer: peer.map(String::from),
+        display: state.render.displays.get(&cache_key).cloned(),
+    }
+}
+
+/// Who the dashboard is shown to.
+pub enum Viewer {
+    /// The instance's watchlist; `sign_in` links to `/login`
+    Visitor { sign_in: bool },
+    /// A signed-in user's own watchlist; `unlinked` are the sign-in
+    /// providers (name and label) it could link a login of
+    User {
+        username: String,
+        feed_url: String,
+        unlinked: Vec<(String, String)>,
+    },
+}
+
+/// Watched repos, then those taken from federation peers; for signed-in
+/// users, their watchlist.
+pub async fn dashboard_page(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    if let Some(accounts) = &state.accounts
+        && let Some(account) = accounts.user(&headers)
+    {
+        let entries: Vec<DashboardEntry> = accounts::watched_repos(&account)
+            .iter()
+            .map(|(route, repo)| entry(&state, route, repo, None))
+            .collect();
+        let viewer = Viewer::User {
+            feed_url: accounts.feed_url(&account),
+            unlinked: oidc::unlinked(&accounts.oidc, &account),
+            username: account.username,
+        };
+        return (
+            // Not for shared caches; the service worker still keeps it
+            [(header::CACHE_CONTROL, "private, no-cache")],
+            Html(format_html::format_dashboard_html(
+                &entries,
+                &state.render.freshness,
+                &viewer,
+            )),
+        )
+            .into_response();
+    }
+
+    let mut entries: Vec<DashboardEntry> = state
+        .refresher
+        .repos()
+        .iter()
+        .map(|watched| entry(&state, &watched.route, &watched.repo, None))
+        .collect();
+    if let Some(federation) = &state.federation {
+        entries.extend(federation.repos().iter().map(|federated| {
+            entry(
+                &state,
+                &federated.route,
+                &federated.repo,
+                Some(&federated.peer),
+            )
+        }));
+    }
+
+    let viewer = Viewer::Visitor {
+        sign_in: state.accounts.is_some(),
+    };
+    Html(format_html::format_dashboard_html(
+        &entries,
+        &state.render.freshness,
+        &viewer,
+    ))
+    .into_response()
+}
+
+pub async fn manifest() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/manifest+json")],
+        include_str!("pwa/manifest.webmanifest"),
+    )
+}
+
+pub async fn service_worker() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "text/javascript"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        include_str!("pwa/sw.js"),
+    )
+}
+
+pub async fn icon() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "image/svg+xml")],
+        include_str!("pwa/icon.svg"),
+    )
+}
+
+pub async fn maskable_icon() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "image/svg+xml")],
+        include_str!("pwa/icon-maskable.svg"),
+    )
+}
@@ -0,0 +1,175 @@
+//! `/debug/{provider}/{...}` (admin only): a live fetch of a repo's releases
+//! that returns each step taken, URLs called, statuses and headers, and how
+//! much was parsed, as JSON. Nothing is cached.
+//!
+//! Providers report steps with [`request`], [`response`] and [`parsed`], which
+//! do nothing outside of a traced fetch.
+//!
+//! `/debug/rename?name=` (open to all) shows how an asset name maps to its
+//! `latest` name under the `[latest]` grammar.
+
+use crate::{
+    AppState,
+    admin::require_admin,
+    error::{ApiError, AppError},
+    provider,
+    signing::json_response,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::BTreeMap, sync::Arc, time::Instant};
+
+tokio::task_local! {
+    static TRACE: RefCell<Trace>;
+}
+
+struct Trace {
+    started: Instant,
+    steps: Vec<Step>,
+}
+
+#[derive(Serialize)]
+struct Step {
+    elapsed_ms: u64,
+    #[serde(flatten)]
+    kind: StepKind,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+enum StepKind {
+    Request {
+        method: String,
+        url: String,
+    },
+    Response {
+        url: String,
+        status: u16,
+        headers: BTreeMap<String, String>,
+    },
+    Parsed {
+        what: String,
+        count: usize,
+    },
+}
+
+fn push(kind: StepKind) {
+    let _ = TRACE.try_with(|trace| {
+        let mut trace = trace.borrow_mut();
+        let elapsed_ms = trace.started.elapsed().as_millis() as u64;
+        trace.steps.push(Step { elapsed_ms, kind });
+    });
+}
+
+/// Record a request about to be sent.
+pub fn request(method: &str, url: &str) {
+    push(StepKind::Request {
+        method: method.to_string(),
+        url: url.to_string(),
+    });
+}
+
+/// Record the status and headers of an upstream response.
+pub fn response(response: &reqwest::Response) {
+    if TRACE.try_with(|_| ()).is_err() {
+        return;
+    }
+    let headers = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| *name != reqwest::header::SET_COOKIE)
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+    push(StepKind::Response {
+        url: response.url().to_string(),
+        status: response.status().as_u16(),
+        headers,
+    });
+}
+
+/// Record how many items were parsed from a response, e.g. ("releases", 12).
+pub fn parsed(what: &str, count: usize) {
+    push(StepKind::Parsed {
+        what: what.to_string(),
+        count,
+    });
+}
+
+#[derive(Serialize)]
+struct DebugReport {
+    provider: &'static str,
+    repo_path: String,
+    ok: bool,
+    error: Option<String>,
+    elapsed_ms: u64,
+    releases: usize,
+    assets: usize,
+    steps: Vec<Step>,
+}
+
+pub async fn debug_handler(
+    Path(path): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let (prefix, repo) = provider::parse_route(&path).map_err(|e| AppError::from_route(&e))?;
+
+    let started = Instant::now();
+    let trace = RefCell::new(Trace {
+        started,
+        steps: Vec::new(),
+    });
+    let (result, steps) = TRACE
+        .scope(trace, async {
+            let result = provider::fetch_releases(&state, prefix, &repo).await;
+            (
+                result,
+                TRACE.with(|t| std::mem::take(&mut t.borrow_mut().steps)),
+            )
+        })
+        .await;
+
+    let releases = result.as_deref().unwrap_or_default();
+    let report = DebugReport {
+        provider: prefix,
+        repo_path: repo.cache_key(),
+        ok: result.is_ok(),
+        error: result.as_ref().err().map(|e| e.to_string()),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        releases: releases.len(),
+        assets: releases.iter().map(|r| r.assets.len()).sum(),
+        steps,
+    };
+    let body = serde_json::to_string_pretty(&report).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
+
+#[derive(Deserialize)]
+pub struct RenameQuery {
+    name: Option<String>,
+}
+
+pub async fn rename_handler(
+    Query(query): Query<RenameQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    let name = query.name.filter(|n| !n.is_empty()).ok_or_else(|| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Missing ?name=, e.g. ?name=tool-v1.2.0-x86_64-linux.tar.gz",
+        )
+    })?;
+    let rename = state.render.latest_names.explain(&name);
+    let body = serde_json::to_string_pretty(&rename).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
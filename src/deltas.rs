@@ -0,0 +1,413 @@
+//! `/delta/{provider}/{...}/{tag}/{asset}`: a zstd patch that turns the
+//! matching asset of an earlier release into this one, for repos configured
+//! with `deltas = true`. Clients holding the earlier file download the patch
+//! instead of the whole asset and apply it with
+//! `zstd -d --long=31 --patch-from=<earlier file> <patch> -o <asset>`.
+//!
+//! The earlier release is `?from={tag}`, else the next older one with a
+//! matching asset. Assets match by their `latest` name, so
+//! "app-1.1.0-linux.tar.gz" is the base of "app-1.2.0-linux.tar.gz". Both
+//! files are downloaded once to make the patch, which is kept in the repo's
+//! cache directory, and their hashes are recorded as the proxy records them.
+//! With `keep_releases` (`retention.rs`), patches are only made to assets of
+//! the newest releases, and removed once their release is older.
+
+use crate::{
+    AppState, RepoPath,
+    config::{Config, DeltasConfig},
+    error::AppError,
+    provider::{self, Asset, CachedReleases, Release},
+    proxy, routes,
+};
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::Response,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{collections::HashSet, path::Path as FsPath, sync::Arc};
+use tokio::{io::AsyncWriteExt, sync::Semaphore};
+
+pub struct Deltas {
+    config: DeltasConfig,
+    /// Cache keys of the repos with deltas
+    repos: HashSet<String>,
+    /// Patches are made one at a time; zstd at high levels takes a whole
+    /// core and memory in proportion to the files
+    making: Semaphore,
+}
+
+impl Deltas {
+    /// Fails on `deltas = true` for routes that don't name a repo.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let repos = config
+            .repo
+            .iter()
+            .filter(|entry| entry.deltas)
+            .map(|entry| Ok(provider::parse_route(&entry.repo)?.1.cache_key()))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            config: config.deltas.clone(),
+            repos,
+            making: Semaphore::new(1),
+        })
+    }
+
+    /// Whether any repo has deltas
+    pub fn enabled(&self) -> bool {
+        !self.repos.is_empty()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeltaQuery {
+    /// Tag of the release to patch from
+    from: Option<String>,
+}
+
+pub async fn delta_handler(
+    Path(path): Path<String>,
+    Query(query): Query<DeltaQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, AppError> {
+    let invalid = || {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Use /delta/{provider}/{...}/{tag}/{asset}",
+        )
+    };
+    let (prefix, path) = provider::split_prefix(&path).map_err(|e| AppError::from_route(&e))?;
+    // The split whose repo has deltas and a release of that tag, else the one
+    // with no slash in the tag, for the errors below
+    let candidates = routes::split_tag_file(prefix, path);
+    let (repo, tag, asset_name) = candidates
+        .iter()
+        .find(|(repo, tag, _)| {
+            state.deltas.repos.contains(&repo.cache_key())
+                && state
+                    .cache
+                    .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|cached| cached.releases.iter().any(|r| r.tag_name == *tag))
+        })
+        .or(candidates.first())
+        .cloned()
+        .ok_or_else(invalid)?;
+    if !state.deltas.repos.contains(&repo.cache_key()) {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!(
+                "Deltas are not enabled for {}",
+                provider::repo_route(prefix, &repo)
+            ),
+        ));
+    }
+
+    let cached = state
+        .cache
+        .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Repository is not cached; open {} first",
+                    provider::repo_route(prefix, &repo)
+                ),
+            )
+        })?;
+    let index = cached
+        .releases
+        .iter()
+        .position(|r| r.tag_name == tag)
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                format!("No release tagged '{}' found", tag),
+            )
+        })?;
+    if !state
+        .retention
+        .keeps(&repo.cache_key(), &cached.releases, index)
+    {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!(
+                "Deltas are only made for the newest {} releases",
+                state.retention.keep(&repo.cache_key()).unwrap_or_default()
+            ),
+        ));
+    }
+    let target = cached.releases[index]
+        .assets
+        .iter()
+        .find(|a| a.name == asset_name)
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                format!("No asset '{}' in release '{}'", asset_name, tag),
+            )
+        })?;
+    let (base_tag, base) = base_asset(&state, &cached.releases, index, target, query.from)?;
+
+    let max_bytes = state.deltas.config.max_asset_bytes;
+    if let Some(large) = [base, target].into_iter().find(|a| a.size > max_bytes) {
+        return Err(too_large(large, max_bytes));
+    }
+
+    let dir = state.cache.deltas_dir(&repo.host, &repo.owner, &repo.repo);
+    let patch = dir.join(format!("{}.zst", patch_key(base, target)));
+    if !patch.exists() {
+        state.maintenance.check("making patches")?;
+        make_patch(&state, prefix, &repo, base, target, &patch).await?;
+    }
+
+    let file = tokio::fs::File::open(&patch)
+        .await
+        .map_err(AppError::internal)?;
+    let len = file.metadata().await.map_err(AppError::internal)?.len();
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/zstd")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}.zst\"",
+                asset_name.replace('"', "")
+            ),
+        )
+        .header(header::CONTENT_LENGTH, len)
+        .header("X-Delta-From", format!("{}/{}", base_tag, base.name))
+        .body(Body::from_stream(provider::file_chunks(file)))
+        .map_err(AppError::internal)
+}
+
+/// The asset of `from`, or of the next older release, matching `target` of
+/// `releases[index]`, with its release's tag.
+fn base_asset<'a>(
+    state: &AppState,
+    releases: &'a [Release],
+    index: usize,
+    target: &Asset,
+    from: Option<String>,
+) -> Result<(&'a str, &'a Asset), AppError> {
+    let names = &state.render.latest_names;
+    let latest = names.rename(&target.name);
+    let matching = |release: &'a Release| {
+        release
+            .assets
+            .iter()
+            .find(|a| names.rename(&a.name) == latest)
+            .map(|a| (release.tag_name.as_str(), a))
+    };
+    match from {
+        Some(from) => {
+            let release = releases
+                .iter()
+                .find(|r| r.tag_name == from)
+                .ok_or_else(|| {
+                    AppError::new(
+                        StatusCode::NOT_FOUND,
+                        format!("No release tagged '{}' found", from),
+                    )
+                })?;
+            matching(release).ok_or_else(|| {
+                AppError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("Release '{}' has no asset matching '{}'", from, target.name),
+                )
+            })
+        }
+        None => releases[index + 1..]
+            .iter()
+            .filter(|r| !r.draft)
+            .find_map(matching)
+            .ok_or_else(|| {
+                AppError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("No earlier release has an asset matching '{}'", target.name),
+                )
+            }),
+    }
+}
+
+/// Remove the repo's patches to assets of releases not in `kept`
+/// (`retention.rs`).
+pub fn remove_patches(
+    state: &AppState,
+    repo: &RepoPath,
+    releases: &[Release],
+    kept: &HashSet<&str>,
+) -> Result<()> {
+    let dir = state.cache.deltas_dir(&repo.host, &repo.owner, &repo.repo);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(());
+    };
+    let assets: Vec<&Asset> = releases.iter().flat_map(|r| &r.assets).collect();
+    let wanted: HashSet<String> = releases
+        .iter()
+        .filter(|r| kept.contains(r.tag_name.as_str()))
+        .flat_map(|r| &r.assets)
+        .flat_map(|target| assets.iter().map(move |base| patch_key(base, target)))
+        .collect();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let Some(key) = name.to_str().and_then(|n| n.strip_suffix(".zst")) else {
+            continue;
+        };
+        if !wanted.contains(key) {
+            state.cache.remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Name of the patch between two files: a hash of their URLs, as tags may
+/// hold characters file names can't.
+fn patch_key(base: &Asset, target: &Asset) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(base.url.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(target.url.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn too_large(asset: &Asset, max_bytes: u64) -> AppError {
+    AppError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        format!(
+            "'{}' is larger than the {} MB deltas are made for; download it whole instead",
+            asset.name,
+            max_bytes / (1024 * 1024)
+        ),
+    )
+}
+
+/// Download both files next to `patch` and diff them into it. The downloads
+/// are removed afterwards, whether the patch was made or not.
+async fn make_patch(
+    state: &Arc<AppState>,
+    prefix: &'static str,
+    repo: &RepoPath,
+    base: &Asset,
+    target: &Asset,
+    patch: &FsPath,
+) -> Result<(), AppError> {
+    let _permit = state
+        .deltas
+        .making
+        .acquire()
+        .await
+        .map_err(AppError::internal)?;
+    // Made by another request while this one waited
+    if patch.exists() {
+        return Ok(());
+    }
+    let dir = patch
+        .parent()
+        .ok_or_else(|| AppError::internal("Patch without a directory"))?;
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(AppError::internal)?;
+    let name = patch.file_name().unwrap_or_default().to_string_lossy();
+    let base_file = dir.join(format!(".{}.base", name));
+    let target_file = dir.join(format!(".{}.target", name));
+    let temp = dir.join(format!(".{}.tmp", name));
+
+    let made = async {
+        download(state, prefix, repo, base, &base_file).await?;
+        download(state, prefix, repo, target, &target_file).await?;
+        run_zstd(&state.deltas.config, &base_file, &target_file, &temp).await?;
+        tokio::fs::rename(&temp, patch)
+            .await
+            .map_err(AppError::internal)
+    }
+    .await;
+    for file in [&base_file, &target_file, &temp] {
+        let _ = tokio::fs::remove_file(file).await;
+    }
+    made?;
+    state.cache.track_file(patch);
+    Ok(())
+}
+
+/// Download an asset to `to`, recording its hash once it is whole. A file
+/// that doesn't match the asset's known hash makes no patch.
+async fn download(
+    state: &Arc<AppState>,
+    prefix: &'static str,
+    repo: &RepoPath,
+    asset: &Asset,
+    to: &FsPath,
+) -> Result<(), AppError> {
+    let bad_gateway = |e: reqwest::Error| AppError::new(StatusCode::BAD_GATEWAY, e.to_string());
+    let mut upstream = state
+        .client
+        .get(&asset.url)
+        .send()
+        .await
+        .map_err(bad_gateway)?;
+    if !upstream.status().is_success() {
+        return Err(AppError::new(
+            StatusCode::BAD_GATEWAY,
+            format!("Upstream returned {} for {}", upstream.status(), asset.url),
+        ));
+    }
+
+    let max_bytes = state.deltas.config.max_asset_bytes;
+    let mut file = tokio::fs::File::create(to)
+        .await
+        .map_err(AppError::internal)?;
+    let mut hasher = Sha256::new();
+    let mut len = 0u64;
+    while let Some(chunk) = upstream.chunk().await.map_err(bad_gateway)? {
+        len += chunk.len() as u64;
+        // Sizes aren't known upfront on every forge
+        if len > max_bytes {
+            return Err(too_large(asset, max_bytes));
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk).await.map_err(AppError::internal)?;
+    }
+    file.flush().await.map_err(AppError::internal)?;
+
+    let sha256 = format!("{:x}", hasher.finalize());
+    proxy::check_sha256(&asset.url, asset.sha256.as_deref(), &sha256)
+        .map_err(|e| AppError::new(StatusCode::BAD_GATEWAY, e))?;
+    proxy::record_hash(state, prefix, repo, &asset.url, &sha256);
+    Ok(())
+}
+
+async fn run_zstd(
+    config: &DeltasConfig,
+    base: &FsPath,
+    target: &FsPath,
+    out: &FsPath,
+) -> Result<(), AppError> {
+    let level = config.level.clamp(1, 22);
+    let mut command = tokio::process::Command::new(&config.command);
+    command.arg("-q").arg("-f").arg(format!("-{}", level));
+    if level > 19 {
+        command.arg("--ultra");
+    }
+    let output = command
+        .arg(format!("--patch-from={}", base.display()))
+        .arg(target)
+        .arg("-o")
+        .arg(out)
+        .output()
+        .await
+        .map_err(|e| AppError::internal(format!("Running {} failed: {}", config.command, e)))?;
+    if !output.status.success() {
+        return Err(AppError::internal(format!(
+            "{} failed: {}",
+            config.command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! How repos are presented on their release page and the dashboard.
+//! `display_name`, `description`, `icon` and `homepage` on a `[[repo]]` entry
+//! replace what the upstream path says, for repos whose names are cryptic or
+//! forks gathered under a friendlier label.
+
+use crate::{config::RepoConfig, provider};
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Link targets allowed for icons and homepages
+const URL_PREFIXES: [&str; 3] = ["https://", "http://", "/"];
+
+#[derive(Debug, Clone, Default)]
+pub struct RepoDisplay {
+    /// Shown instead of the repo path
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// URL of an image shown next to the name
+    pub icon: Option<String>,
+    /// Project website, linked from the page
+    pub homepage: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RepoDisplays {
+    /// By cache key
+    by_repo: HashMap<String, RepoDisplay>,
+}
+
+fn check_url(repo: &str, url: &str) -> Result<()> {
+    if !URL_PREFIXES.iter().any(|prefix| url.starts_with(prefix)) {
+        anyhow::bail!(
+            "Invalid URL {:?} for {}: use an http(s) URL or a path",
+            url,
+            repo
+        );
+    }
+    Ok(())
+}
+
+impl RepoDisplays {
+    /// Fails on routes that don't name a repo and on icons and homepages
+    /// that aren't http(s) URLs or paths.
+    pub fn from_config(repos: &[RepoConfig]) -> Result<Self> {
+        let mut by_repo = HashMap::new();
+        for entry in repos {
+            let display = RepoDisplay {
+                name: entry.display_name.clone(),
+                description: entry.description.clone(),
+                icon: entry.icon.clone(),
+                homepage: entry.homepage.clone(),
+            };
+            if display.name.is_none()
+                && display.description.is_none()
+                && display.icon.is_none()
+                && display.homepage.is_none()
+            {
+                continue;
+            }
+            for url in display.icon.iter().chain(&display.homepage) {
+                check_url(&entry.repo, url)?;
+            }
+            let (_, repo) = provider::parse_route(&entry.repo)?;
+            by_repo.insert(repo.cache_key(), display);
+        }
+        Ok(Self { by_repo })
+    }
+
+    pub fn get(&self, cache_key: &str) -> Option<&RepoDisplay> {
+        self.by_repo.get(cache_key)
+    }
+}
@@ -0,0 +1,244 @@
+//! Deployment drift: which version of a repo is deployed, set with `deployed`
+//! on `[[repo]]` config entries or registered at `/deployments/{...}`, and how
+//! far it is behind the latest cached release. Drift is shown on the
+//! dashboard, in the weekly report and at `/deployments/+json`.
+//!
+//! Registered versions are kept in deployments.json and take precedence over
+//! the config; removing one falls back to the configured version, if any.
+
+use crate::{
+    AppState,
+    admin::require_admin,
+    cache::CacheManager,
+    config::RepoConfig,
+    error::{ApiError, AppError},
+    provider,
+    provider::CachedReleases,
+    signing::json_response,
+    versions,
+};
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashMap, sync::Arc};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deployment {
+    /// Repo route as used in URLs, e.g. "github/owner/repo"
+    pub route: String,
+    pub version: String,
+}
+
+pub struct Deployments {
+    /// From `[[repo]]` entries, by cache key
+    configured: HashMap<String, Deployment>,
+    /// Registered through the API, by cache key
+    registered: DashMap<String, Deployment>,
+    cache: CacheManager,
+}
+
+impl Deployments {
+    /// Fails on routes that don't name a repo and on an unreadable
+    /// deployments.json.
+    pub fn load(repos: &[RepoConfig], cache: CacheManager) -> Result<Self> {
+        let mut configured = HashMap::new();
+        for entry in repos {
+            let Some(version) = &entry.deployed else {
+                continue;
+            };
+            let (_, repo) = provider::parse_route(&entry.repo)?;
+            configured.insert(
+                repo.cache_key(),
+                Deployment {
+                    route: entry.repo.trim_matches('/').to_string(),
+                    version: version.clone(),
+                },
+            );
+        }
+        let registered = DashMap::new();
+        for deployment in cache.read_deployments::<Vec<Deployment>>()? {
+            let (_, repo) = provider::parse_route(&deployment.route)?;
+            registered.insert(repo.cache_key(), deployment);
+        }
+        Ok(Self {
+            configured,
+            registered,
+            cache,
+        })
+    }
+
+    pub fn get(&self, cache_key: &str) -> Option<Deployment> {
+        self.registered
+            .get(cache_key)
+            .map(|d| d.clone())
+            .or_else(|| self.configured.get(cache_key).cloned())
+    }
+
+    /// Every deployment, sorted by route.
+    pub fn all(&self) -> Vec<Deployment> {
+        let mut all: HashMap<String, Deployment> = self.configured.clone();
+        for entry in self.registered.iter() {
+            all.insert(entry.key().clone(), entry.value().clone());
+        }
+        let mut all: Vec<Deployment> = all.into_values().collect();
+        all.sort_by(|a, b| a.route.cmp(&b.route));
+        all
+    }
+
+    fn register(&self, cache_key: String, deployment: Deployment) -> Result<()> {
+        self.registered.insert(cache_key, deployment);
+        self.save()
+    }
+
+    fn remove(&self, cache_key: &str) -> Result<bool> {
+        let removed = self.registered.remove(cache_key).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self) -> Result<()> {
+        let mut registered: Vec<Deployment> =
+            self.registered.iter().map(|d| d.value().clone()).collect();
+        registered.sort_by(|a, b| a.route.cmp(&b.route));
+        self.cache.write_deployments(&registered)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Drift {
+    pub route: String,
+    pub deployed: String,
+    /// Latest stable release's tag; none while the repo is not cached
+    pub latest: Option<String>,
+    pub up_to_date: bool,
+    /// Stable releases newer than the deployed version
+    pub releases_behind: usize,
+    /// Days since the first of those releases was published
+    pub days_behind: Option<i64>,
+}
+
+/// Drift of a deployment against a repo's cached releases (newest first).
+/// Versions are compared after the repo's tag rules are applied; drafts and
+/// prereleases don't count.
+pub fn drift(state: &AppState, deployment: &Deployment) -> Drift {
+    let uncached = Drift {
+        route: deployment.route.clone(),
+        deployed: deployment.version.clone(),
+        latest: None,
+        up_to_date: false,
+        releases_behind: 0,
+        days_behind: None,
+    };
+    let Ok((_, repo)) = provider::parse_route(&deployment.route) else {
+        return uncached;
+    };
+    let Ok(Some(cached)) =
+        state
+            .cache
+            .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+    else {
+        return uncached;
+    };
+    let rules = state.tag_rules.for_repo(&repo.cache_key());
+    let deployed = rules.normalize(&deployment.version);
+
+    let stable: Vec<_> = cached
+        .releases
+        .iter()
+        .filter(|r| !r.draft && !r.prerelease)
+        .collect();
+    let newer: Vec<_> = stable
+        .iter()
+        .filter(|r| {
+            let version = r
+                .version
+                .clone()
+                .unwrap_or_else(|| rules.normalize(&r.tag_name));
+            versions::compare_versions(&version, &deployed) == Ordering::Greater
+        })
+        .collect();
+    let oldest_newer: Option<DateTime<Utc>> = newer.iter().map(|r| r.published_at).min();
+
+    Drift {
+        latest: stable.first().map(|r| r.tag_name.clone()),
+        up_to_date: newer.is_empty(),
+        releases_behind: newer.len(),
+        days_behind: oldest_newer.map(|t| (Utc::now() - t).num_days()),
+        ..uncached
+    }
+}
+
+/// `GET /deployments/+json`: drift of every deployment.
+pub async fn deployments_json(State(state): State<Arc<AppState>>) -> Result<Response, ApiError> {
+    let drift: Vec<Drift> = state
+        .deployments
+        .all()
+        .iter()
+        .map(|d| drift(&state, d))
+        .collect();
+    let body = serde_json::to_string_pretty(&drift).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
+
+/// `PUT /deployments/{provider}/{...}` (admin only), with the deployed
+/// version as the request body.
+pub async fn register_deployment(
+    Path(route): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    version: String,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let (_, repo) = provider::parse_route(&route).map_err(|e| AppError::from_route(&e))?;
+    let version = version.trim();
+    if version.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Missing deployed version in request body".to_string(),
+        )
+        .into());
+    }
+
+    let deployment = Deployment {
+        route: route.trim_matches('/').to_string(),
+        version: version.to_string(),
+    };
+    state
+        .deployments
+        .register(repo.cache_key(), deployment.clone())
+        .map_err(AppError::internal)?;
+
+    let body =
+        serde_json::to_string_pretty(&drift(&state, &deployment)).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
+
+/// `DELETE /deployments/{provider}/{...}` (admin only).
+pub async fn remove_deployment(
+    Path(route): Path<String>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let (_, repo) = provider::parse_route(&route).map_err(|e| AppError::from_route(&e))?;
+    let removed = state
+        .deployments
+        .remove(&repo.cache_key())
+        .map_err(AppError::internal)?;
+    if !removed {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("No deployment registered for {}", route),
+        )
+        .into());
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
@@ -0,0 +1,102 @@
+//! Asset metadata enrichment (`--enrich-assets`): HEAD requests that fill in
+//! size and content type for assets the provider reports without them, such
+//! as cgit snapshots and GitLab source archives. Requests run concurrently,
+//! bounded overall and per host.
+
+use crate::{
+    AppState,
+    provider::{Asset, Release, classify_assets},
+};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use reqwest::{Client, header};
+use std::collections::HashMap;
+use tokio::sync::Semaphore;
+
+/// Only the newest releases are enriched; older ones are rarely downloaded
+/// and cgit may build each snapshot on demand.
+const ENRICH_RELEASES: usize = 10;
+
+/// HEAD requests in flight at once
+const MAX_CONCURRENT: usize = 8;
+
+/// HEAD requests in flight at once against a single host
+const MAX_PER_HOST: usize = 4;
+
+pub async fn fill_asset_metadata(state: &AppState, releases: &mut [Release]) {
+    if !state.enrich_assets {
+        return;
+    }
+
+    let assets: Vec<&mut Asset> = releases
+        .iter_mut()
+        .take(ENRICH_RELEASES)
+        .flat_map(|r| r.assets.iter_mut())
+        .filter(|a| a.size == 0)
+        .collect();
+    head_concurrently(&state.client, assets).await;
+
+    // A content type may now be known for assets that had none
+    classify_assets(releases);
+}
+
+async fn head_concurrently(client: &Client, assets: Vec<&mut Asset>) {
+    let mut assets = assets
+        .into_iter()
+        .map(|asset| (asset_host(asset), asset))
+        .collect::<Vec<_>>()
+        .into_iter();
+
+    let host_limits: HashMap<String, Semaphore> = assets
+        .as_slice()
+        .iter()
+        .map(|(host, _)| (host.clone(), Semaphore::new(MAX_PER_HOST)))
+        .collect();
+
+    let mut in_flight = FuturesUnordered::new();
+    for (host, asset) in assets.by_ref().take(MAX_CONCURRENT) {
+        in_flight.push(enrich_asset(client, &host_limits[&host], asset));
+    }
+    while in_flight.next().await.is_some() {
+        if let Some((host, asset)) = assets.next() {
+            in_flight.push(enrich_asset(client, &host_limits[&host], asset));
+        }
+    }
+}
+
+async fn enrich_asset(client: &Client, host_limit: &Semaphore, asset: &mut Asset) {
+    // Only fails once the semaphore is closed, which never happens here
+    let Ok(_permit) = host_limit.acquire().await else {
+        return;
+    };
+    // Best effort: keep the provider's values when the HEAD fails
+    let _ = head_asset(client, asset).await;
+}
+
+fn asset_host(asset: &Asset) -> String {
+    url::Url::parse(&asset.url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+async fn head_asset(client: &Client, asset: &mut Asset) -> reqwest::Result<()> {
+    let response = client.head(&asset.url).send().await?.error_for_status()?;
+    let headers = response.headers();
+
+    if let Some(length) = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        asset.size = length;
+    }
+    if asset.content_type.as_deref().is_none_or(str::is_empty)
+        && let Some(content_type) = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+    {
+        asset.content_type = Some(content_type.to_string());
+    }
+
+    Ok(())
+}
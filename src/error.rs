@@ -0,0 +1,285 @@
+//! Errors returned from route handlers, rendered as branded HTML pages with
+//! hints about the URL formats each provider accepts, or for API routes as
+//! JSON with a machine-readable [`ErrorCode`]:
+//!
+//! ```json
+//! { "status": 404, "code": "repo_not_found", "message": "GitHub API returned status: 404 Not Found" }
+//! ```
+
+use crate::{
+    format_html::format_app_error_html, provider::UnknownProviderError,
+    upstream::UpstreamStatusError,
+};
+use axum::{
+    http::{StatusCode, Uri, header},
+    response::{Html, IntoResponse, Response},
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// URL formats per provider prefix, with an example each.
+const URL_FORMATS: &[(&str, &str, &str)] = &[
+    ("github", "/github/{owner}/{repo}", "/github/sharkdp/bat"),
+    (
+        "gitlab",
+        "/gitlab/[{host}/]{owner}/{repo}",
+        "/gitlab/gitlab-org/gitlab",
+    ),
+    (
+        "forgejo",
+        "/forgejo/{host}/{owner}/{repo}",
+        "/forgejo/codeberg.org/forgejo/forgejo",
+    ),
+    (
+        "gitea",
+        "/gitea/{host}/{owner}/{repo}",
+        "/gitea/gitea.com/gitea/tea",
+    ),
+    (
+        "codeberg",
+        "/codeberg/{owner}/{repo}",
+        "/codeberg/forgejo/forgejo",
+    ),
+    (
+        "sourcehut",
+        "/sourcehut/~{owner}/{repo}",
+        "/sourcehut/~sircmpwn/scdoc",
+    ),
+    ("launchpad", "/launchpad/{project}", "/launchpad/bzr"),
+    ("snap", "/snap/{name}", "/snap/hello"),
+    (
+        "huggingface",
+        "/huggingface/[datasets/]{owner}/{repo}",
+        "/huggingface/openai-community/gpt2",
+    ),
+    (
+        "pagure",
+        "/pagure/{host}/{repo}",
+        "/pagure/pagure.io/pagure",
+    ),
+    (
+        "gitiles",
+        "/gitiles/{host}/{repo_path}",
+        "/gitiles/chromium.googlesource.com/chromium/src",
+    ),
+    (
+        "hg",
+        "/hg/{host}/{repo_path}",
+        "/hg/hg.mozilla.org/hgcustom/version-control-tools",
+    ),
+    (
+        "cgit",
+        "/cgit/{host}/{repo_path}",
+        "/cgit/git.kernel.org/pub/scm/git/git.git",
+    ),
+    (
+        "autoindex",
+        "/autoindex/{host}/{directory}",
+        "/autoindex/download.gnome.org/sources/gtk/4.14",
+    ),
+];
+
+/// What went wrong, for API clients to branch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Malformed path or query parameter
+    InvalidRequest,
+    /// No such page, release, tag or asset
+    NotFound,
+    /// The forge says the repository doesn't exist
+    RepoNotFound,
+    /// Host or provider this instance doesn't serve
+    UnsupportedHost,
+    /// The repo is being fetched; try again shortly
+    Pending,
+    /// The repo has not been fetched yet
+    NotCached,
+    /// The client used up its fetch quota
+    RateLimited,
+    /// The server is shedding load
+    Overloaded,
+    /// The forge rejected the request because of its rate limit
+    UpstreamRateLimited,
+    /// The forge failed or could not be reached
+    UpstreamError,
+    /// Missing or invalid admin token
+    Unauthorized,
+    /// The feature is not enabled on this instance
+    Disabled,
+    /// The server is in maintenance mode
+    Maintenance,
+    Internal,
+}
+
+impl ErrorCode {
+    /// The code for an error known only by its status.
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => Self::InvalidRequest,
+            StatusCode::NOT_FOUND => Self::NotFound,
+            StatusCode::UNAUTHORIZED => Self::Unauthorized,
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
+            StatusCode::BAD_GATEWAY => Self::UpstreamError,
+            _ => Self::Internal,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AppError {
+    pub status: StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+    /// Provider whose URL formats to suggest; all providers when unset
+    provider: Option<&'static str>,
+    /// JSON body for API routes (`/+json`, `/+check`, `/tags.json`, `SHA256SUMS`, ...)
+    json: bool,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code: ErrorCode::from_status(status),
+            message: message.into(),
+            provider: None,
+            json: false,
+        }
+    }
+
+    pub fn internal(error: impl std::fmt::Display) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+    }
+
+    pub fn with_code(self, code: ErrorCode) -> Self {
+        Self { code, ..self }
+    }
+
+    /// A failed upstream fetch: 404 when the forge says the repo doesn't
+    /// exist, 502 for everything else.
+    pub fn from_upstream(error: &anyhow::Error) -> Self {
+        let message = error.to_string();
+        match error
+            .downcast_ref::<UpstreamStatusError>()
+            .map(|e| e.status)
+        {
+            Some(reqwest::StatusCode::NOT_FOUND) => {
+                Self::new(StatusCode::NOT_FOUND, message).with_code(ErrorCode::RepoNotFound)
+            }
+            Some(reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::TOO_MANY_REQUESTS) => {
+                Self::new(StatusCode::BAD_GATEWAY, message)
+                    .with_code(ErrorCode::UpstreamRateLimited)
+            }
+            _ => Self::new(StatusCode::BAD_GATEWAY, message),
+        }
+    }
+
+    /// A repo route that doesn't parse: `unsupported_host` for an unknown
+    /// provider, `invalid_request` otherwise.
+    pub fn from_route(error: &anyhow::Error) -> Self {
+        let invalid = Self::new(StatusCode::BAD_REQUEST, error.to_string());
+        if error.is::<UnknownProviderError>() {
+            invalid.with_code(ErrorCode::UnsupportedHost)
+        } else {
+            invalid
+        }
+    }
+
+    /// Wrap an error from a provider's handler. `path` is the path after the
+    /// provider prefix; API routes get JSON errors.
+    pub fn from_provider(provider: &'static str, path: &str, error: AppError) -> Self {
+        Self {
+            provider: Some(provider),
+            json: path.ends_with("/+json")
+                || path.ends_with("/+check")
+                || path.ends_with("/tags.json")
+                || path.ends_with("/badge.svg")
+                || path.ends_with("/releases.ics")
+                || path.ends_with("/update-info.json")
+                || path.ends_with("/pin")
+                || path.ends_with("/releases")
+                || path.ends_with("/SHA256SUMS")
+                || path.ends_with("/SHA256SUMS.sig"),
+            ..error
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match (self.code, self.status) {
+            (ErrorCode::RepoNotFound, _) => "Repository Not Found",
+            (ErrorCode::UpstreamRateLimited, _) => "Upstream Rate Limit Reached",
+            (_, StatusCode::BAD_REQUEST) => "Invalid Repository Path",
+            (_, StatusCode::NOT_FOUND) => "Not Found",
+            (_, StatusCode::TOO_MANY_REQUESTS) => "Too Many Requests",
+            (_, StatusCode::BAD_GATEWAY) => "Upstream Unavailable",
+            (ErrorCode::Maintenance, _) => "Under Maintenance",
+            _ => "Something Went Wrong",
+        }
+    }
+
+    fn json_response(self) -> Response {
+        let body = ErrorBody {
+            status: self.status.as_u16(),
+            code: self.code,
+            message: &self.message,
+        };
+        (
+            self.status,
+            [(header::CONTENT_TYPE, "application/json")],
+            serde_json::to_string_pretty(&body).unwrap_or_default(),
+        )
+            .into_response()
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if self.json {
+            return self.json_response();
+        }
+
+        // URL hints only help when the path itself may be wrong
+        let formats: Vec<(&str, &str)> = match self.status {
+            StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND => URL_FORMATS
+                .iter()
+                .filter(|(prefix, _, _)| self.provider.is_none_or(|p| p == *prefix))
+                .map(|(_, format, example)| (*format, *example))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let html =
+            format_app_error_html(self.status.as_u16(), self.title(), &self.message, &formats);
+        (self.status, Html(html)).into_response()
+    }
+}
+
+/// JSON body of errors on API routes
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ErrorBody<'a> {
+    /// HTTP status code
+    pub status: u16,
+    pub code: ErrorCode,
+    pub message: &'a str,
+}
+
+/// Error of a JSON-only endpoint, always answered with a JSON body.
+#[derive(Debug)]
+pub struct ApiError(pub AppError);
+
+impl From<AppError> for ApiError {
+    fn from(error: AppError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        self.0.json_response()
+    }
+}
+
+/// Fallback for routes that don't exist.
+pub async fn not_found(uri: Uri) -> AppError {
+    AppError::new(StatusCode::NOT_FOUND, format!("No page at {}", uri.path()))
+}
@@ -0,0 +1,245 @@
+//! Federation of instances, so communities can share the fetching: every
+//! instance serves the repos it watches, with when each was cached, at
+//! `/federation/+json`. With `[federation] peers` set, an instance polls
+//! those feeds and takes each repo a peer cached more recently than it did
+//! from the peer's `/+json` into its own cache, instead of fetching it
+//! upstream. Taken repos are listed on the dashboard and their new releases
+//! mailed to subscribers, as if fetched here.
+//!
+//! Peers are trusted with what they serve, asset URLs included. Only repos
+//! a peer watches itself are in its feed, so releases are never passed on
+//! further than one hop.
+
+use crate::{
+    AppState, RepoPath,
+    config::FederationConfig,
+    provider::{self, CachedReleases},
+    signing,
+};
+use anyhow::Result;
+use axum::{extract::State, http::StatusCode, response::Response};
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+/// Repos taken from one peer per poll, at most
+const MAX_PEER_REPOS: usize = 1000;
+/// `/+json` requests to a peer running at once
+const PEER_REQUESTS: usize = 4;
+
+/// A repo in `/federation/+json`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedEntry {
+    /// Repo route as used in URLs, e.g. "github/owner/repo"
+    pub route: String,
+    /// When the instance last fetched it; none while it is not cached
+    pub cached_at: Option<DateTime<Utc>>,
+    /// Tag of the latest non-draft release
+    pub latest: Option<String>,
+}
+
+/// A repo a peer watches and this instance doesn't
+#[derive(Debug, Clone)]
+pub struct FederatedRepo {
+    /// Route of the repo page, e.g. "/github/owner/repo"
+    pub route: String,
+    pub provider: &'static str,
+    pub repo: RepoPath,
+    /// Base URL of the peer
+    pub peer: String,
+}
+
+pub struct Federation {
+    peers: Vec<String>,
+    interval: std::time::Duration,
+    /// From the last poll of every peer
+    repos: RwLock<Vec<FederatedRepo>>,
+}
+
+impl Federation {
+    /// None without peers.
+    pub fn from_config(config: &FederationConfig) -> Option<Self> {
+        if config.peers.is_empty() {
+            return None;
+        }
+        Some(Self {
+            peers: config
+                .peers
+                .iter()
+                .map(|p| p.trim_end_matches('/').to_string())
+                .collect(),
+            interval: std::time::Duration::from_secs(config.interval_minutes.max(1) * 60),
+            repos: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Repos of the peers that aren't watched here, as of their last poll.
+    pub fn repos(&self) -> Vec<FederatedRepo> {
+        self.repos.read().unwrap().clone()
+    }
+
+    /// Whether a peer watches the repo with `cache_key`.
+    pub fn covers(&self, cache_key: &str) -> bool {
+        self.repos
+            .read()
+            .unwrap()
+            .iter()
+            .any(|r| r.repo.cache_key() == cache_key)
+    }
+
+    /// Poll the peers every interval, for as long as the server runs.
+    pub fn spawn(self: Arc<Self>, state: Arc<AppState>) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                ticker.tick().await;
+                self.poll(&state).await;
+            }
+        });
+    }
+
+    async fn poll(&self, state: &AppState) {
+        if state.maintenance.active() {
+            return;
+        }
+        let watched: Vec<String> = state
+            .refresher
+            .repos()
+            .iter()
+            .map(|w| w.repo.cache_key())
+            .collect();
+        let mut repos: Vec<FederatedRepo> = Vec::new();
+        for peer in &self.peers {
+            let peer_repos = match poll_peer(state, peer).await {
+                Ok(peer_repos) => peer_repos,
+                Err(e) => {
+                    eprintln!("Federation poll of {} failed: {}", peer, e);
+                    // Keep listing its repos until it answers again
+                    self.repos()
+                        .into_iter()
+                        .filter(|r| r.peer == *peer)
+                        .collect()
+                }
+            };
+            for peer_repo in peer_repos {
+                let cache_key = peer_repo.repo.cache_key();
+                if !watched.contains(&cache_key)
+                    && !repos.iter().any(|r| r.repo.cache_key() == cache_key)
+                {
+                    repos.push(peer_repo);
+                }
+            }
+        }
+        *self.repos.write().unwrap() = repos;
+    }
+}
+
+/// Read a peer's feed and take every repo it cached more recently than this
+/// instance. Returns all the repos of the feed.
+async fn poll_peer(state: &AppState, peer: &str) -> Result<Vec<FederatedRepo>> {
+    let response = state
+        .client
+        .get(format!("{}/federation/+json", peer))
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        anyhow::bail!("{} returned {}", peer, response.status());
+    }
+    let mut feed: Vec<FeedEntry> = response.json().await?;
+    feed.truncate(MAX_PEER_REPOS);
+
+    let mut repos = Vec::new();
+    let mut stale = Vec::new();
+    for entry in feed {
+        let Ok((provider, repo)) = provider::parse_route(&entry.route) else {
+            continue;
+        };
+        let local = state
+            .cache
+            .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+            .ok()
+            .flatten()
+            .map(|c| c.cached_at);
+        // A peer's clock ahead of ours doesn't make its copy newer
+        if let Some(cached_at) = entry.cached_at.map(|c| c.min(Utc::now()))
+            && !state.cache.is_archived(&repo.cache_key())
+            && local.is_none_or(|local| cached_at > local)
+        {
+            stale.push((provider, repo.clone()));
+        }
+        repos.push(FederatedRepo {
+            route: provider::repo_route(provider, &repo),
+            provider,
+            repo,
+            peer: peer.to_string(),
+        });
+    }
+
+    stream::iter(stale)
+        .for_each_concurrent(PEER_REQUESTS, |(provider, repo)| async move {
+            // Left alone while a fetch of it runs here
+            if !state.fetches.start(provider, &repo) {
+                return;
+            }
+            if let Err(e) = take_repo(state, peer, provider, &repo).await {
+                eprintln!("Taking {} from {} failed: {}", repo.cache_key(), peer, e);
+            }
+            state.fetches.finish(provider, &repo, None);
+        })
+        .await;
+    Ok(repos)
+}
+
+/// Cache a repo's releases as served by the peer's `/+json`.
+async fn take_repo(
+    state: &AppState,
+    peer: &str,
+    provider: &'static str,
+    repo: &RepoPath,
+) -> Result<()> {
+    let url = format!("{}{}/+json", peer, provider::repo_route(provider, repo));
+    let response = state
+        .client
+        .get(&url)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        anyhow::bail!("{} returned {}", url, response.status());
+    }
+    let cached: CachedReleases = response.json().await?;
+    if cached.provider.as_deref().is_some_and(|p| p != provider) {
+        anyhow::bail!("{} is cached for another provider", url);
+    }
+    provider::store_federated(state, provider, repo, cached.releases, cached.cached_at).await
+}
+
+/// `GET /federation/+json`: the watched repos, for other instances to take
+/// into their caches.
+pub async fn feed_json(State(state): State<Arc<AppState>>) -> Response {
+    let feed: Vec<FeedEntry> = state
+        .refresher
+        .repos()
+        .iter()
+        .map(|watched| {
+            let repo = &watched.repo;
+            let cached = state
+                .cache
+                .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+                .ok()
+                .flatten();
+            FeedEntry {
+                route: watched.route.trim_start_matches('/').to_string(),
+                cached_at: cached.as_ref().map(|c| c.cached_at),
+                latest: cached
+                    .as_ref()
+                    .and_then(|c| c.releases.iter().find(|r| !r.draft))
+                    .map(|r| r.tag_name.clone()),
+            }
+        })
+        .collect();
+    let body = serde_json::to_string_pretty(&feed).unwrap_or_default();
+    signing::json_response(&state, StatusCode::OK, body)
+}
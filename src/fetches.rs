@@ -0,0 +1,230 @@
+//! Repos being fetched in the background, and the errors of failed fetches,
+//! kept in fetches.json so a restart doesn't lose them: a visitor waiting on
+//! a "processing" page would otherwise wait forever, and a failing repo would
+//! be refetched by its next visitor as if it had never failed.
+//!
+//! At startup, fetches that were running are resumed, unless the repo has
+//! since been cached, the fetch started more than an hour ago or the
+//! instance is in maintenance mode; then they are dropped, and the next
+//! visit fetches again. Failures are kept until the cache TTL has passed
+//! since, like a cached snapshot would be.
+
+use crate::{AppState, RepoPath, cache::CacheManager, provider};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::{DashMap, mapref::entry::Entry};
+use futures_util::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Fetches resumed at once after a restart
+const RESUMED_FETCHES: usize = 4;
+/// Running fetches older than this when the server stopped are dropped
+const STALE_PENDING_MINUTES: i64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingFetch {
+    /// Repo route as used in URLs, e.g. "github/owner/repo"
+    pub route: String,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedFetch {
+    pub route: String,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedFetches {
+    #[serde(default)]
+    pending: Vec<PendingFetch>,
+    #[serde(default)]
+    failed: Vec<FailedFetch>,
+}
+
+/// Route of `repo` as stored, without the leading slash.
+fn route(prefix: &str, repo: &RepoPath) -> String {
+    provider::repo_route(prefix, repo)
+        .trim_start_matches('/')
+        .to_string()
+}
+
+pub struct Fetches {
+    /// By cache key
+    pending: DashMap<String, PendingFetch>,
+    /// By cache key
+    failed: DashMap<String, FailedFetch>,
+    cache: CacheManager,
+    /// Held while fetches.json is written, so writes don't interleave
+    saving: Mutex<()>,
+}
+
+impl Fetches {
+    /// Fails on an unreadable fetches.json. Running fetches stay pending
+    /// until [`resume`] decides about them.
+    pub fn load(cache: CacheManager) -> Result<Self> {
+        let saved: SavedFetches = cache.read_fetches()?;
+        let by_key = |route: &str| {
+            provider::parse_route(route)
+                .ok()
+                .map(|(_, repo)| repo.cache_key())
+        };
+        let pending = DashMap::new();
+        for entry in saved.pending {
+            if let Some(cache_key) = by_key(&entry.route) {
+                pending.insert(cache_key, entry);
+            }
+        }
+        let failed = DashMap::new();
+        for entry in saved.failed {
+            if let Some(cache_key) = by_key(&entry.route) {
+                failed.insert(cache_key, entry);
+            }
+        }
+        Ok(Self {
+            pending,
+            failed,
+            cache,
+            saving: Mutex::new(()),
+        })
+    }
+
+    pub fn is_pending(&self, cache_key: &str) -> bool {
+        self.pending.contains_key(cache_key)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Error of the repo's last fetch, if it failed.
+    pub fn failure(&self, cache_key: &str) -> Option<String> {
+        self.failed.get(cache_key).map(|f| f.error.clone())
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.failed.len()
+    }
+
+    /// Mark a fetch of `repo` as running; false when one already is.
+    pub fn start(&self, prefix: &str, repo: &RepoPath) -> bool {
+        let entry = PendingFetch {
+            route: route(prefix, repo),
+            started_at: Utc::now(),
+        };
+        let started = match self.pending.entry(repo.cache_key()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(vacant) => {
+                vacant.insert(entry);
+                true
+            }
+        };
+        if started {
+            self.save();
+        }
+        started
+    }
+
+    /// Mark the fetch of `repo` as done, recording `error` if it failed.
+    pub fn finish(&self, prefix: &str, repo: &RepoPath, error: Option<String>) {
+        let cache_key = repo.cache_key();
+        self.pending.remove(&cache_key);
+        if let Some(error) = error {
+            let entry = FailedFetch {
+                route: route(prefix, repo),
+                error,
+                failed_at: Utc::now(),
+            };
+            self.failed.insert(cache_key, entry);
+        }
+        self.save();
+    }
+
+    /// Forget a failure once the repo was cached after all.
+    pub fn clear_failure(&self, cache_key: &str) {
+        if self.failed.remove(cache_key).is_some() {
+            self.save();
+        }
+    }
+
+    /// Write fetches.json. A failed write is logged rather than failing the
+    /// fetch; at worst a restart forgets about it.
+    fn save(&self) {
+        let _saving = self.saving.lock().unwrap();
+        let mut saved = SavedFetches {
+            pending: self.pending.iter().map(|e| e.value().clone()).collect(),
+            failed: self.failed.iter().map(|e| e.value().clone()).collect(),
+        };
+        saved.pending.sort_by(|a, b| a.route.cmp(&b.route));
+        saved.failed.sort_by(|a, b| a.route.cmp(&b.route));
+        if let Err(e) = self.cache.write_fetches(&saved) {
+            eprintln!("Failed to save fetches.json: {}", e);
+        }
+    }
+}
+
+/// Whether `repo` has a fresh snapshot, so fetching it again is moot.
+fn cached(state: &AppState, repo: &RepoPath) -> bool {
+    matches!(
+        state.cache.read_timestamp(&repo.host, &repo.owner, &repo.repo),
+        Ok(Some(cached_at))
+            if !state.cache.is_repo_expired(&repo.host, &repo.owner, &repo.repo, cached_at)
+    )
+}
+
+/// Reconcile the fetches saved before the last restart: drop failures older
+/// than the cache TTL and fetches that are stale or moot, and resume the rest
+/// in the background.
+pub fn resume(state: &Arc<AppState>) {
+    let fetches = &state.fetches;
+    let now = Utc::now();
+    let failures = fetches.failed.len();
+    fetches.failed.retain(|_, failed| {
+        now - failed.failed_at < state.cache.cache_duration
+            && provider::parse_route(&failed.route).is_ok_and(|(_, repo)| !cached(state, &repo))
+    });
+    let mut resumed = Vec::new();
+    let mut dropped = Vec::new();
+    for entry in fetches.pending.iter() {
+        let repo = provider::parse_route(&entry.route).ok();
+        let stale = now - entry.started_at >= Duration::minutes(STALE_PENDING_MINUTES);
+        match repo {
+            Some((prefix, repo))
+                if !stale && !state.maintenance.active() && !cached(state, &repo) =>
+            {
+                resumed.push((prefix, repo));
+            }
+            _ => dropped.push(entry.key().clone()),
+        }
+    }
+    for cache_key in &dropped {
+        fetches.pending.remove(cache_key);
+    }
+    if !dropped.is_empty() || failures != fetches.failed.len() {
+        fetches.save();
+    }
+    if resumed.is_empty() {
+        return;
+    }
+
+    println!("Resuming {} fetches from before the restart", resumed.len());
+    let state = state.clone();
+    tokio::spawn(async move {
+        stream::iter(resumed)
+            .for_each_concurrent(RESUMED_FETCHES, |(prefix, repo)| {
+                let state = state.clone();
+                async move {
+                    let result = provider::fetch_and_cache(&state, prefix, &repo).await;
+                    if let Err(e) = &result {
+                        eprintln!("Resumed fetch of {} failed: {}", repo.cache_key(), e);
+                    }
+                    state
+                        .fetches
+                        .finish(prefix, &repo, result.err().map(|e| e.to_string()));
+                }
+            })
+            .await;
+    });
+}
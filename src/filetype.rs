@@ -0,0 +1,186 @@
+//! File type of a release asset, from its filename and the upstream
+//! `content_type`, with a human-readable label and a matching icon.
+
+use crate::{icons, sbom};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    AppImage,
+    Dmg,
+    MacPkg,
+    Exe,
+    Msi,
+    Deb,
+    Rpm,
+    Apk,
+    Flatpak,
+    Snap,
+    Wheel,
+    Jar,
+    Checksum,
+    Signature,
+    Sbom,
+    Source,
+    Archive,
+    Other,
+}
+
+impl FileType {
+    /// Stable identifier used in the JSON API, e.g. "appimage"
+    pub fn id(&self) -> &'static str {
+        match self {
+            FileType::AppImage => "appimage",
+            FileType::Dmg => "dmg",
+            FileType::MacPkg => "pkg",
+            FileType::Exe => "exe",
+            FileType::Msi => "msi",
+            FileType::Deb => "deb",
+            FileType::Rpm => "rpm",
+            FileType::Apk => "apk",
+            FileType::Flatpak => "flatpak",
+            FileType::Snap => "snap",
+            FileType::Wheel => "wheel",
+            FileType::Jar => "jar",
+            FileType::Checksum => "checksum",
+            FileType::Signature => "signature",
+            FileType::Sbom => "sbom",
+            FileType::Source => "source",
+            FileType::Archive => "archive",
+            FileType::Other => "other",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileType::AppImage => "AppImage",
+            FileType::Dmg => "macOS disk image",
+            FileType::MacPkg => "macOS installer",
+            FileType::Exe => "Windows executable",
+            FileType::Msi => "Windows installer",
+            FileType::Deb => "Debian package",
+            FileType::Rpm => "RPM package",
+            FileType::Apk => "Android app",
+            FileType::Flatpak => "Flatpak bundle",
+            FileType::Snap => "Snap package",
+            FileType::Wheel => "Python wheel",
+            FileType::Jar => "Java archive",
+            FileType::Checksum => "Checksums",
+            FileType::Signature => "Signature",
+            FileType::Sbom => "SBOM",
+            FileType::Source => "Source code",
+            FileType::Archive => "Archive",
+            FileType::Other => "File",
+        }
+    }
+
+    pub fn icon(&self, size: u32) -> String {
+        match self {
+            FileType::Exe | FileType::Msi => icons::WINDOWS(size),
+            FileType::Deb | FileType::Rpm => icons::LINUX(size),
+            FileType::Dmg | FileType::MacPkg => icons::APPLE(size),
+            FileType::AppImage => icons::PACKAGE(size),
+            FileType::Flatpak | FileType::Snap => icons::CUBE(size),
+            FileType::Apk => icons::ANDROID(size),
+            FileType::Wheel => icons::WHEEL(size),
+            FileType::Jar => icons::COFFEE(size),
+            FileType::Checksum => icons::HASH(size),
+            FileType::Signature => icons::SEAL(size),
+            FileType::Sbom => icons::LIST(size),
+            FileType::Source => icons::FILE(size),
+            FileType::Archive => icons::ARCHIVE(size),
+            FileType::Other => icons::PAPERCLIP(size),
+        }
+    }
+}
+
+/// Classify an asset by filename, falling back to the upstream content type.
+/// e.g., "app-1.0-x86_64.AppImage" -> AppImage, "SHA256SUMS" -> Checksum,
+/// "app.spdx.json" -> Sbom
+pub fn classify(filename: &str, content_type: Option<&str>) -> FileType {
+    let lower = filename.to_lowercase();
+    let ends = |ext: &str| lower.ends_with(ext);
+
+    if [".sig", ".asc", ".minisig", ".sigstore", ".pem", ".cert"]
+        .iter()
+        .any(|ext| ends(ext))
+        || ends(".sigstore.json")
+    {
+        FileType::Signature
+    } else if sbom::is_sbom(filename) {
+        FileType::Sbom
+    } else if [".sha256", ".sha256sum", ".sha512", ".sha1", ".md5"]
+        .iter()
+        .any(|ext| ends(ext))
+        || lower.contains("sha256sums")
+        || lower.contains("sha512sums")
+        || lower.contains("checksums")
+    {
+        FileType::Checksum
+    } else if ends(".appimage") {
+        FileType::AppImage
+    } else if ends(".dmg") {
+        FileType::Dmg
+    } else if ends(".pkg") {
+        FileType::MacPkg
+    } else if ends(".msi") || ends(".msix") {
+        FileType::Msi
+    } else if ends(".exe") {
+        FileType::Exe
+    } else if ends(".deb") {
+        FileType::Deb
+    } else if ends(".rpm") {
+        FileType::Rpm
+    } else if ends(".apk") || ends(".aab") {
+        FileType::Apk
+    } else if ends(".flatpak") || ends(".flatpakref") || ends(".flatpakrepo") {
+        FileType::Flatpak
+    } else if ends(".snap") {
+        FileType::Snap
+    } else if ends(".whl") {
+        FileType::Wheel
+    } else if ends(".jar") || ends(".war") {
+        FileType::Jar
+    } else if lower.contains("source") || lower.contains("-src") || lower.contains("_src") {
+        FileType::Source
+    } else if [
+        ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.bz2", ".tar.zst", ".zip", ".7z", ".gz", ".xz",
+        ".bz2", ".zst",
+    ]
+    .iter()
+    .any(|ext| ends(ext))
+    {
+        FileType::Archive
+    } else {
+        content_type.map_or(FileType::Other, from_content_type)
+    }
+}
+
+fn from_content_type(content_type: &str) -> FileType {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    match mime.as_str() {
+        "application/vnd.android.package-archive" => FileType::Apk,
+        "application/java-archive" | "application/x-java-archive" => FileType::Jar,
+        "application/vnd.debian.binary-package" | "application/x-debian-package" => FileType::Deb,
+        "application/x-rpm" | "application/x-redhat-package-manager" => FileType::Rpm,
+        "application/x-apple-diskimage" => FileType::Dmg,
+        "application/x-msi" | "application/x-ms-installer" => FileType::Msi,
+        "application/x-msdownload" | "application/vnd.microsoft.portable-executable" => {
+            FileType::Exe
+        }
+        "application/pgp-signature" | "application/pkcs7-signature" => FileType::Signature,
+        "application/zip"
+        | "application/gzip"
+        | "application/x-gzip"
+        | "application/x-tar"
+        | "application/x-xz"
+        | "application/x-bzip2"
+        | "application/zstd"
+        | "application/x-7z-compressed" => FileType::Archive,
+        _ => FileType::Other,
+    }
+}
@@ -0,0 +1,222 @@
+//! `/api/v2/firehose?since=`: every release the instance has observed, across
+//! all cached repos, oldest first, for downstream indexes and bots that
+//! mirror everything an instance learns. A release is observed when it is
+//! published or first seen, whichever is later (see `since.rs`); releases of
+//! a repo's first snapshot count as observed when published.
+//!
+//! Pages end with a cursor, the position of their last release; passing it
+//! back as `?cursor=` continues after it, and polling with the last cursor
+//! picks up releases observed since. The feed is built from the cache
+//! directory and rebuilt at most once a minute, when used.
+
+use crate::{
+    AppState,
+    cache_control::RouteKind,
+    error::{ApiError, AppError},
+    provider::{CachedReleases, Release},
+    search, signing,
+    since::{self, observed_at},
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Response,
+};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// How long a built feed is reused before the cache directory is read again.
+const FEED_TTL_SECS: i64 = 60;
+/// Releases per page, unless `?limit=` asks for fewer
+const PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct FirehoseQuery {
+    /// RFC 3339 timestamp; releases observed after it
+    since: Option<String>,
+    /// `next_cursor` of a previous page; takes precedence over `since`
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Position in the feed: releases sort by when they were observed, then by
+/// repo route and tag, so releases observed at the same instant keep an
+/// order across pages.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Position {
+    observed_at: DateTime<Utc>,
+    route: String,
+    tag_name: String,
+}
+
+impl Position {
+    /// Opaque for clients: "{observed_at}\n{route}\n{tag}", URL-safe base64.
+    fn encode(&self) -> String {
+        let raw = format!(
+            "{}\n{}\n{}",
+            self.observed_at.to_rfc3339(),
+            self.route,
+            self.tag_name
+        );
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        let raw = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(cursor.trim())
+            .ok()?;
+        let raw = String::from_utf8(raw).ok()?;
+        let mut parts = raw.splitn(3, '\n');
+        let observed_at = DateTime::parse_from_rfc3339(parts.next()?).ok()?;
+        Some(Self {
+            observed_at: observed_at.with_timezone(&Utc),
+            route: parts.next()?.to_string(),
+            tag_name: parts.next()?.to_string(),
+        })
+    }
+
+    /// Past every release observed at `since`, and before any observed
+    /// after it, as `/releases?since=` counts them.
+    fn since(since: DateTime<Utc>) -> Self {
+        Self {
+            observed_at: since + Duration::nanoseconds(1),
+            route: String::new(),
+            tag_name: String::new(),
+        }
+    }
+}
+
+struct FeedEntry {
+    position: Position,
+    /// Cache key, e.g. "github.com/owner/repo"
+    repo_path: String,
+    release: Release,
+}
+
+/// One page of the firehose.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FirehosePage {
+    /// Oldest first
+    pub releases: Vec<FirehoseRelease>,
+    /// Position after the last release, or the requested one when the page
+    /// is empty; pass it as `?cursor=` for the next page, or to poll later
+    pub next_cursor: String,
+    /// Whether more releases follow the cursor already
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FirehoseRelease {
+    /// When the instance observed the release: published, or first seen if
+    /// that came later
+    pub observed_at: DateTime<Utc>,
+    /// Route of the repo page, e.g. "/github/owner/repo"
+    pub route: String,
+    pub repo_path: String,
+    pub release: Release,
+}
+
+struct BuiltFeed {
+    built_at: DateTime<Utc>,
+    /// Sorted by position
+    entries: Arc<Vec<FeedEntry>>,
+}
+
+#[derive(Default)]
+pub struct Firehose {
+    built: Mutex<Option<BuiltFeed>>,
+}
+
+impl Firehose {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn entries(&self, state: &AppState) -> Arc<Vec<FeedEntry>> {
+        let mut built = self.built.lock().unwrap();
+        if let Some(feed) = built.as_ref()
+            && Utc::now() - feed.built_at < Duration::seconds(FEED_TTL_SECS)
+        {
+            return feed.entries.clone();
+        }
+
+        let mut entries: Vec<FeedEntry> = Vec::new();
+        for cached in state.cache.read_all_json::<CachedReleases>() {
+            let route = search::route_for(cached.provider.as_deref(), &cached.repo_path);
+            entries.extend(
+                cached
+                    .releases
+                    .into_iter()
+                    .filter(|r| !r.draft)
+                    .map(|release| FeedEntry {
+                        position: Position {
+                            observed_at: observed_at(&release),
+                            route: route.clone(),
+                            tag_name: release.tag_name.clone(),
+                        },
+                        repo_path: cached.repo_path.clone(),
+                        release,
+                    }),
+            );
+        }
+        entries.sort_by(|a, b| a.position.cmp(&b.position));
+        let entries = Arc::new(entries);
+        *built = Some(BuiltFeed {
+            built_at: Utc::now(),
+            entries: entries.clone(),
+        });
+        entries
+    }
+
+    /// Up to `limit` releases after `after`, from the start of the feed
+    /// without one.
+    fn page(&self, state: &AppState, after: Option<Position>, limit: usize) -> FirehosePage {
+        let entries = self.entries(state);
+        let start = after
+            .as_ref()
+            .map_or(0, |after| entries.partition_point(|e| e.position <= *after));
+        let page = &entries[start..entries.len().min(start + limit)];
+        let next = page.last().map(|e| e.position.clone()).or(after);
+        FirehosePage {
+            releases: page
+                .iter()
+                .map(|e| FirehoseRelease {
+                    observed_at: e.position.observed_at,
+                    route: e.position.route.clone(),
+                    repo_path: e.repo_path.clone(),
+                    release: e.release.clone(),
+                })
+                .collect(),
+            next_cursor: next.map(|p| p.encode()).unwrap_or_default(),
+            has_more: start + page.len() < entries.len(),
+        }
+    }
+}
+
+/// `GET /api/v2/firehose`
+pub async fn firehose_handler(
+    Query(query): Query<FirehoseQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    let after = match query.cursor.as_deref().filter(|c| !c.is_empty()) {
+        Some(cursor) => Some(Position::decode(cursor).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid cursor '{}'", cursor),
+            )
+        })?),
+        None => since::parse_since(query.since.as_deref())?.map(Position::since),
+    };
+    let limit = query.limit.unwrap_or(PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let page = state.firehose.page(&state, after, limit);
+    let body = serde_json::to_string_pretty(&page).map_err(AppError::internal)?;
+    Ok(state.cache_policy.apply(
+        RouteKind::Json,
+        signing::json_response(&state, StatusCode::OK, body),
+    ))
+}
@@ -1,6 +1,28 @@
+use crate::calendar::CalendarRelease;
+use crate::catalog::CatalogRepo;
+use crate::channels::Channel;
+use crate::compare::{MirrorComparison, TagStatus};
+use crate::dashboard::{DashboardEntry, Viewer};
+use crate::display::{RepoDisplay, RepoDisplays};
+use crate::drift::Drift;
+use crate::filetype;
+use crate::freshness::{self, FreshnessThresholds};
+use crate::hidden_assets::HiddenAssets;
 use crate::icons;
-use crate::provider::Release;
-use chrono::{DateTime, Utc};
+use crate::install::InstallSource;
+use crate::latest_names::LatestNames;
+use crate::markdown;
+use crate::platform::{self, Os};
+use crate::provider::{Asset, Release, gitlab};
+use crate::publishers::SignatureStatus;
+use crate::report::{self, WeeklyReport};
+use crate::sbom::{self, SbomSummary};
+use crate::search::SearchHit;
+use crate::stats::RepoRequests;
+use crate::telemetry::{Telemetry, Usage};
+use crate::upstream::{BreakerState, HostStatus};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
 
 fn format_size(size: u64) -> String {
     const KB: u64 = 1024;
@@ -18,146 +40,719 @@ fn format_size(size: u64) -> String {
     }
 }
 
-/// Split a filename into stem and known compound extension.
-/// e.g., "bat-v0.26.1-x86_64.tar.gz" -> ("bat-v0.26.1-x86_64", ".tar.gz")
-pub fn split_stem_ext(filename: &str) -> (&str, &str) {
-    const KNOWN_EXTS: &[&str] = &[
-        ".tar.gz.sha256",
-        ".tar.xz.sha256",
-        ".tar.bz2.sha256",
-        ".tar.zst.sha256",
-        ".zip.sha256",
-        ".xz.sha256",
-        ".gz.sha256",
-        ".bz2.sha256",
-        ".xz.asc",
-        ".tar.gz",
-        ".tar.xz",
-        ".tar.bz2",
-        ".tar.zst",
-        ".xz",
-        ".gz",
-        ".bz2",
-        ".zst",
-        ".zip",
-        ".sha256",
-        ".sha512",
-        ".exe",
-        ".msi",
-        ".deb",
-        ".rpm",
-    ];
-
-    for ext in KNOWN_EXTS {
-        if let Some(stem) = filename.strip_suffix(ext) {
-            return (stem, ext);
+fn os_icon(os: Os, size: u32) -> String {
+    match os {
+        Os::Linux => icons::LINUX(size),
+        Os::Mac => icons::APPLE(size),
+        Os::Windows => icons::WINDOWS(size),
+        Os::Other => icons::PACKAGE(size),
+    }
+}
+
+/// Small badges describing an asset's file type and target (arch, musl/gnu).
+fn format_platform_badges(asset: &Asset) -> String {
+    let platform = platform::classify(&asset.name);
+    let file_type = filetype::classify(&asset.name, asset.content_type.as_deref());
+    [Some(file_type.label()), platform.arch, platform.libc]
+        .into_iter()
+        .flatten()
+        .map(|label| {
+            format!(
+                r#" <span style="background: #f1f3f5; color: #555; padding: 1px 6px; border-radius: 3px; font-size: 0.75em;">{}</span>"#,
+                label
+            )
+        })
+        .collect()
+}
+
+/// Escaped in query values; file names keep their dots and dashes
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_');
+
+/// Short SHA-256 display for an asset, with the full digest in the tooltip,
+/// and a link to the asset on IPFS once it is pinned.
+fn format_digest(asset: &Asset, ipfs_gateway: &str) -> String {
+    let digest = match &asset.sha256 {
+        Some(hash) => format!(
+            r#" <code title="sha256:{}" style="background: #f6f8fa; color: #555; padding: 1px 5px; border-radius: 3px; font-size: 0.75em;">sha256:{}&hellip;</code>"#,
+            escape_html(hash),
+            escape_html(hash.get(..12).unwrap_or(hash))
+        ),
+        None => String::new(),
+    };
+    let cid = match &asset.cid {
+        Some(cid) => format!(
+            r#" <a href="{}/ipfs/{}?filename={}" title="ipfs://{}" style="background: #e6f7f9; color: #0b7285; padding: 1px 5px; border-radius: 3px; font-size: 0.75em;">IPFS</a>"#,
+            escape_html(ipfs_gateway),
+            utf8_percent_encode(cid, QUERY_VALUE),
+            utf8_percent_encode(&asset.name, QUERY_VALUE),
+            escape_html(cid)
+        ),
+        None => String::new(),
+    };
+    digest + &cid
+}
+
+/// Render assets grouped under Linux/macOS/Windows/Other headings.
+fn format_grouped_assets(assets: &[&Asset], render: impl Fn(&Asset) -> String) -> String {
+    Os::ALL
+        .iter()
+        .filter_map(|os| {
+            let rows = assets
+                .iter()
+                .filter(|a| platform::classify(&a.name).os == *os)
+                .map(|a| render(a))
+                .collect::<Vec<_>>();
+            if rows.is_empty() {
+                return None;
+            }
+            Some(format!(
+                r#"<div style="margin: 10px 0 4px 0; color: #444; font-weight: 600; font-size: 0.95em; display: flex; align-items: center; gap: 6px;">{} {} <span style="color: #999; font-weight: normal;">({})</span></div>
+                {}"#,
+                os_icon(*os, 16),
+                os.label(),
+                rows.len(),
+                rows.join("\n")
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Assets grouped by OS, with the ones `options` hides for the repo in a
+/// collapsed "Other files" section below.
+fn format_asset_sections(
+    assets: &[Asset],
+    repo_path: &str,
+    options: &RenderOptions,
+    render: impl Fn(&Asset) -> String,
+) -> String {
+    let (hidden, shown): (Vec<&Asset>, Vec<&Asset>) = assets
+        .iter()
+        .partition(|a| options.hidden_assets.is_hidden(repo_path, &a.name));
+    let mut html = format_grouped_assets(&shown, &render);
+    if !hidden.is_empty() {
+        html.push_str(&format!(
+            r#"<details style="margin-top: 8px;">
+                <summary style="cursor: pointer; color: #777; font-weight: 500;">Other files ({})</summary>
+                {}
+            </details>"#,
+            hidden.len(),
+            hidden.iter().map(|a| render(a)).collect::<Vec<_>>().join("\n")
+        ));
+    }
+    html
+}
+
+/// Escape text for safe inclusion in HTML element content and attributes.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Put `banner` at the top of a page's body.
+pub fn insert_banner(page: &str, banner: &str) -> String {
+    match page.find("<body>") {
+        Some(at) => {
+            let at = at + "<body>".len();
+            format!("{}\n    {}{}", &page[..at], banner, &page[at..])
+        }
+        None => page.to_string(),
+    }
+}
+
+/// Put `footer` at the bottom of a page's body.
+pub fn insert_footer(page: &str, footer: &str) -> String {
+    match page.rfind("</body>") {
+        Some(at) => format!("{}    {}\n{}", &page[..at], footer, &page[at..]),
+        None => page.to_string(),
+    }
+}
+
+/// Where an asset's expected hash can be obtained for verification.
+pub enum Checksum {
+    /// The hex SHA-256 is known up front (upstream digest or checksum file)
+    Sha256(String),
+    /// A sibling checksum file (e.g. "app.tar.gz.sha256") downloadable from `url`
+    File { url: String, name: String },
+}
+
+/// Find a checksum for `asset`: its known hash, or a sidecar among the other assets.
+fn find_checksum(asset: &Asset, assets: &[Asset]) -> Option<Checksum> {
+    if let Some(hash) = &asset.sha256 {
+        return Some(Checksum::Sha256(hash.clone()));
+    }
+    let sidecar = format!("{}.sha256", asset.name);
+    assets
+        .iter()
+        .find(|a| a.name == sidecar)
+        .map(|a| Checksum::File {
+            url: a.url.clone(),
+            name: a.name.clone(),
+        })
+}
+
+/// Quote `s` as one word for POSIX shells: single quotes, with each `'`
+/// closed, escaped and reopened.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Quote `s` as a PowerShell string literal. Single quotes inside are
+/// doubled, including the typographic ones PowerShell also accepts.
+fn powershell_quote(s: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in s.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
         }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Build copyable `curl`, `wget` and PowerShell commands downloading `url` to
+/// `filename`. Upstream URLs, names and hashes are quoted, so pasting a
+/// snippet never runs more than the download and the check.
+fn install_snippets(
+    url: &str,
+    filename: &str,
+    checksum: Option<&Checksum>,
+) -> Vec<(&'static str, String)> {
+    let (sh_url, sh_file) = (shell_quote(url), shell_quote(filename));
+    let (ps_url, ps_file) = (powershell_quote(url), powershell_quote(filename));
+    let mut curl = format!("curl -LO {}", sh_url);
+    let mut wget = format!("wget {}", sh_url);
+    let mut powershell = format!("Invoke-WebRequest -Uri {} -OutFile {}", ps_url, ps_file);
+
+    if let Some(Checksum::Sha256(hash)) = checksum {
+        let verify = format!(
+            "echo {} | sha256sum -c -",
+            shell_quote(&format!("{}  {}", hash, filename))
+        );
+        curl = format!("{} && {}", curl, verify);
+        wget = format!("{} && {}", wget, verify);
+        powershell = format!(
+            "{}; if ((Get-FileHash -LiteralPath {} -Algorithm SHA256).Hash -ne {}) {{ throw 'Checksum mismatch' }}",
+            powershell,
+            ps_file,
+            powershell_quote(hash)
+        );
+    } else if let Some(Checksum::File {
+        url: sum_url,
+        name: sum_name,
+    }) = checksum
+    {
+        let sh_sum = shell_quote(sum_name);
+        let ps_sum = powershell_quote(sum_name);
+        let verify = format!(
+            "printf '%s  %s\\n' \"$(cut -d' ' -f1 {})\" {} | sha256sum -c -",
+            sh_sum, sh_file
+        );
+        curl = format!(
+            "{} && curl -LO {} && {}",
+            curl,
+            shell_quote(sum_url),
+            verify
+        );
+        wget = format!("{} && wget {} && {}", wget, shell_quote(sum_url), verify);
+        powershell = format!(
+            "{}; Invoke-WebRequest -Uri {} -OutFile {}; if ((Get-FileHash -LiteralPath {} -Algorithm SHA256).Hash -ne (Get-Content -LiteralPath {}).Split(' ')[0]) {{ throw 'Checksum mismatch' }}",
+            powershell,
+            powershell_quote(sum_url),
+            ps_sum,
+            ps_file,
+            ps_sum
+        );
+    }
+
+    vec![("curl", curl), ("wget", wget), ("PowerShell", powershell)]
+}
+
+/// Collapsible block of install snippets with copy-to-clipboard buttons.
+fn format_install_snippets(url: &str, filename: &str, checksum: Option<&Checksum>) -> String {
+    let snippets = install_snippets(url, filename, checksum)
+        .into_iter()
+        .map(|(label, cmd)| {
+            format!(
+                r#"<div style="display: flex; align-items: center; gap: 6px; margin: 4px 0;">
+                    <span style="width: 80px; flex-shrink: 0; font-size: 0.8em; color: #666;">{}</span>
+                    <code style="flex: 1; overflow-x: auto; white-space: nowrap; background: #f6f8fa; padding: 4px 6px; border-radius: 4px; font-size: 0.8em; color: #333;">{}</code>
+                    <button onClick="navigator.clipboard.writeText(this.previousElementSibling.innerText)" style="cursor: pointer; border: 1px solid #e1e4e8; background: #fff; border-radius: 4px; font-size: 0.8em; padding: 2px 8px;">Copy</button>
+                </div>"#,
+                label,
+                escape_html(&cmd)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"<details style="flex-basis: 100%; margin-top: 6px;">
+            <summary style="cursor: pointer; color: #777; font-size: 0.85em;">Install commands</summary>
+            {}
+        </details>"#,
+        snippets
+    )
+}
+
+/// `docker pull` commands for a release's container images.
+fn format_container_images(images: &[String]) -> String {
+    if images.is_empty() {
+        return String::new();
+    }
+    let commands = images
+        .iter()
+        .map(|image| {
+            format!(
+                r#"<div style="display: flex; align-items: center; gap: 6px; margin: 4px 0;">
+                    <code style="flex: 1; overflow-x: auto; white-space: nowrap; background: #f6f8fa; padding: 4px 6px; border-radius: 4px; font-size: 0.8em; color: #333;">docker pull {}</code>
+                    <button onClick="navigator.clipboard.writeText(this.previousElementSibling.innerText)" style="cursor: pointer; border: 1px solid #e1e4e8; background: #fff; border-radius: 4px; font-size: 0.8em; padding: 2px 8px;">Copy</button>
+                </div>"#,
+                escape_html(image)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"<div style="margin: 15px 0;">
+            <strong style="font-size: 1.1em;">Container image:</strong>
+            <div style="margin-top: 8px;">
+                {}
+            </div>
+        </div>"#,
+        commands
+    )
+}
+
+/// "Install" section with the latest release's install commands.
+fn format_install_commands(releases: &[Release]) -> String {
+    let Some(latest) = releases.iter().find(|r| !r.draft) else {
+        return String::new();
+    };
+    if latest.install.is_empty() {
+        return String::new();
+    }
+    let commands = latest
+        .install
+        .iter()
+        .map(|c| {
+            let source = match c.source {
+                InstallSource::Notes => "release notes",
+                InstallSource::Readme => "README",
+            };
+            format!(
+                r#"<div style="display: flex; align-items: center; gap: 6px; margin: 4px 0;">
+                    <span style="flex-shrink: 0; width: 110px; color: #586069; font-size: 0.85em;" title="From the {}">{}</span>
+                    <code style="flex: 1; overflow-x: auto; white-space: nowrap; background: #f6f8fa; padding: 4px 6px; border-radius: 4px; font-size: 0.8em; color: #333;">{}</code>
+                    <button onClick="navigator.clipboard.writeText(this.previousElementSibling.innerText)" style="cursor: pointer; border: 1px solid #e1e4e8; background: #fff; border-radius: 4px; font-size: 0.8em; padding: 2px 8px;">Copy</button>
+                </div>"#,
+                source,
+                c.kind.label(),
+                escape_html(&c.command)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"<div style="margin-bottom: 30px; padding: 15px 20px; background: #fff; border: 1px solid #e1e4e8; border-radius: 12px;">
+            <h2 style="margin: 0 0 10px 0; color: #333; font-size: 1.2em;">Install</h2>
+            {}
+            <p style="margin: 8px 0 0 0; color: #666; font-size: 0.85em;">Found in the release notes and README of {}; check them before running.</p>
+        </div>"#,
+        commands,
+        escape_html(&latest.tag_name)
+    )
+}
+
+/// Instance-wide settings affecting rendered pages.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Public base URL used in generated links, without trailing slash
+    pub public_url: String,
+    /// Number of releases per page in the "All Releases" section
+    pub page_size: usize,
+    /// Age thresholds for the latest-release freshness badge
+    pub freshness: FreshnessThresholds,
+    /// Assets collapsed into "Other files"
+    pub hidden_assets: HiddenAssets,
+    /// `latest` names of assets in latest links
+    pub latest_names: LatestNames,
+    /// Names, descriptions, icons and homepages set for repos
+    pub displays: RepoDisplays,
+    /// IPFS gateway linked for pinned assets, without trailing slash
+    pub ipfs_gateway: String,
+    /// Pages end in a form subscribing to release emails
+    pub email_subscriptions: bool,
+}
+
+/// When a release was published, and rebuilt last for rolling tags.
+fn published_label(release: &Release) -> String {
+    let published = release.published_at.format("%Y-%m-%d %H:%M:%S UTC");
+    match release.updated_at {
+        Some(updated_at) => format!(
+            "{} • Rebuilt: {}",
+            published,
+            updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ),
+        None => published.to_string(),
     }
+}
+
+/// Colored badge for how long before `as_of` `published_at` was, e.g.
+/// "Active · 12 days ago".
+fn format_freshness_badge(
+    published_at: DateTime<Utc>,
+    as_of: DateTime<Utc>,
+    thresholds: &FreshnessThresholds,
+) -> String {
+    let freshness = thresholds.classify(published_at, as_of);
+    format!(
+        r#"<span style="background: {}; color: white; padding: 2px 8px; border-radius: 3px; font-size: 0.8em; font-weight: 600;" title="Latest release {}">{} · {} ago</span>"#,
+        freshness.color(),
+        published_at.format("%Y-%m-%d"),
+        freshness.label(),
+        freshness::format_age(published_at, as_of)
+    )
+}
 
-    // Fallback: split on last dot, but only if suffix looks like a real file extension
-    // (not a version fragment like ".2-linux-amd64" or ".2" in "forgejo-14.0.2-linux-amd64")
-    if let Some(pos) = filename.rfind('.') {
-        let suffix = &filename[pos + 1..];
-        if !suffix.is_empty()
-            && !suffix.contains('-')
-            && !suffix.chars().all(|c| c.is_ascii_digit())
-        {
-            return (&filename[..pos], &filename[pos..]);
+/// "Trusted publisher" badge when the latest release is signed by one of
+/// the repo's expected keys, and a warning when it isn't; nothing for repos
+/// without expected keys.
+fn format_publisher(releases: &[Release]) -> String {
+    let Some(latest) = releases.iter().find(|r| !r.draft) else {
+        return String::new();
+    };
+    let Some(check) = &latest.signature else {
+        return String::new();
+    };
+    let key = check.key.as_deref().map(escape_html).unwrap_or_default();
+    let problem = match check.status {
+        SignatureStatus::Trusted => {
+            return format!(
+                r#"<p><span style="background: #1a7f37; color: white; padding: 4px 10px; border-radius: 4px; font-weight: 600;" title="{} is signed by {}">&#10004; Trusted publisher</span></p>"#,
+                escape_html(&latest.tag_name),
+                key
+            );
         }
+        SignatureStatus::OtherKey => format!("is signed by a key that isn't expected ({})", key),
+        SignatureStatus::Invalid => format!("has a signature by {} that doesn't verify", key),
+        SignatureStatus::Unverifiable => "is signed in a format that can't be verified".to_string(),
+        SignatureStatus::Unsigned => "is unsigned".to_string(),
+    };
+    format!(
+        r#"<div style="margin: 10px 0; padding: 12px 16px; background: #ffebe9; border: 2px solid #cf222e; border-radius: 6px; color: #82071e;"><strong>&#9888; Not from the trusted publisher:</strong> the latest release {} {}.</div>"#,
+        escape_html(&latest.tag_name),
+        problem
+    )
+}
+
+/// Signature label of a release checked against expected keys.
+fn format_signature_badge(release: &Release) -> String {
+    let Some(check) = &release.signature else {
+        return String::new();
+    };
+    let color = if check.status == SignatureStatus::Trusted {
+        "#1a7f37"
+    } else {
+        "#cf222e"
+    };
+    format!(
+        r#" <span style="background: {}; color: white; padding: 2px 6px; border-radius: 3px; font-size: 0.8em;" title="{}">{}</span>"#,
+        color,
+        check.key.as_deref().map(escape_html).unwrap_or_default(),
+        check.status.label()
+    )
+}
+
+/// License label of a release, linking to its first license file when it
+/// ships one.
+fn format_license_badge(release: &Release) -> String {
+    let Some(license) = &release.license else {
+        return String::new();
+    };
+    let files = license.files.join(", ");
+    let title: Vec<&str> = [license.name.as_deref(), Some(files.as_str())]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect();
+    let badge = format!(
+        r#"<span style="background: #6e7781; color: white; padding: 2px 6px; border-radius: 3px; font-size: 0.8em;" title="{}">&#9878; {}</span>"#,
+        escape_html(&title.join("; ")),
+        escape_html(license.label())
+    );
+    let file = license
+        .files
+        .first()
+        .and_then(|f| release.assets.iter().find(|a| &a.name == f));
+    match file {
+        Some(asset) => format!(
+            r#" <a href="{}" style="text-decoration: none;">{}</a>"#,
+            escape_html(&asset.url),
+            badge
+        ),
+        None => format!(" {}", badge),
+    }
+}
+
+/// Number of pages needed to list `count` releases.
+pub fn page_count(count: usize, page_size: usize) -> usize {
+    count.div_ceil(page_size.max(1)).max(1)
+}
+
+/// Page links; `query` is prepended to `page=`, e.g. "component=cli&".
+fn format_pagination(page: usize, pages: usize, query: &str) -> String {
+    if pages <= 1 {
+        return String::new();
     }
-    (filename, "")
-}
-
-/// Rename an asset filename to a "latest" variant, stripping app name and version.
-/// e.g., "bat-v0.26.1-x86_64-linux.tar.gz" -> "latest-x86_64-linux.tar.gz"
-///       "forgejo-14.0.2-linux-amd64.xz"    -> "latest-linux-amd64.xz"
-///       "bat_0.26.1_amd64.deb"             -> "latest_amd64.deb"
-///       "linux-6.19.2.tar.gz"              -> "latest.tar.gz"
-pub fn rename_to_latest(filename: &str) -> String {
-    let (stem, ext) = split_stem_ext(filename);
-
-    // Detect separator: use '_' if stem contains underscores but no dashes
-    let sep = if stem.contains('-') { '-' } else { '_' };
-    let parts: Vec<&str> = stem.split(sep).collect();
-
-    // Find the version segment (optional "v" prefix + at least major.minor numeric)
-    let version_idx = parts.iter().position(|p| {
-        let p = p.strip_prefix('v').unwrap_or(p);
-        let mut iter = p.split('.');
-        iter.next().map_or(false, |s| s.parse::<u64>().is_ok())
-            && iter.next().map_or(false, |s| s.parse::<u64>().is_ok())
-    });
-
-    let suffix_parts = match version_idx {
-        // Drop everything up to and including the version
-        Some(idx) => &parts[idx + 1..],
-        // No version found: drop just the app name (first segment)
-        None => &parts[1..],
-    };
-
-    if suffix_parts.is_empty() {
-        format!("latest{}", ext)
+    let prev = if page > 1 {
+        format!(r#"<a href="?{}page={}">&larr; Newer</a>"#, query, page - 1)
     } else {
-        format!(
-            "latest{}{}{}",
-            sep,
-            suffix_parts.join(&sep.to_string()),
-            ext
-        )
+        String::new()
+    };
+    let next = if page < pages {
+        format!(r#"<a href="?{}page={}">Older &rarr;</a>"#, query, page + 1)
+    } else {
+        String::new()
+    };
+    format!(
+        r#"<div style="display: flex; justify-content: space-between; align-items: center; margin: 15px 0; color: #666;"><span>{}</span><span>Page {} of {}</span><span>{}</span></div>"#,
+        prev, page, pages, next
+    )
+}
+
+/// Repo path as used in routes: "github.com/owner/repo" -> "owner/repo" for
+/// GitHub, Codeberg, SourceHut, Hugging Face and gitlab.com, the project for
+/// Launchpad, the snap name for the Snap Store, the cache path elsewhere.
+fn route_path(route_prefix: &str, repo_path: &str) -> String {
+    if route_prefix == "gitlab" {
+        gitlab::route_path(repo_path).to_string()
+    } else if matches!(
+        route_prefix,
+        "github" | "codeberg" | "sourcehut" | "huggingface"
+    ) {
+        repo_path
+            .split_once('/')
+            .map_or(repo_path, |(_, rest)| rest)
+            .to_string()
+    } else if matches!(
+        route_prefix,
+        "cgit" | "gitiles" | "hg" | "pagure" | "autoindex"
+    ) {
+        repo_path.replace("//", "/")
+    } else if matches!(route_prefix, "launchpad" | "snap") {
+        repo_path
+            .rsplit_once('/')
+            .map_or(repo_path, |(_, project)| project)
+            .to_string()
+    } else {
+        repo_path.to_string()
     }
 }
 
+/// Render the releases page. `page` is 1-based and selects the slice of
+/// releases shown under "All Releases"; the latest box is always shown.
 pub fn format_releases_html(
     releases: &[Release],
     repo_path: &str,
     route_prefix: &str,
     cached_at: Option<DateTime<Utc>>,
+    options: &RenderOptions,
+    page: usize,
+) -> String {
+    render_releases(
+        releases,
+        repo_path,
+        route_prefix,
+        cached_at,
+        options,
+        page,
+        &PageFilter::default(),
+    )
+}
+
+/// Query parameters a releases page was asked for, kept in its links.
+#[derive(Default)]
+pub struct PageFilter<'a> {
+    /// Monorepo component, kept in latest links and page links
+    pub component: Option<&'a str>,
+    /// Language the notes were translated into, kept in page links
+    pub lang: Option<&'a str>,
+    /// Release channel of a `/channel/{name}` page, kept in latest links
+    pub channel: Option<Channel>,
+}
+
+/// Render the releases page of one monorepo component or release channel,
+/// and/or with translated notes.
+pub fn format_filtered_releases_html(
+    releases: &[Release],
+    repo_path: &str,
+    route_prefix: &str,
+    cached_at: Option<DateTime<Utc>>,
+    options: &RenderOptions,
+    page: usize,
+    filter: &PageFilter,
+) -> String {
+    render_releases(
+        releases,
+        repo_path,
+        route_prefix,
+        cached_at,
+        options,
+        page,
+        filter,
+    )
+}
+
+fn render_releases(
+    releases: &[Release],
+    repo_path: &str,
+    route_prefix: &str,
+    cached_at: Option<DateTime<Utc>>,
+    options: &RenderOptions,
+    page: usize,
+    filter: &PageFilter,
 ) -> String {
+    let encode = |v: &str| url::form_urlencoded::byte_serialize(v.as_bytes()).collect::<String>();
+    let component = filter.component.map(encode);
+    // Appended to latest links; the component is also put before `page=` in
+    // page links, while channel pages have their channel in the path
+    let channel = filter.channel.map(|c| c.name());
+    let latest_params: Vec<String> = component
+        .iter()
+        .map(|c| format!("component={}", c))
+        .chain(channel.map(|c| format!("channel={}", c)))
+        .collect();
+    let latest_query = if latest_params.is_empty() {
+        String::new()
+    } else {
+        format!("?{}", latest_params.join("&"))
+    };
+    let mut page_query = component
+        .as_ref()
+        .map(|c| format!("component={}&", c))
+        .unwrap_or_default();
+    if let Some(lang) = filter.lang {
+        page_query.push_str(&format!("lang={}&", encode(lang)));
+    }
+    let labels: Vec<String> = component
+        .iter()
+        .map(|c| escape_html(c))
+        .chain(channel.map(String::from))
+        .collect();
+    let display = options.displays.get(repo_path);
+    let name = display
+        .and_then(|d| d.name.as_deref())
+        .map_or_else(|| repo_path.to_string(), escape_html);
+    let title = if labels.is_empty() {
+        name
+    } else {
+        format!("{} ({})", name, labels.join(", "))
+    };
+    let public_url = options.public_url.as_str();
+    let pages = page_count(releases.len(), options.page_size);
+    let page = page.clamp(1, pages);
+    let page_start = (page - 1) * options.page_size;
+
+    // Aged as of the fetch, so a page renders the same until the next one
+    let freshness_info = releases
+        .iter()
+        .find(|r| !r.draft)
+        .map(|r| {
+            format!(
+                "<p>{}</p>",
+                format_freshness_badge(
+                    r.published_at,
+                    cached_at.unwrap_or_else(Utc::now),
+                    &options.freshness
+                )
+            )
+        })
+        .unwrap_or_default();
     let cache_info = cached_at
         .map(|t| {
             format!(
-                "<p><em>Cached at: {}</em></p>",
+                "{}<p><em>Cached at: {}</em></p>",
+                freshness_info,
                 t.format("%Y-%m-%d %H:%M:%S UTC")
             )
         })
-        .unwrap_or_default();
+        .unwrap_or(freshness_info);
+    let cache_info = format!("{}{}", cache_info, format_publisher(releases));
+    let cache_info = match filter.lang {
+        Some(lang) => format!(
+            "{}<p><em>Release notes machine-translated into '{}'; <a href=\"?{}\">show originals</a></em></p>",
+            cache_info,
+            escape_html(lang),
+            latest_query.trim_start_matches('?')
+        ),
+        None => cache_info,
+    };
+
+    let path_for_url = route_path(route_prefix, repo_path);
 
     // Latest assets box at the top
     let latest_assets_box = if let Some(latest) = releases.first() {
         if !latest.assets.is_empty() {
-            let assets_list = latest
-                .assets
-                .iter()
-                .map(|a| {
-                    let size_info = if a.size > 0 {
-                        format!(" <span style='color: #666;'>({})</span>", format_size(a.size))
-                    } else {
-                        String::new()
-                    };
-                    let icon = icons::get_file_icon(&a.name, 18);
-                    let latest_name = rename_to_latest(&a.name);
-                    let path_for_url = if route_prefix == "github" || route_prefix == "gitlab" {
-                        // Strip the host part (e.g., "github.com/owner/repo" -> "owner/repo")
-                        repo_path.splitn(2, '/').nth(1).unwrap_or(repo_path).to_string()
-                    } else if route_prefix == "cgit" {
-                        repo_path.replace("//", "/")
-                    } else {
-                        repo_path.to_string()
-                    };
-
-                    let latest_url = format!("/{}/{}/{}", route_prefix, path_for_url, latest_name);
+            let assets_list = format_asset_sections(&latest.assets, repo_path, options, |a| {
+                let size_info = if a.size > 0 {
                     format!(
-                        r#"<div style="padding: 10px; margin: 6px 0; color: #777; background: #fff; border: 1px solid #28a745; border-radius: 6px; display: flex; justify-content: space-between; align-items: center;">
-                            <div style="display: flex; align-items: center; gap: 6px;"><span style="display: flex; flex-shrink: 0;">{}</span> <a href="{}" style="font-weight: 600; color: #0366d6; font-size: 1.05em;">{}</a>{}</div>
+                        " <span style='color: #666;'>({})</span>",
+                        format_size(a.size)
+                    )
+                } else {
+                    String::new()
+                };
+                let icon = icons::get_file_icon(&a.name, a.content_type.as_deref(), 18);
+                let latest_name = options.latest_names.rename(&a.name);
+
+                let latest_url = format!(
+                    "/{}/{}/{}{}",
+                    route_prefix, path_for_url, latest_name, latest_query
+                );
+                // Latest-redirect checksum sidecars are renamed the same way as their asset
+                let checksum = find_checksum(a, &latest.assets).map(|c| match c {
+                    Checksum::Sha256(hash) => Checksum::Sha256(hash),
+                    Checksum::File { name, .. } => {
+                        let latest_sum = options.latest_names.rename(&name);
+                        Checksum::File {
+                            url: format!(
+                                "{}/{}/{}/{}{}",
+                                public_url, route_prefix, path_for_url, latest_sum, latest_query
+                            ),
+                            name: latest_sum,
+                        }
+                    }
+                });
+                format!(
+                    r#"<div style="padding: 10px; margin: 6px 0; color: #777; background: #fff; border: 1px solid #28a745; border-radius: 6px; display: flex; flex-wrap: wrap; justify-content: space-between; align-items: center;">
+                            <div style="display: flex; align-items: center; gap: 6px;"><span style="display: flex; flex-shrink: 0;">{}</span> <a href="{}" style="font-weight: 600; color: #0366d6; font-size: 1.05em;">{}</a>{}{}{}</div>
                             <div>
                                 <a href="{}" style="background: #28a745; color: white; padding: 6px 12px; border-radius: 4px; text-decoration: none; font-weight: 500; display: inline-flex; align-items: center; gap: 4px;">{} Download</a>
                             </div>
+                            {}
                         </div>"#,
-                        icon, a.url, a.name, size_info, latest_url, icons::DOWNLOAD(16)
+                    icon,
+                    escape_html(&a.url),
+                    escape_html(&a.name),
+                    size_info,
+                    format_platform_badges(a),
+                    format_digest(a, &options.ipfs_gateway),
+                    escape_html(&latest_url),
+                    icons::DOWNLOAD(16),
+                    format_install_snippets(
+                        &format!("{}{}", public_url, latest_url),
+                        &latest_name,
+                        checksum.as_ref()
                     )
-                })
-                .collect::<Vec<_>>()
-                .join("\n");
+                )
+            });
 
             let version_name = latest.name.as_ref().unwrap_or(&latest.tag_name);
             format!(
@@ -169,8 +764,8 @@ pub fn format_releases_html(
                     </div>
                 </div>"#,
                 icons::STAR(16),
-                version_name,
-                latest.published_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                escape_html(version_name),
+                published_label(latest),
                 latest.assets.len(),
                 assets_list
             )
@@ -184,6 +779,8 @@ pub fn format_releases_html(
     let releases_html = releases
         .iter()
         .enumerate()
+        .skip(page_start)
+        .take(options.page_size)
         .map(|(idx, r)| {
             let latest_badge = if idx == 0 {
                 &format!(r#" <span style="background: #28a745; color: white; padding: 2px 8px; border-radius: 3px; font-size: 0.8em; font-weight: bold; display: inline-flex; align-items: center; gap: 4px;">{} Latest</span>"#, icons::STAR(12))
@@ -204,10 +801,7 @@ pub fn format_releases_html(
 
             // Format assets - show prominently at the top
             let assets_html = if !r.assets.is_empty() {
-                let assets_list = r
-                    .assets
-                    .iter()
-                    .map(|a| {
+                let assets_list = format_asset_sections(&r.assets, repo_path, options, |a| {
                         let size_info = if a.size > 0 {
                             format!(" <span style='color: #666;'>({})</span>", format_size(a.size))
                         } else {
@@ -218,17 +812,26 @@ pub fn format_releases_html(
                         } else {
                             String::new()
                         };
-                        let icon = icons::get_file_icon(&a.name, 16);
+                        let icon = icons::get_file_icon(&a.name, a.content_type.as_deref(), 16);
+                        let sbom_link = if sbom::is_json_sbom(&a.name) {
+                            format!(
+                                r#" <a href="/{}/{}/{}/sbom" style="font-size: 0.85em; color: #0366d6;">Summary</a>"#,
+                                route_prefix, path_for_url, escape_html(&r.tag_name)
+                            )
+                        } else {
+                            String::new()
+                        };
                         format!(
-                            r#"<div style="padding: 8px; color: #777; margin: 4px 0; background: #fff; border: 1px solid #e1e4e8; border-radius: 6px; display: flex; align-items: center; gap: 6px;">
+                            r#"<div class="asset" data-category="{}" data-name="{}" style="padding: 8px; color: #777; margin: 4px 0; background: #fff; border: 1px solid #e1e4e8; border-radius: 6px; display: flex; flex-wrap: wrap; align-items: center; gap: 6px;">
                                 <span style="display: flex; flex-shrink: 0;">{}</span>
-                                <a href="{}" style="font-weight: 500; color: #0366d6;">{}</a>{}{}
+                                <a href="{}" style="font-weight: 500; color: #0366d6;">{}</a>{}{}{}{}{}
+                                {}
                             </div>"#,
-                            icon, a.url, a.name, size_info, download_info
+                            platform::category(&a.name, &r.tag_name), escape_html(&a.name.to_lowercase()),
+                            icon, escape_html(&a.url), escape_html(&a.name), format_platform_badges(a), size_info, download_info, format_digest(a, &options.ipfs_gateway), sbom_link,
+                            format_install_snippets(&a.url, &a.name, find_checksum(a, &r.assets).as_ref())
                         )
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n");
+                    });
 
                 format!(
                     r#"<div style="margin: 15px 0;">
@@ -248,13 +851,12 @@ pub fn format_releases_html(
             // Body text - collapsible/hidden by default
             let body_html = if let Some(body) = &r.body {
                 if !body.is_empty() {
-                    let body_preview = body.lines().take(3).collect::<Vec<_>>().join("<br>");
                     format!(
                         r#"<details style="margin-top: 10px;">
                             <summary style="cursor: pointer; color: #777; font-weight: 500; display: inline-flex; align-items: center; gap: 4px;">{} Show release notes</summary>
-                            <div style="margin-top: 10px; padding: 10px; background: #f6f8fa; border-radius: 6px; white-space: pre-wrap; font-size: 0.9em;">{}</div>
+                            <div class="notes" style="margin-top: 10px; padding: 10px; background: #f6f8fa; border-radius: 6px; font-size: 0.9em;">{}</div>
                         </details>"#,
-                        icons::NOTE(16), body_preview
+                        icons::NOTE(16), markdown::render_markdown(body)
                     )
                 } else {
                     String::new()
@@ -264,22 +866,27 @@ pub fn format_releases_html(
             };
 
             format!(
-                r#"<li style="margin-bottom: 25px; padding: 20px; background: #fff; border: 1px solid #e1e4e8; border-radius: 8px; list-style: none;">
+                r#"<li class="release" data-search="{}" style="margin-bottom: 25px; padding: 20px; background: #fff; border: 1px solid #e1e4e8; border-radius: 8px; list-style: none;">
                     <div style="display: flex; align-items: center; gap: 10px; margin-bottom: 10px;">
-                        <strong style="font-size: 1.3em;"><a href="{}" target="_blank" style="color: #0366d6;">{}</a></strong>{}{}{}
+                        <strong style="font-size: 1.3em;"><a href="{}" target="_blank" style="color: #0366d6;">{}</a></strong>{}{}{}{}{}
                     </div>
                     <small style="color: #586069; display: inline-flex; align-items: center; gap: 4px;">{} Published: {}</small>
                     {}
                     {}
+                    {}
                 </li>"#,
-                r.html_url,
-                name,
+                escape_html(&format!("{} {}", r.tag_name, name).to_lowercase()),
+                escape_html(&r.html_url),
+                escape_html(name),
                 latest_badge,
                 prerelease_badge,
                 draft_badge,
+                format_signature_badge(r),
+                format_license_badge(r),
                 icons::CALENDAR(16),
-                r.published_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                published_label(r),
                 assets_html,
+                format_container_images(&r.container_images),
                 body_html
             )
         })
@@ -301,24 +908,131 @@ pub fn format_releases_html(
         a:hover {{ text-decoration: underline; }}
         small {{ color: #666; }}
         p {{ color: #444; margin: 5px 0; }}
+        .notes pre {{ padding: 10px; border: 1px solid #e1e4e8; border-radius: 6px; overflow-x: auto; }}
+        .notes img {{ max-width: 100%; }}
     </style>
 </head>
 <body>
-    <h1>Releases for {}</h1>
+    <h1>{}Releases for {}</h1>
+    {}
+    {}
     {}
     {}
     <h2 style="margin-top: 30px; color: #333; display: flex; align-items: center; gap: 6px;">{} All Releases</h2>
+    <div style="display: flex; flex-wrap: wrap; gap: 8px; align-items: center; margin: 15px 0;">
+        <input id="search" type="search" placeholder="Search releases and assets..." oninput="filterReleases()" style="flex: 1; min-width: 200px; padding: 8px 10px; border: 1px solid #e1e4e8; border-radius: 6px; font-size: 0.95em;">
+        <select id="category" onchange="filterReleases()" style="padding: 8px; border: 1px solid #e1e4e8; border-radius: 6px; background: #fff;">
+            <option value="">All files</option>
+            <option value="binary">Binaries</option>
+            <option value="checksum">Checksums</option>
+            <option value="source">Source</option>
+        </select>
+    </div>
     <ul>
         {}
     </ul>
+    {}
+    {}
+    <script>
+        function filterReleases() {{
+            const query = document.getElementById('search').value.trim().toLowerCase();
+            const category = document.getElementById('category').value;
+            document.querySelectorAll('li.release').forEach(function (release) {{
+                const releaseMatches = release.dataset.search.includes(query);
+                let visibleAssets = 0;
+                release.querySelectorAll('.asset').forEach(function (asset) {{
+                    const show = (!category || asset.dataset.category === category)
+                        && (releaseMatches || asset.dataset.name.includes(query));
+                    asset.style.display = show ? '' : 'none';
+                    if (show) visibleAssets++;
+                }});
+                const filtering = query || category;
+                release.style.display = !filtering || visibleAssets > 0 || (releaseMatches && !category) ? '' : 'none';
+            }});
+        }}
+    </script>
 </body>
 </html>"#,
-        repo_path,
-        repo_path,
+        title,
+        display
+            .map(|d| format_display_icon(d, 32))
+            .unwrap_or_default(),
+        title,
+        display
+            .map(|d| format_display_details(d, repo_path))
+            .unwrap_or_default(),
         cache_info,
         latest_assets_box,
+        format_install_commands(releases),
         icons::NOTE(18),
-        releases_html
+        releases_html,
+        format_pagination(page, pages, &page_query),
+        format_subscribe_form(route_prefix, &path_for_url, options)
+    )
+}
+
+/// A repo's own icon, before its name.
+fn format_display_icon(display: &RepoDisplay, size: u32) -> String {
+    display
+        .icon
+        .as_ref()
+        .map(|icon| {
+            format!(
+                r#"<img src="{}" alt="" width="{}" height="{}" style="vertical-align: middle; margin-right: 8px; border-radius: 4px;">"#,
+                escape_html(icon),
+                size,
+                size
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Description and homepage under a repo's name, with the upstream path when
+/// the name replaces it.
+fn format_display_details(display: &RepoDisplay, repo_path: &str) -> String {
+    let mut details: Vec<String> = Vec::new();
+    if let Some(description) = &display.description {
+        details.push(escape_html(description));
+    }
+    if let Some(homepage) = &display.homepage {
+        details.push(format!(
+            r#"<a href="{}">{}</a>"#,
+            escape_html(homepage),
+            escape_html(
+                homepage
+                    .trim_start_matches("https://")
+                    .trim_start_matches("http://")
+                    .trim_end_matches('/')
+            )
+        ));
+    }
+    if display.name.is_some() {
+        details.push(format!("<small>{}</small>", escape_html(repo_path)));
+    }
+    if details.is_empty() {
+        return String::new();
+    }
+    format!("<p>{}</p>", details.join(" &middot; "))
+}
+
+/// Form posting to `/subscribe`, when email subscriptions are on.
+fn format_subscribe_form(
+    route_prefix: &str,
+    path_for_url: &str,
+    options: &RenderOptions,
+) -> String {
+    if !options.email_subscriptions {
+        return String::new();
+    }
+    format!(
+        r#"<form method="post" action="/subscribe" style="margin-top: 30px; padding: 15px; background: #f6f8fa; border: 1px solid #e1e4e8; border-radius: 8px; display: flex; flex-wrap: wrap; gap: 8px; align-items: center;">
+        <label for="subscribe-email" style="color: #444;">Get an email for each new release:</label>
+        <input type="hidden" name="repo" value="{}/{}">
+        <input id="subscribe-email" type="email" name="email" required placeholder="you@example.com" style="flex: 1; min-width: 200px; padding: 8px 10px; border: 1px solid #e1e4e8; border-radius: 6px;">
+        <button type="submit" style="padding: 8px 14px; background: #0366d6; color: white; border: none; border-radius: 6px; cursor: pointer;">Subscribe</button>
+    </form>"#,
+        route_prefix,
+        escape_html(path_for_url)
     )
 }
 
@@ -367,7 +1081,8 @@ pub fn format_processing_html(repo_path: &str, _route_prefix: &str) -> String {
     </div>
 </body>
 </html>"#,
-        repo_path, repo_path
+        escape_html(repo_path),
+        escape_html(repo_path)
     )
 }
 
@@ -408,6 +1123,1377 @@ pub fn format_error_html(repo_path: &str, error_message: &str, _route_prefix: &s
     </div>
 </body>
 </html>"#,
-        repo_path, repo_path, error_message
+        escape_html(repo_path),
+        escape_html(repo_path),
+        escape_html(error_message)
+    )
+}
+
+/// Branded page for errors returned from handlers (bad paths, unknown routes,
+/// upstream failures). `formats` lists (URL format, example) pairs to hint at.
+pub fn format_app_error_html(
+    status: u16,
+    title: &str,
+    message: &str,
+    formats: &[(&str, &str)],
+) -> String {
+    let hints = if formats.is_empty() {
+        String::new()
+    } else {
+        let rows = formats
+            .iter()
+            .map(|(format, example)| {
+                format!(
+                    r#"<li><code>{}</code><br><small>e.g. <a href="{}">{}</a></small></li>"#,
+                    escape_html(format),
+                    escape_html(example),
+                    escape_html(example)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            r#"<div class="hints">
+            <p>Supported URL formats:</p>
+            <ul>{}</ul>
+        </div>"#,
+            rows
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>{} {} - Checkup</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
+        .container {{ text-align: center; padding: 60px 20px; }}
+        .status {{ font-size: 64px; font-weight: bold; color: #d73a49; margin-bottom: 10px; }}
+        h1 {{ margin-bottom: 10px; }}
+        p {{ color: #666; font-size: 1.1em; }}
+        .error-box {{
+            background: #fff5f5;
+            border: 1px solid #f5c6cb;
+            border-radius: 6px;
+            padding: 15px;
+            margin: 20px auto;
+            max-width: 600px;
+            text-align: left;
+            color: #721c24;
+            font-family: monospace;
+            font-size: 0.9em;
+        }}
+        .hints {{ max-width: 600px; margin: 0 auto; text-align: left; }}
+        .hints ul {{ list-style: none; padding: 0; }}
+        .hints li {{ margin: 10px 0; }}
+        code {{ background: #f6f8fa; padding: 2px 6px; border-radius: 4px; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="status">{}</div>
+        <h1>{}</h1>
+        <div class="error-box">{}</div>
+        {}
+        <p><a href="/">&larr; Back to Checkup</a></p>
+    </div>
+</body>
+</html>"#,
+        status,
+        escape_html(title),
+        status,
+        escape_html(title),
+        escape_html(message),
+        hints
     )
 }
+
+const NOTICE_STYLE: &str = r#"body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }
+        .container { text-align: center; padding: 60px 20px; }
+        h1 { margin-bottom: 10px; }
+        p { color: #666; font-size: 1.1em; }
+        button { padding: 10px 20px; background: #0366d6; color: white; border: none; border-radius: 6px; font-size: 1em; cursor: pointer; }
+        a { color: #0366d6; text-decoration: none; }"#;
+
+/// Username and password form posting to `action` (`/login` or
+/// `/signup`), with `error` from the last attempt and a button for each
+/// OpenID Connect provider in `providers` (name and label).
+pub fn format_sign_in_html(
+    action: &str,
+    title: &str,
+    error: Option<&str>,
+    signup_link: bool,
+    providers: &[(String, String)],
+) -> String {
+    let error = error
+        .map(|e| format!(r#"<p style="color: #d73a49;">{}</p>"#, escape_html(e)))
+        .unwrap_or_default();
+    let signup = if signup_link {
+        r#"<p>No account yet? <a href="/signup">Create one</a>.</p>"#
+    } else {
+        ""
+    };
+    let providers: String = providers
+        .iter()
+        .map(|(name, label)| {
+            format!(
+                r#"<p><a class="button" href="/login/oidc/{}">Sign in with {}</a></p>"#,
+                escape_html(name),
+                escape_html(label)
+            )
+        })
+        .collect();
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>{} - Checkup</title>
+    <style>
+        {}
+        input {{ display: block; margin: 10px auto; padding: 8px; width: 260px; font-size: 1em; }}
+        a.button {{ display: inline-block; padding: 10px 20px; border: 1px solid #0366d6; border-radius: 6px; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>{}</h1>
+        {}
+        <form method="post" action="{}">
+            <input name="username" placeholder="Username" autocomplete="username" required>
+            <input name="password" type="password" placeholder="Password" autocomplete="{}" required>
+            <button type="submit">{}</button>
+        </form>
+        {}
+        {}
+    </div>
+</body>
+</html>"#,
+        escape_html(title),
+        NOTICE_STYLE,
+        escape_html(title),
+        error,
+        action,
+        if action == "/signup" {
+            "new-password"
+        } else {
+            "current-password"
+        },
+        escape_html(title),
+        providers,
+        signup
+    )
+}
+
+/// Page with a short message, e.g. the outcome of a subscription request.
+pub fn format_notice_html(title: &str, message: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>{} - Checkup</title>
+    <style>
+        {}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>{}</h1>
+        <p>{}</p>
+        <p><a href="/">&larr; Back to Checkup</a></p>
+    </div>
+</body>
+</html>"#,
+        escape_html(title),
+        NOTICE_STYLE,
+        escape_html(title),
+        escape_html(message)
+    )
+}
+
+/// Page with a button posting `token` to `action`, for links in emails that
+/// must not take effect by merely being opened.
+pub fn format_token_form_html(
+    title: &str,
+    message: &str,
+    action: &str,
+    token: &str,
+    button: &str,
+) -> String {
+    let token = url::form_urlencoded::byte_serialize(token.as_bytes()).collect::<String>();
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>{} - Checkup</title>
+    <style>
+        {}
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>{}</h1>
+        <p>{}</p>
+        <form method="post" action="{}?token={}"><button type="submit">{}</button></form>
+    </div>
+</body>
+</html>"#,
+        escape_html(title),
+        NOTICE_STYLE,
+        escape_html(title),
+        escape_html(message),
+        action,
+        token,
+        escape_html(button)
+    )
+}
+
+pub fn format_status_html(hosts: &[HostStatus]) -> String {
+    let format_time = |t: Option<DateTime<Utc>>| {
+        t.map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_else(|| "never".to_string())
+    };
+
+    let rows = hosts
+        .iter()
+        .map(|h| {
+            let (breaker_color, breaker_label) = match h.breaker {
+                BreakerState::Closed => ("#28a745", "closed"),
+                BreakerState::HalfOpen => ("#f0ad4e", "half-open"),
+                BreakerState::Open => ("#d73a49", "open"),
+            };
+            let rate_limit = h
+                .rate_limit
+                .as_ref()
+                .map(|r| match (r.limit, r.reset_at) {
+                    (Some(limit), Some(reset)) => format!(
+                        "{}/{} (resets {})",
+                        r.remaining,
+                        limit,
+                        reset.format("%H:%M UTC")
+                    ),
+                    (Some(limit), None) => format!("{}/{}", r.remaining, limit),
+                    _ => r.remaining.to_string(),
+                })
+                .unwrap_or_else(|| "-".to_string());
+            let last_error = h
+                .last_error
+                .as_ref()
+                .map(|e| {
+                    format!(
+                        r#"<br><small style="color: #d73a49;">{}: {}</small>"#,
+                        format_time(h.last_error_at),
+                        escape_html(e)
+                    )
+                })
+                .unwrap_or_default();
+            format!(
+                r#"<tr>
+                    <td><code>{}</code>{}</td>
+                    <td>{}</td>
+                    <td>{:.0}% of {}</td>
+                    <td>{}</td>
+                    <td><span style="background: {}; color: white; padding: 2px 8px; border-radius: 3px; font-size: 0.85em;">{}</span></td>
+                </tr>"#,
+                escape_html(&h.host),
+                last_error,
+                format_time(h.last_success),
+                h.recent_error_rate * 100.0,
+                h.recent_requests,
+                rate_limit,
+                breaker_color,
+                breaker_label
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let table = if hosts.is_empty() {
+        "<p>No upstream requests made since the server started.</p>".to_string()
+    } else {
+        format!(
+            r#"<table>
+            <tr><th>Host</th><th>Last success</th><th>Recent errors</th><th>Rate limit</th><th>Circuit</th></tr>
+            {}
+        </table>"#,
+            rows
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Upstream Status</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 900px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        p {{ color: #444; }}
+        table {{ width: 100%; border-collapse: collapse; margin: 20px 0; }}
+        th, td {{ padding: 10px 12px; text-align: left; border: 1px solid #e1e4e8; vertical-align: top; }}
+        th {{ background: #f6f8fa; font-weight: 600; }}
+        code {{ background: #f6f8fa; padding: 2px 6px; border-radius: 4px; }}
+    </style>
+</head>
+<body>
+    <h1>Upstream Status</h1>
+    <p>Health of the forges this instance fetches from. A host whose circuit is <em>open</em> failed repeatedly and is not contacted until the cooldown ends.</p>
+    {}
+</body>
+</html>"#,
+        table
+    )
+}
+
+pub fn format_about_html(usage: &Usage, telemetry: &Telemetry) -> String {
+    let total: u64 = usage.providers.values().sum();
+    let rows = usage
+        .providers
+        .iter()
+        .map(|(provider, requests)| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{:.1}%</td></tr>",
+                provider,
+                requests,
+                *requests as f64 * 100.0 / total as f64
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n            ");
+    let providers = if usage.providers.is_empty() {
+        "<p>No release pages have been requested since the server started.</p>".to_string()
+    } else {
+        format!(
+            r#"<table>
+            <tr><th>Provider</th><th>Requests</th><th>Share</th></tr>
+            {}
+        </table>"#,
+            rows
+        )
+    };
+    let hit_ratio = usage
+        .cache
+        .hit_ratio
+        .map(|r| format!("{:.1}%", r * 100.0))
+        .unwrap_or_else(|| "-".to_string());
+
+    let reporting = match &telemetry.endpoint {
+        Some(endpoint) => {
+            let last = match telemetry.last_report() {
+                Some(report) => format!(
+                    " Last report: {}{}.",
+                    report.at.format("%Y-%m-%d %H:%M:%S UTC"),
+                    report
+                        .error
+                        .map(|e| format!(r#", <span style="color: #d73a49;">failed: {}</span>"#, escape_html(&e)))
+                        .unwrap_or_default()
+                ),
+                None => " No report sent yet.".to_string(),
+            };
+            format!(
+                "<p>This instance reports the statistics below to <code>{}</code> every {} hours, as JSON:{}</p>",
+                escape_html(endpoint),
+                telemetry.interval_hours,
+                last
+            )
+        }
+        None => "<p>Reporting is off: nothing is sent anywhere. Instances opt in by setting <code>[telemetry] endpoint</code> in their config file, and would then send:</p>".to_string(),
+    };
+    let payload = serde_json::to_string_pretty(usage).unwrap_or_default();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>About</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 900px; margin: 0 auto; padding: 20px; }}
+        h1, h2 {{ color: #333; }}
+        p {{ color: #444; }}
+        table {{ width: 100%; border-collapse: collapse; margin: 20px 0; }}
+        th, td {{ padding: 10px 12px; text-align: left; border: 1px solid #e1e4e8; vertical-align: top; }}
+        th {{ background: #f6f8fa; font-weight: 600; }}
+        code {{ background: #f6f8fa; padding: 2px 6px; border-radius: 4px; }}
+        pre {{ background: #f6f8fa; padding: 12px; border-radius: 6px; overflow-x: auto; }}
+    </style>
+</head>
+<body>
+    <h1>About</h1>
+    <p>checkup {} &middot; up {} hours</p>
+    <h2>Requests by provider</h2>
+    {}
+    <h2>Cache</h2>
+    <p>Release pages and JSON served from the cache: {} of {} ({})</p>
+    <h2>Usage statistics</h2>
+    {}
+    <pre>{}</pre>
+</body>
+</html>"#,
+        usage.version,
+        usage.uptime_hours,
+        providers,
+        usage.cache.hits,
+        usage.cache.hits + usage.cache.misses,
+        hit_ratio,
+        reporting,
+        escape_html(&payload)
+    )
+}
+
+pub fn format_popular_html(repos: &[RepoRequests]) -> String {
+    let list = if repos.is_empty() {
+        "<p>No repositories have been requested yet.</p>".to_string()
+    } else {
+        let items = repos
+            .iter()
+            .map(|r| {
+                format!(
+                    r#"<li style="display: flex; justify-content: space-between; padding: 10px 0; border-bottom: 1px solid #eee;"><a href="{}">{}</a><span style="color: #666;">{} requests</span></li>"#,
+                    escape_html(&r.route),
+                    escape_html(r.route.trim_start_matches('/')),
+                    r.requests
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("<ol style=\"padding-left: 20px;\">{}</ol>", items)
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Popular Repositories</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        p {{ color: #444; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+    </style>
+</head>
+<body>
+    <h1>Popular Repositories</h1>
+    <p>Most requested repositories on this instance since it started.</p>
+    {}
+</body>
+</html>"#,
+        list
+    )
+}
+
+/// "deployed 1.2.0 · 3 releases behind, for 45 days", colored by whether
+/// the deployment is current.
+fn format_drift(drift: &Drift) -> String {
+    let (color, state) = if drift.latest.is_none() {
+        ("#666", String::new())
+    } else if drift.up_to_date {
+        ("#28a745", " · up to date".to_string())
+    } else {
+        ("#d9822b", format!(" · {}", report::drift_summary(drift)))
+    };
+    format!(
+        r#"<small style="color: {};">deployed {}{}</small>"#,
+        color,
+        escape_html(&drift.deployed),
+        escape_html(&state)
+    )
+}
+
+pub fn format_dashboard_html(
+    entries: &[DashboardEntry],
+    freshness: &FreshnessThresholds,
+    viewer: &Viewer,
+) -> String {
+    let user = matches!(viewer, Viewer::User { .. });
+    let list = if entries.is_empty() && user {
+        "<p>You don't watch any repositories yet. Add one below.</p>".to_string()
+    } else if entries.is_empty() {
+        "<p>No repositories are watched. Add <code>[[watch]]</code> entries to the config file (<code>--config</code>).</p>".to_string()
+    } else {
+        let items = entries
+            .iter()
+            .map(|e| {
+                let release = match (&e.latest_tag, e.published_at) {
+                    (Some(tag), Some(published)) => format!(
+                        r#"<strong>{}</strong> {}"#,
+                        escape_html(tag),
+                        format_freshness_badge(published, Utc::now(), freshness)
+                    ),
+                    _ => r#"<span style="color: #666;">no releases cached</span>"#.to_string(),
+                };
+                let status = if e.pending {
+                    r#"<span style="color: #f0ad4e;">refreshing</span>"#.to_string()
+                } else if let Some(error) = &e.error {
+                    format!(
+                        r#"<span style="color: #d73a49;" title="{}">refresh failed</span>"#,
+                        escape_html(error)
+                    )
+                } else if let Some(cached_at) = e.cached_at {
+                    let via = e
+                        .peer
+                        .as_ref()
+                        .map(|peer| format!(" via {}", escape_html(peer)))
+                        .unwrap_or_default();
+                    format!(
+                        r#"<span style="color: #666;">checked {}{}</span>"#,
+                        cached_at.format("%Y-%m-%d %H:%M UTC"),
+                        via
+                    )
+                } else {
+                    String::new()
+                };
+                let drift = e
+                    .drift
+                    .as_ref()
+                    .map(|d| format!("<br>{}", format_drift(d)))
+                    .unwrap_or_default();
+                let name = e
+                    .display
+                    .as_ref()
+                    .and_then(|d| d.name.as_deref())
+                    .unwrap_or(e.route.trim_start_matches('/'));
+                let description = e
+                    .display
+                    .as_ref()
+                    .and_then(|d| d.description.as_deref())
+                    .map(|d| format!(r#"<br><span style="color: #666;">{}</span>"#, escape_html(d)))
+                    .unwrap_or_default();
+                let remove = if user {
+                    format!(
+                        r#"<form method="post" action="/dashboard/unwatch" style="float: right;"><input type="hidden" name="repo" value="{}"><button type="submit" class="link">Remove</button></form>"#,
+                        escape_html(e.route.trim_start_matches('/'))
+                    )
+                } else {
+                    String::new()
+                };
+                format!(
+                    r#"<li style="padding: 12px 0; border-bottom: 1px solid #eee;">{}{}<a href="{}">{}</a>{}<br>{}{}<br><small>{}</small></li>"#,
+                    remove,
+                    e.display
+                        .as_ref()
+                        .map(|d| format_display_icon(d, 20))
+                        .unwrap_or_default(),
+                    escape_html(&e.route),
+                    escape_html(name),
+                    description,
+                    release,
+                    drift,
+                    status
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("<ul>{}</ul>", items)
+    };
+
+    let (intro, add) = match viewer {
+        Viewer::Visitor { sign_in: false } => (
+            "<p>Latest releases of the watched repositories.</p>".to_string(),
+            String::new(),
+        ),
+        Viewer::Visitor { sign_in: true } => (
+            r#"<p>Latest releases of the watched repositories. <a href="/login">Sign in</a> to keep a watchlist of your own.</p>"#.to_string(),
+            String::new(),
+        ),
+        Viewer::User {
+            username,
+            feed_url,
+            unlinked,
+        } => (
+            format!(
+                r#"<form method="post" action="/logout" style="float: right;"><button type="submit" class="link">Sign out</button></form>
+    <p>Latest releases of the repositories you watch, signed in as <strong>{}</strong>.</p>"#,
+                escape_html(username)
+            ),
+            format!(
+                r#"<form method="post" action="/dashboard/watch" style="margin: 20px 0;">
+        <input name="repo" placeholder="github/owner/repo" required style="padding: 6px; width: 60%;">
+        <button type="submit">Watch</button>
+    </form>
+    <p><small>Calendar feed of these releases, private to you: <code>{}</code></small></p>{}"#,
+                escape_html(feed_url),
+                if unlinked.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        "\n    <p><small>Also sign in with: {}</small></p>",
+                        unlinked
+                            .iter()
+                            .map(|(name, label)| format!(
+                                r#"<a href="/login/oidc/{}">{}</a>"#,
+                                escape_html(name),
+                                escape_html(label)
+                            ))
+                            .collect::<Vec<_>>()
+                            .join(" · ")
+                    )
+                }
+            ),
+        ),
+    };
+
+    format!(
+        r##"<!DOCTYPE html>
+<html>
+<head>
+    <title>Dashboard - Checkup</title>
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <meta name="theme-color" content="#0366d6">
+    <link rel="manifest" href="/manifest.webmanifest">
+    <link rel="icon" href="/icon.svg" type="image/svg+xml">
+    <link rel="apple-touch-icon" href="/icon-maskable.svg">
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        p {{ color: #444; }}
+        ul {{ list-style: none; padding: 0; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+        code {{ background: #f6f8fa; padding: 2px 6px; border-radius: 4px; }}
+        button.link {{ background: none; border: none; padding: 0; color: #0366d6; cursor: pointer; font: inherit; }}
+    </style>
+</head>
+<body>
+    <h1>Dashboard</h1>
+    {}
+    {}
+    {}
+    <script>
+        if ("serviceWorker" in navigator) {{
+            navigator.serviceWorker.register("/sw.js");
+        }}
+    </script>
+</body>
+</html>"##,
+        intro, list, add
+    )
+}
+
+pub fn format_compare_html(comparison: &MirrorComparison) -> String {
+    let differing = comparison
+        .tags
+        .iter()
+        .filter(|t| !matches!(t.status, TagStatus::Same))
+        .count();
+    let summary = if comparison.tags.is_empty() {
+        "Neither mirror has any releases.".to_string()
+    } else if differing == 0 {
+        format!(
+            "Both mirrors carry the same {} tags with matching assets.",
+            comparison.tags.len()
+        )
+    } else {
+        format!(
+            "{} of {} tags differ between the mirrors.",
+            differing,
+            comparison.tags.len()
+        )
+    };
+
+    let rows = comparison
+        .tags
+        .iter()
+        .map(|t| {
+            let (class, a, b, notes) = match &t.status {
+                TagStatus::Same => ("", "✓", "✓", String::new()),
+                TagStatus::OnlyA => ("differs", "✓", "missing", String::new()),
+                TagStatus::OnlyB => ("differs", "missing", "✓", String::new()),
+                TagStatus::Differs(diffs) => (
+                    "differs",
+                    "✓",
+                    "✓",
+                    diffs
+                        .iter()
+                        .map(|d| escape_html(d))
+                        .collect::<Vec<_>>()
+                        .join("<br>"),
+                ),
+            };
+            format!(
+                r#"<tr class="{}"><td><strong>{}</strong><br><small>{}</small></td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+                class,
+                escape_html(&t.tag),
+                t.published_at.format("%Y-%m-%d"),
+                a,
+                b,
+                notes
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Mirror Comparison - Checkup</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 1000px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        p {{ color: #444; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+        table {{ width: 100%; border-collapse: collapse; }}
+        th, td {{ text-align: left; padding: 8px; border-bottom: 1px solid #eee; vertical-align: top; }}
+        small {{ color: #666; }}
+        tr.differs {{ background: #fff5f5; }}
+        tr.differs td:nth-child(4) {{ color: #d73a49; }}
+    </style>
+</head>
+<body>
+    <h1>Mirror Comparison</h1>
+    <p><strong>A:</strong> <a href="{a}">{a}</a><br><strong>B:</strong> <a href="{b}">{b}</a></p>
+    <p>{summary}</p>
+    <table>
+        <tr><th>Tag</th><th>A</th><th>B</th><th>Differences</th></tr>
+        {rows}
+    </table>
+</body>
+</html>"#,
+        a = escape_html(&comparison.a),
+        b = escape_html(&comparison.b),
+        summary = summary,
+        rows = rows
+    )
+}
+
+pub fn format_sbom_html(repo_path: &str, tag: &str, summary: &SbomSummary) -> String {
+    let described = summary
+        .name
+        .as_deref()
+        .map(|name| format!(" describing <strong>{}</strong>", escape_html(name)))
+        .unwrap_or_default();
+    let spec = summary
+        .spec_version
+        .as_deref()
+        .map(|v| format!(" ({})", escape_html(v)))
+        .unwrap_or_default();
+    let mut rows: Vec<String> = summary
+        .licenses
+        .iter()
+        .map(|l| {
+            format!(
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape_html(&l.license),
+                l.components
+            )
+        })
+        .collect();
+    if summary.unlicensed > 0 {
+        rows.push(format!(
+            "<tr class=\"unknown\"><td>No license information</td><td>{}</td></tr>",
+            summary.unlicensed
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>SBOM of {repo} {tag} - Checkup</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 1000px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        p {{ color: #444; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+        table {{ width: 100%; border-collapse: collapse; }}
+        th, td {{ text-align: left; padding: 8px; border-bottom: 1px solid #eee; }}
+        td:nth-child(2), th:nth-child(2) {{ text-align: right; width: 120px; }}
+        tr.unknown td {{ color: #d73a49; }}
+    </style>
+</head>
+<body>
+    <h1>SBOM of {repo} {tag}</h1>
+    <p><a href="{url}">{asset}</a>: {format}{spec} document{described} listing <strong>{components}</strong> components.</p>
+    <h2>Licenses</h2>
+    <table>
+        <tr><th>License</th><th>Components</th></tr>
+        {rows}
+    </table>
+</body>
+</html>"#,
+        repo = escape_html(repo_path),
+        tag = escape_html(tag),
+        url = escape_html(&summary.url),
+        asset = escape_html(&summary.asset),
+        format = escape_html(&summary.format),
+        spec = spec,
+        described = described,
+        components = summary.components,
+        rows = rows.join("\n        ")
+    )
+}
+
+/// Full release notes of one release, served by `/{tag}/notes`.
+pub fn format_notes_html(
+    repo_path: &str,
+    route_prefix: &str,
+    release: &Release,
+    body: &str,
+) -> String {
+    let name = release.name.as_deref().unwrap_or(&release.tag_name);
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>{name} - {repo} - Checkup</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+        small {{ color: #666; }}
+        .notes pre {{ padding: 10px; border: 1px solid #e1e4e8; border-radius: 6px; overflow-x: auto; }}
+        .notes img {{ max-width: 100%; }}
+    </style>
+</head>
+<body>
+    <p><a href="/{prefix}/{path}">&larr; All releases of {repo}</a></p>
+    <h1><a href="{url}">{name}</a></h1>
+    <small>Published: {published}</small>
+    <div class="notes">{notes}</div>
+</body>
+</html>"#,
+        name = escape_html(name),
+        repo = escape_html(repo_path),
+        prefix = route_prefix,
+        path = route_path(route_prefix, repo_path),
+        url = escape_html(&release.html_url),
+        published = published_label(release),
+        notes = markdown::render_markdown(body)
+    )
+}
+
+pub fn format_search_html(query: &str, hits: &[SearchHit]) -> String {
+    let results = if query.trim().is_empty() {
+        String::new()
+    } else if hits.is_empty() {
+        format!(
+            "<p>No cached repository matches <strong>{}</strong>. Open its page, e.g. <code>/github/owner/repo</code>, to start tracking it.</p>",
+            escape_html(query)
+        )
+    } else {
+        let items = hits
+            .iter()
+            .map(|h| {
+                let latest = h
+                    .latest_tag
+                    .as_deref()
+                    .map(|t| format!(" <span style=\"color: #666;\">latest {}</span>", escape_html(t)))
+                    .unwrap_or_default();
+                let tags = if h.matching_tags.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        "<br><small>Tags: {}</small>",
+                        h.matching_tags
+                            .iter()
+                            .map(|t| format!("<code>{}</code>", escape_html(t)))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    )
+                };
+                format!(
+                    r#"<li style="padding: 10px 0; border-bottom: 1px solid #eee;"><a href="{}">{}</a>{}{}</li>"#,
+                    escape_html(&h.route),
+                    escape_html(h.route.trim_start_matches('/')),
+                    latest,
+                    tags
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("<ul>{}</ul>", items)
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Search - Checkup</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        p {{ color: #444; }}
+        ul {{ list-style: none; padding: 0; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+        code {{ background: #f6f8fa; padding: 2px 6px; border-radius: 4px; }}
+        small {{ color: #666; }}
+        input {{ width: 70%; padding: 8px; font-size: 16px; }}
+        button {{ padding: 8px 16px; font-size: 16px; }}
+    </style>
+</head>
+<body>
+    <h1>Search</h1>
+    <p>Find repositories already cached on this instance by name or release tag.</p>
+    <form action="/search" method="get">
+        <input type="search" name="q" value="{}" placeholder="owner/repo or v1.2" autofocus>
+        <button type="submit">Search</button>
+    </form>
+    {}
+</body>
+</html>"#,
+        escape_html(query),
+        results
+    )
+}
+
+/// One page of a forge instance's repository list.
+pub fn format_catalog_html<'a>(
+    host: &str,
+    repos: impl Iterator<Item = &'a CatalogRepo>,
+    total: usize,
+    page: usize,
+    pages: usize,
+    cached_at: DateTime<Utc>,
+) -> String {
+    let items = repos
+        .map(|r| {
+            let description = r
+                .description
+                .as_deref()
+                .filter(|d| !d.is_empty())
+                .map(|d| format!("<br><small>{}</small>", escape_html(d)))
+                .unwrap_or_default();
+            format!(
+                r#"<li style="padding: 10px 0; border-bottom: 1px solid #eee;"><a href="{}">{}</a>{}</li>"#,
+                escape_html(&r.route),
+                escape_html(&r.name),
+                description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let list = if items.is_empty() {
+        "<p>No repositories found on this instance.</p>".to_string()
+    } else {
+        format!("<ul>{}</ul>", items)
+    };
+
+    let pager = if pages <= 1 {
+        String::new()
+    } else {
+        let prev = if page > 1 {
+            format!(r#"<a href="?page={}">&larr; Previous</a>"#, page - 1)
+        } else {
+            String::new()
+        };
+        let next = if page < pages {
+            format!(r#"<a href="?page={}">Next &rarr;</a>"#, page + 1)
+        } else {
+            String::new()
+        };
+        format!(
+            r#"<div style="display: flex; justify-content: space-between; align-items: center; margin: 15px 0; color: #666;"><span>{}</span><span>Page {} of {}</span><span>{}</span></div>"#,
+            prev, page, pages, next
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>{host} - Checkup</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        p {{ color: #444; }}
+        ul {{ list-style: none; padding: 0; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+        small {{ color: #666; }}
+    </style>
+</head>
+<body>
+    <h1>{host}</h1>
+    <p>{total} repositories. Pick one to see its releases.</p>
+    {list}
+    {pager}
+    <p><small>Cached at {cached_at}</small></p>
+</body>
+</html>"#,
+        host = escape_html(host),
+        total = total,
+        list = list,
+        pager = pager,
+        cached_at = cached_at.format("%Y-%m-%d %H:%M:%S UTC"),
+    )
+}
+
+/// The homepage (`index.html`) with the repos recently cached on this instance
+/// and their latest releases; both lists are left out while the cache is empty.
+pub fn format_home_html(version: &str, cached: &[SearchHit], released: &[SearchHit]) -> String {
+    let list = |hits: &[SearchHit], date: fn(&SearchHit) -> Option<DateTime<Utc>>| {
+        hits.iter()
+            .map(|h| {
+                format!(
+                    r#"<li><a href="{}">{}</a> {}<br><small>{}</small></li>"#,
+                    escape_html(&h.route),
+                    escape_html(h.route.trim_start_matches('/')),
+                    h.latest_tag
+                        .as_deref()
+                        .map(|t| format!("<code>{}</code>", escape_html(t)))
+                        .unwrap_or_default(),
+                    date(h)
+                        .map(|d| d.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let recent = if cached.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"    <h2>On This Instance</h2>
+    <div class="recent-grid">
+        <div>
+            <h3>Recently Cached</h3>
+            <ul>{}</ul>
+        </div>
+        <div>
+            <h3>Latest Releases</h3>
+            <ul>{}</ul>
+        </div>
+    </div>
+    <p><a href="/search">Search all cached repositories →</a></p>
+"#,
+            list(cached, |h| Some(h.cached_at)),
+            list(released, |h| h.latest_published_at)
+        )
+    };
+
+    include_str!("index.html")
+        .replace("{VERSION}", version)
+        .replace("{RECENT}", &recent)
+}
+
+pub fn format_weekly_report_html(report: &WeeklyReport) -> String {
+    let sections = if report.groups.is_empty() {
+        "<p>No watched repository published a release this week.</p>".to_string()
+    } else {
+        report
+            .groups
+            .iter()
+            .map(|group| {
+                let items = group
+                    .releases
+                    .iter()
+                    .map(|r| {
+                        let prerelease = if r.prerelease {
+                            r#" <span style="background: #f0ad4e; padding: 2px 6px; border-radius: 3px; font-size: 0.8em;">Pre-release</span>"#
+                        } else {
+                            ""
+                        };
+                        let excerpt = r
+                            .excerpt
+                            .as_deref()
+                            .map(|e| format!("<br><small>{}</small>", escape_html(e)))
+                            .unwrap_or_default();
+                        format!(
+                            r#"<li style="padding: 10px 0; border-bottom: 1px solid #eee;"><a href="{}">{}</a> <a href="{}"><strong>{}</strong></a>{} <span style="color: #666;">{}</span>{}</li>"#,
+                            escape_html(&r.route),
+                            escape_html(r.route.trim_start_matches('/')),
+                            escape_html(&r.html_url),
+                            escape_html(r.name.as_deref().unwrap_or(&r.tag_name)),
+                            prerelease,
+                            r.date_label(),
+                            excerpt
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    "<h2>{}</h2>\n<ul>{}</ul>",
+                    escape_html(&group.category),
+                    items
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let drift = if report.drift.is_empty() {
+        String::new()
+    } else {
+        let items = report
+            .drift
+            .iter()
+            .map(|d| {
+                format!(
+                    r#"<li style="padding: 10px 0; border-bottom: 1px solid #eee;"><a href="/{}">{}</a> {} → <strong>{}</strong> <span style="color: #d9822b;">{}</span></li>"#,
+                    escape_html(&d.route),
+                    escape_html(&d.route),
+                    escape_html(&d.deployed),
+                    escape_html(d.latest.as_deref().unwrap_or_default()),
+                    escape_html(&report::drift_summary(d))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("<h2>Deployment drift</h2>\n<ul>{}</ul>", items)
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Weekly Release Report - Checkup</title>
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }}
+        h1, h2 {{ color: #333; }}
+        p {{ color: #444; }}
+        ul {{ list-style: none; padding: 0; }}
+        small {{ color: #666; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+    </style>
+</head>
+<body>
+    <h1>Weekly Release Report</h1>
+    <p>Releases of the watched repositories from {} to {}. Also available as <a href="/reports/weekly.md">Markdown</a>.</p>
+    {}
+    {}
+</body>
+</html>"#,
+        report.from.format("%Y-%m-%d"),
+        report.to.format("%Y-%m-%d"),
+        sections,
+        drift
+    )
+}
+
+/// Month grid (Monday first) of `releases`, which all fall within `month`.
+pub fn format_calendar_html(month: NaiveDate, releases: &[CalendarRelease]) -> String {
+    let prev = month - chrono::Months::new(1);
+    let next = month + chrono::Months::new(1);
+    let days_in_month = (next - month).num_days() as u32;
+    let offset = month.weekday().num_days_from_monday();
+
+    let mut cells: Vec<String> = (0..offset)
+        .map(|_| r#"<td class="empty"></td>"#.to_string())
+        .collect();
+    for day in 1..=days_in_month {
+        let items = releases
+            .iter()
+            .filter(|r| r.published_at.day() == day)
+            .map(|r| {
+                let prerelease = if r.prerelease {
+                    r#" <span style="background: #f0ad4e; padding: 0 4px; border-radius: 3px; font-size: 0.85em;">pre</span>"#
+                } else {
+                    ""
+                };
+                format!(
+                    r#"<div class="release"><a href="{}">{}</a> <a href="{}"><strong>{}</strong></a>{}</div>"#,
+                    escape_html(&r.route),
+                    escape_html(r.route.rsplit('/').next().unwrap_or_default()),
+                    escape_html(&r.html_url),
+                    escape_html(&r.tag_name),
+                    prerelease
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("");
+        cells.push(format!(
+            r#"<td><div class="day">{}</div>{}</td>"#,
+            day, items
+        ));
+    }
+    while !cells.len().is_multiple_of(7) {
+        cells.push(r#"<td class="empty"></td>"#.to_string());
+    }
+    let rows = cells
+        .chunks(7)
+        .map(|week| format!("<tr>{}</tr>", week.join("")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Release Calendar {} - Checkup</title>
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, Ubuntu, sans-serif; max-width: 1100px; margin: 0 auto; padding: 20px; }}
+        h1 {{ color: #333; }}
+        p {{ color: #444; }}
+        a {{ color: #0366d6; text-decoration: none; }}
+        a:hover {{ text-decoration: underline; }}
+        nav {{ display: flex; justify-content: space-between; align-items: center; margin: 15px 0; font-weight: 600; }}
+        table {{ width: 100%; border-collapse: collapse; table-layout: fixed; }}
+        th {{ color: #666; font-weight: 500; padding: 6px; }}
+        td {{ border: 1px solid #e1e4e8; vertical-align: top; height: 90px; padding: 4px; font-size: 0.85em; overflow-wrap: anywhere; }}
+        td.empty {{ background: #fafbfc; }}
+        .day {{ color: #999; text-align: right; }}
+        .release {{ margin: 3px 0; }}
+    </style>
+</head>
+<body>
+    <h1>Release Calendar</h1>
+    <p>Releases of the watched repositories. Subscribe in a calendar app with <a href="/calendar.ics">/calendar.ics</a>.</p>
+    <nav><a href="/calendar?month={}">&laquo; {}</a><span>{}</span><a href="/calendar?month={}">{} &raquo;</a></nav>
+    <table>
+        <tr><th>Mon</th><th>Tue</th><th>Wed</th><th>Thu</th><th>Fri</th><th>Sat</th><th>Sun</th></tr>
+        {}
+    </table>
+</body>
+</html>"#,
+        month.format("%B %Y"),
+        prev.format("%Y-%m"),
+        prev.format("%B"),
+        month.format("%B %Y"),
+        next.format("%Y-%m"),
+        next.format("%B"),
+        rows
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTILE: &str = "app'; rm -rf ~; $(id) `id`.tar.gz";
+
+    #[test]
+    fn install_snippets_quote_hostile_names() {
+        let url = format!("https://example.com/{}", HOSTILE);
+        let checksum = Checksum::Sha256("ab12".to_string());
+        let snippets = install_snippets(&url, HOSTILE, Some(&checksum));
+        assert_eq!(
+            snippets[0].1,
+            r#"curl -LO 'https://example.com/app'\''; rm -rf ~; $(id) `id`.tar.gz' && echo 'ab12  app'\''; rm -rf ~; $(id) `id`.tar.gz' | sha256sum -c -"#
+        );
+        assert_eq!(
+            snippets[2].1,
+            "Invoke-WebRequest -Uri 'https://example.com/app''; rm -rf ~; $(id) `id`.tar.gz' \
+             -OutFile 'app''; rm -rf ~; $(id) `id`.tar.gz'; \
+             if ((Get-FileHash -LiteralPath 'app''; rm -rf ~; $(id) `id`.tar.gz' -Algorithm SHA256).Hash -ne 'ab12') \
+             { throw 'Checksum mismatch' }"
+        );
+        assert_eq!(powershell_quote("a\u{2019}b"), "'a\u{2019}\u{2019}b'");
+
+        // The shell reads the quoted name back as it was
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", shell_quote(HOSTILE)))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), HOSTILE);
+    }
+
+    #[test]
+    fn release_pages_escape_asset_and_release_names() {
+        let markup = "<img src=x onerror=alert(1)>";
+        let release: Release = serde_json::from_value(serde_json::json!({
+            "tag_name": "v1.0",
+            "name": markup,
+            "published_at": "2024-01-01T00:00:00Z",
+            "html_url": "https://example.com/\"><script>",
+            "body": null,
+            "prerelease": false,
+            "draft": false,
+            "assets": [{
+                "name": format!("{}-1.0.tar.gz", markup),
+                "url": format!("https://example.com/{}-1.0.tar.gz", markup),
+                "content_type": null,
+                "size": 0,
+                "download_count": 0,
+            }],
+            "source_tarball": null,
+            "source_zipball": null,
+        }))
+        .unwrap();
+        let options = RenderOptions {
+            public_url: String::new(),
+            page_size: 10,
+            freshness: FreshnessThresholds::from_config(&Default::default()).unwrap(),
+            hidden_assets: Default::default(),
+            latest_names: Default::default(),
+            displays: Default::default(),
+            ipfs_gateway: String::new(),
+            email_subscriptions: false,
+        };
+        let html = format_releases_html(
+            &[release],
+            "example.com/dir",
+            "autoindex",
+            None,
+            &options,
+            1,
+        );
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(!html.contains("\"><script>"), "{}", html);
+        assert!(html.contains("&lt;img src=x onerror=alert(1)&gt;-1.0.tar.gz"));
+    }
+
+    #[test]
+    fn digests_are_escaped() {
+        let mut asset: Asset = serde_json::from_value(serde_json::json!({
+            "name": "app.tar.gz",
+            "url": "https://example.com/app.tar.gz",
+            "content_type": null,
+            "size": 0,
+            "download_count": 0,
+            "sha256": "ééééééé\"><script>",
+            "cid": "\"><script>",
+        }))
+        .unwrap();
+        let html = format_digest(&asset, "https://ipfs.io");
+        assert!(!html.contains("<script>"), "{}", html);
+
+        asset.sha256 =
+            Some("E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855".to_string());
+        let mut bad = asset.clone();
+        bad.sha256 = Some("\"><script>".to_string());
+        bad.sri = Some("sha256-forged".to_string());
+        let release: Release = serde_json::from_value(serde_json::json!({
+            "tag_name": "v1.0",
+            "name": null,
+            "published_at": "2024-01-01T00:00:00Z",
+            "html_url": "",
+            "body": null,
+            "prerelease": false,
+            "draft": false,
+            "assets": [asset, bad],
+            "source_tarball": null,
+            "source_zipball": null,
+        }))
+        .unwrap();
+        let mut releases = vec![release];
+        crate::checksums::drop_invalid_hashes(&mut releases);
+        let assets = &releases[0].assets;
+        assert_eq!(
+            assets[0].sha256.as_deref(),
+            Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        assert!(assets[0].sri.is_some());
+        assert_eq!(
+            (assets[1].sha256.as_deref(), assets[1].sri.as_deref()),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn status_escapes_hosts() {
+        let host = HostStatus {
+            host: "<script>alert(1)</script>".to_string(),
+            last_success: None,
+            last_error_at: None,
+            last_error: None,
+            recent_requests: 1,
+            recent_error_rate: 0.0,
+            breaker: BreakerState::Closed,
+            rate_limit: None,
+        };
+        let html = format_status_html(&[host]);
+        assert!(!html.contains("<script>alert"), "{}", html);
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+    }
+}
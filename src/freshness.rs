@@ -0,0 +1,78 @@
+//! How recently a repo last published a release, shown as a green, yellow or
+//! red badge so stale or abandoned projects stand out.
+
+use crate::config::FreshnessConfig;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    Fresh,
+    Aging,
+    Stale,
+}
+
+impl Freshness {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Freshness::Fresh => "Active",
+            Freshness::Aging => "Aging",
+            Freshness::Stale => "Stale",
+        }
+    }
+
+    pub fn color(&self) -> &'static str {
+        match self {
+            Freshness::Fresh => "#28a745",
+            Freshness::Aging => "#f0ad4e",
+            Freshness::Stale => "#d73a49",
+        }
+    }
+}
+
+/// Release ages below `fresh` are fresh, below `stale` aging, stale beyond.
+#[derive(Debug, Clone)]
+pub struct FreshnessThresholds {
+    fresh: Duration,
+    stale: Duration,
+}
+
+impl FreshnessThresholds {
+    pub fn from_config(config: &FreshnessConfig) -> Result<Self> {
+        if config.fresh_days <= 0 || config.stale_days <= config.fresh_days {
+            anyhow::bail!(
+                "Invalid freshness thresholds: need 0 < fresh_days < stale_days (got {} and {})",
+                config.fresh_days,
+                config.stale_days
+            );
+        }
+        Ok(Self {
+            fresh: Duration::days(config.fresh_days),
+            stale: Duration::days(config.stale_days),
+        })
+    }
+
+    /// Freshness of a release as of `as_of`, e.g. when its repo was cached.
+    pub fn classify(&self, published_at: DateTime<Utc>, as_of: DateTime<Utc>) -> Freshness {
+        let age = as_of - published_at;
+        if age < self.fresh {
+            Freshness::Fresh
+        } else if age < self.stale {
+            Freshness::Aging
+        } else {
+            Freshness::Stale
+        }
+    }
+}
+
+/// Age at `as_of` in the largest sensible unit, e.g. "12 days", "5 months",
+/// "3 years".
+pub fn format_age(published_at: DateTime<Utc>, as_of: DateTime<Utc>) -> String {
+    let days = (as_of - published_at).num_days().max(0);
+    let (count, unit) = match days {
+        0..60 => (days, "day"),
+        60..730 => (days / 30, "month"),
+        _ => (days / 365, "year"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
@@ -0,0 +1,61 @@
+//! Assets tucked away in a collapsed "Other files" section of release pages,
+//! such as checksums, signatures and SBOMs. Patterns are globs matched
+//! against asset names (`*` any run of characters, `?` one character),
+//! ignoring case. JSON output always lists every asset.
+
+use crate::{config::RepoConfig, provider};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct HiddenAssets {
+    /// Patterns applied to every repo (`[assets] hide`)
+    global: Vec<Regex>,
+    /// Additional patterns per repo (`[[repo]] hide_assets`), by cache key
+    by_repo: HashMap<String, Vec<Regex>>,
+}
+
+impl HiddenAssets {
+    pub fn from_config(global: &[String], repos: &[RepoConfig]) -> Result<Self> {
+        let mut by_repo = HashMap::new();
+        for entry in repos.iter().filter(|e| !e.hide_assets.is_empty()) {
+            let (_, repo) = provider::parse_route(&entry.repo)?;
+            by_repo.insert(repo.cache_key(), compile(&entry.hide_assets)?);
+        }
+        Ok(Self {
+            global: compile(global)?,
+            by_repo,
+        })
+    }
+
+    pub fn is_hidden(&self, cache_key: &str, name: &str) -> bool {
+        self.global
+            .iter()
+            .chain(self.by_repo.get(cache_key).into_iter().flatten())
+            .any(|pattern| pattern.is_match(name))
+    }
+}
+
+fn compile(globs: &[String]) -> Result<Vec<Regex>> {
+    globs
+        .iter()
+        .map(|glob| {
+            glob_to_regex(glob).with_context(|| format!("Invalid asset pattern {:?}", glob))
+        })
+        .collect()
+}
+
+/// "*.sha256" → `(?i)^.*\.sha256$`
+fn glob_to_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let mut pattern = String::from("(?i)^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern)
+}
@@ -0,0 +1,32 @@
+//! Homepage listing the repos recently cached on this instance, and `/go`,
+//! the target of its quick-add form.
+
+use crate::{AppState, VERSION, error::AppError, format_html, provider};
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse, Redirect},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Repos shown per homepage list
+const RECENT_LIMIT: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct GoQuery {
+    #[serde(default)]
+    repo: String,
+}
+
+pub async fn home_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let cached = state.search_index.recently_cached(&state, RECENT_LIMIT);
+    let released = state.search_index.recently_released(&state, RECENT_LIMIT);
+    Html(format_html::format_home_html(VERSION, &cached, &released))
+}
+
+/// Redirect a pasted repo URL or path to its release page.
+pub async fn go(Query(query): Query<GoQuery>) -> Result<Redirect, AppError> {
+    let (prefix, repo) =
+        provider::resolve_repo(&query.repo).map_err(|e| AppError::from_route(&e))?;
+    Ok(Redirect::to(&provider::repo_route(prefix, &repo)))
+}
@@ -102,32 +102,55 @@ pub fn NOTE(size: u32) -> String {
     )
 }
 
-/// Returns the appropriate icon SVG based on file extension
-pub fn get_file_icon(filename: &str, size: u32) -> String {
-    if filename.ends_with(".exe") || filename.ends_with(".msi") {
-        WINDOWS(size)
-    } else if filename.ends_with(".deb") || filename.ends_with(".rpm") {
-        LINUX(size)
-    } else if filename.ends_with(".dmg")
-        || filename.contains("darwin")
-        || filename.contains("macos")
-    {
-        APPLE(size)
-    } else if filename.ends_with(".AppImage") {
-        PACKAGE(size)
-    } else if filename.ends_with(".tar.gz")
-        || filename.ends_with(".tgz")
-        || filename.ends_with(".tar.xz")
-        || filename.ends_with(".tar.bz2")
-    {
-        ARCHIVE(size)
-    } else if filename.ends_with(".zip") {
-        ARCHIVE(size)
-    } else if filename.ends_with(".jar") {
-        COFFEE(size)
-    } else if filename.contains("source") || filename.contains("src") {
-        FILE(size)
-    } else {
-        PAPERCLIP(size)
-    }
+/// Android/APK icon
+pub fn ANDROID(size: u32) -> String {
+    format!(
+        r#"<svg width="{size}" height="{size}" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 256 256"><path fill="currentColor" fill-rule="evenodd" d="M40,176a88,88,0,0,1,176,0v8a8,8,0,0,1-8,8H48a8,8,0,0,1-8-8Zm52-40a12,12,0,1,0,12,12A12,12,0,0,0,92,136Zm72,0a12,12,0,1,0,12,12A12,12,0,0,0,164,136Z"/><path fill="currentColor" d="M66.34,57.66l11.32-11.32,30,30L96.34,87.66Zm123.32,0-11.32-11.32-30,30,11.32,11.32Z"/></svg>"#,
+        size = size
+    )
+}
+
+/// Hash/Checksum icon
+pub fn HASH(size: u32) -> String {
+    format!(
+        r#"<svg width="{size}" height="{size}" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 256 256"><path fill="currentColor" d="M88,40h16V216H88Zm64,0h16V216H152ZM40,88H216v16H40Zm0,64H216v16H40Z"/></svg>"#,
+        size = size
+    )
+}
+
+/// Seal/Signature icon
+pub fn SEAL(size: u32) -> String {
+    format!(
+        r#"<svg width="{size}" height="{size}" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 256 256"><path fill="currentColor" d="M128,24A104,104,0,1,0,232,128,104.11,104.11,0,0,0,128,24Zm45.66,85.66-56,56a8,8,0,0,1-11.32,0l-24-24a8,8,0,0,1,11.32-11.32L112,148.69l50.34-50.35a8,8,0,0,1,11.32,11.32Z"/></svg>"#,
+        size = size
+    )
+}
+
+/// List/SBOM icon
+pub fn LIST(size: u32) -> String {
+    format!(
+        r#"<svg width="{size}" height="{size}" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 256 256"><path fill="currentColor" d="M88,56H224V72H88Zm0,64H224v16H88Zm0,64H224v16H88ZM40,52H64V76H40Zm0,64H64v24H40Zm0,64H64v24H40Z"/></svg>"#,
+        size = size
+    )
+}
+
+/// Cube/Snap and Flatpak icon
+pub fn CUBE(size: u32) -> String {
+    format!(
+        r#"<svg width="{size}" height="{size}" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 256 256"><path fill="currentColor" d="M128,32,40,80l88,48,88-48ZM32,94v86l88,48V142Zm192,0v86l-88,48V142Z"/></svg>"#,
+        size = size
+    )
+}
+
+/// Wheel/Python wheel icon
+pub fn WHEEL(size: u32) -> String {
+    format!(
+        r#"<svg width="{size}" height="{size}" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 256 256"><path fill="currentColor" fill-rule="evenodd" d="M128,32a96,96,0,1,0,96,96A96,96,0,0,0,128,32Zm0,24a72,72,0,1,1-72,72A72,72,0,0,1,128,56Zm0,52a20,20,0,1,0,20,20A20,20,0,0,0,128,108Z"/><path fill="currentColor" d="M120,56h16v52H120Zm0,92h16v52H120ZM56,120h52v16H56Zm92,0h52v16H148Z"/></svg>"#,
+        size = size
+    )
+}
+
+/// Returns the icon SVG for an asset's file type (see filetype.rs)
+pub fn get_file_icon(filename: &str, content_type: Option<&str>, size: u32) -> String {
+    crate::filetype::classify(filename, content_type).icon(size)
 }
@@ -0,0 +1,88 @@
+//! Container images published alongside releases. `images` on a `[[repo]]`
+//! entry lists image name templates such as "ghcr.io/owner/project:{version}";
+//! when releases are fetched, each gets the images with `{version}` (the
+//! version per the repo's tag rules) and `{tag}` filled in. Release pages show
+//! them as `docker pull` commands, and `+json` lists them in
+//! `container_images`.
+
+use crate::{config::RepoConfig, provider, provider::Release};
+use anyhow::Result;
+use std::collections::HashMap;
+
+const PLACEHOLDERS: [&str; 2] = ["{version}", "{tag}"];
+
+#[derive(Debug)]
+pub struct ImageTemplates {
+    /// Templates by cache key
+    by_repo: HashMap<String, Vec<String>>,
+}
+
+impl ImageTemplates {
+    /// Fails on routes that don't name a repo and on templates without a
+    /// tag, or with placeholders other than `{version}` and `{tag}`.
+    pub fn from_config(repos: &[RepoConfig]) -> Result<Self> {
+        let mut by_repo = HashMap::new();
+        for entry in repos.iter().filter(|e| !e.images.is_empty()) {
+            let (_, repo) = provider::parse_route(&entry.repo)?;
+            for template in &entry.images {
+                check_template(template).map_err(|e| {
+                    anyhow::anyhow!("Invalid image {:?} for {}: {}", template, entry.repo, e)
+                })?;
+            }
+            by_repo.insert(repo.cache_key(), entry.images.clone());
+        }
+        Ok(Self { by_repo })
+    }
+
+    /// Set each release's `container_images`. Runs after the tag rules, so
+    /// `{version}` is the normalized version. Drafts get none, and neither
+    /// do releases whose tag doesn't make a valid image tag.
+    pub fn apply(&self, cache_key: &str, releases: &mut [Release]) {
+        let Some(templates) = self.by_repo.get(cache_key) else {
+            return;
+        };
+        for release in releases.iter_mut().filter(|r| !r.draft) {
+            let version = release.version.as_deref().unwrap_or(&release.tag_name);
+            release.container_images = templates
+                .iter()
+                .map(|t| {
+                    t.replace("{version}", version)
+                        .replace("{tag}", &release.tag_name)
+                })
+                .filter(|image| image_tag(image).is_some_and(is_valid_tag))
+                .collect();
+        }
+    }
+}
+
+fn check_template(template: &str) -> Result<(), &'static str> {
+    let mut rest = template.to_string();
+    for placeholder in PLACEHOLDERS {
+        rest = rest.replace(placeholder, "");
+    }
+    if rest.contains(['{', '}']) {
+        return Err("only {version} and {tag} can be filled in");
+    }
+    let tag =
+        image_tag(template).ok_or("expected a tag, e.g. \"ghcr.io/owner/project:{version}\"")?;
+    if !PLACEHOLDERS.iter().any(|p| tag.contains(p)) {
+        return Err("the tag needs {version} or {tag}");
+    }
+    Ok(())
+}
+
+/// Tag part of an image reference: after the last ':' that follows the last
+/// '/', so a registry port isn't taken for one.
+fn image_tag(image: &str) -> Option<&str> {
+    let name_start = image.rfind('/').map_or(0, |i| i + 1);
+    image[name_start..].split_once(':').map(|(_, tag)| tag)
+}
+
+/// Docker tags: up to 128 of `[A-Za-z0-9_.-]`, not starting with '.' or '-'.
+fn is_valid_tag(tag: &str) -> bool {
+    (1..=128).contains(&tag.len())
+        && !tag.starts_with(['.', '-'])
+        && tag
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'))
+}
@@ -0,0 +1,217 @@
+//! Bulk import at `POST /api/v2/import`: pasted repo URLs are resolved into
+//! routes like `/go` resolves one, optionally added to the watchlist, and
+//! fetched a few at a time so their pages are cached when first visited.
+
+use crate::{
+    AppState, RepoPath,
+    admin::require_admin,
+    config::WatchEntry,
+    error::{ApiError, AppError},
+    provider,
+    signing::json_response,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use futures_util::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc};
+
+/// URLs accepted per request
+const MAX_URLS: usize = 200;
+/// Fetches running at once
+const CONCURRENT_FETCHES: usize = 4;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImportRequest {
+    /// Repo URLs or routes, as accepted by `/go`
+    urls: Vec<String>,
+    /// Add the repos to the watchlist
+    #[serde(default)]
+    watch: bool,
+    /// Report section of repos added to the watchlist
+    category: Option<String>,
+    interval_hours: Option<i64>,
+    /// Fetch repos whose cache is missing or expired
+    #[serde(default = "default_fetch")]
+    fetch: bool,
+}
+
+fn default_fetch() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ImportStatus {
+    /// The URL names no repo
+    Invalid,
+    /// The repo appeared earlier in the list
+    Duplicate,
+    /// Cached and not expired, so not fetched
+    Cached,
+    Fetched,
+    /// Already being fetched by another request
+    Pending,
+    Failed,
+    /// Not fetched, as asked with `"fetch": false` or in maintenance mode
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportedRepo {
+    input: String,
+    /// Route of the repo's release page, e.g. "/github/owner/repo"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    route: Option<String>,
+    status: ImportStatus,
+    /// Whether this request added the repo to the watchlist
+    watched: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportResponse {
+    repos: Vec<ImportedRepo>,
+    fetched: usize,
+    failed: usize,
+    invalid: usize,
+}
+
+/// `POST /api/v2/import` (admin only), with a JSON body listing the URLs.
+pub async fn import_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let request: ImportRequest = serde_json::from_str(&body).map_err(|e| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid import request: {}", e),
+        )
+    })?;
+    if request.urls.len() > MAX_URLS {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!("At most {} URLs can be imported at once", MAX_URLS),
+        )
+        .into());
+    }
+
+    // Futures don't start until polled, so only `CONCURRENT_FETCHES` run at once
+    let mut seen = HashSet::new();
+    let imports: Vec<_> = request
+        .urls
+        .iter()
+        .map(|input| {
+            let resolved = provider::resolve_repo(input);
+            let duplicate = resolved
+                .as_ref()
+                .is_ok_and(|(_, repo)| !seen.insert(repo.cache_key()));
+            import_one(&state, &request, input.clone(), resolved, duplicate)
+        })
+        .collect();
+    let repos: Vec<ImportedRepo> = stream::iter(imports)
+        .buffered(CONCURRENT_FETCHES)
+        .collect()
+        .await;
+
+    let count = |status| repos.iter().filter(|r| r.status == status).count();
+    let response = ImportResponse {
+        fetched: count(ImportStatus::Fetched),
+        failed: count(ImportStatus::Failed),
+        invalid: count(ImportStatus::Invalid),
+        repos,
+    };
+    let body = serde_json::to_string_pretty(&response).map_err(AppError::internal)?;
+    Ok(json_response(&state, StatusCode::OK, body))
+}
+
+async fn import_one(
+    state: &Arc<AppState>,
+    request: &ImportRequest,
+    input: String,
+    resolved: anyhow::Result<(&'static str, RepoPath)>,
+    duplicate: bool,
+) -> ImportedRepo {
+    let (prefix, repo) = match resolved {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            return ImportedRepo {
+                input,
+                route: None,
+                status: ImportStatus::Invalid,
+                watched: false,
+                error: Some(e.to_string()),
+            };
+        }
+    };
+    let route = provider::repo_route(prefix, &repo);
+    let mut imported = ImportedRepo {
+        input,
+        route: Some(route.clone()),
+        status: if duplicate {
+            ImportStatus::Duplicate
+        } else {
+            ImportStatus::Skipped
+        },
+        watched: false,
+        error: None,
+    };
+    if duplicate {
+        return imported;
+    }
+
+    if request.watch {
+        let entry = WatchEntry {
+            repo: route.trim_start_matches('/').to_string(),
+            interval_hours: request.interval_hours,
+            category: request.category.clone(),
+        };
+        match state.refresher.watch(entry) {
+            Ok(added) => imported.watched = added,
+            Err(e) => imported.error = Some(e.to_string()),
+        }
+    }
+    if request.fetch && !state.maintenance.active() {
+        match prefetch(state, prefix, &repo).await {
+            Ok(status) => imported.status = status,
+            Err(e) => {
+                imported.status = ImportStatus::Failed;
+                imported.error = Some(e.to_string());
+            }
+        }
+    }
+    imported
+}
+
+/// Fetch a repo unless it is cached and fresh or already being fetched.
+/// Failures are recorded like those of background fetches.
+async fn prefetch(
+    state: &Arc<AppState>,
+    prefix: &str,
+    repo: &RepoPath,
+) -> anyhow::Result<ImportStatus> {
+    if let Ok(Some(cached_at)) = state
+        .cache
+        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
+        && !state
+            .cache
+            .is_repo_expired(&repo.host, &repo.owner, &repo.repo, cached_at)
+    {
+        return Ok(ImportStatus::Cached);
+    }
+
+    if !state.fetches.start(prefix, repo) {
+        return Ok(ImportStatus::Pending);
+    }
+    let result = provider::fetch_and_cache(state, prefix, repo).await;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    state.fetches.finish(prefix, repo, error);
+    result.map(|()| ImportStatus::Fetched)
+}
@@ -0,0 +1,170 @@
+//! Install commands found in release notes and READMEs. When releases are
+//! fetched, the latest release's notes and the repo's README are scanned for
+//! `brew install`, `cargo install` and `curl ... | sh` commands, in code
+//! blocks and inline code. Release pages show them in an "Install" section
+//! and `+json` lists them in the latest release's `install`, notes first.
+
+use crate::provider::Release;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Commands kept per repo
+const MAX_COMMANDS: usize = 6;
+/// Longer code lines are taken for scripts rather than commands, and skipped
+const MAX_COMMAND_LEN: usize = 300;
+/// Shells piped-to scripts run with
+const SHELLS: [&str; 3] = ["sh", "bash", "zsh"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallKind {
+    Brew,
+    Cargo,
+    /// A script downloaded and piped to a shell
+    Script,
+}
+
+impl InstallKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Brew => "Homebrew",
+            Self::Cargo => "Cargo",
+            Self::Script => "Install script",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallSource {
+    Notes,
+    Readme,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct InstallCommand {
+    pub kind: InstallKind,
+    pub command: String,
+    /// Found in the release notes or the README
+    pub source: InstallSource,
+}
+
+/// What kind of install command `line` is, if any.
+fn classify(line: &str) -> Option<InstallKind> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["brew", "install", _, ..] => return Some(InstallKind::Brew),
+        ["cargo", "install" | "binstall", _, ..] => return Some(InstallKind::Cargo),
+        _ => {}
+    }
+    let (fetch, shell) = line.split_once('|')?;
+    let fetches = matches!(fetch.split_whitespace().next(), Some("curl" | "wget"));
+    let mut shell = shell.split_whitespace();
+    let shell = match shell.next()? {
+        "sudo" => shell.next()?,
+        first => first,
+    };
+    (fetches && SHELLS.contains(&shell)).then_some(InstallKind::Script)
+}
+
+/// A command as written in a code line: without a prompt, on one line.
+fn clean(line: &str) -> &str {
+    let line = line.trim();
+    line.strip_prefix("$ ")
+        .or_else(|| line.strip_prefix("% "))
+        .or_else(|| line.strip_prefix("> "))
+        .unwrap_or(line)
+        .trim()
+}
+
+/// Code lines of markdown `text`: those of fenced code blocks, with
+/// backslash-continued lines joined, and inline code spans.
+fn code_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut pending = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => {
+                fence = None;
+                pending.clear();
+            }
+            (Some(_), _) => {
+                let line = line.trim_end();
+                match line.strip_suffix('\\') {
+                    Some(continued) => {
+                        pending.push_str(continued.trim());
+                        pending.push(' ');
+                    }
+                    None => {
+                        pending.push_str(line.trim_start());
+                        lines.push(std::mem::take(&mut pending));
+                    }
+                }
+            }
+            (None, None) => {
+                lines.extend(line.split('`').skip(1).step_by(2).map(String::from));
+            }
+        }
+    }
+    lines
+}
+
+/// Install commands in markdown `text`, in order and without duplicates.
+pub fn extract(text: &str, source: InstallSource) -> Vec<InstallCommand> {
+    let mut commands: Vec<InstallCommand> = Vec::new();
+    for line in code_lines(text) {
+        let command = clean(&line);
+        if command.len() > MAX_COMMAND_LEN || commands.iter().any(|c| c.command == command) {
+            continue;
+        }
+        if let Some(kind) = classify(command) {
+            commands.push(InstallCommand {
+                kind,
+                command: command.to_string(),
+                source,
+            });
+        }
+    }
+    commands
+}
+
+/// The README's commands the latest non-draft release was given, for
+/// releases cached without fetching the README again.
+pub fn readme_commands(releases: &[Release]) -> Vec<InstallCommand> {
+    releases
+        .iter()
+        .find(|r| !r.draft)
+        .map(|latest| {
+            latest
+                .install
+                .iter()
+                .filter(|c| c.source == InstallSource::Readme)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Set the latest non-draft release's `install` from its notes, then the
+/// README's `readme` commands. Runs before the notes are truncated, so none
+/// are missed.
+pub fn apply(releases: &mut [Release], readme: Vec<InstallCommand>) {
+    let Some(latest) = releases.iter_mut().find(|r| !r.draft) else {
+        return;
+    };
+    let mut commands = extract(
+        latest.body.as_deref().unwrap_or_default(),
+        InstallSource::Notes,
+    );
+    for command in readme {
+        if !commands.iter().any(|c| c.command == command.command) {
+            commands.push(command);
+        }
+    }
+    commands.truncate(MAX_COMMANDS);
+    latest.install = commands;
+}
@@ -0,0 +1,259 @@
+//! `GET /api/v2/config`: the instance's effective configuration, so client
+//! tooling and the dashboard can adapt to what it offers. Built once at
+//! startup from the command line and config file. Secrets are never part of
+//! it: tokens, keys and credentials only show up as whether they are set.
+
+use crate::{
+    AppState, Args, VERSION,
+    cache_control::RouteKind,
+    config::{Config, ProviderLimits},
+    provider::{self, huggingface},
+    signing,
+};
+use axum::{extract::State, http::StatusCode, response::Response};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct InstanceConfig {
+    pub version: &'static str,
+    pub public_url: String,
+    pub cache: CacheSettings,
+    pub refresh: RefreshSettings,
+    pub providers: Vec<ProviderSettings>,
+    pub features: Features,
+    pub limits: Limits,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CacheSettings {
+    /// How long fetched releases are served before being refetched
+    pub ttl_hours: i64,
+    /// Releases per HTML page
+    pub page_size: usize,
+    /// Release notes longer than this are truncated, with the full notes at
+    /// `/{tag}/notes`; 0 when notes are kept whole
+    pub max_notes_bytes: usize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RefreshSettings {
+    /// Repos kept fresh in the background
+    pub watched_repos: usize,
+    /// Default refresh interval of watched repos
+    pub interval_hours: i64,
+    pub max_concurrent: usize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ProviderSettings {
+    pub name: &'static str,
+    /// Route pattern of a repo, e.g. "/github/{owner}/{repo}"
+    pub route: &'static str,
+    /// Whether API requests are authenticated; absent for providers whose
+    /// hosts vary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authenticated: Option<bool>,
+    /// `[providers.*]` limits; null ones keep the upstream defaults
+    pub limits: ProviderLimits,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Features {
+    /// JSON responses carry an `X-Checkup-Signature` header
+    pub signed_responses: bool,
+    /// `?lang=` translation of release notes
+    pub translation: bool,
+    /// Languages offered for `?lang=`; any when empty
+    pub translation_languages: Vec<String>,
+    pub enrich_assets: bool,
+    pub github_latest_fast_path: bool,
+    /// `/popular` is public
+    pub popular_page: bool,
+    /// Admin routes are enabled
+    pub admin_api: bool,
+    /// Instances listed at `/forgejo/{host}`
+    pub forgejo_catalog_hosts: Vec<String>,
+    /// Some repos have `/delta/...` patches between releases
+    pub deltas: bool,
+    /// Proxied assets are pinned to IPFS
+    pub ipfs: bool,
+    /// Release pages offer email subscriptions
+    pub email_subscriptions: bool,
+    /// Users can sign in and keep watchlists of their own
+    pub accounts: bool,
+    /// Visitors can create accounts at `/signup`
+    pub signup: bool,
+    /// OpenID Connect providers users can sign in with, at
+    /// `/login/oidc/{name}`
+    pub sign_in_providers: Vec<String>,
+    /// Instances whose watched repos are taken into the cache
+    pub federation_peers: Vec<String>,
+    /// Aggregate usage statistics are reported, see `/about`
+    pub telemetry: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Limits {
+    /// Uncached fetches a client may trigger per hour
+    pub fetch_quota_per_hour: Option<u32>,
+    pub max_pending_fetches: Option<usize>,
+    pub max_inflight_fetches: Option<usize>,
+}
+
+impl InstanceConfig {
+    pub(crate) fn new(args: &Args, config: &Config, public_url: &str) -> Self {
+        let providers = &config.providers;
+        let github_limits = ProviderLimits {
+            max_pages: Some(
+                providers
+                    .github
+                    .max_pages
+                    .unwrap_or(provider::DEFAULT_MAX_PAGES),
+            ),
+            ..providers.github.clone()
+        };
+        Self {
+            version: VERSION,
+            public_url: public_url.to_string(),
+            cache: CacheSettings {
+                ttl_hours: args.cache_hours,
+                page_size: args.page_size.max(1),
+                max_notes_bytes: config.notes.max_bytes,
+            },
+            refresh: RefreshSettings {
+                watched_repos: config.watch.len(),
+                interval_hours: config.refresh.interval_hours.unwrap_or(args.cache_hours),
+                max_concurrent: config.refresh.max_concurrent.max(1),
+            },
+            providers: vec![
+                ProviderSettings {
+                    name: "github",
+                    route: "/github/{owner}/{repo}",
+                    authenticated: Some(args.github_token.as_ref().is_some_and(|t| !t.is_empty())),
+                    limits: github_limits,
+                },
+                ProviderSettings {
+                    name: "gitlab",
+                    route: "/gitlab/[{host}/]{owner}/{repo}",
+                    authenticated: None,
+                    limits: providers.gitlab.clone(),
+                },
+                ProviderSettings {
+                    name: "forgejo",
+                    route: "/forgejo/{host}/{owner}/{repo}",
+                    authenticated: None,
+                    limits: providers.forgejo.clone(),
+                },
+                ProviderSettings {
+                    name: "gitea",
+                    route: "/gitea/{host}/{owner}/{repo}",
+                    authenticated: None,
+                    limits: providers.forgejo.clone(),
+                },
+                ProviderSettings {
+                    name: "codeberg",
+                    route: "/codeberg/{owner}/{repo}",
+                    authenticated: Some(config.credentials.contains_key("codeberg.org")),
+                    limits: providers.forgejo.clone(),
+                },
+                ProviderSettings {
+                    name: "sourcehut",
+                    route: "/sourcehut/~{owner}/{repo}",
+                    authenticated: Some(false),
+                    limits: providers.sourcehut.clone(),
+                },
+                ProviderSettings {
+                    name: "launchpad",
+                    route: "/launchpad/{project}",
+                    authenticated: Some(false),
+                    limits: providers.launchpad.clone(),
+                },
+                ProviderSettings {
+                    name: "snap",
+                    route: "/snap/{name}",
+                    authenticated: Some(false),
+                    limits: providers.snap.clone(),
+                },
+                ProviderSettings {
+                    name: "huggingface",
+                    route: "/huggingface/[datasets/]{owner}/{repo}",
+                    authenticated: Some(config.credentials.contains_key(huggingface::HOST)),
+                    limits: providers.huggingface.clone(),
+                },
+                ProviderSettings {
+                    name: "pagure",
+                    route: "/pagure/{host}/{repo}",
+                    authenticated: None,
+                    limits: providers.pagure.clone(),
+                },
+                ProviderSettings {
+                    name: "gitiles",
+                    route: "/gitiles/{host}/{repo_path}",
+                    authenticated: None,
+                    limits: providers.gitiles.clone(),
+                },
+                ProviderSettings {
+                    name: "hg",
+                    route: "/hg/{host}/{repo_path}",
+                    authenticated: None,
+                    limits: providers.hg.clone(),
+                },
+                ProviderSettings {
+                    name: "cgit",
+                    route: "/cgit/{host}/{repo_path}",
+                    authenticated: None,
+                    limits: providers.cgit.clone(),
+                },
+                ProviderSettings {
+                    name: "autoindex",
+                    route: "/autoindex/{host}/{directory}",
+                    authenticated: None,
+                    limits: providers.autoindex.clone(),
+                },
+            ],
+            features: Features {
+                signed_responses: args.signing_key.is_some(),
+                translation: config.translate.url.is_some(),
+                translation_languages: config.translate.languages.clone(),
+                enrich_assets: args.enrich_assets,
+                github_latest_fast_path: args.github_latest_fast_path,
+                popular_page: args.public_popular,
+                admin_api: args.admin_token.is_some()
+                    || config.accounts.enabled && !config.accounts.admins.is_empty(),
+                forgejo_catalog_hosts: config.catalog.forgejo_hosts.clone(),
+                deltas: config.repo.iter().any(|r| r.deltas),
+                ipfs: config.ipfs.api.is_some(),
+                email_subscriptions: config.email.from.is_some(),
+                accounts: config.accounts.enabled,
+                signup: config.accounts.enabled && config.accounts.signup,
+                sign_in_providers: if config.accounts.enabled {
+                    config
+                        .accounts
+                        .oidc
+                        .iter()
+                        .map(|o| o.name.clone())
+                        .collect()
+                } else {
+                    Vec::new()
+                },
+                federation_peers: config.federation.peers.clone(),
+                telemetry: config.telemetry.endpoint.is_some(),
+            },
+            limits: Limits {
+                fetch_quota_per_hour: args.fetch_quota,
+                max_pending_fetches: args.max_pending_fetches,
+                max_inflight_fetches: args.max_inflight_fetches,
+            },
+        }
+    }
+}
+
+pub async fn config_json(State(state): State<Arc<AppState>>) -> Response {
+    let body = serde_json::to_string_pretty(&*state.instance).unwrap_or_default();
+    state.cache_policy.apply(
+        RouteKind::Json,
+        signing::json_response(&state, StatusCode::OK, body),
+    )
+}
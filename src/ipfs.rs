@@ -0,0 +1,255 @@
+//! Pinning of release assets to IPFS: with `[ipfs] api` set, each asset
+//! downloaded whole through `/proxy/` is copied aside while it streams, then
+//! added to the node (Kubo's `/api/v0/add`, pinned, as CIDv1) and the copy
+//! removed. CIDs are kept in cids.json by asset URL and show in `+json` and
+//! on release pages, linked through `[ipfs] gateway`, giving releases a way
+//! to be fetched that doesn't depend on the forge or this instance.
+
+use crate::{
+    AppState, RepoPath,
+    config::IpfsConfig,
+    provider::{Release, file_chunks},
+    proxy,
+};
+use anyhow::Result;
+use futures_util::{StreamExt, stream};
+use reqwest::{Client, header};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::io::AsyncWriteExt;
+
+/// Client of the node's RPC API
+pub struct Ipfs {
+    api: String,
+    client: Client,
+}
+
+/// Answer of `/api/v0/add` for a single file
+#[derive(Debug, Deserialize)]
+struct Added {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+impl Ipfs {
+    pub fn from_config(config: &IpfsConfig) -> Option<Self> {
+        let api = config.api.as_deref()?.trim_end_matches('/').to_string();
+        Some(Self {
+            api,
+            client: Client::new(),
+        })
+    }
+
+    /// Add the file at `path` to the node, pinned, as `name`; returns its CID.
+    async fn add(&self, path: &Path, name: &str) -> Result<String> {
+        let file = tokio::fs::File::open(path).await?;
+        let len = file.metadata().await?.len();
+        let boundary = format!("checkup-{:016x}", fastrand::u64(..));
+        let head = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            boundary,
+            name.replace(['"', '\r', '\n'], "")
+        );
+        let tail = format!("\r\n--{}--\r\n", boundary);
+        let content_length = head.len() as u64 + len + tail.len() as u64;
+        let body = stream::once(async move { Ok(head.into_bytes()) })
+            .chain(file_chunks(file))
+            .chain(stream::once(async move { Ok(tail.into_bytes()) }));
+
+        let response = self
+            .client
+            .post(format!("{}/api/v0/add?pin=true&cid-version=1", self.api))
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .header(header::CONTENT_LENGTH, content_length)
+            .body(reqwest::Body::wrap_stream(body))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("IPFS node returned {}", response.status());
+        }
+        let added: Added = response.json().await?;
+        Ok(added.hash)
+    }
+
+    /// Unpin `cid`, leaving the node's garbage collection to free it.
+    async fn unpin(&self, cid: &str) -> Result<()> {
+        let response = self
+            .client
+            .post(format!("{}/api/v0/pin/rm", self.api))
+            .query(&[("arg", cid)])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("IPFS node returned {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+/// A copy of a proxied download, removed when dropped.
+pub struct Spool {
+    path: PathBuf,
+    file: tokio::fs::File,
+    name: String,
+}
+
+impl Spool {
+    pub async fn write(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        self.file.write_all(chunk).await
+    }
+
+    /// Pin the complete copy in the background and record its CID.
+    pub fn pin(self, state: Arc<AppState>, prefix: &'static str, repo: RepoPath, url: String) {
+        tokio::spawn(async move {
+            let Some(ipfs) = &state.ipfs else {
+                return;
+            };
+            let pinned = async {
+                let mut spool = self;
+                spool.file.flush().await?;
+                let cid = ipfs.add(&spool.path, &spool.name).await?;
+                let (state, url) = (state.clone(), url.clone());
+                tokio::task::spawn_blocking(move || {
+                    state.cache.with_repo_lock(&repo.cache_key(), || {
+                        record_cid(&state, prefix, &repo, &url, &cid)
+                    })
+                })
+                .await?
+            };
+            if let Err(e) = pinned.await {
+                eprintln!("Pinning {} to IPFS failed: {}", url, e);
+            }
+        });
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A copy to spool a download of `url` into, when pinning is on and the
+/// asset isn't pinned yet.
+pub async fn spool(state: &AppState, repo: &RepoPath, url: &str, name: &str) -> Option<Spool> {
+    state.ipfs.as_ref()?;
+    let cids = state
+        .cache
+        .read_cids(&repo.host, &repo.owner, &repo.repo)
+        .ok()?;
+    if cids.contains_key(url) {
+        return None;
+    }
+    let dir = state
+        .cache
+        .get_repo_dir(&repo.host, &repo.owner, &repo.repo);
+    let path = dir.join(format!(".ipfs-{:016x}.part", fastrand::u64(..)));
+    let created = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::File::create(&path).await
+    };
+    match created.await {
+        Ok(file) => Some(Spool {
+            path,
+            file,
+            name: name.to_string(),
+        }),
+        Err(e) => {
+            eprintln!("Spooling {} for IPFS failed: {}", url, e);
+            None
+        }
+    }
+}
+
+fn record_cid(state: &AppState, prefix: &str, repo: &RepoPath, url: &str, cid: &str) -> Result<()> {
+    let cache = &state.cache;
+    let mut cids = cache.read_cids(&repo.host, &repo.owner, &repo.repo)?;
+    cids.insert(url.to_string(), cid.to_string());
+    cache.write_cids(&repo.host, &repo.owner, &repo.repo, &cids)?;
+    proxy::update_cached(state, prefix, repo, |releases| fill_cids(releases, &cids))
+}
+
+/// Set recorded CIDs on assets; returns whether any asset changed.
+fn fill_cids(releases: &mut [Release], cids: &BTreeMap<String, String>) -> bool {
+    let mut changed = false;
+    for asset in releases.iter_mut().flat_map(|r| r.assets.iter_mut()) {
+        let cid = cids.get(&asset.url);
+        if cid.is_some() && asset.cid.as_ref() != cid {
+            asset.cid = cid.cloned();
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Apply recorded CIDs to freshly fetched releases.
+pub fn apply_recorded_cids(state: &AppState, repo: &RepoPath, releases: &mut [Release]) {
+    if let Ok(cids) = state.cache.read_cids(&repo.host, &repo.owner, &repo.repo)
+        && !cids.is_empty()
+    {
+        fill_cids(releases, &cids);
+    }
+}
+
+/// Drop the CIDs of assets not in `kept` (`retention.rs`) and unpin them
+/// in the background, unless an asset kept here or in another repo has the
+/// same file.
+pub fn release_pins(state: &AppState, repo: &RepoPath, kept: &HashSet<&str>) -> Result<()> {
+    let Some(ipfs) = state.ipfs.clone() else {
+        return Ok(());
+    };
+    let cache = &state.cache;
+    let released = cache.with_repo_lock(&repo.cache_key(), || {
+        let mut cids = cache.read_cids(&repo.host, &repo.owner, &repo.repo)?;
+        let mut released = Vec::new();
+        cids.retain(|url, cid| {
+            let keep = kept.contains(url.as_str());
+            if !keep {
+                released.push(cid.clone());
+            }
+            keep
+        });
+        if !released.is_empty() {
+            cache.write_cids(&repo.host, &repo.owner, &repo.repo, &cids)?;
+        }
+        anyhow::Ok(released)
+    })?;
+    if released.is_empty() {
+        return Ok(());
+    }
+    let cache = cache.clone();
+    tokio::spawn(async move {
+        let Ok(pinned) = tokio::task::spawn_blocking(move || cache.read_all_cids()).await else {
+            return;
+        };
+        for cid in released.iter().filter(|cid| !pinned.contains(*cid)) {
+            if let Err(e) = ipfs.unpin(cid).await {
+                eprintln!("Unpinning {} from IPFS failed: {}", cid, e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Drop the CIDs of assets whose file was replaced under the same URL
+/// (`rolling.rs`), so the next proxy download pins the new file. The old
+/// file stays pinned on the node.
+pub fn forget_cids(state: &AppState, repo: &RepoPath, urls: &[String]) -> Result<()> {
+    let cache = &state.cache;
+    cache.with_repo_lock(&repo.cache_key(), || {
+        let mut cids = cache.read_cids(&repo.host, &repo.owner, &repo.repo)?;
+        let before = cids.len();
+        cids.retain(|url, _| !urls.contains(url));
+        if cids.len() != before {
+            cache.write_cids(&repo.host, &repo.owner, &repo.repo, &cids)?;
+        }
+        Ok(())
+    })
+}
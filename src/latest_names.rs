@@ -0,0 +1,192 @@
+//! `latest` names of assets: "bat-v0.26.1-x86_64-linux.tar.gz" is linked as
+//! "latest-x86_64-linux.tar.gz", a name that stays the same from release to
+//! release. The name is split into stem and extension, the stem into
+//! segments, and everything up to the version segment is dropped. The known
+//! extensions, the version pattern and the separators come from `[latest]`;
+//! `/debug/rename?name=` shows how a name maps.
+
+use crate::config::LatestConfig;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+
+/// Extensions kept whole, tried in order, so compound ones come first.
+const KNOWN_EXTENSIONS: &[&str] = &[
+    ".tar.gz.sha256",
+    ".tar.xz.sha256",
+    ".tar.bz2.sha256",
+    ".tar.zst.sha256",
+    ".zip.sha256",
+    ".xz.sha256",
+    ".gz.sha256",
+    ".bz2.sha256",
+    ".xz.asc",
+    ".tar.gz",
+    ".tar.xz",
+    ".tar.bz2",
+    ".tar.zst",
+    ".xz",
+    ".gz",
+    ".bz2",
+    ".zst",
+    ".zip",
+    ".sha256",
+    ".sha512",
+    ".exe",
+    ".msi",
+    ".deb",
+    ".rpm",
+];
+
+/// Optional "v" and at least major.minor
+const VERSION_PATTERN: &str = r"^v?[0-9]+\.[0-9]+(\.|$)";
+
+const SEPARATORS: [char; 2] = ['-', '_'];
+
+#[derive(Debug, Clone)]
+pub struct LatestNames {
+    /// `[latest] extensions`, tried before the built-in ones
+    extensions: Vec<String>,
+    version: Regex,
+    separators: Vec<char>,
+}
+
+impl Default for LatestNames {
+    fn default() -> Self {
+        Self {
+            extensions: Vec::new(),
+            version: Regex::new(VERSION_PATTERN).unwrap(),
+            separators: SEPARATORS.to_vec(),
+        }
+    }
+}
+
+/// How a name maps to its `latest` name, step by step
+#[derive(Debug, Serialize)]
+pub struct Rename<'a> {
+    pub name: &'a str,
+    pub stem: &'a str,
+    pub extension: &'a str,
+    pub separator: char,
+    pub segments: Vec<&'a str>,
+    /// Segment taken for the version; without one, only the first segment
+    /// (the app name) is dropped
+    pub version: Option<&'a str>,
+    pub latest: String,
+}
+
+impl LatestNames {
+    pub fn from_config(config: &LatestConfig) -> Result<Self> {
+        let mut names = Self::default();
+        for ext in &config.extensions {
+            if !ext.starts_with('.') || ext.len() < 2 {
+                anyhow::bail!(
+                    "Invalid [latest] extension {:?}, expected e.g. \".AppImage\"",
+                    ext
+                );
+            }
+        }
+        names.extensions = config.extensions.clone();
+        if let Some(pattern) = &config.version_pattern {
+            names.version = Regex::new(pattern)
+                .with_context(|| format!("Invalid [latest] version_pattern {:?}", pattern))?;
+        }
+        if let Some(separators) = &config.separators {
+            names.separators = separators
+                .iter()
+                .map(|s| {
+                    let mut chars = s.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if c != '.' => Ok(c),
+                        _ => anyhow::bail!(
+                            "Invalid [latest] separator {:?}, expected one character other than '.'",
+                            s
+                        ),
+                    }
+                })
+                .collect::<Result<_>>()?;
+            if names.separators.is_empty() {
+                anyhow::bail!("[latest] separators can't be empty");
+            }
+        }
+        Ok(names)
+    }
+
+    /// Split a filename into stem and extension.
+    /// e.g., "bat-v0.26.1-x86_64.tar.gz" -> ("bat-v0.26.1-x86_64", ".tar.gz")
+    pub fn split_stem_ext<'a>(&self, filename: &'a str) -> (&'a str, &'a str) {
+        let known = self
+            .extensions
+            .iter()
+            .map(String::as_str)
+            .chain(KNOWN_EXTENSIONS.iter().copied());
+        for ext in known {
+            if let Some(stem) = filename.strip_suffix(ext) {
+                return (stem, &filename[stem.len()..]);
+            }
+        }
+
+        // Fallback: split on last dot, but only if suffix looks like a real file extension
+        // (not a version fragment like ".2-linux-amd64" or ".2" in "forgejo-14.0.2-linux-amd64")
+        if let Some(pos) = filename.rfind('.') {
+            let suffix = &filename[pos + 1..];
+            if !suffix.is_empty()
+                && !suffix.contains('-')
+                && !suffix.chars().all(|c| c.is_ascii_digit())
+            {
+                return (&filename[..pos], &filename[pos..]);
+            }
+        }
+        (filename, "")
+    }
+
+    /// Rename an asset filename to a "latest" variant, stripping app name and version.
+    /// e.g., "bat-v0.26.1-x86_64-linux.tar.gz" -> "latest-x86_64-linux.tar.gz"
+    ///       "forgejo-14.0.2-linux-amd64.xz"    -> "latest-linux-amd64.xz"
+    ///       "bat_0.26.1_amd64.deb"             -> "latest_amd64.deb"
+    ///       "linux-6.19.2.tar.gz"              -> "latest.tar.gz"
+    pub fn rename(&self, filename: &str) -> String {
+        self.explain(filename).latest
+    }
+
+    pub fn explain<'a>(&self, filename: &'a str) -> Rename<'a> {
+        let (stem, extension) = self.split_stem_ext(filename);
+
+        // The first separator the stem contains, else the last one
+        let separator = self
+            .separators
+            .iter()
+            .copied()
+            .find(|&sep| stem.contains(sep))
+            .unwrap_or(self.separators[self.separators.len() - 1]);
+        let segments: Vec<&str> = stem.split(separator).collect();
+
+        let version_idx = segments.iter().position(|s| self.version.is_match(s));
+        let suffix_parts = match version_idx {
+            // Drop everything up to and including the version
+            Some(idx) => &segments[idx + 1..],
+            // No version found: drop just the app name (first segment)
+            None => &segments[1..],
+        };
+
+        let latest = if suffix_parts.is_empty() {
+            format!("latest{}", extension)
+        } else {
+            format!(
+                "latest{}{}{}",
+                separator,
+                suffix_parts.join(&separator.to_string()),
+                extension
+            )
+        };
+        Rename {
+            name: filename,
+            stem,
+            extension,
+            separator,
+            version: version_idx.map(|idx| segments[idx]),
+            segments,
+            latest,
+        }
+    }
+}
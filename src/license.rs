@@ -0,0 +1,134 @@
+//! Release licenses, for redistributing releases through the mirror. Each
+//! release's LICENSE, COPYING and NOTICE assets are listed in its `license`,
+//! and the latest release's also carries the repo license the forge
+//! reports, where the provider can ask for it. Without a forge license the
+//! SPDX identifier is guessed from names like `LICENSE-MIT`. Release pages
+//! show it as a badge.
+
+use crate::provider::{Asset, Release};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct License {
+    /// SPDX expression, e.g. "MIT" or "Apache-2.0 OR MIT"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spdx_id: Option<String>,
+    /// As the forge names it, e.g. "MIT License"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Names of the release's license and notice assets
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+impl License {
+    /// Short label for badges: the SPDX expression, else the forge's name.
+    pub fn label(&self) -> &str {
+        self.spdx_id
+            .as_deref()
+            .or(self.name.as_deref())
+            .unwrap_or("License files")
+    }
+}
+
+/// Names of license and notice files, without extension, uppercased
+const FILE_NAMES: [&str; 8] = [
+    "LICENSE",
+    "LICENCE",
+    "COPYING",
+    "NOTICE",
+    "UNLICENSE",
+    "THIRD-PARTY-NOTICES",
+    "THIRDPARTYNOTICES",
+    "THIRD-PARTY-LICENSES",
+];
+/// Extensions license files are published with
+const EXTENSIONS: [&str; 3] = [".txt", ".md", ".rst"];
+/// `LICENSE-{suffix}` files and the license they hold, if one license
+const SUFFIXES: [(&str, Option<&str>); 9] = [
+    ("MIT", Some("MIT")),
+    ("APACHE", Some("Apache-2.0")),
+    ("APACHE-2.0", Some("Apache-2.0")),
+    ("BSL", Some("BSL-1.0")),
+    ("MPL", Some("MPL-2.0")),
+    ("ZLIB", Some("Zlib")),
+    ("UNLICENSE", Some("Unlicense")),
+    ("THIRD-PARTY", None),
+    ("THIRDPARTY", None),
+];
+
+/// Whether `name` is a license or notice file, and the license it names.
+fn classify(name: &str) -> Option<Option<&'static str>> {
+    let upper = name.to_uppercase().replace('_', "-");
+    let stem = EXTENSIONS
+        .iter()
+        .find_map(|ext| upper.strip_suffix(&ext.to_uppercase()))
+        .unwrap_or(&upper);
+    if FILE_NAMES.contains(&stem) {
+        return Some(if stem == "UNLICENSE" {
+            Some("Unlicense")
+        } else {
+            None
+        });
+    }
+    let suffix = ["LICENSE-", "LICENCE-", "COPYING-"]
+        .into_iter()
+        .find_map(|prefix| stem.strip_prefix(prefix))?;
+    SUFFIXES
+        .iter()
+        .find(|(s, _)| *s == suffix)
+        .map(|&(_, spdx_id)| spdx_id)
+}
+
+/// The license of `assets`, from the license files among them.
+fn from_assets(assets: &[Asset]) -> Option<License> {
+    let mut files = Vec::new();
+    let mut ids: Vec<&str> = Vec::new();
+    for asset in assets {
+        let Some(spdx_id) = classify(&asset.name) else {
+            continue;
+        };
+        files.push(asset.name.clone());
+        if let Some(id) = spdx_id
+            && !ids.contains(&id)
+        {
+            ids.push(id);
+        }
+    }
+    (!files.is_empty()).then(|| License {
+        spdx_id: (!ids.is_empty()).then(|| ids.join(" OR ")),
+        name: None,
+        files,
+    })
+}
+
+/// The latest non-draft release's license without its files, standing in
+/// for the forge's when releases are cached without asking the forge again.
+pub fn forge_license(releases: &[Release]) -> Option<License> {
+    let license = releases.iter().find(|r| !r.draft)?.license.as_ref()?;
+    Some(License {
+        files: Vec::new(),
+        ..license.clone()
+    })
+}
+
+/// Set each release's `license` from its assets, and the latest non-draft
+/// release's from the forge's repo license too.
+pub fn apply(releases: &mut [Release], forge: Option<License>) {
+    let latest = releases.iter().position(|r| !r.draft);
+    for (i, release) in releases.iter_mut().enumerate() {
+        let assets = from_assets(&release.assets);
+        release.license = match (&forge, assets) {
+            (Some(forge), assets) if Some(i) == latest => Some(License {
+                spdx_id: forge
+                    .spdx_id
+                    .clone()
+                    .or_else(|| assets.as_ref().and_then(|a| a.spdx_id.clone())),
+                name: forge.name.clone(),
+                files: assets.map(|a| a.files).unwrap_or_default(),
+            }),
+            (_, assets) => assets,
+        };
+    }
+}
@@ -0,0 +1,199 @@
+//! Running in containers: the cache and state directories are the only
+//! places written to, so the root filesystem can be read-only, and both are
+//! checked for being writable before the server starts. `/health` answers
+//! as long as the process serves (liveness); `/ready` only while both
+//! directories are writable and the server isn't shutting down
+//! (readiness). On SIGTERM or Ctrl-C `/ready` fails first, for
+//! `--shutdown-grace-secs`, so load balancers stop sending traffic before
+//! the server stops accepting it and finishes the requests in flight.
+//!
+//! The cache layout is versioned by `.layout-version` in the cache
+//! directory. An older layout is served as is, and upgraded in place at
+//! startup with `--migrate-cache`; a newer one, from a newer checkup, is
+//! refused so a rollback can't misread it.
+
+use crate::{AppState, provider::CachedReleases};
+use anyhow::{Context, Result};
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// Layout of the cache this build writes. 1: unversioned. 2: every
+/// cache.json names its provider.
+pub const CURRENT_LAYOUT: u32 = 2;
+
+const LAYOUT_FILE: &str = ".layout-version";
+
+#[derive(Default)]
+pub struct Lifecycle {
+    /// Set once a shutdown signal arrives
+    draining: AtomicBool,
+}
+
+impl Lifecycle {
+    pub fn draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+}
+
+/// Create `dir` and check a file can be written to it, naming the flag
+/// that sets it when that fails, e.g. on a read-only root filesystem.
+pub fn ensure_writable(dir: &Path, flag: &str) -> Result<()> {
+    let hint = || {
+        format!(
+            "{} {:?} is not writable; mount a writable volume there or point {} elsewhere",
+            flag, dir, flag
+        )
+    };
+    fs::create_dir_all(dir).with_context(hint)?;
+    probe(dir).with_context(hint)
+}
+
+/// Write and remove a probe file in `dir`.
+fn probe(dir: &Path) -> std::io::Result<()> {
+    let file = dir.join(format!(".probe-{}", std::process::id()));
+    fs::write(&file, b"")?;
+    fs::remove_file(&file)
+}
+
+/// The layout version of the cache in `dir`: none for an empty cache, 1 for
+/// one written before layouts were versioned.
+fn read_layout(dir: &Path) -> Result<Option<u32>> {
+    let file = dir.join(LAYOUT_FILE);
+    if !file.exists() {
+        return Ok(dir.join("repo").exists().then_some(1));
+    }
+    let content = fs::read_to_string(&file).context("Failed to read .layout-version file")?;
+    let version = content
+        .trim()
+        .parse()
+        .context("Failed to parse .layout-version file")?;
+    Ok(Some(version))
+}
+
+fn write_layout(dir: &Path, version: u32) -> Result<()> {
+    fs::write(dir.join(LAYOUT_FILE), format!("{}\n", version))
+        .context("Failed to write .layout-version file")
+}
+
+/// Check the layout of the cache in `dir` before anything reads it.
+/// Returns whether it needs [`migrate_cache`].
+pub fn check_layout(dir: &Path, migrate: bool) -> Result<bool> {
+    match read_layout(dir)? {
+        None => {
+            write_layout(dir, CURRENT_LAYOUT)?;
+            Ok(false)
+        }
+        Some(version) if version > CURRENT_LAYOUT => anyhow::bail!(
+            "Cache {:?} has layout {}, newer than the {} this checkup reads; upgrade checkup or use another --cache",
+            dir,
+            version,
+            CURRENT_LAYOUT
+        ),
+        Some(version) if version < CURRENT_LAYOUT && !migrate => {
+            eprintln!(
+                "Cache {:?} has layout {} (current: {}); serving it as is, start with --migrate-cache to upgrade it",
+                dir, version, CURRENT_LAYOUT
+            );
+            Ok(false)
+        }
+        Some(version) => Ok(version < CURRENT_LAYOUT),
+    }
+}
+
+/// Upgrade the cache to [`CURRENT_LAYOUT`], one version at a time.
+pub fn migrate_cache(state: &AppState) -> Result<()> {
+    let dir = &state.cache.cache_dir;
+    let mut version = read_layout(dir)?.unwrap_or(CURRENT_LAYOUT);
+    while version < CURRENT_LAYOUT {
+        let migrated = match version {
+            1 => record_providers(state)?,
+            _ => 0,
+        };
+        version += 1;
+        write_layout(dir, version)?;
+        println!(
+            "Migrated cache to layout {} ({} repos updated)",
+            version, migrated
+        );
+    }
+    Ok(())
+}
+
+/// Layout 2: name the provider in cache.json files written before it was
+/// recorded, as inferred from the host.
+fn record_providers(state: &AppState) -> Result<usize> {
+    let mut migrated = 0;
+    for mut cached in state.cache.read_all_json::<CachedReleases>() {
+        if cached.provider.is_some() {
+            continue;
+        }
+        let mut parts = cached.repo_path.splitn(3, '/');
+        let (host, owner, repo) = (
+            parts.next().unwrap_or_default().to_string(),
+            parts.next().unwrap_or_default().to_string(),
+            parts.next().unwrap_or_default().to_string(),
+        );
+        let provider = match host.as_str() {
+            _ if owner.is_empty() => "cgit",
+            "github.com" => "github",
+            "gitlab.com" => "gitlab",
+            _ => "forgejo",
+        };
+        cached.provider = Some(provider.to_string());
+        state.cache.write_json(&host, &owner, &repo, &cached)?;
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+/// `GET /ready`: 200 while the cache and state directories are writable and
+/// the server isn't shutting down, 503 otherwise.
+pub async fn ready_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    if state.lifecycle.draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Shutting down".to_string());
+    }
+    for dir in [&state.cache.cache_dir, &state.cache.state_dir] {
+        if let Err(e) = probe(dir) {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("{:?} is not writable: {}", dir, e),
+            );
+        }
+    }
+    (StatusCode::OK, "READY".to_string())
+}
+
+/// Resolves once the server should stop: on SIGTERM or Ctrl-C, after
+/// failing `/ready` for `grace`.
+pub async fn shutdown_signal(lifecycle: Arc<Lifecycle>, grace: Duration) {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    lifecycle.draining.store(true, Ordering::Relaxed);
+    println!("Shutting down: not ready, stopping in {}s", grace.as_secs());
+    tokio::time::sleep(grace).await;
+}
@@ -0,0 +1,119 @@
+//! Outgoing email, handed to a sendmail-compatible binary (`[email]
+//! sendmail`) so delivery, queueing and retries are left to the host's MTA.
+//! Messages are plain UTF-8 text.
+
+use crate::config::EmailConfig;
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::Utc;
+use std::process::Stdio;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+pub struct Mailer {
+    from: String,
+    sendmail: String,
+}
+
+pub struct Message {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    /// URL of a one-click unsubscribe (RFC 8058)
+    pub unsubscribe: Option<String>,
+}
+
+/// Header values with line breaks removed, so no value can add headers.
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// A subject as RFC 2047 base64 when it isn't plain ASCII.
+fn encode_subject(subject: &str) -> String {
+    let subject = header_value(subject);
+    if subject.is_ascii() {
+        return subject;
+    }
+    format!(
+        "=?utf-8?B?{}?=",
+        base64::engine::general_purpose::STANDARD.encode(subject)
+    )
+}
+
+/// Whether `address` is plausibly a mailbox: "local@domain.tld", without
+/// spaces, brackets or a leading "-" sendmail would take for an option.
+pub fn valid_address(address: &str) -> bool {
+    let Some((local, domain)) = address.rsplit_once('@') else {
+        return false;
+    };
+    address.len() <= 254
+        && !local.is_empty()
+        && !local.starts_with('-')
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "<>,;:\"()[]\\".contains(c))
+}
+
+impl Mailer {
+    pub fn from_config(config: &EmailConfig) -> Option<Self> {
+        let from = config.from.as_deref()?.trim();
+        Some(Self {
+            from: header_value(from),
+            sendmail: config.sendmail.clone(),
+        })
+    }
+
+    /// Render `message` with its headers.
+    fn render(&self, message: &Message) -> String {
+        let mut mail = format!(
+            "From: {}\nTo: {}\nSubject: {}\nDate: {}\nMIME-Version: 1.0\nContent-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: 8bit\nAuto-Submitted: auto-generated\n",
+            self.from,
+            header_value(&message.to),
+            encode_subject(&message.subject),
+            Utc::now().to_rfc2822()
+        );
+        if let Some(url) = &message.unsubscribe {
+            mail.push_str(&format!(
+                "List-Unsubscribe: <{}>\nList-Unsubscribe-Post: List-Unsubscribe=One-Click\n",
+                header_value(url)
+            ));
+        }
+        mail.push('\n');
+        mail.push_str(&message.body);
+        if !mail.ends_with('\n') {
+            mail.push('\n');
+        }
+        mail
+    }
+
+    /// Hand `message` to sendmail; fails when it can't be run or exits
+    /// with an error.
+    pub async fn send(&self, message: &Message) -> Result<()> {
+        if !valid_address(&message.to) {
+            anyhow::bail!("Invalid recipient address: {}", message.to);
+        }
+        let mut child = Command::new(&self.sendmail)
+            .args(["-i", "--", &message.to])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.sendmail))?;
+        let mut stdin = child.stdin.take().context("sendmail has no stdin")?;
+        stdin.write_all(self.render(message).as_bytes()).await?;
+        drop(stdin);
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{} exited with {}: {}",
+                self.sendmail,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,34 +1,138 @@
 use anyhow::Result;
 use axum::{
     Router,
+    extract::State,
     http::StatusCode,
     response::{Html, IntoResponse},
-    routing::get,
+    routing::{get, post, put},
 };
 use clap::Parser;
-use dashmap::{DashMap, DashSet};
+use provider::{
+    autoindex::Autoindex,
+    cgit::Cgit,
+    forgejo::{Codeberg, Forgejo, Gitea},
+    github::GitHub,
+    gitiles::Gitiles,
+    gitlab::GitLab,
+    hgweb::Hgweb,
+    huggingface::HuggingFace,
+    launchpad::Launchpad,
+    pagure::Pagure,
+    snap::Snap,
+    sourcehut::SourceHut,
+};
 use regex::Regex;
-use std::{fs, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
 
+mod accounts;
+mod admin;
+mod archive;
+mod badge;
+mod bench;
+mod branding;
 mod cache;
+mod cache_control;
+mod calendar;
+mod catalog;
+mod changelog;
+mod channels;
+mod checksums;
+mod compare;
+mod config;
+mod dashboard;
+mod debug;
+mod deltas;
+mod display;
+mod drift;
+mod enrich;
+mod error;
+mod federation;
+mod fetches;
+mod filetype;
+mod firehose;
 mod format_html;
+mod freshness;
+mod hidden_assets;
+mod home;
 mod icons;
+mod images;
+mod import;
+mod install;
+mod instance;
+mod ipfs;
+mod latest_names;
+mod license;
+mod lifecycle;
+mod mail;
+mod maintenance;
+mod markdown;
+mod notes;
+mod oidc;
+mod pins;
+mod platform;
 mod provider;
+mod proxy;
+mod publishers;
+mod quota;
+mod refresh;
+mod report;
+mod resolve;
+mod retention;
+mod rewrite;
+mod rolling;
+mod routes;
+mod sbom;
+mod schema;
+mod search;
+mod shedding;
+mod signing;
+mod since;
+mod stats;
+mod subscriptions;
+mod telemetry;
+mod translate;
+mod update_info;
+mod upstream;
+mod versions;
 
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(Parser, Debug)]
 #[command(name = "checkup")]
 #[command(about = "HTTP server for caching and serving repository releases", version, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Cache directory path
-    #[arg(short, long, default_value = "data/cache")]
+    #[arg(short, long, env = "CHECKUP_CACHE_DIR", default_value = "data/cache")]
     cache: PathBuf,
 
+    /// Directory for the watchlist, deployments and subscriptions (defaults to the cache directory)
+    #[arg(long, env = "CHECKUP_STATE_DIR")]
+    state_dir: Option<PathBuf>,
+
+    /// Upgrade a cache written by an older checkup to the current layout at startup
+    #[arg(long, env = "CHECKUP_MIGRATE_CACHE")]
+    migrate_cache: bool,
+
+    /// Seconds /ready fails after SIGTERM before the server stops accepting connections
+    #[arg(long, env = "CHECKUP_SHUTDOWN_GRACE_SECS", default_value = "5")]
+    shutdown_grace_secs: u64,
+
     /// Cache expiration time in hours
     #[arg(short = 'e', long, default_value = "24")]
     cache_hours: i64,
 
+    /// TOML config file (watchlist and refresh settings)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
     /// Server port
     #[arg(short, long, default_value = "3000")]
     port: u16,
@@ -36,6 +140,64 @@ struct Args {
     /// Server host
     #[arg(long, default_value = "127.0.0.1")]
     host: String,
+
+    /// Public base URL used in generated links (defaults to http://{host}:{port})
+    #[arg(long)]
+    public_url: Option<String>,
+
+    /// Number of releases per page on HTML release pages
+    #[arg(long, default_value = "25")]
+    page_size: usize,
+
+    /// ed25519 private key (PKCS#8 PEM) used to sign JSON responses
+    #[arg(long)]
+    signing_key: Option<PathBuf>,
+
+    /// Bearer token for /admin, /debug, /metrics and deployment registration (disabled when unset)
+    #[arg(long, env = "CHECKUP_ADMIN_TOKEN")]
+    admin_token: Option<String>,
+
+    /// GitHub token for API requests, raising the rate limit from 60 to 5000 requests per hour
+    #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+    github_token: Option<String>,
+
+    /// Publish the most requested repos at /popular
+    #[arg(long)]
+    public_popular: bool,
+
+    /// Max uncached repos a single client IP may trigger fetches for per hour
+    #[arg(long)]
+    fetch_quota: Option<u32>,
+
+    /// Client IP exempt from --fetch-quota (repeatable)
+    #[arg(long = "fetch-quota-allow", value_name = "IP")]
+    fetch_quota_allow: Vec<IpAddr>,
+
+    /// Fill in missing asset sizes and content types with HEAD requests when caching
+    #[arg(long)]
+    enrich_assets: bool,
+
+    /// Serve GitHub latest* redirects from /releases/latest while the full release list is not cached
+    #[arg(long)]
+    github_latest_fast_path: bool,
+
+    /// Refuse requests that would start a new fetch (429) while this many background fetches are pending
+    #[arg(long)]
+    max_pending_fetches: Option<usize>,
+
+    /// Refuse requests that would start a new fetch (429) while this many upstream fetches are running
+    #[arg(long)]
+    max_inflight_fetches: Option<usize>,
+
+    /// Take the client IP from X-Forwarded-For (only behind a trusted reverse proxy)
+    #[arg(long)]
+    trust_proxy: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Replay traffic against a running instance and report latencies and cache hits
+    Bench(bench::BenchArgs),
 }
 
 #[derive(Debug, Clone)]
@@ -69,48 +231,351 @@ impl RepoPath {
 pub struct AppState {
     pub client: reqwest::Client,
     pub cache: cache::CacheManager,
-    pub pending_repos: Arc<DashSet<String>>,
-    pub failed_repos: Arc<DashMap<String, String>>,
+    /// Running and failed background fetches, kept across restarts
+    pub fetches: Arc<fetches::Fetches>,
+    pub render: format_html::RenderOptions,
+    pub signer: Option<signing::ResponseSigner>,
+    pub upstream: Arc<upstream::UpstreamTracker>,
+    pub stats: Arc<stats::RequestStats>,
+    pub admin_token: Option<String>,
+    pub fetch_quota: Arc<quota::FetchQuota>,
+    pub cache_policy: cache_control::CachePolicy,
+    pub refresher: Arc<refresh::Refresher>,
+    pub enrich_assets: bool,
+    /// Release notes kept in cache.json and on pages (`[notes]` in the
+    /// config file)
+    pub max_notes_bytes: usize,
+    pub github_latest_fast_path: bool,
+    pub github_token: Option<String>,
+    pub search_index: Arc<search::SearchIndex>,
+    /// Releases observed across the instance, served at `/api/v2/firehose`
+    pub firehose: Arc<firehose::Firehose>,
+    /// Forgejo hosts listed at `/forgejo/{host}`
+    pub forgejo_catalog_hosts: Vec<String>,
+    pub tag_rules: Arc<versions::TagRuleSet>,
+    pub channel_rules: Arc<channels::ChannelRules>,
+    /// Repos with `/delta/...` patches and how they are made
+    pub deltas: Arc<deltas::Deltas>,
+    /// Node proxied assets are pinned to (`[ipfs]` in the config file)
+    pub ipfs: Option<Arc<ipfs::Ipfs>>,
+    /// Releases whose pins and patches are kept (`keep_releases`)
+    pub retention: Arc<retention::Retention>,
+    /// Release emails to subscribers of repo pages (`[email]` in the config
+    /// file)
+    pub subscriptions: Option<Arc<subscriptions::Subscriptions>>,
+    /// Users with watchlists of their own (`[accounts]` in the config file)
+    pub accounts: Option<Arc<accounts::Accounts>>,
+    /// Instances whose watched repos are taken into the cache
+    /// (`[federation]` in the config file)
+    pub federation: Option<Arc<federation::Federation>>,
+    pub image_templates: Arc<images::ImageTemplates>,
+    /// Expected signing keys of repos (`signing_keys` in `[[repo]]`)
+    pub publishers: Arc<publishers::TrustedPublishers>,
+    pub url_rewriter: Arc<rewrite::UrlRewriter>,
+    pub load_shedder: Arc<shedding::LoadShedder>,
+    /// Switched at `/admin/maintenance`
+    pub maintenance: Arc<maintenance::Maintenance>,
+    /// Whether the server is shutting down, for `/ready`
+    pub lifecycle: Arc<lifecycle::Lifecycle>,
+    pub deployments: Arc<drift::Deployments>,
+    /// Repos archived at `/admin/archive/`
+    pub archive: Arc<archive::Archive>,
+    /// Request shaping per provider (`[providers.*]` in the config file)
+    pub providers: config::ProvidersConfig,
+    /// Backend for `?lang=` (`[translate]` in the config file)
+    pub translator: Option<Arc<translate::Translator>>,
+    /// API credentials by host (`[credentials]` in the config file)
+    pub credentials: HashMap<String, config::HostCredentials>,
+    /// Usage statistics shown at `/about` and reported where opted in
+    /// (`[telemetry]` in the config file)
+    pub telemetry: Arc<telemetry::Telemetry>,
+    /// Effective, non-secret configuration served at `/api/v2/config`
+    pub instance: Arc<instance::InstanceConfig>,
+    /// Name, footer and announcement of HTML pages (`[branding]` in the
+    /// config file)
+    pub branding: Arc<branding::Branding>,
 }
 
 async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
 
+async fn popular_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Html(format_html::format_popular_html(&state.stats.top(50)))
+}
+
+async fn status_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Html(format_html::format_status_html(&state.upstream.snapshot()))
+}
+
+async fn status_json(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let body = serde_json::to_string_pretty(&state.upstream.snapshot()).unwrap_or_default();
+    signing::json_response(&state, StatusCode::OK, body)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    if let Some(Command::Bench(bench)) = args.command {
+        return bench::run(bench).await;
+    }
+
+    let state_dir = args.state_dir.clone().unwrap_or_else(|| args.cache.clone());
+    lifecycle::ensure_writable(&args.cache, "--cache")?;
+    lifecycle::ensure_writable(&state_dir, "--state-dir")?;
+    let migrate = lifecycle::check_layout(&args.cache, args.migrate_cache)?;
 
-    fs::create_dir_all(&args.cache)?;
+    let public_url = args
+        .public_url
+        .clone()
+        .unwrap_or_else(|| format!("http://{}:{}", args.host, args.port))
+        .trim_end_matches('/')
+        .to_string();
+
+    let config = match &args.config {
+        Some(path) => config::Config::load(path)?,
+        None => config::Config::default(),
+    };
+    let cache = cache::CacheManager::new(args.cache.clone(), state_dir, args.cache_hours);
+    let refresher = Arc::new(refresh::Refresher::from_config(
+        &config,
+        args.cache_hours,
+        cache.clone(),
+    )?);
+
+    let signer = args
+        .signing_key
+        .as_deref()
+        .map(signing::ResponseSigner::from_pem_file)
+        .transpose()?;
 
     let state = Arc::new(AppState {
         client: reqwest::Client::builder()
             .user_agent(format!("checkup/{}", VERSION))
             .build()?,
-        cache: cache::CacheManager::new(args.cache.clone(), args.cache_hours),
-        pending_repos: Arc::new(DashSet::new()),
-        failed_repos: Arc::new(DashMap::new()),
+        deployments: Arc::new(drift::Deployments::load(&config.repo, cache.clone())?),
+        archive: Arc::new(archive::Archive::load(cache.clone())?),
+        fetches: Arc::new(fetches::Fetches::load(cache.clone())?),
+        subscriptions: subscriptions::Subscriptions::load(
+            &config.email,
+            cache.clone(),
+            &public_url,
+        )?
+        .map(Arc::new),
+        accounts: accounts::Accounts::load(&config.accounts, cache.clone(), &public_url)?
+            .map(Arc::new),
+        federation: federation::Federation::from_config(&config.federation).map(Arc::new),
+        providers: config.providers.clone(),
+        credentials: config.credentials.clone(),
+        instance: Arc::new(instance::InstanceConfig::new(&args, &config, &public_url)),
+        telemetry: Arc::new(telemetry::Telemetry::from_config(&config.telemetry)),
+        branding: Arc::new(branding::Branding::from_config(&config.branding)?),
+        translator: translate::Translator::from_config(&config.translate)?.map(Arc::new),
+        cache,
+        render: format_html::RenderOptions {
+            public_url,
+            page_size: args.page_size.max(1),
+            freshness: freshness::FreshnessThresholds::from_config(&config.freshness)?,
+            hidden_assets: hidden_assets::HiddenAssets::from_config(
+                &config.assets.hide,
+                &config.repo,
+            )?,
+            latest_names: latest_names::LatestNames::from_config(&config.latest)?,
+            displays: display::RepoDisplays::from_config(&config.repo)?,
+            ipfs_gateway: config.ipfs.gateway.trim_end_matches('/').to_string(),
+            email_subscriptions: config.email.from.is_some(),
+        },
+        signer,
+        upstream: Arc::new(upstream::UpstreamTracker::new()),
+        stats: Arc::new(stats::RequestStats::new()),
+        admin_token: args.admin_token.clone(),
+        fetch_quota: Arc::new(quota::FetchQuota::new(
+            args.fetch_quota,
+            args.fetch_quota_allow.clone(),
+            args.trust_proxy,
+        )),
+        cache_policy: cache_control::CachePolicy::from_config(&config.cache_control)?,
+        refresher: refresher.clone(),
+        enrich_assets: args.enrich_assets,
+        max_notes_bytes: config.notes.max_bytes,
+        github_latest_fast_path: args.github_latest_fast_path,
+        github_token: args.github_token.clone().filter(|t| !t.is_empty()),
+        search_index: Arc::new(search::SearchIndex::new()),
+        firehose: Arc::new(firehose::Firehose::new()),
+        forgejo_catalog_hosts: config.catalog.forgejo_hosts.clone(),
+        tag_rules: Arc::new(versions::TagRuleSet::from_config(&config.repo)?),
+        channel_rules: Arc::new(channels::ChannelRules::from_config(&config.repo)?),
+        deltas: Arc::new(deltas::Deltas::from_config(&config)?),
+        ipfs: ipfs::Ipfs::from_config(&config.ipfs).map(Arc::new),
+        retention: Arc::new(retention::Retention::from_config(
+            config.assets.keep_releases,
+            &config.repo,
+        )?),
+        image_templates: Arc::new(images::ImageTemplates::from_config(&config.repo)?),
+        publishers: Arc::new(publishers::TrustedPublishers::from_config(&config.repo)?),
+        url_rewriter: Arc::new(rewrite::UrlRewriter::from_config(&config.rewrite)?),
+        maintenance: Arc::new(maintenance::Maintenance::default()),
+        lifecycle: Arc::new(lifecycle::Lifecycle::default()),
+        load_shedder: Arc::new(shedding::LoadShedder::new(
+            args.max_pending_fetches,
+            args.max_inflight_fetches,
+        )),
     });
 
-    let app = Router::new()
-        .route("/github/*repo_path", get(provider::github::handler))
-        .route("/gitlab/*repo_path", get(provider::gitlab::handler))
-        .route("/forgejo/*forgejo_path", get(provider::forgejo::handler))
-        .route("/cgit/*cgit_path", get(provider::cgit::handler))
+    if migrate {
+        lifecycle::migrate_cache(&state)?;
+    }
+    fetches::resume(&state);
+    refresher.spawn(state.clone());
+    if let Some(federation) = &state.federation {
+        federation.clone().spawn(state.clone());
+    }
+    state.telemetry.clone().spawn(state.client.clone());
+
+    let mut app = Router::new()
+        .route("/github/*repo_path", get(provider::handler::<GitHub>))
+        .route("/gitlab/*repo_path", get(provider::handler::<GitLab>))
+        .route("/forgejo/*forgejo_path", get(provider::handler::<Forgejo>))
+        .route("/gitea/*gitea_path", get(provider::handler::<Gitea>))
+        .route("/codeberg/*repo_path", get(provider::handler::<Codeberg>))
+        .route("/sourcehut/*repo_path", get(provider::handler::<SourceHut>))
+        .route("/launchpad/*project", get(provider::handler::<Launchpad>))
+        .route("/snap/*name", get(provider::handler::<Snap>))
+        .route(
+            "/huggingface/*repo_path",
+            get(provider::handler::<HuggingFace>),
+        )
+        .route("/pagure/*pagure_path", get(provider::handler::<Pagure>))
+        .route("/gitiles/*gitiles_path", get(provider::handler::<Gitiles>))
+        .route("/hg/*hg_path", get(provider::handler::<Hgweb>))
+        .route("/cgit/*cgit_path", get(provider::handler::<Cgit>))
+        .route(
+            "/autoindex/*autoindex_path",
+            get(provider::handler::<Autoindex>),
+        )
         .route("/health", get(health_check))
+        .route("/ready", get(lifecycle::ready_handler))
+        .route("/status", get(status_page))
+        .route("/status/+json", get(status_json))
+        .route("/about", get(telemetry::about_page))
+        .route("/schema.json", get(schema::schema_handler))
+        .route("/api/v2/config", get(instance::config_json))
+        .route("/api/v2/import", post(import::import_handler))
+        .route("/api/v2/resolve", get(resolve::resolve_handler))
+        .route("/api/v2/firehose", get(firehose::firehose_handler))
+        .route("/admin/stats", get(admin::stats_handler))
+        .route(
+            "/admin/maintenance",
+            get(maintenance::status_handler)
+                .put(maintenance::enable_handler)
+                .delete(maintenance::disable_handler),
+        )
+        .route("/admin/accounts", get(accounts::list_handler))
+        .route(
+            "/admin/accounts/:username",
+            put(accounts::put_handler).delete(accounts::delete_handler),
+        )
+        .route("/admin/archive", get(archive::list_handler))
+        .route(
+            "/admin/archive/*route",
+            put(archive::archive_handler).delete(archive::unarchive_handler),
+        )
+        .route("/metrics", get(admin::metrics_handler))
+        .route("/debug/rename", get(debug::rename_handler))
+        .route("/debug/*path", get(debug::debug_handler))
+        .route("/proxy/*path", get(proxy::proxy_asset))
+        .route("/delta/*path", get(deltas::delta_handler))
+        .route("/search", get(search::search_page))
+        .route("/search/+json", get(search::search_json))
+        .route("/compare-mirrors", get(compare::compare_mirrors))
+        .route("/dashboard", get(dashboard::dashboard_page))
+        .route("/dashboard/watch", post(accounts::watch_handler))
+        .route("/dashboard/unwatch", post(accounts::unwatch_handler))
         .route(
-            "/",
-            get(|| async { Html(include_str!("index.html").replace("{VERSION}", VERSION)) }),
+            "/login",
+            get(accounts::login_page).post(accounts::login_handler),
         )
-        .with_state(state);
+        .route("/login/oidc/:provider", get(oidc::start_handler))
+        .route(
+            "/login/oidc/:provider/callback",
+            get(oidc::callback_handler),
+        )
+        .route("/logout", post(accounts::logout_handler))
+        .route(
+            "/signup",
+            get(accounts::signup_page).post(accounts::signup_handler),
+        )
+        .route("/feed/:token/calendar.ics", get(accounts::feed_ics))
+        .route("/reports/weekly", get(report::weekly_html))
+        .route("/reports/weekly.md", get(report::weekly_markdown))
+        .route("/calendar", get(calendar::calendar_page))
+        .route("/calendar.ics", get(calendar::calendar_ics))
+        .route("/subscribe", post(subscriptions::subscribe_handler))
+        .route(
+            "/subscribe/confirm",
+            get(subscriptions::confirm_page).post(subscriptions::confirm_handler),
+        )
+        .route(
+            "/unsubscribe",
+            get(subscriptions::unsubscribe_page).post(subscriptions::unsubscribe_handler),
+        )
+        .route("/federation/+json", get(federation::feed_json))
+        .route("/deployments/+json", get(drift::deployments_json))
+        .route(
+            "/deployments/*route",
+            put(drift::register_deployment).delete(drift::remove_deployment),
+        )
+        .route("/manifest.webmanifest", get(dashboard::manifest))
+        .route("/sw.js", get(dashboard::service_worker))
+        .route("/icon.svg", get(dashboard::icon))
+        .route("/icon-maskable.svg", get(dashboard::maskable_icon))
+        .route(
+            "/.well-known/checkup-signing-key",
+            get(signing::public_key_handler),
+        )
+        .route("/", get(home::home_page))
+        .route("/go", get(home::go))
+        .fallback(error::not_found)
+        .with_state(state.clone());
+
+    if args.public_popular {
+        app = app.route("/popular", get(popular_page).with_state(state.clone()));
+    }
+    let app = app
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            branding::decorate,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            maintenance::banner,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            archive::banner,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            telemetry::count,
+        ))
+        .layer(axum::middleware::from_fn(shedding::retry_after_header));
 
     let addr = format!("{}:{}", args.host, args.port);
     println!("Server listening on http://{}", addr);
     println!("Cache directory: {:?}", args.cache);
+    println!("State directory: {:?}", state.cache.state_dir);
     println!("Cache expiration: {} hours", args.cache_hours);
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(lifecycle::shutdown_signal(
+        state.lifecycle.clone(),
+        std::time::Duration::from_secs(args.shutdown_grace_secs),
+    ))
+    .await?;
 
     Ok(())
 }
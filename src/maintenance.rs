@@ -0,0 +1,146 @@
+//! Maintenance mode, switched at `/admin/maintenance`, for upstream incidents
+//! and cache migrations: cached repos are served however old they are, with
+//! a banner on every page; background refreshes and federation polls pause;
+//! downloads through `/proxy/` and new `/delta/` patches are refused, as are
+//! requests that would fetch from upstream. It lasts until switched off or
+//! the server restarts.
+
+use crate::{
+    AppState,
+    admin::require_admin,
+    error::{ApiError, AppError, ErrorCode},
+    format_html::{escape_html, insert_banner},
+    signing::json_response,
+};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStatus {
+    pub since: DateTime<Utc>,
+    /// Shown in the banner and in refusals
+    pub message: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Maintenance {
+    status: RwLock<Option<MaintenanceStatus>>,
+}
+
+impl Maintenance {
+    pub fn status(&self) -> Option<MaintenanceStatus> {
+        self.status.read().unwrap().clone()
+    }
+
+    pub fn active(&self) -> bool {
+        self.status.read().unwrap().is_some()
+    }
+
+    /// Refuse work that maintenance mode pauses, with 503.
+    pub fn check(&self, what: &str) -> Result<(), AppError> {
+        let Some(status) = self.status() else {
+            return Ok(());
+        };
+        let mut message = format!("The server is in maintenance mode; {} is paused.", what);
+        if let Some(reason) = &status.message {
+            message.push(' ');
+            message.push_str(reason);
+        }
+        Err(AppError::new(StatusCode::SERVICE_UNAVAILABLE, message)
+            .with_code(ErrorCode::Maintenance))
+    }
+}
+
+#[derive(Serialize)]
+struct StatusBody {
+    enabled: bool,
+    #[serde(flatten)]
+    status: Option<MaintenanceStatus>,
+}
+
+fn status_response(state: &AppState) -> Result<Response, ApiError> {
+    let status = state.maintenance.status();
+    let body = StatusBody {
+        enabled: status.is_some(),
+        status,
+    };
+    let body = serde_json::to_string_pretty(&body).map_err(AppError::internal)?;
+    Ok(json_response(state, StatusCode::OK, body))
+}
+
+/// `GET /admin/maintenance` (admin only)
+pub async fn status_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    status_response(&state)
+}
+
+/// `PUT /admin/maintenance` (admin only), with an optional message for
+/// visitors as the request body.
+pub async fn enable_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    message: String,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    let message = Some(message.trim().to_string()).filter(|m| !m.is_empty());
+    {
+        let mut status = state.maintenance.status.write().unwrap();
+        let since = status.as_ref().map_or_else(Utc::now, |s| s.since);
+        *status = Some(MaintenanceStatus { since, message });
+    }
+    state.cache.set_frozen(true);
+    status_response(&state)
+}
+
+/// `DELETE /admin/maintenance` (admin only)
+pub async fn disable_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+    *state.maintenance.status.write().unwrap() = None;
+    state.cache.set_frozen(false);
+    status_response(&state)
+}
+
+/// Put the maintenance banner at the top of HTML pages.
+pub async fn banner(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let Some(status) = state.maintenance.status() else {
+        return response;
+    };
+    let html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if !html {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let banner = format!(
+        r#"<div style="margin: 0 0 20px 0; padding: 12px 16px; background: #fff8e1; border: 1px solid #f0ad4e; border-radius: 6px; color: #6d4c00;"><strong>Maintenance:</strong> releases are served from the cache and may be out of date.{}</div>"#,
+        status
+            .message
+            .map(|m| format!(" {}", escape_html(&m)))
+            .unwrap_or_default()
+    );
+    let page = insert_banner(&String::from_utf8_lossy(&bytes), &banner);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(page))
+}
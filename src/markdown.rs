@@ -0,0 +1,155 @@
+//! Markdown rendering for release notes, with server-side syntax highlighting
+//! of fenced code blocks.
+
+use crate::format_html::escape_html;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd, html};
+use std::sync::LazyLock;
+use syntect::{highlighting::ThemeSet, html::highlighted_html_for_string, parsing::SyntaxSet};
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// Highlight a code block, falling back to plain escaped text for unknown languages.
+fn highlight_code(code: &str, lang: &str) -> String {
+    let syntax = SYNTAXES
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+
+    highlighted_html_for_string(code, &SYNTAXES, syntax, &THEMES.themes["InspiredGitHub"])
+        .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", escape_html(code)))
+}
+
+/// URL schemes links and images may use; relative URLs are allowed too
+const URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// `url` if it is relative or uses an allowed scheme, `#` otherwise, so
+/// notes can't link to `javascript:` or `data:` URLs.
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    // Browsers ignore whitespace and control characters in a scheme
+    let cleaned: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect();
+    let scheme = cleaned
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+    match scheme {
+        Some(scheme) if !URL_SCHEMES.iter().any(|s| s.eq_ignore_ascii_case(scheme)) => {
+            CowStr::Borrowed("#")
+        }
+        _ => url,
+    }
+}
+
+/// Render release notes Markdown to HTML. Raw HTML in the source is escaped,
+/// and links and images with URLs of other schemes than [`URL_SCHEMES`] lead
+/// nowhere.
+pub fn render_markdown(text: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut events = Vec::new();
+    let mut code_block: Option<(String, String)> = None;
+
+    for event in Parser::new_ext(text, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code_block = Some((lang, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, code)) = code_block.take() {
+                    events.push(Event::Html(highlight_code(&code, &lang).into()));
+                }
+            }
+            Event::Text(t) if code_block.is_some() => {
+                if let Some((_, code)) = code_block.as_mut() {
+                    code.push_str(&t);
+                }
+            }
+            Event::Html(raw) | Event::InlineHtml(raw) => events.push(Event::Text(raw)),
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => events.push(Event::Start(Tag::Link {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            })),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => events.push(Event::Start(Tag::Image {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            })),
+            other => events.push(other),
+        }
+    }
+
+    let mut out = String::new();
+    html::push_html(&mut out, events.into_iter());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_keep_allowed_urls() {
+        for url in [
+            "https://example.com/a",
+            "http://example.com",
+            "mailto:dev@example.com",
+            "/owner/repo",
+            "docs/notes.md#usage",
+            "#changes",
+        ] {
+            let html = render_markdown(&format!("[x]({})", url));
+            assert!(html.contains(&format!(r#"href="{}""#, url)), "{}", html);
+        }
+    }
+
+    #[test]
+    fn links_drop_other_schemes() {
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "&#106;avascript:alert(1)",
+            "data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==",
+            "vbscript:msgbox(1)",
+            "file:///etc/passwd",
+        ] {
+            let html = render_markdown(&format!("[x]({}) ![y]({})", url, url));
+            assert!(html.contains(r##"<a href="#">x</a>"##), "{}: {}", url, html);
+            assert!(
+                html.contains(r##"<img src="#" alt="y" />"##),
+                "{}: {}",
+                url,
+                html
+            );
+        }
+    }
+
+    #[test]
+    fn reference_links_drop_other_schemes() {
+        let html = render_markdown("[x][evil]\n\n[evil]: javascript:alert(1)");
+        assert!(html.contains(r##"<a href="#">x</a>"##), "{}", html);
+    }
+}
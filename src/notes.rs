@@ -0,0 +1,127 @@
+//! Release notes longer than `[notes] max_bytes` are cut short in cache.json
+//! and on release pages, ending in a marker that links to the full notes.
+//! The full notes are kept per tag in notes.json and served on demand by
+//! `/{provider}/{...}/{tag}/notes` (rendered) and `/{tag}/notes.md`, so a
+//! project attaching its whole changelog to every release doesn't weigh down
+//! the cache and every page load.
+
+use crate::{
+    AppState, RepoPath, cache_control::RouteKind, error::AppError, format_html, provider::Release,
+};
+use axum::{
+    http::{StatusCode, header},
+    response::{Html, IntoResponse, Response},
+};
+use std::collections::BTreeMap;
+
+/// Notes kept whole by default, in bytes
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024;
+
+/// Where to cut `body` to keep at most `max_bytes`: the last line break in
+/// the second half of the allowance, or the last character that fits.
+fn cut_point(body: &str, max_bytes: usize) -> usize {
+    let mut end = max_bytes.min(body.len());
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    match body[..end].rfind('\n') {
+        Some(line_end) if line_end >= end / 2 => line_end,
+        _ => end,
+    }
+}
+
+fn kilobytes(bytes: usize) -> usize {
+    bytes.div_ceil(1024)
+}
+
+/// Cut the notes of releases longer than `max_bytes` (0 keeps all whole) and
+/// mark them as truncated, linking to `{route_url}/{tag}/notes`. Returns the
+/// full notes of the truncated releases by tag.
+pub fn truncate_notes(
+    releases: &mut [Release],
+    max_bytes: usize,
+    route_url: &str,
+) -> BTreeMap<String, String> {
+    let mut full = BTreeMap::new();
+    for release in releases {
+        let Some(body) = release
+            .body
+            .as_mut()
+            .filter(|b| max_bytes > 0 && b.len() > max_bytes)
+        else {
+            continue;
+        };
+        let end = cut_point(body, max_bytes);
+        let mut kept = body[..end].trim_end().to_string();
+        // Close a code block cut in the middle, or it swallows the marker
+        if kept
+            .lines()
+            .filter(|l| l.trim_start().starts_with("```"))
+            .count()
+            % 2
+            == 1
+        {
+            kept.push_str("\n```");
+        }
+        kept.push_str(&format!(
+            "\n\n---\n\n*Release notes truncated to {} of {} KB. [Full release notes](<{}/{}/notes>)*",
+            kilobytes(end),
+            kilobytes(body.len()),
+            route_url,
+            release.tag_name
+        ));
+        full.insert(release.tag_name.clone(), std::mem::replace(body, kept));
+        release.body_truncated = true;
+    }
+    full
+}
+
+/// Serve `/{tag}/notes`, or with `markdown` `/{tag}/notes.md`: the release's
+/// full notes, from notes.json when they were truncated.
+pub fn notes_response(
+    state: &AppState,
+    repo: &RepoPath,
+    route_prefix: &str,
+    releases: &[Release],
+    tag: &str,
+    markdown: bool,
+) -> Result<Response, AppError> {
+    let release = releases
+        .iter()
+        .find(|r| r.tag_name == tag)
+        .ok_or(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("No release tagged '{}' found", tag),
+        ))?;
+    let body = if release.body_truncated {
+        state
+            .cache
+            .read_notes(&repo.host, &repo.owner, &repo.repo)
+            .map_err(AppError::internal)?
+            .remove(tag)
+            .or_else(|| release.body.clone())
+    } else {
+        release.body.clone()
+    };
+    let body = body.filter(|b| !b.trim().is_empty()).ok_or(AppError::new(
+        StatusCode::NOT_FOUND,
+        format!("Release '{}' has no release notes", tag),
+    ))?;
+
+    let response = if markdown {
+        (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            body,
+        )
+            .into_response()
+    } else {
+        let html = format_html::format_notes_html(&repo.cache_key(), route_prefix, release, &body);
+        Html(html).into_response()
+    };
+    let kind = if markdown {
+        RouteKind::Json
+    } else {
+        RouteKind::Html
+    };
+    Ok(state.cache_policy.apply(kind, response))
+}
@@ -0,0 +1,563 @@
+//! Sign-in through OpenID Connect providers and GitHub (`[[accounts.oidc]]`
+//! in the config file), so deployments can reuse the single sign-on they
+//! have instead of managing passwords and admin tokens.
+//!
+//! `/login/oidc/{name}` sends the browser to the provider with the
+//! authorization code flow and PKCE. Its callback exchanges the code for an
+//! access token and reads the user's subject from the userinfo endpoint
+//! (GitHub's `/user`), which is asked directly over TLS, so the ID token
+//! isn't needed. The login, "{name}:{subject}", then:
+//!
+//! - signs into the account it is linked to,
+//! - is linked to the account of a user who was signed in already,
+//! - or, with `signup`, gets a new account without a password.
+//!
+//! Otherwise the page names the login, so the operator can link it with
+//! `PUT /admin/accounts/{name}`. The `state` parameter is kept in a cookie
+//! too, so a callback only completes in the browser that started it.
+
+use crate::{
+    AppState,
+    accounts::{self, disabled, page, redirect_with_cookie},
+    config::{OidcConfig, OidcProvider},
+    error::AppError,
+};
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use openssl::rand::rand_bytes;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Cookie holding the `state` of a sign-in in progress
+const STATE_COOKIE: &str = "checkup_oidc_state";
+/// How long a user has to finish signing in at the provider
+const PENDING_MINUTES: i64 = 10;
+/// Sign-ins in progress kept at most
+const MAX_PENDING: usize = 10_000;
+/// Per request to a provider
+const TIMEOUT_SECS: u64 = 20;
+
+const GITLAB_ISSUER: &str = "https://gitlab.com";
+const GITHUB_AUTHORIZE: &str = "https://github.com/login/oauth/authorize";
+const GITHUB_TOKEN: &str = "https://github.com/login/oauth/access_token";
+const GITHUB_USER: &str = "https://api.github.com/user";
+
+/// The parts of a provider's `/.well-known/openid-configuration` used.
+#[derive(Debug, Clone, Deserialize)]
+struct Endpoints {
+    #[serde(default)]
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+    /// `client_secret_basic` when empty, as the spec has it
+    #[serde(default)]
+    token_endpoint_auth_methods_supported: Vec<String>,
+}
+
+struct Provider {
+    config: OidcConfig,
+    /// Discovered on the first sign-in; set from the start for GitHub
+    endpoints: OnceCell<Endpoints>,
+}
+
+/// A sign-in sent to a provider, by its `state`.
+struct Pending {
+    provider: String,
+    /// PKCE code verifier
+    verifier: String,
+    /// The user who was signed in when it started, to link the login to
+    link_to: Option<String>,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// The configured providers and the sign-ins in progress.
+pub struct Providers {
+    providers: Vec<Provider>,
+    pending: DashMap<String, Pending>,
+    public_url: String,
+}
+
+impl Providers {
+    /// Fails on a provider without a usable name, or a generic one without
+    /// an issuer.
+    pub fn new(configs: &[OidcConfig], public_url: &str) -> Result<Self> {
+        let mut providers: Vec<Provider> = Vec::new();
+        for config in configs {
+            let valid_name = !config.name.is_empty()
+                && config
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !valid_name {
+                anyhow::bail!(
+                    "[[accounts.oidc]] name '{}' must be lowercase letters, digits and '-'",
+                    config.name
+                );
+            }
+            if providers.iter().any(|p| p.config.name == config.name) {
+                anyhow::bail!("[[accounts.oidc]] name '{}' is used twice", config.name);
+            }
+            let endpoints = match config.provider {
+                OidcProvider::Github => {
+                    if config.issuer.is_some() {
+                        anyhow::bail!(
+                            "[[accounts.oidc]] '{}': GitHub takes no issuer",
+                            config.name
+                        );
+                    }
+                    OnceCell::new_with(Some(Endpoints {
+                        issuer: String::new(),
+                        authorization_endpoint: GITHUB_AUTHORIZE.to_string(),
+                        token_endpoint: GITHUB_TOKEN.to_string(),
+                        userinfo_endpoint: GITHUB_USER.to_string(),
+                        token_endpoint_auth_methods_supported: vec![
+                            "client_secret_post".to_string(),
+                        ],
+                    }))
+                }
+                OidcProvider::Generic if config.issuer.is_none() => {
+                    anyhow::bail!("[[accounts.oidc]] '{}' needs an issuer", config.name);
+                }
+                OidcProvider::Generic | OidcProvider::Gitlab => OnceCell::new(),
+            };
+            providers.push(Provider {
+                config: config.clone(),
+                endpoints,
+            });
+        }
+        Ok(Self {
+            providers,
+            pending: DashMap::new(),
+            public_url: public_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Name and button label of each provider, for `/login`.
+    pub fn buttons(&self) -> Vec<(String, String)> {
+        self.providers
+            .iter()
+            .map(|p| (p.config.name.clone(), p.label()))
+            .collect()
+    }
+
+    fn get(&self, name: &str) -> Option<&Provider> {
+        self.providers.iter().find(|p| p.config.name == name)
+    }
+
+    /// Fails unless `identity` is "{provider}:{subject}" for a configured
+    /// provider.
+    pub fn check_identity(&self, identity: &str) -> Result<()> {
+        match identity.split_once(':') {
+            Some((name, subject)) if self.get(name).is_some() && !subject.is_empty() => Ok(()),
+            _ => anyhow::bail!(
+                "Invalid login '{}': use \"{{provider}}:{{subject}}\" with a provider from [[accounts.oidc]]",
+                identity
+            ),
+        }
+    }
+
+    fn redirect_uri(&self, name: &str) -> String {
+        format!("{}/login/oidc/{}/callback", self.public_url, name)
+    }
+}
+
+impl Provider {
+    fn label(&self) -> String {
+        match (&self.config.label, self.config.provider) {
+            (Some(label), _) => label.clone(),
+            (None, OidcProvider::Github) => "GitHub".to_string(),
+            (None, OidcProvider::Gitlab) => "GitLab".to_string(),
+            (None, OidcProvider::Generic) => self.config.name.clone(),
+        }
+    }
+
+    fn issuer(&self) -> &str {
+        self.config
+            .issuer
+            .as_deref()
+            .unwrap_or(GITLAB_ISSUER)
+            .trim_end_matches('/')
+    }
+
+    fn scope(&self) -> &'static str {
+        match self.config.provider {
+            OidcProvider::Github => "read:user",
+            OidcProvider::Generic | OidcProvider::Gitlab => "openid profile email",
+        }
+    }
+
+    async fn endpoints(&self, client: &reqwest::Client) -> Result<&Endpoints> {
+        self.endpoints
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.issuer());
+                let endpoints: Endpoints = client
+                    .get(&url)
+                    .timeout(std::time::Duration::from_secs(TIMEOUT_SECS))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await
+                    .with_context(|| format!("Reading {}", url))?;
+                if endpoints.issuer.trim_end_matches('/') != self.issuer() {
+                    anyhow::bail!(
+                        "{} names issuer '{}', not '{}'",
+                        url,
+                        endpoints.issuer,
+                        self.issuer()
+                    );
+                }
+                Ok(endpoints)
+            })
+            .await
+    }
+
+    /// Redeem an authorization code, returning the user's subject and what
+    /// the provider calls them.
+    async fn login(
+        &self,
+        client: &reqwest::Client,
+        code: &str,
+        verifier: &str,
+        redirect_uri: &str,
+    ) -> Result<(String, String)> {
+        let endpoints = self.endpoints(client).await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", verifier),
+            ("client_id", &self.config.client_id),
+        ];
+        let methods = &endpoints.token_endpoint_auth_methods_supported;
+        let basic = methods.is_empty() || methods.iter().any(|m| m == "client_secret_basic");
+        let mut request = client
+            .post(&endpoints.token_endpoint)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .timeout(std::time::Duration::from_secs(TIMEOUT_SECS));
+        if basic {
+            request = request.basic_auth(&self.config.client_id, Some(&self.config.client_secret));
+        } else {
+            form.push(("client_secret", &self.config.client_secret));
+        }
+        // GitHub answers errors with 200 OK, so the body is read either way
+        let token: TokenResponse = request.form(&form).send().await?.json().await?;
+        let Some(access_token) = token.access_token else {
+            anyhow::bail!(
+                "Token request failed: {}",
+                token
+                    .error_description
+                    .or(token.error)
+                    .unwrap_or_else(|| "no access token".to_string())
+            );
+        };
+        let user: serde_json::Value = client
+            .get(&endpoints.userinfo_endpoint)
+            .bearer_auth(&access_token)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .timeout(std::time::Duration::from_secs(TIMEOUT_SECS))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let text = |key: &str| user.get(key).and_then(|v| v.as_str()).map(String::from);
+        let (subject, hint) = match self.config.provider {
+            // Logins can be renamed and then taken by others; ids stay
+            OidcProvider::Github => (
+                user.get("id")
+                    .and_then(|v| v.as_u64())
+                    .map(|id| id.to_string()),
+                text("login"),
+            ),
+            OidcProvider::Generic | OidcProvider::Gitlab => (
+                text("sub"),
+                text("preferred_username")
+                    .or_else(|| text("nickname"))
+                    .or_else(|| text("email"))
+                    .or_else(|| text("name")),
+            ),
+        };
+        let subject = subject.context("The userinfo response has no subject")?;
+        Ok((subject.clone(), hint.unwrap_or(subject)))
+    }
+}
+
+/// 32 random bytes, URL-safe.
+fn random() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    rand_bytes(&mut bytes)?;
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn state_cookie(value: &str, max_age: i64, secure: bool) -> Option<HeaderValue> {
+    let secure = if secure { "; Secure" } else { "" };
+    HeaderValue::from_str(&format!(
+        "{}={}; Path=/login/oidc; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        STATE_COOKIE, value, max_age, secure
+    ))
+    .ok()
+}
+
+fn cookie_state(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|c| c.trim().strip_prefix(STATE_COOKIE)?.strip_prefix('='))
+}
+
+fn unknown_provider() -> Response {
+    page(
+        StatusCode::NOT_FOUND,
+        "Not Found",
+        "No such sign-in provider.",
+    )
+}
+
+/// `GET /login/oidc/{name}`: send the browser to the provider. Signed-in
+/// users link the login to their account.
+pub async fn start_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(accounts) = &state.accounts else {
+        return disabled();
+    };
+    let Some(provider) = accounts.oidc.get(&name) else {
+        return unknown_provider();
+    };
+    let endpoints = match provider.endpoints(&state.client).await {
+        Ok(endpoints) => endpoints,
+        Err(e) => {
+            eprintln!("Sign-in with {} failed: {:#}", name, e);
+            return page(
+                StatusCode::BAD_GATEWAY,
+                "Sign-in Unavailable",
+                &format!("{} could not be reached.", provider.label()),
+            );
+        }
+    };
+    let pending = &accounts.oidc.pending;
+    let now = Utc::now();
+    pending.retain(|_, p| p.expires_at > now);
+    if pending.len() >= MAX_PENDING {
+        return page(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Try Again Later",
+            "Too many sign-ins are in progress.",
+        );
+    }
+    let (Ok(state_param), Ok(verifier)) = (random(), random()) else {
+        return AppError::internal("No random bytes").into_response();
+    };
+    let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(Sha256::digest(verifier.as_bytes()));
+    let redirect_uri = accounts.oidc.redirect_uri(&name);
+    let url = match reqwest::Url::parse_with_params(
+        &endpoints.authorization_endpoint,
+        [
+            ("response_type", "code"),
+            ("client_id", provider.config.client_id.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("scope", provider.scope()),
+            ("state", state_param.as_str()),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    ) {
+        Ok(url) => url,
+        Err(e) => return AppError::internal(e).into_response(),
+    };
+    pending.insert(
+        state_param.clone(),
+        Pending {
+            provider: name,
+            verifier,
+            link_to: accounts.user(&headers).map(|a| a.username),
+            expires_at: now + Duration::minutes(PENDING_MINUTES),
+        },
+    );
+    let mut response = Redirect::to(url.as_str()).into_response();
+    if let Some(cookie) = state_cookie(
+        &state_param,
+        PENDING_MINUTES * 60,
+        accounts.secure_cookies(),
+    ) {
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+    response
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// `GET /login/oidc/{name}/callback`, where the provider sends the browser
+/// back.
+pub async fn callback_handler(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Query(query): Query<CallbackQuery>,
+) -> Response {
+    let Some(accounts) = state.accounts.clone() else {
+        return disabled();
+    };
+    let Some(provider) = accounts.oidc.get(&name) else {
+        return unknown_provider();
+    };
+    let label = provider.label();
+    if let Some(error) = &query.error {
+        return page(
+            StatusCode::UNAUTHORIZED,
+            "Sign-in Failed",
+            &format!("{} answered: {}", label, error),
+        );
+    }
+    // The state must be the one this browser was given when it started
+    let pending = query
+        .state
+        .as_deref()
+        .filter(|s| cookie_state(&headers) == Some(*s))
+        .and_then(|s| accounts.oidc.pending.remove(s))
+        .map(|(_, p)| p)
+        .filter(|p| p.provider == name && p.expires_at > Utc::now());
+    let (Some(pending), Some(code)) = (pending, &query.code) else {
+        return page(
+            StatusCode::BAD_REQUEST,
+            "Sign-in Expired",
+            "This sign-in is unknown or took too long. Start again from the sign-in page.",
+        );
+    };
+    let redirect_uri = accounts.oidc.redirect_uri(&name);
+    let subject = match provider
+        .login(&state.client, code, &pending.verifier, &redirect_uri)
+        .await
+    {
+        Ok(subject) => subject,
+        Err(e) => {
+            eprintln!("Sign-in with {} failed: {:#}", name, e);
+            return page(
+                StatusCode::BAD_GATEWAY,
+                "Sign-in Failed",
+                &format!("The sign-in with {} could not be completed.", label),
+            );
+        }
+    };
+    let (subject, hint) = subject;
+    let identity = format!("{}:{}", name, subject);
+
+    let username = if let Some(username) = pending.link_to {
+        if let Err(e) = accounts.link_identity(&username, &identity) {
+            return AppError::internal(e).into_response();
+        }
+        username
+    } else if let Some(account) = accounts.by_identity(&identity) {
+        account.username
+    } else if accounts.signup_enabled() || accounts.is_admin_identity(&identity) {
+        match accounts.create_for_identity(&hint, &identity) {
+            Ok(username) => username,
+            Err(e) => return AppError::internal(e).into_response(),
+        }
+    } else {
+        return page(
+            StatusCode::FORBIDDEN,
+            "No Account",
+            &format!(
+                "No account is linked to your {} login, \"{}\". Ask the operator to link it, or sign in with your password and then with {} to link it yourself.",
+                label, identity, label
+            ),
+        );
+    };
+    let mut response = match accounts.start_session(&username) {
+        Ok(cookie) => redirect_with_cookie("/dashboard", cookie),
+        Err(e) => return AppError::internal(e).into_response(),
+    };
+    if let Some(cookie) = state_cookie("", 0, accounts.secure_cookies()) {
+        response.headers_mut().append(header::SET_COOKIE, cookie);
+    }
+    response
+}
+
+/// Providers whose logins aren't linked to `account` yet, for its
+/// dashboard.
+pub fn unlinked(providers: &Providers, account: &accounts::Account) -> Vec<(String, String)> {
+    providers
+        .buttons()
+        .into_iter()
+        .filter(|(name, _)| !account.has_identity_from(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str, provider: OidcProvider, issuer: Option<&str>) -> OidcConfig {
+        OidcConfig {
+            name: name.to_string(),
+            provider,
+            issuer: issuer.map(String::from),
+            label: None,
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn providers_are_validated() {
+        let generic = |name| config(name, OidcProvider::Generic, Some("https://sso.example.com"));
+        assert!(Providers::new(&[generic("sso"), generic("sso-2")], "").is_ok());
+        assert!(Providers::new(&[generic("sso"), generic("sso")], "").is_err());
+        assert!(Providers::new(&[generic("a/b")], "").is_err());
+        assert!(Providers::new(&[generic("")], "").is_err());
+        assert!(Providers::new(&[config("sso", OidcProvider::Generic, None)], "").is_err());
+        assert!(Providers::new(&[config("gl", OidcProvider::Gitlab, None)], "").is_ok());
+        let github = config("gh", OidcProvider::Github, Some("https://github.com"));
+        assert!(Providers::new(&[github], "").is_err());
+    }
+
+    #[test]
+    fn identities_name_a_configured_provider() {
+        let providers = Providers::new(
+            &[config("gh", OidcProvider::Github, None)],
+            "https://releases.example.com/",
+        )
+        .unwrap();
+        assert!(providers.check_identity("gh:583231").is_ok());
+        assert!(providers.check_identity("gh:").is_err());
+        assert!(providers.check_identity("gl:583231").is_err());
+        assert!(providers.check_identity("583231").is_err());
+        assert_eq!(
+            providers.redirect_uri("gh"),
+            "https://releases.example.com/login/oidc/gh/callback"
+        );
+        assert_eq!(
+            providers.buttons(),
+            [("gh".to_string(), "GitHub".to_string())]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! `/{provider}/{...}/pin`: the latest stable release as a pin for other
+//! tools, so infra repos can fetch their pins from the instance.
+//! `?format=` picks the output:
+//! - `version` (the default): a plain `VERSION` file
+//! - `renovate`: a `renovate.json` package rule allowing only that release
+//! - `flake`: a `flake.lock`-style node locking the repo to its tag
+//!
+//! The release is the newest non-draft one in the stable channel, per the
+//! repo's channel patterns; `?component=` narrows it to a monorepo
+//! component first.
+
+use crate::{
+    AppState, RepoPath,
+    cache_control::RouteKind,
+    channels::Channel,
+    error::AppError,
+    provider::{Release, sourcehut},
+    signing,
+};
+use axum::{
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value, json};
+
+/// Formats of `?format=`
+const FORMATS: [&str; 3] = ["version", "renovate", "flake"];
+
+/// Where the repo is browsed, for providers without a flake input type.
+fn web_url(repo: &RepoPath) -> String {
+    let path: Vec<&str> = [repo.host.as_str(), &repo.owner, &repo.repo]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect();
+    format!("https://{}", path.join("/"))
+}
+
+/// Renovate's datasource for the provider's releases, with the registry of
+/// self-hosted forges.
+fn renovate_datasource(prefix: &str, repo: &RepoPath) -> (&'static str, Option<String>) {
+    let registry = |default: &str| (repo.host != default).then(|| format!("https://{}", repo.host));
+    match prefix {
+        "github" => ("github-releases", None),
+        "gitlab" => ("gitlab-releases", registry("gitlab.com")),
+        "forgejo" | "gitea" | "codeberg" => {
+            ("gitea-releases", Some(format!("https://{}", repo.host)))
+        }
+        _ => ("git-tags", None),
+    }
+}
+
+fn renovate_pin(prefix: &str, repo: &RepoPath, release: &Release) -> Value {
+    let (datasource, registry) = renovate_datasource(prefix, repo);
+    let package = if datasource == "git-tags" {
+        web_url(repo)
+    } else {
+        format!("{}/{}", repo.owner, repo.repo)
+    };
+    let mut rule = Map::new();
+    rule.insert("matchDatasources".into(), json!([datasource]));
+    rule.insert("matchPackageNames".into(), json!([package]));
+    if let Some(registry) = registry {
+        rule.insert("registryUrls".into(), json!([registry]));
+    }
+    rule.insert("allowedVersions".into(), json!(release.tag_name));
+    json!({ "packageRules": [rule] })
+}
+
+/// The flake input locked to the release's tag. Forges without a flake
+/// input type of their own are fetched with `git`, Mercurial ones with `hg`.
+fn flake_input(prefix: &str, repo: &RepoPath, release: &Release) -> Map<String, Value> {
+    let mut input = Map::new();
+    match prefix {
+        "github" | "gitlab" => {
+            input.insert("type".into(), json!(prefix));
+            input.insert("owner".into(), json!(repo.owner));
+            input.insert("repo".into(), json!(repo.repo));
+            if prefix == "gitlab" && repo.host != "gitlab.com" {
+                input.insert("host".into(), json!(repo.host));
+            }
+        }
+        "sourcehut" => {
+            input.insert("type".into(), json!("sourcehut"));
+            input.insert("owner".into(), json!(repo.owner));
+            input.insert("repo".into(), json!(repo.repo));
+            if repo.host != sourcehut::HOST {
+                input.insert("host".into(), json!(repo.host));
+            }
+        }
+        "hg" => {
+            input.insert("type".into(), json!("hg"));
+            input.insert("url".into(), json!(web_url(repo)));
+        }
+        _ => {
+            input.insert("type".into(), json!("git"));
+            input.insert("url".into(), json!(web_url(repo)));
+        }
+    }
+    input.insert("ref".into(), json!(release.tag_name));
+    input
+}
+
+fn flake_pin(prefix: &str, repo: &RepoPath, release: &Release) -> Value {
+    let original = flake_input(prefix, repo, release);
+    let mut locked = original.clone();
+    locked.insert(
+        "lastModified".into(),
+        json!(release.published_at.timestamp()),
+    );
+    json!({
+        "nodes": {
+            repo.repo.clone(): {
+                "locked": locked,
+                "original": original,
+            }
+        },
+        "version": 7
+    })
+}
+
+/// Serve `/pin` for `releases`, already narrowed to `?component=`.
+pub fn pin_response(
+    state: &AppState,
+    prefix: &str,
+    repo: &RepoPath,
+    releases: &[Release],
+    format: Option<&str>,
+) -> Result<Response, AppError> {
+    let format = format.unwrap_or("version");
+    if !FORMATS.contains(&format) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unknown pin format '{}'; use {}",
+                format,
+                FORMATS.join(", ")
+            ),
+        ));
+    }
+    let cache_key = repo.cache_key();
+    let release = releases
+        .iter()
+        .filter(|r| !r.draft)
+        .find(|r| state.channel_rules.classify(&cache_key, r) == Channel::Stable)
+        .ok_or(AppError::new(
+            StatusCode::NOT_FOUND,
+            "Repository has no stable release".to_string(),
+        ))?;
+
+    let pin = match format {
+        "renovate" => renovate_pin(prefix, repo, release),
+        "flake" => flake_pin(prefix, repo, release),
+        _ => {
+            let version = release.version.as_deref().unwrap_or(&release.tag_name);
+            let response = (
+                [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+                format!("{}\n", version),
+            )
+                .into_response();
+            return Ok(state.cache_policy.apply(RouteKind::Json, response));
+        }
+    };
+    let body = serde_json::to_string_pretty(&pin).map_err(AppError::internal)?;
+    Ok(state.cache_policy.apply(
+        RouteKind::Json,
+        signing::json_response(state, StatusCode::OK, body),
+    ))
+}
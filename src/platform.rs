@@ -0,0 +1,136 @@
+//! Classification of release assets by target platform.
+//!
+//! The classifier works purely on the filename, using the conventions most
+//! projects follow (`-x86_64-unknown-linux-musl`, `_darwin_arm64`, `.msi`, ...).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Os {
+    Linux,
+    Mac,
+    Windows,
+    Other,
+}
+
+impl Os {
+    pub const ALL: [Os; 4] = [Os::Linux, Os::Mac, Os::Windows, Os::Other];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Os::Linux => "Linux",
+            Os::Mac => "macOS",
+            Os::Windows => "Windows",
+            Os::Other => "Other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Platform {
+    pub os: Os,
+    pub arch: Option<&'static str>,
+    pub libc: Option<&'static str>,
+}
+
+/// Classify an asset filename by OS, architecture and libc.
+/// e.g., "bat-v0.26.1-x86_64-unknown-linux-musl.tar.gz" -> Linux, x86_64, musl
+pub fn classify(filename: &str) -> Platform {
+    let lower = filename.to_lowercase();
+    let tokens: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .collect();
+    let has = |t: &str| tokens.contains(&t);
+    let ends = |ext: &str| lower.ends_with(ext);
+
+    let os = if ends(".exe")
+        || ends(".msi")
+        || ends(".msix")
+        || has("windows")
+        || has("win")
+        || has("win32")
+        || has("win64")
+        || has("msvc")
+        || has("mingw")
+    {
+        Os::Windows
+    } else if ends(".dmg")
+        || ends(".pkg")
+        || has("darwin")
+        || has("macos")
+        || has("osx")
+        || has("mac")
+        || has("apple")
+    {
+        Os::Mac
+    } else if ends(".deb")
+        || ends(".rpm")
+        || ends(".appimage")
+        || ends(".flatpak")
+        || ends(".snap")
+        || has("linux")
+        || has("musl")
+        || has("glibc")
+    {
+        Os::Linux
+    } else {
+        Os::Other
+    };
+
+    let arch = if lower.contains("x86_64") || lower.contains("x86-64") || has("amd64") || has("x64")
+    {
+        Some("x86_64")
+    } else if has("aarch64") || has("arm64") {
+        Some("aarch64")
+    } else if has("armv7") || has("armv7l") || has("armhf") {
+        Some("armv7")
+    } else if has("arm") || has("armv6") || has("armv6l") || has("armel") {
+        Some("arm")
+    } else if has("i686") || has("i386") || has("386") || has("x86") || has("win32") {
+        Some("i686")
+    } else if has("riscv64") || has("riscv64gc") {
+        Some("riscv64")
+    } else if has("ppc64le") || has("powerpc64le") {
+        Some("ppc64le")
+    } else if has("s390x") {
+        Some("s390x")
+    } else if has("universal") || has("universal2") {
+        Some("universal")
+    } else {
+        None
+    };
+
+    let libc = if has("musl") {
+        Some("musl")
+    } else if os == Os::Linux && (has("gnu") || has("glibc")) {
+        Some("gnu")
+    } else {
+        None
+    };
+
+    Platform { os, arch, libc }
+}
+
+/// Coarse asset category used for filtering: "binary", "checksum" or "source".
+pub fn category(filename: &str, tag_name: &str) -> &'static str {
+    let lower = filename.to_lowercase();
+    let checksum_exts = [
+        ".sha256", ".sha512", ".sha1", ".md5", ".sig", ".asc", ".minisig",
+    ];
+    if checksum_exts.iter().any(|ext| lower.ends_with(ext))
+        || lower.contains("sha256sums")
+        || lower.contains("checksums")
+    {
+        "checksum"
+    } else if (filename.starts_with(tag_name)
+        && [".tar.gz", ".zip", ".tar.bz2", ".tar.xz"]
+            .iter()
+            .any(|ext| &filename[tag_name.len()..] == *ext))
+        || lower.contains("source")
+        || lower.contains("-src")
+        || lower.contains("_src")
+    {
+        "source"
+    } else {
+        "binary"
+    }
+}
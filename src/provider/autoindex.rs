@@ -0,0 +1,282 @@
+//! Plain HTTP directories listing release files, as Apache's mod_autoindex,
+//! nginx's `autoindex on` and lighttpd's dirlisting generate them. There are
+//! no tags; file names are split like `latest*` names (`latest_names.rs`),
+//! and files sharing a version segment make up a release:
+//! "app-1.2.0.tar.gz", "app-1.2.0.tar.gz.asc" and "app-1.2.0-x86_64.zip"
+//! are the assets of release "1.2.0". Files without a version are left out.
+
+use super::{Asset, Provider, Release, Tag, base_url, classify_assets};
+use crate::{
+    AppState, RepoPath, config::ProviderLimits, debug, error::AppError, latest_names::LatestNames,
+    routes, upstream::UpstreamStatusError, versions,
+};
+use anyhow::Result;
+use axum::http::StatusCode;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use percent_encoding::percent_decode_str;
+use reqwest::Client;
+use scraper::{ElementRef, Html as ScraperHtml, Selector};
+
+/// Suffixes of detached signatures and checksums, dropped before a name is
+/// split so "app-1.2.0.tar.gz.asc" goes with "app-1.2.0.tar.gz"
+const SIDECAR_SUFFIXES: [&str; 7] = [
+    ".asc", ".sig", ".sign", ".minisig", ".sha256", ".sha512", ".md5",
+];
+/// Prerelease segments kept with the version before them, e.g. "rc1" in
+/// "app-2.0.0-rc1.tar.gz"
+const PRERELEASE_WORDS: [&str; 6] = ["alpha", "beta", "rc", "pre", "preview", "dev"];
+/// Modification times as the servers print them: Apache, nginx, lighttpd
+const DATE_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M",
+    "%d-%b-%Y %H:%M",
+    "%Y-%b-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+];
+
+/// A file in the listing.
+struct Listed {
+    name: String,
+    /// As linked, relative to the directory
+    href: String,
+    modified: Option<DateTime<Utc>>,
+    /// Exact sizes only; Apache's "1.2M" is no use for `Content-Length`
+    size: u64,
+}
+
+/// The text that follows a file's link on its line: the next cells of its
+/// table row (Apache, lighttpd), or the text up to the next link (nginx,
+/// Apache's `<pre>` listings).
+fn trailing_text(link: ElementRef) -> String {
+    if let Some(cell) = link.parent().and_then(ElementRef::wrap)
+        && cell.value().name() == "td"
+    {
+        return cell
+            .next_siblings()
+            .filter_map(ElementRef::wrap)
+            .map(|td| td.text().collect::<String>())
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    let mut text = String::new();
+    for node in link.next_siblings() {
+        if let Some(element) = node.value().as_element()
+            && element.name() == "a"
+        {
+            break;
+        }
+        if let Some(t) = node.value().as_text() {
+            text.push_str(t);
+        }
+    }
+    text.lines().next().unwrap_or_default().to_string()
+}
+
+/// Modification time and exact size from a file's trailing text, e.g.
+/// "15-Jan-2024 10:30    1234567".
+fn parse_details(text: &str) -> (Option<DateTime<Utc>>, u64) {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut modified = None;
+    let mut rest = &words[..];
+    for (i, pair) in words.windows(2).enumerate() {
+        let joined = pair.join(" ");
+        if let Some(date) = DATE_FORMATS
+            .iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(&joined, f).ok())
+        {
+            // Listings print server-local times without a zone
+            modified = Some(date.and_utc());
+            rest = &words[i + 2..];
+            break;
+        }
+    }
+    let size = rest.first().and_then(|size| size.parse().ok()).unwrap_or(0);
+    (modified, size)
+}
+
+/// Files linked from a listing; subdirectories, sort links and links out of
+/// the directory are skipped.
+fn parse_listing(html: &str) -> Vec<Listed> {
+    let document = ScraperHtml::parse_document(html);
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let mut files: Vec<Listed> = Vec::new();
+    for link in document.select(&link_selector) {
+        let href = link.value().attr("href").unwrap_or_default();
+        if href.is_empty()
+            || href.contains(['/', '?', '#', ':'])
+            || files.iter().any(|f| f.href == href)
+        {
+            continue;
+        }
+        let (modified, size) = parse_details(&trailing_text(link));
+        files.push(Listed {
+            name: percent_decode_str(href).decode_utf8_lossy().into_owned(),
+            href: href.to_string(),
+            modified,
+            size,
+        });
+    }
+    files
+}
+
+/// The version a file name carries, per the `latest` name rules, with a
+/// prerelease segment right after it.
+fn file_version(latest_names: &LatestNames, name: &str) -> Option<String> {
+    let name = SIDECAR_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+    let rename = latest_names.explain(name);
+    let version = rename.version?;
+    let index = rename.segments.iter().position(|s| *s == version)?;
+    let prerelease = rename.segments.get(index + 1).filter(|next| {
+        let word = next.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        PRERELEASE_WORDS.contains(&word.to_ascii_lowercase().as_str())
+    });
+    Some(match prerelease {
+        Some(pre) => format!("{}{}{}", version, rename.separator, pre),
+        None => version.to_string(),
+    })
+}
+
+/// Releases from the listing of `dir` (a path below `host`), highest version
+/// first: listings are often copied by mirrors, so modification times don't
+/// say which release came last.
+pub async fn fetch_releases(
+    client: &Client,
+    host: &str,
+    dir: &str,
+    latest_names: &LatestNames,
+    limits: &ProviderLimits,
+) -> Result<Vec<Release>> {
+    let dir_url = format!("{}/{}/", base_url(host), dir);
+    debug::request("GET", &dir_url);
+    let response = limits
+        .request(client.get(&dir_url))
+        .header("Accept", "text/html")
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("Directory listing ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
+    }
+
+    let html = response.text().await?;
+    let files = parse_listing(limits.truncate_html(&html));
+    debug::parsed("files", files.len());
+
+    let mut releases: Vec<Release> = Vec::new();
+    for file in files {
+        let Some(version) = file_version(latest_names, &file.name) else {
+            continue;
+        };
+        let asset = Asset {
+            url: format!("{}{}", dir_url, file.href),
+            name: file.name,
+            content_type: None,
+            size: file.size,
+            download_count: 0,
+            sha256: None,
+            sri: None,
+            file_type: None,
+            type_label: None,
+            updated_at: file.modified,
+            cid: None,
+        };
+        if let Some(release) = releases.iter_mut().find(|r| r.tag_name == version) {
+            if let Some(modified) = file.modified {
+                release.published_at = release.published_at.min(modified);
+            }
+            release.assets.push(asset);
+            continue;
+        }
+        releases.push(Release {
+            name: Some(version.clone()),
+            published_at: file.modified.unwrap_or_else(Utc::now),
+            html_url: dir_url.clone(),
+            body: None,
+            changes: Default::default(),
+            prerelease: versions::prerelease(&version).is_some(),
+            draft: false,
+            assets: vec![asset],
+            source_tarball: None,
+            source_zipball: None,
+            version: None,
+            container_images: Vec::new(),
+            signature: None,
+            install: Vec::new(),
+            license: None,
+            body_truncated: false,
+            updated_at: None,
+            first_seen_at: None,
+            tag_name: version,
+        });
+    }
+    releases.sort_by(|a, b| versions::compare_versions(&b.tag_name, &a.tag_name));
+
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+/// Directories have no tags; the versions of the files stand in.
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    dir: &str,
+    latest_names: &LatestNames,
+    limits: &ProviderLimits,
+) -> Result<Vec<Tag>> {
+    let releases = fetch_releases(client, host, dir, latest_names, limits).await?;
+    Ok(releases
+        .into_iter()
+        .map(|r| Tag {
+            name: r.tag_name,
+            commit: None,
+            date: Some(r.published_at),
+        })
+        .collect())
+}
+
+pub struct Autoindex;
+
+impl Provider for Autoindex {
+    const PREFIX: &'static str = "autoindex";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /autoindex/{host}/{directory}".to_string(),
+            )
+        })
+    }
+
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            &repo.host,
+            &repo.repo,
+            &state.render.latest_names,
+            &state.providers.autoindex,
+        )
+        .await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            &repo.host,
+            &repo.repo,
+            &state.render.latest_names,
+            &state.providers.autoindex,
+        )
+        .await
+    }
+}
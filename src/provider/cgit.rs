@@ -1,39 +1,42 @@
-use super::{Asset, Release};
+use super::{Asset, Provider, Release, Tag, base_url, classify_assets};
 use crate::{
-    AppState, RepoPath,
-    format_html::{
-        format_error_html, format_processing_html, format_releases_html, rename_to_latest,
-    },
-    provider::CachedReleases,
+    AppState, RepoPath, catalog::CatalogRepo, config::ProviderLimits, debug, error::AppError,
+    routes, upstream::UpstreamStatusError,
 };
 use anyhow::Result;
-use axum::{
-    body::Body,
-    extract::{Path, State},
-    http::{StatusCode, header},
-    response::{Html, IntoResponse, Redirect, Response},
-};
+use axum::http::StatusCode;
 use chrono::{DateTime, Utc};
 use reqwest::Client;
 use scraper::{Html as ScraperHtml, Selector};
-use std::sync::Arc;
-
-pub async fn fetch_releases(client: &Client, host: &str, repo_path: &str) -> Result<Vec<Release>> {
-    let url = format!("https://{}/{}/refs/tags", host, repo_path);
 
-    let response = client
-        .get(&url)
+pub async fn fetch_releases(
+    client: &Client,
+    host: &str,
+    repo_path: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Release>> {
+    let url = format!("{}/{}/refs/tags", base_url(host), repo_path);
+
+    debug::request("GET", &url);
+    let response = limits
+        .request(client.get(&url))
         .header("Accept", "text/html")
         .header("User-Agent", "checkup/0.1.0")
         .send()
         .await?;
+    debug::response(&response);
 
     if !response.status().is_success() {
-        anyhow::bail!("cgit ({}) returned status: {}", host, response.status());
+        return Err(UpstreamStatusError {
+            service: format!("cgit ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
     }
 
     let html = response.text().await?;
-    let document = ScraperHtml::parse_document(&html);
+    let document = ScraperHtml::parse_document(limits.truncate_html(&html));
 
     let mut releases = Vec::new();
 
@@ -43,275 +46,213 @@ pub async fn fetch_releases(client: &Client, host: &str, repo_path: &str) -> Res
     let age_selector = Selector::parse("td:nth-child(4) span, td:nth-child(5) span").unwrap();
 
     for row in document.select(&row_selector).skip(1) {
-        let tag_elem = row.select(&tag_selector).next();
-        let download_elem = row.select(&download_selector).next();
-
-        if let (Some(tag_elem), Some(download_elem)) = (tag_elem, download_elem) {
-            let tag_name = tag_elem.text().collect::<String>().trim().to_string();
-            let download_url = download_elem.value().attr("href").unwrap_or("").to_string();
-
-            if tag_name.is_empty() || download_url.is_empty() {
-                continue;
-            }
-
-            let asset_name = download_url
-                .rsplit('/')
-                .next()
-                .unwrap_or(&tag_name)
-                .to_string();
-
-            let published_at = row
-                .select(&age_selector)
-                .next()
-                .and_then(|el| {
-                    el.value()
-                        .attr("title")
-                        .and_then(|t| DateTime::parse_from_str(t, "%Y-%m-%d %H:%M:%S %z").ok())
-                        .map(|dt| dt.with_timezone(&Utc))
-                })
-                .unwrap_or_else(Utc::now);
-
-            let full_download_url = if download_url.starts_with("http") {
-                download_url
-            } else {
-                format!("https://{}{}", host, download_url)
-            };
-
-            let html_url = format!("https://{}/{}/tag/?h={}", host, repo_path, tag_name);
+        let Some(tag_elem) = row.select(&tag_selector).next() else {
+            continue;
+        };
+        let tag_name = tag_elem.text().collect::<String>().trim().to_string();
+        if tag_name.is_empty() {
+            continue;
+        }
 
-            releases.push(Release {
-                tag_name: tag_name.clone(),
-                name: Some(tag_name.clone()),
-                published_at,
-                html_url,
-                body: None,
-                prerelease: false,
-                draft: false,
-                assets: vec![Asset {
-                    name: asset_name,
-                    url: full_download_url,
-                    content_type: Some("application/gzip".to_string()),
+        // One asset per snapshot link (tar.gz, tar.xz, zip, ...)
+        let assets: Vec<Asset> = row
+            .select(&download_selector)
+            .filter_map(|a| a.value().attr("href"))
+            .filter(|href| !href.is_empty())
+            .map(|href| {
+                let name = href.rsplit('/').next().unwrap_or(&tag_name).to_string();
+                let url = if href.starts_with("http") {
+                    href.to_string()
+                } else {
+                    format!("{}{}", base_url(host), href)
+                };
+                Asset {
+                    content_type: Some(snapshot_content_type(&name).to_string()),
+                    name,
+                    url,
                     size: 0,
                     download_count: 0,
-                }],
-                source_tarball: None,
-                source_zipball: None,
-            });
+                    sha256: None,
+                    sri: None,
+                    file_type: None,
+                    type_label: None,
+                    updated_at: None,
+                    cid: None,
+                }
+            })
+            .collect();
+        if assets.is_empty() {
+            continue;
         }
+
+        let published_at = row
+            .select(&age_selector)
+            .next()
+            .and_then(|el| {
+                el.value()
+                    .attr("title")
+                    .and_then(|t| DateTime::parse_from_str(t, "%Y-%m-%d %H:%M:%S %z").ok())
+                    .map(|dt| dt.with_timezone(&Utc))
+            })
+            .unwrap_or_else(Utc::now);
+
+        let html_url = format!("{}/{}/tag/?h={}", base_url(host), repo_path, tag_name);
+
+        releases.push(Release {
+            tag_name: tag_name.clone(),
+            name: Some(tag_name.clone()),
+            published_at,
+            html_url,
+            body: None,
+            changes: Default::default(),
+            prerelease: false,
+            draft: false,
+            assets,
+            source_tarball: None,
+            source_zipball: None,
+            version: None,
+            container_images: Vec::new(),
+            signature: None,
+            install: Vec::new(),
+            license: None,
+            body_truncated: false,
+            updated_at: None,
+            first_seen_at: None,
+        });
     }
 
+    debug::parsed("tag rows", releases.len());
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+
     Ok(releases)
 }
 
-pub async fn handler(
-    Path(cgit_path): Path<String>,
-    State(state): State<Arc<AppState>>,
-) -> Result<Response, (StatusCode, String)> {
-    if let Some(pos) = cgit_path.rfind('/') {
-        let last_segment = &cgit_path[pos + 1..];
-        if last_segment.starts_with("latest") {
-            let repo_part = &cgit_path[..pos];
-            let parts: Vec<&str> = repo_part.splitn(2, '/').collect();
-            if parts.len() != 2 {
-                return Err((StatusCode::BAD_REQUEST, "Invalid path format".to_string()));
-            }
-            let repo = RepoPath {
-                host: parts[0].to_string(),
-                owner: String::new(),
-                repo: parts[1].to_string(),
-            };
-            let releases = fetch_blocking(&state, &repo).await?;
-
-            if let Some(latest) = releases.first() {
-                for asset in &latest.assets {
-                    if rename_to_latest(&asset.name) == last_segment {
-                        return Ok(Redirect::temporary(&asset.url).into_response());
-                    }
-                }
-            }
-            return Err((
-                StatusCode::NOT_FOUND,
-                format!("No asset matching '{}' found", last_segment),
-            ));
-        }
+fn snapshot_content_type(name: &str) -> &'static str {
+    match name {
+        n if n.ends_with(".tar.gz") || n.ends_with(".tgz") => "application/gzip",
+        n if n.ends_with(".tar.xz") => "application/x-xz",
+        n if n.ends_with(".tar.bz2") => "application/x-bzip2",
+        n if n.ends_with(".tar.zst") => "application/zstd",
+        n if n.ends_with(".tar.lz") => "application/x-lzip",
+        n if n.ends_with(".zip") => "application/zip",
+        _ => "application/octet-stream",
     }
+}
 
-    let (path_str, want_json) = if cgit_path.ends_with("/+json") {
-        (cgit_path.trim_end_matches("/+json").to_string(), true)
-    } else {
-        (cgit_path.clone(), false)
-    };
-
-    let parts: Vec<&str> = path_str.splitn(2, '/').collect();
-    if parts.len() != 2 {
-        return Err((StatusCode::BAD_REQUEST, "Invalid path format".to_string()));
-    }
-    let repo = RepoPath {
-        host: parts[0].to_string(),
-        owner: String::new(),
-        repo: parts[1].to_string(),
-    };
-    let cache_key = repo.cache_key();
+/// Pages of the repository index followed, at `max-repo-count` (50 by
+/// default) repos each.
+const MAX_INDEX_PAGES: usize = 40;
 
-    if want_json {
-        if let Some(json_content) = state
-            .cache
-            .read_json_raw(&repo.host, &repo.owner, &repo.repo)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        {
-            return Ok(Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, "application/json")
-                .body(Body::from(json_content))
-                .unwrap());
+/// Every repo on the instance's index page, following its `?ofs=` pages.
+pub async fn fetch_repo_index(client: &Client, host: &str) -> Result<Vec<CatalogRepo>> {
+    let row_selector = Selector::parse("table.list tr").unwrap();
+    let repo_selector = Selector::parse("td.toplevel-repo a, td.sublevel-repo a").unwrap();
+    let description_selector = Selector::parse("td:nth-child(2)").unwrap();
+
+    let mut repos: Vec<CatalogRepo> = Vec::new();
+    for _ in 0..MAX_INDEX_PAGES {
+        let url = format!("{}/?ofs={}", base_url(host), repos.len());
+        debug::request("GET", &url);
+        let response = client
+            .get(&url)
+            .header("Accept", "text/html")
+            .header("User-Agent", "checkup/0.1.0")
+            .send()
+            .await?;
+        debug::response(&response);
+
+        if !response.status().is_success() {
+            return Err(UpstreamStatusError {
+                service: format!("cgit ({})", host),
+                status: response.status(),
+                rate_limit: None,
+            }
+            .into());
         }
-        return Err((
-            StatusCode::NOT_FOUND,
-            "No cached data available".to_string(),
-        ));
-    }
 
-    match get_or_spawn_fetch(&state, &repo).await {
-        Ok(FetchResult::Cached) => {
-            if let Some(html) = state
-                .cache
-                .read_html(&repo.host, &repo.owner, &repo.repo)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            {
-                return Ok(Html(html).into_response());
+        let html = response.text().await?;
+        let document = ScraperHtml::parse_document(&html);
+        let (before, mut found) = (repos.len(), 0);
+        for row in document.select(&row_selector) {
+            let Some(path) = row
+                .select(&repo_selector)
+                .next()
+                .and_then(|a| a.value().attr("href"))
+                .map(|href| href.trim_matches('/').to_string())
+                .filter(|path| !path.is_empty())
+            else {
+                continue;
+            };
+            found += 1;
+            if repos.iter().any(|r| r.name == path) {
+                continue;
             }
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to read cached HTML".to_string(),
-            ))
-        }
-        Ok(FetchResult::Processing) => {
-            let html = format_processing_html(&cache_key, "cgit");
-            Ok(Html(html).into_response())
+            let description = row
+                .select(&description_selector)
+                .next()
+                .map(|td| td.text().collect::<String>().trim().to_string());
+            repos.push(CatalogRepo {
+                route: format!("/cgit/{}/{}", host, path),
+                name: path,
+                description,
+            });
         }
-        Ok(FetchResult::Error(err)) => {
-            let html = format_error_html(&cache_key, &err, "cgit");
-            Ok(Html(html).into_response())
+        debug::parsed("repositories", found);
+
+        // The last page has no pager link past the current offset
+        let next_ofs = format!("ofs={}", repos.len());
+        if repos.len() == before || !html.contains(&next_ofs) {
+            break;
         }
-        Err(e) => Err(e),
     }
+
+    Ok(repos)
 }
 
-pub enum FetchResult {
-    Cached,
-    Processing,
-    Error(String),
+/// cgit has no API; tags come from the same refs/tags page as releases.
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    repo_path: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Tag>> {
+    let releases = fetch_releases(client, host, repo_path, limits).await?;
+    Ok(releases
+        .into_iter()
+        .map(|r| Tag {
+            name: r.tag_name,
+            commit: None,
+            date: Some(r.published_at),
+        })
+        .collect())
 }
 
-pub async fn get_or_spawn_fetch(
-    state: &Arc<AppState>,
-    repo: &RepoPath,
-) -> Result<FetchResult, (StatusCode, String)> {
-    if let Ok(Some(cached_at)) = state
-        .cache
-        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
-    {
-        if !state.cache.is_expired(cached_at) {
-            return Ok(FetchResult::Cached);
-        }
-    }
+pub struct Cgit;
 
-    let cache_key = repo.cache_key();
+impl Provider for Cgit {
+    const PREFIX: &'static str = "cgit";
 
-    if let Some(error) = state.failed_repos.get(&cache_key) {
-        return Ok(FetchResult::Error(error.clone()));
+    /// cgit repos have no owner; the whole path after the host is the repo.
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(StatusCode::BAD_REQUEST, "Invalid path format".to_string())
+        })
     }
 
-    if state.pending_repos.contains(&cache_key) {
-        return Ok(FetchResult::Processing);
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(&state.client, &repo.host, &repo.repo, &state.providers.cgit).await
     }
 
-    state.pending_repos.insert(cache_key.clone());
-
-    let state = state.clone();
-    let repo = repo.clone();
-    tokio::spawn(async move {
-        let result = fetch_and_cache(&state, &repo).await;
-        state.pending_repos.remove(&cache_key);
-        if let Err(e) = result {
-            state.failed_repos.insert(cache_key.clone(), e.to_string());
-        }
-    });
-
-    Ok(FetchResult::Processing)
-}
-
-async fn fetch_and_cache(state: &Arc<AppState>, repo: &RepoPath) -> Result<()> {
-    let releases = fetch_releases(&state.client, &repo.host, &repo.repo).await?;
-    let cached_at = Utc::now();
-    let cache_key = repo.cache_key();
-
-    let cached = CachedReleases {
-        releases: releases.clone(),
-        cached_at,
-        repo_path: cache_key.clone(),
-    };
-
-    let html = format_releases_html(&releases, &cache_key, "cgit", Some(cached_at));
-
-    state
-        .cache
-        .write_timestamp(&repo.host, &repo.owner, &repo.repo)?;
-    state
-        .cache
-        .write_json(&repo.host, &repo.owner, &repo.repo, &cached)?;
-    state
-        .cache
-        .write_html(&repo.host, &repo.owner, &repo.repo, &html)?;
-
-    state.failed_repos.remove(&cache_key);
-
-    Ok(())
-}
-
-async fn fetch_blocking(
-    state: &Arc<AppState>,
-    repo: &RepoPath,
-) -> Result<Vec<Release>, (StatusCode, String)> {
-    if let Ok(Some(cached_at)) = state
-        .cache
-        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
-    {
-        if !state.cache.is_expired(cached_at) {
-            if let Some(cached) = state
-                .cache
-                .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            {
-                return Ok(cached.releases);
-            }
-        }
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(&state.client, &repo.host, &repo.repo, &state.providers.cgit).await
     }
 
-    let releases = fetch_releases(&state.client, &repo.host, &repo.repo)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let cached_at = Utc::now();
-    let cache_key = repo.cache_key();
-
-    let cached = CachedReleases {
-        releases: releases.clone(),
-        cached_at,
-        repo_path: cache_key.clone(),
-    };
-
-    let html = format_releases_html(&releases, &cache_key, "cgit", Some(cached_at));
-
-    let _ = state
-        .cache
-        .write_timestamp(&repo.host, &repo.owner, &repo.repo);
-    let _ = state
-        .cache
-        .write_json(&repo.host, &repo.owner, &repo.repo, &cached);
-    let _ = state
-        .cache
-        .write_html(&repo.host, &repo.owner, &repo.repo, &html);
+    fn has_catalog(_state: &AppState, _host: &str) -> Result<bool, AppError> {
+        Ok(true)
+    }
 
-    Ok(releases)
+    async fn fetch_catalog(state: &AppState, host: &str) -> Result<Vec<CatalogRepo>> {
+        fetch_repo_index(&state.client, host).await
+    }
 }
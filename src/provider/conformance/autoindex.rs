@@ -0,0 +1,123 @@
+//! Directory listings have no tags, so the shared fixture repo can't be
+//! described as files ("release/2024.01" would be a subdirectory); these
+//! tests run on listings of their own, as Apache and nginx render them.
+
+use super::host;
+use crate::{
+    config::ProviderLimits,
+    latest_names::LatestNames,
+    provider::{Release, autoindex},
+    upstream::UpstreamStatusError,
+};
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const DIR: &str = "/releases/repo/";
+
+/// Versions in both listings, highest first
+const VERSIONS: [&str; 4] = ["2.0.0-rc1", "1.10.0", "1.9.2", "0.9"];
+
+async fn serve(listing: &'static str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(DIR))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(listing, "text/html"))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn fetch(server: &MockServer, limits: &ProviderLimits) -> Result<Vec<Release>> {
+    autoindex::fetch_releases(
+        &Client::new(),
+        &host(server),
+        "releases/repo",
+        &LatestNames::default(),
+        limits,
+    )
+    .await
+}
+
+fn asset_names(release: &Release) -> Vec<&str> {
+    release.assets.iter().map(|a| a.name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn apache_releases_by_version() {
+    let server = serve(include_str!("fixtures/autoindex/apache.html")).await;
+    let releases = fetch(&server, &ProviderLimits::default()).await.unwrap();
+    assert_eq!(super::tags(&releases), VERSIONS);
+    assert!(releases[0].prerelease);
+    assert!(!releases[1].prerelease);
+
+    let release = &releases[1];
+    assert_eq!(
+        asset_names(release),
+        [
+            "repo-1.10.0-x86_64-linux.tar.gz",
+            "repo-1.10.0.tar.gz",
+            "repo-1.10.0.tar.gz.asc",
+        ]
+    );
+    // The earliest file dates the release
+    assert_eq!(
+        release.published_at,
+        Utc.with_ymd_and_hms(2024, 2, 1, 8, 55, 0).unwrap()
+    );
+    assert_eq!(
+        release.assets[1].url,
+        format!("http://{}{}repo-1.10.0.tar.gz", host(&server), DIR)
+    );
+    // Apache rounds sizes, which aren't kept
+    assert_eq!(release.assets[1].size, 0);
+    assert_eq!(release.assets[2].size, 833);
+}
+
+#[tokio::test]
+async fn nginx_releases_by_version() {
+    let server = serve(include_str!("fixtures/autoindex/nginx.html")).await;
+    let releases = fetch(&server, &ProviderLimits::default()).await.unwrap();
+    assert_eq!(super::tags(&releases), VERSIONS);
+
+    let release = &releases[1];
+    assert_eq!(release.assets.len(), 4);
+    assert_eq!(release.assets[1].size, 1363148);
+    assert_eq!(
+        release.assets[1].updated_at,
+        Some(Utc.with_ymd_and_hms(2024, 2, 1, 8, 55, 0).unwrap())
+    );
+    assert!(asset_names(release).contains(&"repo_日本語_1.10.0.zip"));
+}
+
+#[tokio::test]
+async fn max_releases() {
+    let server = serve(include_str!("fixtures/autoindex/nginx.html")).await;
+    let limits = ProviderLimits {
+        max_releases: Some(2),
+        ..Default::default()
+    };
+    let releases = fetch(&server, &limits).await.unwrap();
+    assert_eq!(super::tags(&releases), VERSIONS[..2]);
+}
+
+#[tokio::test]
+async fn empty_directory() {
+    let server = serve("<html><body><h1>Index of /releases/repo/</h1><pre><a href=\"../\">../</a>\n</pre></body></html>").await;
+    let releases = fetch(&server, &ProviderLimits::default()).await.unwrap();
+    assert!(releases.is_empty());
+}
+
+#[tokio::test]
+async fn missing_directory() {
+    let server = MockServer::start().await;
+    let err = fetch(&server, &ProviderLimits::default())
+        .await
+        .unwrap_err();
+    let status = err
+        .downcast_ref::<UpstreamStatusError>()
+        .map(|e| e.status.as_u16());
+    assert_eq!(status, Some(404), "{}", err);
+}
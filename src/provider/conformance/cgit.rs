@@ -0,0 +1,51 @@
+use super::ProviderTestSuite;
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, cgit},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REFS: &str = "/repo.git/refs/tags";
+
+pub struct CgitFixtures;
+
+impl ProviderTestSuite for CgitFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const RELEASE_NAMES: bool = false;
+    const UNICODE_ASSET: &str = "repo-日本語-1.0.tar.gz";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(REFS))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(include_str!("fixtures/cgit/refs-tags.html"), "text/html"),
+            )
+            .mount(server)
+            .await;
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(REFS))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/cgit/refs-tags-empty.html"),
+                "text/html",
+            ))
+            .mount(server)
+            .await;
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        cgit::fetch_releases(&Client::new(), host, "repo.git", limits).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        cgit::fetch_tags(&Client::new(), host, "repo.git", &ProviderLimits::default()).await
+    }
+}
+
+conformance_tests!(CgitFixtures);
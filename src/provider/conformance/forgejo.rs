@@ -0,0 +1,99 @@
+use super::{ProviderTestSuite, TAGS as TAG_NAMES, host, mount_tag_pages};
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, forgejo},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const RELEASES: &str = "/api/v1/repos/owner/repo/releases";
+const TAGS: &str = "/api/v1/repos/owner/repo/tags";
+
+pub struct ForgejoFixtures;
+
+impl ProviderTestSuite for ForgejoFixtures {
+    const FOLLOWS_PAGES: bool = false;
+    const FOLLOWS_TAG_PAGES: bool = true;
+    const RELEASE_NAMES: bool = true;
+    const UNICODE_ASSET: &str = "checkup-ünïcödé-😀-x86_64-unknown-linux-gnu.tar.gz";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(RELEASES))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/forgejo/releases-page1.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+        mount_tag_pages(
+            server,
+            TAGS,
+            [
+                include_str!("fixtures/forgejo/tags.json"),
+                include_str!("fixtures/forgejo/tags-page2.json"),
+            ],
+        )
+        .await;
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        for endpoint in [RELEASES, TAGS] {
+            Mock::given(method("GET"))
+                .and(path(endpoint))
+                .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+                .mount(server)
+                .await;
+        }
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        forgejo::fetch_releases(&Client::new(), host, "owner", "repo", limits, None).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        let limits = ProviderLimits::default();
+        forgejo::fetch_tags(&Client::new(), host, "owner", "repo", &limits, None).await
+    }
+}
+
+conformance_tests!(ForgejoFixtures);
+
+/// Repos without releases get one per tag, with their generated archives.
+#[tokio::test]
+async fn tags_fallback() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(RELEASES))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(TAGS))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            include_str!("fixtures/forgejo/tags.json"),
+            "application/json",
+        ))
+        .mount(&server)
+        .await;
+    let host = host(&server);
+    let releases = ForgejoFixtures::fetch_releases(host.as_str(), &ProviderLimits::default())
+        .await
+        .unwrap();
+    assert_eq!(super::tags(&releases), TAG_NAMES[..releases.len()]);
+
+    let release = &releases[1];
+    assert_eq!(
+        release.html_url,
+        format!("http://{}/owner/repo/src/tag/release/2024.01", host)
+    );
+    let asset = &release.assets[0];
+    assert_eq!(asset.name, "release-2024.01.tar.gz");
+    assert_eq!(
+        asset.url,
+        format!("http://{}/owner/repo/archive/release/2024.01.tar.gz", host)
+    );
+    assert_eq!(asset.content_type.as_deref(), Some("application/gzip"));
+}
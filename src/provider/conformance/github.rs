@@ -0,0 +1,101 @@
+use super::{ProviderTestSuite, mount_tag_pages};
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, github},
+    upstream::UpstreamTracker,
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const RELEASES: &str = "/repos/owner/repo/releases";
+const TAGS: &str = "/repos/owner/repo/tags";
+
+pub struct GitHubFixtures;
+
+impl ProviderTestSuite for GitHubFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const FOLLOWS_TAG_PAGES: bool = true;
+    const RELEASE_NAMES: bool = true;
+    const UNICODE_ASSET: &str = "checkup-ünïcödé-😀-x86_64-unknown-linux-gnu.tar.gz";
+
+    async fn mount(server: &MockServer) {
+        let next = format!("{}{}?page=2", server.uri(), RELEASES);
+        Mock::given(method("GET"))
+            .and(path(RELEASES))
+            .and(query_param_is_missing("page"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header(
+                        "link",
+                        format!("<{next}>; rel=\"next\", <{next}>; rel=\"last\"").as_str(),
+                    )
+                    .set_body_raw(
+                        include_str!("fixtures/github/releases-page1.json"),
+                        "application/json",
+                    ),
+            )
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(RELEASES))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/github/releases-page2.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+        mount_tag_pages(
+            server,
+            TAGS,
+            [
+                include_str!("fixtures/github/tags.json"),
+                include_str!("fixtures/github/tags-page2.json"),
+            ],
+        )
+        .await;
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        for endpoint in [RELEASES, TAGS] {
+            Mock::given(method("GET"))
+                .and(path(endpoint))
+                .respond_with(ResponseTemplate::new(200).set_body_raw("[]", "application/json"))
+                .mount(server)
+                .await;
+        }
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        let upstream = UpstreamTracker::new();
+        github::fetch_releases(
+            &Client::new(),
+            &upstream,
+            host,
+            "owner",
+            "repo",
+            limits,
+            None,
+        )
+        .await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        let upstream = UpstreamTracker::new();
+        let limits = ProviderLimits::default();
+        github::fetch_tags(
+            &Client::new(),
+            &upstream,
+            host,
+            "owner",
+            "repo",
+            &limits,
+            None,
+        )
+        .await
+    }
+}
+
+conformance_tests!(GitHubFixtures);
@@ -0,0 +1,81 @@
+use super::ProviderTestSuite;
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, gitiles},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REFS: &str = "/owner/repo/+refs/tags";
+
+/// Commit of each fixture tag, in the order of `TAGS`
+const COMMITS: [(&str, &str); 5] = [
+    (
+        "1111111111111111111111111111111111111111",
+        include_str!("fixtures/gitiles/commit-1.json"),
+    ),
+    (
+        "2222222222222222222222222222222222222222",
+        include_str!("fixtures/gitiles/commit-2.json"),
+    ),
+    (
+        "3333333333333333333333333333333333333333",
+        include_str!("fixtures/gitiles/commit-3.json"),
+    ),
+    (
+        "4444444444444444444444444444444444444444",
+        include_str!("fixtures/gitiles/commit-4.json"),
+    ),
+    (
+        "5555555555555555555555555555555555555555",
+        include_str!("fixtures/gitiles/commit-5.json"),
+    ),
+];
+
+pub struct GitilesFixtures;
+
+impl ProviderTestSuite for GitilesFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const RELEASE_NAMES: bool = false;
+    const UNICODE_ASSET: &str = "repo-日本語-1.0.tar.gz";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(REFS))
+            .and(query_param("format", "JSON"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/gitiles/refs.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+        for (commit, body) in COMMITS {
+            Mock::given(method("GET"))
+                .and(path(format!("/owner/repo/+/{}", commit)))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+                .mount(server)
+                .await;
+        }
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(REFS))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(")]}'\n{}", "application/json"))
+            .mount(server)
+            .await;
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        gitiles::fetch_releases(&Client::new(), host, "owner/repo", limits, None).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        let limits = ProviderLimits::default();
+        gitiles::fetch_tags(&Client::new(), host, "owner/repo", &limits, None).await
+    }
+}
+
+conformance_tests!(GitilesFixtures);
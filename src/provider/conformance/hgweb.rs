@@ -0,0 +1,53 @@
+use super::ProviderTestSuite;
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, hgweb},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const JSON_TAGS: &str = "/repo/json-tags";
+const RAW_TAGS: &str = "/repo/raw-tags";
+
+pub struct HgwebFixtures;
+
+impl ProviderTestSuite for HgwebFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const RELEASE_NAMES: bool = false;
+    const UNICODE_ASSET: &str = "repo-日本語-1.0.tar.gz";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(JSON_TAGS))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/hgweb/json-tags.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+    }
+
+    /// An hgweb without `json-tags`, listing only "tip"
+    async fn mount_empty(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(RAW_TAGS))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "tip\t0000000000000000000000000000000000000000\n",
+                "text/plain",
+            ))
+            .mount(server)
+            .await;
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        hgweb::fetch_releases(&Client::new(), host, "repo", limits).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        hgweb::fetch_tags(&Client::new(), host, "repo", &ProviderLimits::default()).await
+    }
+}
+
+conformance_tests!(HgwebFixtures);
@@ -0,0 +1,93 @@
+use super::ProviderTestSuite;
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, huggingface},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REFS: &str = "/api/models/owner/repo/refs";
+
+/// Revision of each fixture tag, in the order of `TAGS`
+const REVISIONS: [(&str, &str); 5] = [
+    (
+        "/api/models/owner/repo/revision/aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        include_str!("fixtures/huggingface/revision-1.json"),
+    ),
+    (
+        "/api/models/owner/repo/revision/bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+        include_str!("fixtures/huggingface/revision-2.json"),
+    ),
+    (
+        "/api/models/owner/repo/revision/cccccccccccccccccccccccccccccccccccccccc",
+        include_str!("fixtures/huggingface/revision-3.json"),
+    ),
+    (
+        "/api/models/owner/repo/revision/dddddddddddddddddddddddddddddddddddddddd",
+        include_str!("fixtures/huggingface/revision-4.json"),
+    ),
+    (
+        "/api/models/owner/repo/revision/eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee",
+        include_str!("fixtures/huggingface/revision-5.json"),
+    ),
+];
+
+pub struct HuggingFaceFixtures;
+
+impl ProviderTestSuite for HuggingFaceFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const RELEASE_NAMES: bool = false;
+    const UNICODE_ASSET: &str = "repo-日本語-ünïcödé.gguf";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(REFS))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/huggingface/refs.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(REVISIONS[0].0))
+            .and(query_param("blobs", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/huggingface/revision-1-blobs.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+        for (revision_path, body) in REVISIONS {
+            Mock::given(method("GET"))
+                .and(path(revision_path))
+                .and(query_param_is_missing("blobs"))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+                .mount(server)
+                .await;
+        }
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(REFS))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"branches": [], "converts": [], "tags": []}"#,
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        huggingface::fetch_releases(&Client::new(), host, "owner", "repo", limits, None).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        let limits = ProviderLimits::default();
+        huggingface::fetch_tags(&Client::new(), host, "owner", "repo", &limits, None).await
+    }
+}
+
+conformance_tests!(HuggingFaceFixtures);
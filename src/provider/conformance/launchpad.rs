@@ -0,0 +1,93 @@
+use super::ProviderTestSuite;
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, launchpad},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const RELEASES: &str = "/1.0/repo/releases";
+
+/// Files collection of each fixture release, in the order of `TAGS`
+const FILES: [(&str, &str); 5] = [
+    (
+        "/1.0/repo/trunk/v2.0.0-rc.1+build.7/files",
+        include_str!("fixtures/launchpad/files-1.json"),
+    ),
+    (
+        "/1.0/repo/trunk/release/2024.01/files",
+        include_str!("fixtures/launchpad/files-2.json"),
+    ),
+    (
+        "/1.0/repo/trunk/%E6%97%A5%E6%9C%AC%E8%AA%9E-1.0/files",
+        include_str!("fixtures/launchpad/files-3.json"),
+    ),
+    (
+        "/1.0/repo/trunk/v0.9/files",
+        include_str!("fixtures/launchpad/files-4.json"),
+    ),
+    (
+        "/1.0/repo/trunk/0.1/files",
+        include_str!("fixtures/launchpad/files-5.json"),
+    ),
+];
+
+pub struct LaunchpadFixtures;
+
+impl ProviderTestSuite for LaunchpadFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const RELEASE_NAMES: bool = true;
+    const UNICODE_ASSET: &str = "repo-日本語-1.0-ünïcödé.tar.gz";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(RELEASES))
+            .and(query_param_is_missing("ws.start"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/launchpad/releases-page1.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(RELEASES))
+            .and(query_param("ws.start", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/launchpad/releases-page2.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+        for (files_path, body) in FILES {
+            Mock::given(method("GET"))
+                .and(path(files_path))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+                .mount(server)
+                .await;
+        }
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(RELEASES))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"start": 0, "total_size": 0, "entries": []}"#,
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        launchpad::fetch_releases(&Client::new(), host, "repo", limits).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        let limits = ProviderLimits::default();
+        launchpad::fetch_tags(&Client::new(), host, "repo", &limits).await
+    }
+}
+
+conformance_tests!(LaunchpadFixtures);
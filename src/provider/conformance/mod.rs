@@ -0,0 +1,255 @@
+//! Conformance suite every provider must pass, run against recorded upstream
+//! responses (`fixtures/{provider}/`) served by a local mock server.
+//!
+//! The fixtures of every provider describe the same repo "owner/repo": five
+//! releases, newest first, split over two pages where the forge pages its
+//! API. Their tags aren't all semver (`v2.0.0-rc.1+build.7`, a slash in
+//! `release/2024.01`, `日本語-1.0`), and names and asset names go beyond
+//! ASCII. A new provider implements [`ProviderTestSuite`] with its own
+//! fixtures, and runs the suite with `conformance_tests!`. Directory listings
+//! can't hold these tags as file names, and have tests of their own.
+
+use super::{Release, Tag};
+use crate::{config::ProviderLimits, upstream::UpstreamStatusError};
+use anyhow::Result;
+use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Tags of the fixture releases, newest first
+const TAGS: [&str; 5] = [
+    "v2.0.0-rc.1+build.7",
+    "release/2024.01",
+    "日本語-1.0",
+    "v0.9",
+    "0.1",
+];
+
+/// Releases on the first page of paged fixtures
+const FIRST_PAGE: usize = 3;
+
+/// Name of the newest fixture release
+const RELEASE_NAME: &str = "Версия 2.0 🚀";
+
+/// A provider under test: serves its fixtures from a mock server and fetches
+/// them through the provider's own client code.
+trait ProviderTestSuite {
+    /// Whether releases past the upstream's first page are fetched. cgit has
+    /// no pages and lists every tag at once.
+    const FOLLOWS_PAGES: bool;
+    /// Whether tags past the upstream's first page are fetched, from fixtures
+    /// mounted with [`mount_tag_pages`].
+    const FOLLOWS_TAG_PAGES: bool = false;
+    /// Whether releases have names of their own; cgit names them by tag.
+    const RELEASE_NAMES: bool;
+    /// A non-ASCII asset name among the fixture releases
+    const UNICODE_ASSET: &str;
+
+    /// Serve the fixture repo's releases and tags.
+    async fn mount(server: &MockServer);
+    /// Serve a repo without releases or tags.
+    async fn mount_empty(server: &MockServer);
+    /// Fetch the releases of the repo on `host`.
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>>;
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>>;
+}
+
+/// Serve tags at `endpoint` in two pages, the first linking to the second
+/// by `Link` header as GitHub, GitLab and Forgejo do.
+async fn mount_tag_pages(server: &MockServer, endpoint: &str, pages: [&'static str; 2]) {
+    let next = format!("{}{}?page=2", server.uri(), endpoint);
+    Mock::given(method("GET"))
+        .and(path(endpoint))
+        .and(query_param_is_missing("page"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!("<{next}>; rel=\"next\", <{next}>; rel=\"last\"").as_str(),
+                )
+                .set_body_raw(pages[0], "application/json"),
+        )
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(endpoint))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(pages[1], "application/json"))
+        .mount(server)
+        .await;
+}
+
+fn host(server: &MockServer) -> String {
+    server.address().to_string()
+}
+
+fn tags(releases: &[Release]) -> Vec<&str> {
+    releases.iter().map(|r| r.tag_name.as_str()).collect()
+}
+
+async fn releases_in_order<T: ProviderTestSuite>() {
+    let server = MockServer::start().await;
+    T::mount(&server).await;
+    let releases = T::fetch_releases(&host(&server), &ProviderLimits::default())
+        .await
+        .unwrap();
+    let expected = if T::FOLLOWS_PAGES {
+        &TAGS[..]
+    } else {
+        &TAGS[..FIRST_PAGE]
+    };
+    assert_eq!(tags(&releases), expected);
+}
+
+async fn max_releases<T: ProviderTestSuite>() {
+    let server = MockServer::start().await;
+    T::mount(&server).await;
+    let limits = ProviderLimits {
+        max_releases: Some(2),
+        ..Default::default()
+    };
+    let releases = T::fetch_releases(&host(&server), &limits).await.unwrap();
+    assert_eq!(tags(&releases), &TAGS[..2]);
+}
+
+async fn max_assets<T: ProviderTestSuite>() {
+    let server = MockServer::start().await;
+    T::mount(&server).await;
+    let limits = ProviderLimits {
+        max_assets: Some(1),
+        ..Default::default()
+    };
+    let releases = T::fetch_releases(&host(&server), &limits).await.unwrap();
+    assert!(releases.iter().all(|r| r.assets.len() <= 1));
+    assert!(releases.iter().any(|r| r.assets.len() == 1));
+}
+
+async fn unicode_names<T: ProviderTestSuite>() {
+    let server = MockServer::start().await;
+    T::mount(&server).await;
+    let releases = T::fetch_releases(&host(&server), &ProviderLimits::default())
+        .await
+        .unwrap();
+    let expected_name = if T::RELEASE_NAMES {
+        RELEASE_NAME
+    } else {
+        TAGS[0]
+    };
+    assert_eq!(releases[0].name.as_deref(), Some(expected_name));
+    assert!(
+        releases
+            .iter()
+            .flat_map(|r| &r.assets)
+            .any(|a| a.name == T::UNICODE_ASSET),
+        "no asset named {}",
+        T::UNICODE_ASSET
+    );
+}
+
+async fn assets_classified<T: ProviderTestSuite>() {
+    let server = MockServer::start().await;
+    T::mount(&server).await;
+    let releases = T::fetch_releases(&host(&server), &ProviderLimits::default())
+        .await
+        .unwrap();
+    for asset in releases.iter().flat_map(|r| &r.assets) {
+        assert!(asset.file_type.is_some(), "{} is unclassified", asset.name);
+        assert!(!asset.url.is_empty(), "{} has no URL", asset.name);
+    }
+}
+
+async fn tags_in_order<T: ProviderTestSuite>() {
+    let server = MockServer::start().await;
+    T::mount(&server).await;
+    let fetched = T::fetch_tags(&host(&server)).await.unwrap();
+    let names: Vec<&str> = fetched.iter().map(|t| t.name.as_str()).collect();
+    if T::FOLLOWS_TAG_PAGES {
+        assert_eq!(names, TAGS);
+        return;
+    }
+    // Tags are read from the first page only, which holds at least as many
+    // as the first release page
+    assert!(names.len() >= FIRST_PAGE, "{:?}", names);
+    assert_eq!(names, &TAGS[..names.len()]);
+}
+
+async fn empty_repo<T: ProviderTestSuite>() {
+    let server = MockServer::start().await;
+    T::mount_empty(&server).await;
+    let host = host(&server);
+    let releases = T::fetch_releases(&host, &ProviderLimits::default())
+        .await
+        .unwrap();
+    assert!(releases.is_empty());
+    assert!(T::fetch_tags(&host).await.unwrap().is_empty());
+}
+
+/// A missing repo is a 404 from upstream, which callers turn into
+/// `repo_not_found`.
+async fn missing_repo<T: ProviderTestSuite>() {
+    let server = MockServer::start().await;
+    let err = T::fetch_releases(&host(&server), &ProviderLimits::default())
+        .await
+        .unwrap_err();
+    let status = err
+        .downcast_ref::<UpstreamStatusError>()
+        .map(|e| e.status.as_u16());
+    assert_eq!(status, Some(404), "{}", err);
+}
+
+/// Run the suite against a provider's fixtures.
+macro_rules! conformance_tests {
+    ($provider:ty) => {
+        #[tokio::test]
+        async fn releases_in_order() {
+            super::releases_in_order::<$provider>().await
+        }
+
+        #[tokio::test]
+        async fn max_releases() {
+            super::max_releases::<$provider>().await
+        }
+
+        #[tokio::test]
+        async fn max_assets() {
+            super::max_assets::<$provider>().await
+        }
+
+        #[tokio::test]
+        async fn unicode_names() {
+            super::unicode_names::<$provider>().await
+        }
+
+        #[tokio::test]
+        async fn assets_classified() {
+            super::assets_classified::<$provider>().await
+        }
+
+        #[tokio::test]
+        async fn tags_in_order() {
+            super::tags_in_order::<$provider>().await
+        }
+
+        #[tokio::test]
+        async fn empty_repo() {
+            super::empty_repo::<$provider>().await
+        }
+
+        #[tokio::test]
+        async fn missing_repo() {
+            super::missing_repo::<$provider>().await
+        }
+    };
+}
+
+mod autoindex;
+mod cgit;
+mod forgejo;
+mod github;
+mod gitiles;
+mod gitlab;
+mod hgweb;
+mod huggingface;
+mod launchpad;
+mod pagure;
+mod snap;
+mod sourcehut;
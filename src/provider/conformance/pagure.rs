@@ -0,0 +1,84 @@
+use super::ProviderTestSuite;
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, pagure},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TAGS: &str = "/api/0/repo/git/tags";
+
+/// Commit of each fixture tag, in the order of `TAGS`
+const COMMITS: [(&str, &str); 5] = [
+    (
+        "1111111111111111111111111111111111111111",
+        include_str!("fixtures/pagure/commit-1.json"),
+    ),
+    (
+        "2222222222222222222222222222222222222222",
+        include_str!("fixtures/pagure/commit-2.json"),
+    ),
+    (
+        "3333333333333333333333333333333333333333",
+        include_str!("fixtures/pagure/commit-3.json"),
+    ),
+    (
+        "4444444444444444444444444444444444444444",
+        include_str!("fixtures/pagure/commit-4.json"),
+    ),
+    (
+        "5555555555555555555555555555555555555555",
+        include_str!("fixtures/pagure/commit-5.json"),
+    ),
+];
+
+pub struct PagureFixtures;
+
+impl ProviderTestSuite for PagureFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const RELEASE_NAMES: bool = false;
+    const UNICODE_ASSET: &str = "repo-日本語-1.0.tar.gz";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(TAGS))
+            .and(query_param("with_commits", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/pagure/tags.json"),
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+        for (commit, body) in COMMITS {
+            Mock::given(method("GET"))
+                .and(path(format!("/api/0/repo/c/{}/info", commit)))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+                .mount(server)
+                .await;
+        }
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(TAGS))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"tags": {}, "total_tags": 0}"#, "application/json"),
+            )
+            .mount(server)
+            .await;
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        pagure::fetch_releases(&Client::new(), host, "", "repo", limits, None).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        let limits = ProviderLimits::default();
+        pagure::fetch_tags(&Client::new(), host, "", "repo", &limits, None).await
+    }
+}
+
+conformance_tests!(PagureFixtures);
@@ -0,0 +1,53 @@
+use super::ProviderTestSuite;
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, snap},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const INFO: &str = "/v2/snaps/info/repo";
+
+pub struct SnapFixtures;
+
+impl ProviderTestSuite for SnapFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const RELEASE_NAMES: bool = false;
+    const UNICODE_ASSET: &str = "repo_日本語-1.0_arm64.snap";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(INFO))
+            .and(header("Snap-Device-Series", "16"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(include_str!("fixtures/snap/info.json"), "application/json"),
+            )
+            .mount(server)
+            .await;
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(INFO))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"channel-map": [], "name": "repo", "snap-id": "Rep0SnapId"}"#,
+                "application/json",
+            ))
+            .mount(server)
+            .await;
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        snap::fetch_releases(&Client::new(), host, "repo", limits).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        let limits = ProviderLimits::default();
+        snap::fetch_tags(&Client::new(), host, "repo", &limits).await
+    }
+}
+
+conformance_tests!(SnapFixtures);
@@ -0,0 +1,52 @@
+use super::ProviderTestSuite;
+use crate::{
+    config::ProviderLimits,
+    provider::{Release, Tag, sourcehut},
+};
+use anyhow::Result;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REFS: &str = "/~owner/repo/refs/rss.xml";
+
+pub struct SourceHutFixtures;
+
+impl ProviderTestSuite for SourceHutFixtures {
+    const FOLLOWS_PAGES: bool = true;
+    const RELEASE_NAMES: bool = false;
+    const UNICODE_ASSET: &str = "repo-日本語-1.0.tar.gz";
+
+    async fn mount(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(REFS))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/sourcehut/refs.xml"),
+                "application/rss+xml",
+            ))
+            .mount(server)
+            .await;
+    }
+
+    async fn mount_empty(server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path(REFS))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("fixtures/sourcehut/refs-empty.xml"),
+                "application/rss+xml",
+            ))
+            .mount(server)
+            .await;
+    }
+
+    async fn fetch_releases(host: &str, limits: &ProviderLimits) -> Result<Vec<Release>> {
+        sourcehut::fetch_releases(&Client::new(), host, "~owner", "repo", limits).await
+    }
+
+    async fn fetch_tags(host: &str) -> Result<Vec<Tag>> {
+        let limits = ProviderLimits::default();
+        sourcehut::fetch_tags(&Client::new(), host, "~owner", "repo", &limits).await
+    }
+}
+
+conformance_tests!(SourceHutFixtures);
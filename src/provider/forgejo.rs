@@ -1,22 +1,23 @@
-use super::{Asset, Release};
+use super::{
+    Asset, DEFAULT_MAX_PAGES, Provider, Release, Tag, authorize, base_url, classify_assets,
+    last_page, page_url, releases_from_tags,
+};
 use crate::{
     AppState, RepoPath,
-    format_html::{
-        format_error_html, format_processing_html, format_releases_html, rename_to_latest,
-    },
-    provider::CachedReleases,
+    catalog::CatalogRepo,
+    changelog, checksums,
+    config::{HostCredentials, ProviderLimits},
+    debug,
+    error::AppError,
+    license::License,
+    routes,
+    upstream::UpstreamStatusError,
 };
 use anyhow::Result;
-use axum::{
-    body::Body,
-    extract::{Path, State},
-    http::{StatusCode, header},
-    response::{Html, IntoResponse, Redirect, Response},
-};
+use axum::http::StatusCode;
 use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde::Deserialize;
-use std::sync::Arc;
 
 #[derive(Debug, Deserialize)]
 struct ForgejoRelease {
@@ -39,6 +40,8 @@ struct ForgejoAsset {
     browser_download_url: String,
     size: Option<u64>,
     download_count: Option<u64>,
+    /// Upload time; a replaced attachment is a new one
+    created_at: Option<DateTime<Utc>>,
 }
 
 pub async fn fetch_releases(
@@ -46,27 +49,43 @@ pub async fn fetch_releases(
     host: &str,
     owner: &str,
     repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
 ) -> Result<Vec<Release>> {
-    let url = format!("https://{}/api/v1/repos/{}/{}/releases", host, owner, repo);
-
-    let response = client
-        .get(&url)
+    let url = limits.paged_url(
+        format!(
+            "{}/api/v1/repos/{}/{}/releases",
+            base_url(host),
+            owner,
+            repo
+        ),
+        "limit",
+    );
+
+    debug::request("GET", &url);
+    let response = authorize(credentials, limits.request(client.get(&url)))
         .header("Accept", "application/json")
         .header("User-Agent", "checkup/0.1.0")
         .send()
         .await?;
+    debug::response(&response);
 
     if !response.status().is_success() {
-        anyhow::bail!(
-            "Forgejo API ({}) returned status: {}",
-            host,
-            response.status()
-        );
+        return Err(UpstreamStatusError {
+            service: format!("Forgejo API ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
     }
 
     let forgejo_releases: Vec<ForgejoRelease> = response.json().await?;
+    debug::parsed("releases", forgejo_releases.len());
+    if forgejo_releases.is_empty() {
+        return tag_releases(client, host, owner, repo, limits, credentials).await;
+    }
 
-    Ok(forgejo_releases
+    let mut releases: Vec<Release> = forgejo_releases
         .into_iter()
         .map(|r| {
             let mut assets: Vec<Asset> = r
@@ -78,6 +97,12 @@ pub async fn fetch_releases(
                     content_type: None,
                     size: a.size.unwrap_or(0),
                     download_count: a.download_count.unwrap_or(0),
+                    sha256: None,
+                    sri: None,
+                    file_type: None,
+                    type_label: None,
+                    updated_at: a.created_at,
+                    cid: None,
                 })
                 .collect();
 
@@ -88,6 +113,12 @@ pub async fn fetch_releases(
                     content_type: Some("application/gzip".to_string()),
                     size: 0,
                     download_count: 0,
+                    sha256: None,
+                    sri: None,
+                    file_type: None,
+                    type_label: None,
+                    updated_at: None,
+                    cid: None,
                 });
             }
             if let Some(zipball) = r.zipball_url {
@@ -97,6 +128,12 @@ pub async fn fetch_releases(
                     content_type: Some("application/zip".to_string()),
                     size: 0,
                     download_count: 0,
+                    sha256: None,
+                    sri: None,
+                    file_type: None,
+                    type_label: None,
+                    updated_at: None,
+                    cid: None,
                 });
             }
 
@@ -105,226 +142,431 @@ pub async fn fetch_releases(
                 name: Some(r.name),
                 published_at: r.published_at,
                 html_url: r.html_url,
+                changes: changelog::parse(&r.body),
                 body: Some(r.body),
                 prerelease: r.prerelease,
                 draft: r.draft,
                 assets,
                 source_tarball: None,
                 source_zipball: None,
+                version: None,
+                container_images: Vec::new(),
+                signature: None,
+                install: Vec::new(),
+                license: None,
+                body_truncated: false,
+                updated_at: None,
+                first_seen_at: None,
             }
         })
-        .collect())
+        .collect();
+    limits.truncate(&mut releases);
+
+    // Hashes for the latest release's assets, from published checksum files
+    if let Some(latest) = releases.first_mut() {
+        checksums::fill_from_checksum_files(client, &mut latest.assets).await;
+    }
+    classify_assets(&mut releases);
+
+    Ok(releases)
 }
 
-pub async fn handler(
-    Path(forgejo_path): Path<String>,
-    State(state): State<Arc<AppState>>,
-) -> Result<Response, (StatusCode, String)> {
-    if let Some(pos) = forgejo_path.rfind('/') {
-        let last_segment = &forgejo_path[pos + 1..];
-        if last_segment.starts_with("latest") {
-            let repo_part = &forgejo_path[..pos];
-            let parts: Vec<&str> = repo_part.splitn(3, '/').collect();
-            if parts.len() != 3 {
-                return Err((StatusCode::BAD_REQUEST, "Invalid path format".to_string()));
-            }
-            let repo = RepoPath {
-                host: parts[0].to_string(),
-                owner: parts[1].to_string(),
-                repo: parts[2].to_string(),
-            };
-            let releases = fetch_blocking(&state, &repo).await?;
-
-            if let Some(latest) = releases.first() {
-                for asset in &latest.assets {
-                    if rename_to_latest(&asset.name) == last_segment {
-                        return Ok(Redirect::temporary(&asset.url).into_response());
-                    }
-                }
+/// Repos that only push tags have a release per tag, with the archives
+/// Forgejo generates for it.
+async fn tag_releases(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Release>> {
+    let tags = fetch_tags(client, host, owner, repo, limits, credentials).await?;
+    let repo_url = format!("{}/{}/{}", base_url(host), owner, repo);
+    let mut releases = releases_from_tags(
+        tags,
+        |tag| format!("{}/src/tag/{}", repo_url, tag),
+        |tag| {
+            ["tar.gz", "zip"]
+                .map(|ext| (ext, format!("{}/archive/{}.{}", repo_url, tag, ext)))
+                .to_vec()
+        },
+    );
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgejoTag {
+    name: String,
+    commit: Option<ForgejoTagCommit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgejoTagCommit {
+    sha: String,
+    created: Option<DateTime<Utc>>,
+}
+
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Tag>> {
+    let url = format!(
+        "{}/api/v1/repos/{}/{}/tags?limit=100",
+        base_url(host),
+        owner,
+        repo
+    );
+
+    let max_pages = limits.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+    let mut forgejo_tags: Vec<ForgejoTag> = Vec::new();
+    let (mut page, mut pages) = (1, 1);
+    while page <= pages {
+        let url = if page == 1 {
+            url.clone()
+        } else {
+            page_url(&url, page)
+        };
+        let response = authorize(credentials, limits.request(client.get(&url)))
+            .header("Accept", "application/json")
+            .header("User-Agent", "checkup/0.1.0")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(UpstreamStatusError {
+                service: format!("Forgejo API ({})", host),
+                status: response.status(),
+                rate_limit: None,
             }
-            return Err((
-                StatusCode::NOT_FOUND,
-                format!("No asset matching '{}' found", last_segment),
-            ));
+            .into());
         }
+
+        // The first page says how many there are
+        if page == 1 {
+            pages = last_page(response.headers()).unwrap_or(1).min(max_pages);
+        }
+        forgejo_tags.extend(response.json::<Vec<ForgejoTag>>().await?);
+        page += 1;
     }
 
-    let (path_str, want_json) = if forgejo_path.ends_with("/+json") {
-        (forgejo_path.trim_end_matches("/+json").to_string(), true)
-    } else {
-        (forgejo_path.clone(), false)
-    };
+    Ok(forgejo_tags
+        .into_iter()
+        .map(|t| Tag {
+            name: t.name,
+            date: t.commit.as_ref().and_then(|c| c.created),
+            commit: t.commit.map(|c| c.sha),
+        })
+        .collect())
+}
 
-    let parts: Vec<&str> = path_str.splitn(3, '/').collect();
-    if parts.len() != 3 {
-        return Err((StatusCode::BAD_REQUEST, "Invalid path format".to_string()));
+/// The repo's README.md on its default branch; none when it has none.
+pub async fn fetch_readme(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Option<String>> {
+    let url = format!(
+        "{}/api/v1/repos/{}/{}/raw/README.md",
+        base_url(host),
+        owner,
+        repo
+    );
+
+    debug::request("GET", &url);
+    let response = authorize(credentials, limits.request(client.get(&url)))
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
     }
-    let repo = RepoPath {
-        host: parts[0].to_string(),
-        owner: parts[1].to_string(),
-        repo: parts[2].to_string(),
-    };
-    let cache_key = repo.cache_key();
-
-    if want_json {
-        if let Some(json_content) = state
-            .cache
-            .read_json_raw(&repo.host, &repo.owner, &repo.repo)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        {
-            return Ok(Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, "application/json")
-                .body(Body::from(json_content))
-                .unwrap());
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("Forgejo API ({})", host),
+            status: response.status(),
+            rate_limit: None,
         }
-        return Err((
-            StatusCode::NOT_FOUND,
-            "No cached data available".to_string(),
-        ));
+        .into());
     }
+    Ok(Some(response.text().await?))
+}
 
-    match get_or_spawn_fetch(&state, &repo).await {
-        Ok(FetchResult::Cached) => {
-            if let Some(html) = state
-                .cache
-                .read_html(&repo.host, &repo.owner, &repo.repo)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            {
-                return Ok(Html(html).into_response());
-            }
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to read cached HTML".to_string(),
-            ))
-        }
-        Ok(FetchResult::Processing) => {
-            let html = format_processing_html(&cache_key, "forgejo");
-            Ok(Html(html).into_response())
-        }
-        Ok(FetchResult::Error(err)) => {
-            let html = format_error_html(&cache_key, &err, "forgejo");
-            Ok(Html(html).into_response())
+/// The repo's licenses from `/licenses`, as SPDX identifiers; none when
+/// none are detected or the instance predates the endpoint. Several are
+/// taken as alternatives, the common reason for shipping more than one.
+pub async fn fetch_license(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Option<License>> {
+    let url = format!(
+        "{}/api/v1/repos/{}/{}/licenses",
+        base_url(host),
+        owner,
+        repo
+    );
+
+    debug::request("GET", &url);
+    let response = authorize(credentials, limits.request(client.get(&url)))
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("Forgejo API ({})", host),
+            status: response.status(),
+            rate_limit: None,
         }
-        Err(e) => Err(e),
+        .into());
     }
+    let licenses: Vec<String> = response.json().await?;
+    Ok((!licenses.is_empty()).then(|| License {
+        spdx_id: Some(licenses.join(" OR ")),
+        name: None,
+        files: Vec::new(),
+    }))
 }
 
-pub enum FetchResult {
-    Cached,
-    Processing,
-    Error(String),
+#[derive(Debug, Deserialize)]
+struct ForgejoSearchResults {
+    data: Vec<ForgejoRepo>,
 }
 
-pub async fn get_or_spawn_fetch(
-    state: &Arc<AppState>,
-    repo: &RepoPath,
-) -> Result<FetchResult, (StatusCode, String)> {
-    if let Ok(Some(cached_at)) = state
-        .cache
-        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
-    {
-        if !state.cache.is_expired(cached_at) {
-            return Ok(FetchResult::Cached);
+#[derive(Debug, Deserialize)]
+struct ForgejoRepo {
+    full_name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    private: bool,
+    /// Missing on old Gitea versions, where every repo is listed
+    release_counter: Option<u64>,
+}
+
+/// Repos requested per search page; the API's default maximum.
+const CATALOG_PAGE_LIMIT: usize = 50;
+/// Search pages followed, most recently updated repos first.
+const MAX_CATALOG_PAGES: usize = 20;
+
+/// Public repos of the instance that have releases, via the search API.
+/// Private repos visible to the configured token are left out.
+pub async fn fetch_catalog(
+    client: &Client,
+    host: &str,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<CatalogRepo>> {
+    let mut repos = Vec::new();
+    for page in 1..=MAX_CATALOG_PAGES {
+        let url = format!(
+            "{}/api/v1/repos/search?sort=updated&order=desc&limit={}&page={}",
+            base_url(host),
+            CATALOG_PAGE_LIMIT,
+            page
+        );
+        debug::request("GET", &url);
+        let response = authorize(credentials, client.get(&url))
+            .header("Accept", "application/json")
+            .header("User-Agent", "checkup/0.1.0")
+            .send()
+            .await?;
+        debug::response(&response);
+
+        if !response.status().is_success() {
+            return Err(UpstreamStatusError {
+                service: format!("Forgejo API ({})", host),
+                status: response.status(),
+                rate_limit: None,
+            }
+            .into());
         }
+
+        let results: ForgejoSearchResults = response.json().await?;
+        let count = results.data.len();
+        debug::parsed("repositories", count);
+        repos.extend(
+            results
+                .data
+                .into_iter()
+                .filter(|r| !r.private && r.release_counter != Some(0))
+                .map(|r| CatalogRepo {
+                    route: format!("/forgejo/{}/{}", host, r.full_name),
+                    name: r.full_name,
+                    description: Some(r.description),
+                }),
+        );
+        if count < CATALOG_PAGE_LIMIT {
+            break;
+        }
+    }
+
+    Ok(repos)
+}
+
+pub struct Forgejo;
+
+impl Provider for Forgejo {
+    const PREFIX: &'static str = "forgejo";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(StatusCode::BAD_REQUEST, "Invalid path format".to_string())
+        })
     }
 
-    let cache_key = repo.cache_key();
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.forgejo,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
 
-    if let Some(error) = state.failed_repos.get(&cache_key) {
-        return Ok(FetchResult::Error(error.clone()));
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.forgejo,
+            state.credentials.get(&repo.host),
+        )
+        .await
     }
 
-    if state.pending_repos.contains(&cache_key) {
-        return Ok(FetchResult::Processing);
+    async fn fetch_readme(state: &AppState, repo: &RepoPath) -> Result<Option<String>> {
+        fetch_readme(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.forgejo,
+            state.credentials.get(&repo.host),
+        )
+        .await
     }
 
-    state.pending_repos.insert(cache_key.clone());
+    async fn fetch_license(state: &AppState, repo: &RepoPath) -> Result<Option<License>> {
+        fetch_license(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.forgejo,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
 
-    let state = state.clone();
-    let repo = repo.clone();
-    tokio::spawn(async move {
-        let result = fetch_and_cache(&state, &repo).await;
-        state.pending_repos.remove(&cache_key);
-        if let Err(e) = result {
-            state.failed_repos.insert(cache_key.clone(), e.to_string());
+    /// Only instances listed in `forgejo_catalog_hosts` have a repo list.
+    fn has_catalog(state: &AppState, host: &str) -> Result<bool, AppError> {
+        if !state.forgejo_catalog_hosts.iter().any(|h| h == host) {
+            return Err(AppError::new(
+                StatusCode::NOT_FOUND,
+                format!("No repository list is configured for {}", host),
+            ));
         }
-    });
+        Ok(true)
+    }
 
-    Ok(FetchResult::Processing)
+    async fn fetch_catalog(state: &AppState, host: &str) -> Result<Vec<CatalogRepo>> {
+        fetch_catalog(&state.client, host, state.credentials.get(host)).await
+    }
 }
 
-async fn fetch_and_cache(state: &Arc<AppState>, repo: &RepoPath) -> Result<()> {
-    let releases = fetch_releases(&state.client, &repo.host, &repo.owner, &repo.repo).await?;
-    let cached_at = Utc::now();
-    let cache_key = repo.cache_key();
+/// Gitea instances, at `/gitea/{host}/{owner}/{repo}`. Gitea serves the API
+/// Forgejo forked from, so everything goes through [`Forgejo`].
+pub struct Gitea;
 
-    let cached = CachedReleases {
-        releases: releases.clone(),
-        cached_at,
-        repo_path: cache_key.clone(),
-    };
+impl Provider for Gitea {
+    const PREFIX: &'static str = "gitea";
 
-    let html = format_releases_html(&releases, &cache_key, "forgejo", Some(cached_at));
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /gitea/{host}/{owner}/{repo}".to_string(),
+            )
+        })
+    }
 
-    state
-        .cache
-        .write_timestamp(&repo.host, &repo.owner, &repo.repo)?;
-    state
-        .cache
-        .write_json(&repo.host, &repo.owner, &repo.repo, &cached)?;
-    state
-        .cache
-        .write_html(&repo.host, &repo.owner, &repo.repo, &html)?;
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        Forgejo::fetch_releases(state, repo).await
+    }
 
-    state.failed_repos.remove(&cache_key);
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        Forgejo::fetch_tags(state, repo).await
+    }
 
-    Ok(())
-}
+    async fn fetch_readme(state: &AppState, repo: &RepoPath) -> Result<Option<String>> {
+        Forgejo::fetch_readme(state, repo).await
+    }
 
-async fn fetch_blocking(
-    state: &Arc<AppState>,
-    repo: &RepoPath,
-) -> Result<Vec<Release>, (StatusCode, String)> {
-    if let Ok(Some(cached_at)) = state
-        .cache
-        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
-    {
-        if !state.cache.is_expired(cached_at) {
-            if let Some(cached) = state
-                .cache
-                .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            {
-                return Ok(cached.releases);
-            }
-        }
+    async fn fetch_license(state: &AppState, repo: &RepoPath) -> Result<Option<License>> {
+        Forgejo::fetch_license(state, repo).await
     }
 
-    let releases = fetch_releases(&state.client, &repo.host, &repo.owner, &repo.repo)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    fn has_catalog(state: &AppState, host: &str) -> Result<bool, AppError> {
+        Forgejo::has_catalog(state, host)
+    }
 
-    let cached_at = Utc::now();
-    let cache_key = repo.cache_key();
+    async fn fetch_catalog(state: &AppState, host: &str) -> Result<Vec<CatalogRepo>> {
+        Forgejo::fetch_catalog(state, host).await
+    }
+}
 
-    let cached = CachedReleases {
-        releases: releases.clone(),
-        cached_at,
-        repo_path: cache_key.clone(),
-    };
+/// Codeberg, the largest Forgejo instance, at `/codeberg/{owner}/{repo}`.
+/// Repos are cached as on `/forgejo/codeberg.org/...`.
+pub struct Codeberg;
 
-    let html = format_releases_html(&releases, &cache_key, "forgejo", Some(cached_at));
+impl Provider for Codeberg {
+    const PREFIX: &'static str = "codeberg";
 
-    let _ = state
-        .cache
-        .write_timestamp(&repo.host, &repo.owner, &repo.repo);
-    let _ = state
-        .cache
-        .write_json(&repo.host, &repo.owner, &repo.repo, &cached);
-    let _ = state
-        .cache
-        .write_html(&repo.host, &repo.owner, &repo.repo, &html);
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /codeberg/{owner}/{repo}".to_string(),
+            )
+        })
+    }
 
-    Ok(releases)
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        Forgejo::fetch_releases(state, repo).await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        Forgejo::fetch_tags(state, repo).await
+    }
+
+    async fn fetch_readme(state: &AppState, repo: &RepoPath) -> Result<Option<String>> {
+        Forgejo::fetch_readme(state, repo).await
+    }
+
+    async fn fetch_license(state: &AppState, repo: &RepoPath) -> Result<Option<License>> {
+        Forgejo::fetch_license(state, repo).await
+    }
 }
@@ -1,22 +1,32 @@
-use super::{Asset, Release};
+use super::{
+    Asset, DEFAULT_MAX_PAGES, Provider, Release, ReleaseQuery, Tag, base_url, classify_assets,
+    last_page, page_url, sri_from_sha256,
+};
 use crate::{
-    AppState, RepoPath,
-    format_html::{
-        format_error_html, format_processing_html, format_releases_html, rename_to_latest,
-    },
-    provider::CachedReleases,
+    AppState, RepoPath, changelog, checksums,
+    config::ProviderLimits,
+    debug,
+    error::AppError,
+    license::License,
+    quota::ClientIp,
+    routes, shedding,
+    upstream::{UpstreamStatusError, UpstreamTracker},
 };
 use anyhow::Result;
-use axum::{
-    body::Body,
-    extract::{Path, State},
-    http::{StatusCode, header},
-    response::{Html, IntoResponse, Redirect, Response},
-};
+use axum::http::StatusCode;
+use base64::Engine;
 use chrono::{DateTime, Utc};
-use reqwest::Client;
-use serde::Deserialize;
-use std::sync::Arc;
+use futures_util::future::try_join_all;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::sync::Semaphore;
+
+/// Contents of latest.json, written by the `/releases/latest` fast path.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedLatest {
+    release: Release,
+    cached_at: DateTime<Utc>,
+}
 
 #[derive(Debug, Deserialize)]
 struct GitHubRelease {
@@ -39,282 +49,479 @@ struct GitHubAsset {
     content_type: Option<String>,
     size: u64,
     download_count: u64,
+    /// e.g. "sha256:9f86d0..."; only present on assets uploaded after mid-2025
+    digest: Option<String>,
+    updated_at: Option<DateTime<Utc>>,
 }
 
-pub async fn fetch_releases(client: &Client, owner: &str, repo: &str) -> Result<Vec<Release>> {
-    let url = format!("https://api.github.com/repos/{}/{}/releases", owner, repo);
-
-    let response = client
-        .get(&url)
-        .header("Accept", "application/vnd.github.v3+json")
-        .header("User-Agent", "checkup/0.1.0")
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        anyhow::bail!("GitHub API returned status: {}", response.status());
-    }
-
-    let github_releases: Vec<GitHubRelease> = response.json().await?;
-
-    Ok(github_releases
+fn convert_release(r: GitHubRelease) -> Release {
+    let mut assets: Vec<Asset> = r
+        .assets
         .into_iter()
-        .map(|r| {
-            let mut assets: Vec<Asset> = r
-                .assets
-                .into_iter()
-                .map(|a| Asset {
-                    name: a.name,
-                    url: a.browser_download_url,
-                    content_type: a.content_type,
-                    size: a.size,
-                    download_count: a.download_count,
-                })
-                .collect();
-
-            if let Some(tarball) = r.tarball_url {
-                assets.push(Asset {
-                    name: format!("{}.tar.gz", r.tag_name),
-                    url: tarball,
-                    content_type: Some("application/gzip".to_string()),
-                    size: 0,
-                    download_count: 0,
-                });
-            }
-            if let Some(zipball) = r.zipball_url {
-                assets.push(Asset {
-                    name: format!("{}.zip", r.tag_name),
-                    url: zipball,
-                    content_type: Some("application/zip".to_string()),
-                    size: 0,
-                    download_count: 0,
-                });
-            }
-
-            Release {
-                tag_name: r.tag_name,
-                name: r.name,
-                published_at: r.published_at,
-                html_url: r.html_url,
-                body: r.body,
-                prerelease: r.prerelease,
-                draft: r.draft,
-                assets,
-                source_tarball: None,
-                source_zipball: None,
+        .map(|a| {
+            let sha256 = a
+                .digest
+                .as_deref()
+                .and_then(|d| d.strip_prefix("sha256:"))
+                .filter(|hash| checksums::is_sha256_hex(hash))
+                .map(str::to_lowercase);
+            Asset {
+                name: a.name,
+                url: a.browser_download_url,
+                content_type: a.content_type,
+                size: a.size,
+                download_count: a.download_count,
+                sri: sha256.as_deref().and_then(sri_from_sha256),
+                sha256,
+                file_type: None,
+                type_label: None,
+                updated_at: a.updated_at,
+                cid: None,
             }
         })
-        .collect())
-}
-
-pub async fn handler(
-    Path(repo_path): Path<String>,
-    State(state): State<Arc<AppState>>,
-) -> Result<Response, (StatusCode, String)> {
-    if let Some(pos) = repo_path.rfind('/') {
-        let last_segment = &repo_path[pos + 1..];
-        if last_segment.starts_with("latest") {
-            let repo_part = &repo_path[..pos];
-            let repo = parse_github_path(repo_part)?;
-            let releases = fetch_blocking(&state, &repo).await?;
-
-            if let Some(latest) = releases.first() {
-                for asset in &latest.assets {
-                    if rename_to_latest(&asset.name) == last_segment {
-                        return Ok(Redirect::temporary(&asset.url).into_response());
-                    }
-                }
-            }
-            return Err((
-                StatusCode::NOT_FOUND,
-                format!("No asset matching '{}' found", last_segment),
-            ));
-        }
+        .collect();
+
+    if let Some(tarball) = r.tarball_url {
+        assets.push(Asset {
+            name: format!("{}.tar.gz", r.tag_name),
+            url: tarball,
+            content_type: Some("application/gzip".to_string()),
+            size: 0,
+            download_count: 0,
+            sha256: None,
+            sri: None,
+            file_type: None,
+            type_label: None,
+            updated_at: None,
+            cid: None,
+        });
+    }
+    if let Some(zipball) = r.zipball_url {
+        assets.push(Asset {
+            name: format!("{}.zip", r.tag_name),
+            url: zipball,
+            content_type: Some("application/zip".to_string()),
+            size: 0,
+            download_count: 0,
+            sha256: None,
+            sri: None,
+            file_type: None,
+            type_label: None,
+            updated_at: None,
+            cid: None,
+        });
     }
 
-    let (path_str, want_json) = if repo_path.ends_with("/+json") {
-        (repo_path.trim_end_matches("/+json").to_string(), true)
-    } else {
-        (repo_path.clone(), false)
-    };
-
-    let repo = parse_github_path(&path_str)?;
-    let cache_key = repo.cache_key();
+    Release {
+        tag_name: r.tag_name,
+        name: r.name,
+        published_at: r.published_at,
+        html_url: r.html_url,
+        changes: changelog::parse(r.body.as_deref().unwrap_or_default()),
+        body: r.body,
+        prerelease: r.prerelease,
+        draft: r.draft,
+        assets,
+        source_tarball: None,
+        source_zipball: None,
+        version: None,
+        container_images: Vec::new(),
+        signature: None,
+        install: Vec::new(),
+        license: None,
+        body_truncated: false,
+        updated_at: None,
+        first_seen_at: None,
+    }
+}
 
-    if want_json {
-        if let Some(json_content) = state
-            .cache
-            .read_json_raw(&repo.host, &repo.owner, &repo.repo)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        {
-            return Ok(Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, "application/json")
-                .body(Body::from(json_content))
-                .unwrap());
+pub const API_HOST: &str = "api.github.com";
+
+/// GitHub's page size when `per_page` is not set
+const DEFAULT_PAGE_SIZE: usize = 30;
+
+/// Pages requested at once
+const MAX_CONCURRENT_PAGES: usize = 4;
+
+/// GET from the GitHub API, authenticated when a token is configured. The
+/// rate limit is recorded, and included in the error when it was hit.
+async fn api_get(
+    client: &Client,
+    upstream: &UpstreamTracker,
+    limits: &ProviderLimits,
+    token: Option<&str>,
+    url: &str,
+) -> Result<Response> {
+    debug::request("GET", url);
+    let mut request = limits
+        .request(client.get(url))
+        .header("Accept", "application/vnd.github.v3+json")
+        .header("User-Agent", "checkup/0.1.0");
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await?;
+    debug::response(&response);
+
+    let rate_limit = upstream.record_rate_limit("github.com", response.headers());
+
+    let status = response.status();
+    if !status.is_success() {
+        let rate_limited = status == reqwest::StatusCode::FORBIDDEN
+            || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+        return Err(UpstreamStatusError {
+            service: "GitHub API".to_string(),
+            status,
+            rate_limit: rate_limit.filter(|_| rate_limited),
         }
-        return Err((
-            StatusCode::NOT_FOUND,
-            "No cached data available".to_string(),
-        ));
+        .into());
     }
+    Ok(response)
+}
 
-    match get_or_spawn_fetch(&state, &repo).await {
-        Ok(FetchResult::Cached) => {
-            if let Some(html) = state
-                .cache
-                .read_html(&repo.host, &repo.owner, &repo.repo)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            {
-                return Ok(Html(html).into_response());
+/// Every page of a listing, up to `max_pages`: the first page says how many
+/// there are, and the rest are fetched at once.
+async fn api_get_pages<T: DeserializeOwned>(
+    client: &Client,
+    upstream: &UpstreamTracker,
+    limits: &ProviderLimits,
+    token: Option<&str>,
+    url: &str,
+    max_pages: usize,
+) -> Result<Vec<T>> {
+    let response = api_get(client, upstream, limits, token, url).await?;
+    let pages = last_page(response.headers()).unwrap_or(1).min(max_pages);
+    let mut items: Vec<T> = response.json().await?;
+    if pages > 1 {
+        let permits = Semaphore::new(MAX_CONCURRENT_PAGES);
+        let rest = try_join_all((2..=pages).map(|page| {
+            let url = page_url(url, page);
+            let permits = &permits;
+            async move {
+                let _permit = permits.acquire().await?;
+                let response = api_get(client, upstream, limits, token, &url).await?;
+                Ok::<_, anyhow::Error>(response.json::<Vec<T>>().await?)
             }
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to read cached HTML".to_string(),
-            ))
-        }
-        Ok(FetchResult::Processing) => {
-            let html = format_processing_html(&cache_key, "github");
-            Ok(Html(html).into_response())
-        }
-        Ok(FetchResult::Error(err)) => {
-            let html = format_error_html(&cache_key, &err, "github");
-            Ok(Html(html).into_response())
-        }
-        Err(e) => Err(e),
+        }))
+        .await?;
+        items.extend(rest.into_iter().flatten());
     }
+    Ok(items)
 }
 
-fn parse_github_path(path: &str) -> Result<RepoPath, (StatusCode, String)> {
-    let parts: Vec<&str> = path.splitn(2, '/').collect();
-    if parts.len() != 2 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Invalid path format. Use: /github/{owner}/{repo}".to_string(),
-        ));
+pub async fn fetch_releases(
+    client: &Client,
+    upstream: &UpstreamTracker,
+    api_host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    token: Option<&str>,
+) -> Result<Vec<Release>> {
+    let url = limits.paged_url(
+        format!("{}/repos/{}/{}/releases", base_url(api_host), owner, repo),
+        "per_page",
+    );
+    // Only the pages needed for `max_releases` are fetched
+    let page_size = limits
+        .page_size
+        .map_or(DEFAULT_PAGE_SIZE, |size| size as usize);
+    let mut max_pages = limits.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+    if let Some(max_releases) = limits.max_releases {
+        max_pages = max_pages.min(max_releases.div_ceil(page_size));
     }
-    Ok(RepoPath {
-        host: "github.com".to_string(),
-        owner: parts[0].to_string(),
-        repo: parts[1].to_string(),
-    })
+    let github_releases: Vec<GitHubRelease> =
+        api_get_pages(client, upstream, limits, token, &url, max_pages).await?;
+    debug::parsed("releases", github_releases.len());
+
+    let mut releases: Vec<Release> = github_releases.into_iter().map(convert_release).collect();
+    limits.truncate(&mut releases);
+
+    // Hashes for the latest release's assets, from published checksum files
+    if let Some(latest) = releases.first_mut() {
+        checksums::fill_from_checksum_files(client, &mut latest.assets).await;
+    }
+    classify_assets(&mut releases);
+
+    Ok(releases)
 }
 
-pub enum FetchResult {
-    Cached,
-    Processing,
-    Error(String),
+/// Only the latest release via `/releases/latest`: one small response
+/// instead of the full list. GitHub skips drafts and prereleases here.
+pub async fn fetch_latest_release(
+    client: &Client,
+    upstream: &UpstreamTracker,
+    api_host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    token: Option<&str>,
+) -> Result<Release> {
+    let url = format!(
+        "{}/repos/{}/{}/releases/latest",
+        base_url(api_host),
+        owner,
+        repo
+    );
+    let response = api_get(client, upstream, limits, token, &url).await?;
+
+    let mut releases = vec![convert_release(response.json().await?)];
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases.remove(0))
 }
 
-pub async fn get_or_spawn_fetch(
-    state: &Arc<AppState>,
-    repo: &RepoPath,
-) -> Result<FetchResult, (StatusCode, String)> {
-    if let Ok(Some(cached_at)) = state
-        .cache
-        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
-    {
-        if !state.cache.is_expired(cached_at) {
-            return Ok(FetchResult::Cached);
-        }
-    }
+#[derive(Debug, Deserialize)]
+struct GitHubTag {
+    name: String,
+    commit: GitHubTagCommit,
+}
 
-    let cache_key = repo.cache_key();
+#[derive(Debug, Deserialize)]
+struct GitHubTagCommit {
+    sha: String,
+}
 
-    if let Some(error) = state.failed_repos.get(&cache_key) {
-        return Ok(FetchResult::Error(error.clone()));
-    }
+pub async fn fetch_tags(
+    client: &Client,
+    upstream: &UpstreamTracker,
+    api_host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    token: Option<&str>,
+) -> Result<Vec<Tag>> {
+    let url = format!(
+        "{}/repos/{}/{}/tags?per_page=100",
+        base_url(api_host),
+        owner,
+        repo
+    );
+    let max_pages = limits.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+    let github_tags: Vec<GitHubTag> =
+        api_get_pages(client, upstream, limits, token, &url, max_pages).await?;
+
+    // The tags endpoint carries no dates; fetching each commit would cost a request per tag
+    Ok(github_tags
+        .into_iter()
+        .map(|t| Tag {
+            name: t.name,
+            commit: Some(t.commit.sha),
+            date: None,
+        })
+        .collect())
+}
 
-    if state.pending_repos.contains(&cache_key) {
-        return Ok(FetchResult::Processing);
+#[derive(Debug, Deserialize)]
+struct GitHubReadme {
+    content: String,
+    encoding: String,
+}
+
+/// The repo's README from `/readme`; none when it has no README.
+pub async fn fetch_readme(
+    client: &Client,
+    upstream: &UpstreamTracker,
+    api_host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    token: Option<&str>,
+) -> Result<Option<String>> {
+    let url = format!("{}/repos/{}/{}/readme", base_url(api_host), owner, repo);
+    let readme: GitHubReadme = match api_get(client, upstream, limits, token, &url).await {
+        Ok(response) => response.json().await?,
+        Err(e) if UpstreamStatusError::is_not_found(&e) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if readme.encoding != "base64" {
+        anyhow::bail!("Unexpected README encoding {:?}", readme.encoding);
     }
+    let content: String = readme.content.split_whitespace().collect();
+    let content = base64::engine::general_purpose::STANDARD.decode(content)?;
+    Ok(Some(String::from_utf8_lossy(&content).into_owned()))
+}
 
-    state.pending_repos.insert(cache_key.clone());
+#[derive(Debug, Deserialize)]
+struct GitHubRepoLicense {
+    license: GitHubLicense,
+}
 
-    let state = state.clone();
-    let repo = repo.clone();
-    tokio::spawn(async move {
-        let result = fetch_and_cache(&state, &repo).await;
-        state.pending_repos.remove(&cache_key);
-        if let Err(e) = result {
-            state.failed_repos.insert(cache_key.clone(), e.to_string());
-        }
-    });
+#[derive(Debug, Deserialize)]
+struct GitHubLicense {
+    name: String,
+    spdx_id: Option<String>,
+}
 
-    Ok(FetchResult::Processing)
+/// The repo's license from `/license`; none when GitHub detects none.
+/// Licenses GitHub can't identify have the SPDX id "NOASSERTION" and the
+/// name "Other", and are left without either.
+pub async fn fetch_license(
+    client: &Client,
+    upstream: &UpstreamTracker,
+    api_host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    token: Option<&str>,
+) -> Result<Option<License>> {
+    let url = format!("{}/repos/{}/{}/license", base_url(api_host), owner, repo);
+    let license: GitHubRepoLicense = match api_get(client, upstream, limits, token, &url).await {
+        Ok(response) => response.json().await?,
+        Err(e) if UpstreamStatusError::is_not_found(&e) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let license = license.license;
+    Ok(Some(License {
+        spdx_id: license.spdx_id.filter(|id| id != "NOASSERTION"),
+        name: Some(license.name).filter(|name| name != "Other"),
+        files: Vec::new(),
+    }))
 }
 
-async fn fetch_and_cache(state: &Arc<AppState>, repo: &RepoPath) -> Result<()> {
-    let releases = fetch_releases(&state.client, &repo.owner, &repo.repo).await?;
-    let cached_at = Utc::now();
-    let cache_key = repo.cache_key();
+pub struct GitHub;
 
-    let cached = CachedReleases {
-        releases: releases.clone(),
-        cached_at,
-        repo_path: cache_key.clone(),
-    };
+impl Provider for GitHub {
+    const PREFIX: &'static str = "github";
 
-    let html = format_releases_html(&releases, &cache_key, "github", Some(cached_at));
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /github/{owner}/{repo}".to_string(),
+            )
+        })
+    }
 
-    state
-        .cache
-        .write_timestamp(&repo.host, &repo.owner, &repo.repo)?;
-    state
-        .cache
-        .write_json(&repo.host, &repo.owner, &repo.repo, &cached)?;
-    state
-        .cache
-        .write_html(&repo.host, &repo.owner, &repo.repo, &html)?;
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            &state.upstream,
+            API_HOST,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.github,
+            state.github_token.as_deref(),
+        )
+        .await
+    }
 
-    state.failed_repos.remove(&cache_key);
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            &state.upstream,
+            API_HOST,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.github,
+            state.github_token.as_deref(),
+        )
+        .await
+    }
+
+    async fn fetch_readme(state: &AppState, repo: &RepoPath) -> Result<Option<String>> {
+        fetch_readme(
+            &state.client,
+            &state.upstream,
+            API_HOST,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.github,
+            state.github_token.as_deref(),
+        )
+        .await
+    }
+
+    async fn fetch_license(state: &AppState, repo: &RepoPath) -> Result<Option<License>> {
+        fetch_license(
+            &state.client,
+            &state.upstream,
+            API_HOST,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.github,
+            state.github_token.as_deref(),
+        )
+        .await
+    }
 
-    Ok(())
+    // GitHub's latest release may not be the highest version when the repo
+    // orders releases by its tag rules
+    async fn latest_release(
+        state: &AppState,
+        repo: &RepoPath,
+        query: &ReleaseQuery,
+        client: ClientIp,
+    ) -> Result<Option<Release>, AppError> {
+        if !state.github_latest_fast_path
+            || query.fallback.is_some()
+            || query.component.is_some()
+            || query.channel.is_some()
+            || state.tag_rules.has_rules(&repo.cache_key())
+        {
+            return Ok(None);
+        }
+        latest_fast_path(state, repo, client).await
+    }
 }
 
-async fn fetch_blocking(
-    state: &Arc<AppState>,
+/// The latest release for `latest*` redirects while the full release list
+/// is not cached: from latest.json, or fetched via `/releases/latest`.
+/// `None` when the full cache is fresh and should be used instead.
+async fn latest_fast_path(
+    state: &AppState,
     repo: &RepoPath,
-) -> Result<Vec<Release>, (StatusCode, String)> {
+    client: ClientIp,
+) -> Result<Option<Release>, AppError> {
     if let Ok(Some(cached_at)) = state
         .cache
         .read_timestamp(&repo.host, &repo.owner, &repo.repo)
+        && !state
+            .cache
+            .is_repo_expired(&repo.host, &repo.owner, &repo.repo, cached_at)
     {
-        if !state.cache.is_expired(cached_at) {
-            if let Some(cached) = state
-                .cache
-                .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            {
-                return Ok(cached.releases);
-            }
-        }
+        return Ok(None);
     }
 
-    let releases = fetch_releases(&state.client, &repo.owner, &repo.repo)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Ok(Some(cached)) =
+        state
+            .cache
+            .read_latest::<CachedLatest>(&repo.host, &repo.owner, &repo.repo)
+        && !state
+            .cache
+            .is_repo_expired(&repo.host, &repo.owner, &repo.repo, cached.cached_at)
+    {
+        return Ok(Some(cached.release));
+    }
 
-    let cached_at = Utc::now();
-    let cache_key = repo.cache_key();
+    shedding::admit_fetch(state, client)?;
+
+    let mut release = state
+        .upstream
+        .track(
+            &repo.host,
+            fetch_latest_release(
+                &state.client,
+                &state.upstream,
+                API_HOST,
+                &repo.owner,
+                &repo.repo,
+                &state.providers.github,
+                state.github_token.as_deref(),
+            ),
+        )
+        .await
+        .map_err(|e| AppError::from_upstream(&e))?;
+    state.url_rewriter.apply(std::slice::from_mut(&mut release));
 
-    let cached = CachedReleases {
-        releases: releases.clone(),
-        cached_at,
-        repo_path: cache_key.clone(),
+    let cached = CachedLatest {
+        release,
+        cached_at: Utc::now(),
     };
-
-    let html = format_releases_html(&releases, &cache_key, "github", Some(cached_at));
-
-    let _ = state
-        .cache
-        .write_timestamp(&repo.host, &repo.owner, &repo.repo);
-    let _ = state
-        .cache
-        .write_json(&repo.host, &repo.owner, &repo.repo, &cached);
-    let _ = state
+    state
         .cache
-        .write_html(&repo.host, &repo.owner, &repo.repo, &html);
+        .write_latest(&repo.host, &repo.owner, &repo.repo, &cached)
+        .map_err(AppError::internal)?;
 
-    Ok(releases)
+    Ok(Some(cached.release))
 }
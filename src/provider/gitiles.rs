@@ -0,0 +1,236 @@
+use super::{Asset, Provider, Release, Tag, authorize, base_url, classify_assets};
+use crate::{
+    AppState, RepoPath,
+    config::{HostCredentials, ProviderLimits},
+    debug,
+    error::AppError,
+    routes,
+    upstream::UpstreamStatusError,
+    versions,
+};
+use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt, stream};
+use reqwest::Client;
+use serde::{Deserialize, de::DeserializeOwned};
+use std::collections::HashMap;
+
+/// Hosts taken to be Gitiles when resolving repo links
+pub const HOST_SUFFIX: &str = ".googlesource.com";
+
+/// Tags dated at least, the highest versions; the tag list has no dates,
+/// so each one takes a request for its commit.
+const MIN_DATED_TAGS: usize = 100;
+/// Commit lookups running at once
+const COMMIT_REQUESTS: usize = 4;
+
+/// Prefix Gitiles puts before JSON against cross-site script inclusion
+const XSSI_PREFIX: &str = ")]}'";
+
+#[derive(Debug, Deserialize)]
+struct GitilesRef {
+    /// The tag object of annotated tags, else the commit
+    value: String,
+    /// The commit annotated tags point to
+    peeled: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitilesCommit {
+    committer: GitilesIdent,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitilesIdent {
+    /// e.g. "Tue Oct 01 07:50:00 2024 +0000"; older Gitiles leave out the offset
+    time: String,
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_str(time, "%a %b %d %H:%M:%S %Y %z")
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(time, "%a %b %d %H:%M:%S %Y").map(|t| t.and_utc())
+        })
+        .ok()
+}
+
+async fn get_json<T: DeserializeOwned>(
+    client: &Client,
+    host: &str,
+    url: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<T> {
+    debug::request("GET", url);
+    let response = authorize(credentials, limits.request(client.get(url)))
+        .header("Accept", "application/json")
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("Gitiles ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
+    }
+    let body = response.text().await?;
+    let json = body.trim_start().trim_start_matches(XSSI_PREFIX);
+    serde_json::from_str(json).with_context(|| format!("Invalid JSON from {}", url))
+}
+
+/// Tags with their commit dates, newest first. Only the 100 highest
+/// versions, or `max_releases` if that is more, are dated and kept.
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    repo_path: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Tag>> {
+    let repo_url = format!("{}/{}", base_url(host), repo_path);
+    let refs: HashMap<String, GitilesRef> = get_json(
+        client,
+        host,
+        &format!("{}/+refs/tags?format=JSON", repo_url),
+        limits,
+        credentials,
+    )
+    .await?;
+    debug::parsed("tags", refs.len());
+
+    let mut listed: Vec<(String, String)> = refs
+        .into_iter()
+        .map(|(name, r)| {
+            let name = name.strip_prefix("refs/tags/").unwrap_or(&name).to_string();
+            (name, r.peeled.unwrap_or(r.value))
+        })
+        .collect();
+    listed.sort_by(|(a, _), (b, _)| versions::compare_versions(b, a));
+    listed.truncate(MIN_DATED_TAGS.max(limits.max_releases.unwrap_or(0)));
+
+    let lookups: Vec<_> = listed
+        .iter()
+        .map(|(_, commit)| {
+            let url = format!("{}/+/{}?format=JSON", repo_url, commit);
+            async move {
+                let info: GitilesCommit = get_json(client, host, &url, limits, credentials).await?;
+                anyhow::Ok(parse_time(&info.committer.time))
+            }
+        })
+        .collect();
+    let dates: Vec<Option<DateTime<Utc>>> = stream::iter(lookups)
+        .buffered(COMMIT_REQUESTS)
+        .try_collect()
+        .await?;
+
+    let mut tags: Vec<Tag> = listed
+        .into_iter()
+        .zip(dates)
+        .map(|((name, commit), date)| Tag {
+            name,
+            date,
+            commit: Some(commit),
+        })
+        .collect();
+    // Stable, so tags of one commit stay in version order
+    tags.sort_by_key(|t| std::cmp::Reverse(t.date));
+    Ok(tags)
+}
+
+/// Gitiles has no releases; each tag is one, with the tarball Gitiles
+/// generates for it.
+pub async fn fetch_releases(
+    client: &Client,
+    host: &str,
+    repo_path: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Release>> {
+    let tags = fetch_tags(client, host, repo_path, limits, credentials).await?;
+    let repo_url = format!("{}/{}", base_url(host), repo_path);
+    let name = repo_path.rsplit('/').next().unwrap_or(repo_path);
+
+    let mut releases: Vec<Release> = tags
+        .into_iter()
+        .map(|tag| Release {
+            name: Some(tag.name.clone()),
+            published_at: tag.date.unwrap_or_else(Utc::now),
+            html_url: format!("{}/+/refs/tags/{}", repo_url, tag.name),
+            body: None,
+            changes: Default::default(),
+            prerelease: false,
+            draft: false,
+            assets: vec![Asset {
+                name: format!("{}-{}.tar.gz", name, tag.name.replace('/', "-")),
+                url: format!("{}/+archive/refs/tags/{}.tar.gz", repo_url, tag.name),
+                content_type: Some("application/gzip".to_string()),
+                size: 0,
+                download_count: 0,
+                sha256: None,
+                sri: None,
+                file_type: None,
+                type_label: None,
+                updated_at: None,
+                cid: None,
+            }],
+            source_tarball: None,
+            source_zipball: None,
+            version: None,
+            container_images: Vec::new(),
+            signature: None,
+            install: Vec::new(),
+            license: None,
+            body_truncated: false,
+            updated_at: None,
+            first_seen_at: None,
+            tag_name: tag.name,
+        })
+        .collect();
+
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+pub struct Gitiles;
+
+impl Provider for Gitiles {
+    const PREFIX: &'static str = "gitiles";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /gitiles/{host}/{repo_path}".to_string(),
+            )
+        })
+    }
+
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            &repo.host,
+            &repo.repo,
+            &state.providers.gitiles,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            &repo.host,
+            &repo.repo,
+            &state.providers.gitiles,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
+}
@@ -1,22 +1,21 @@
-use super::{Asset, Release};
+use super::{
+    Asset, DEFAULT_MAX_PAGES, Provider, Release, Tag, authorize, base_url, classify_assets,
+    last_page, page_url, releases_from_tags,
+};
 use crate::{
-    AppState, RepoPath,
-    format_html::{
-        format_error_html, format_processing_html, format_releases_html, rename_to_latest,
-    },
-    provider::CachedReleases,
+    AppState, RepoPath, changelog, checksums,
+    config::{HostCredentials, ProviderLimits},
+    debug,
+    error::AppError,
+    license::License,
+    routes,
+    upstream::UpstreamStatusError,
 };
 use anyhow::Result;
-use axum::{
-    body::Body,
-    extract::{Path, State},
-    http::{StatusCode, header},
-    response::{Html, IntoResponse, Redirect, Response},
-};
+use axum::http::StatusCode;
 use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde::Deserialize;
-use std::sync::Arc;
 
 #[derive(Debug, Deserialize)]
 struct GitLabRelease {
@@ -62,27 +61,69 @@ mod urlencoding {
     }
 }
 
-pub async fn fetch_releases(client: &Client, owner: &str, repo: &str) -> Result<Vec<Release>> {
+/// Split the path of a GitLab route into the instance host and the project
+/// path. A first segment that looks like a host, followed by at least a
+/// namespace and a project ("gitlab.example.com/group/project"), names a
+/// self-hosted instance; anything else is on gitlab.com. Namespaces
+/// containing a dot are reached as "gitlab.com/name.space/project".
+pub fn split_host(path: &str) -> (&str, &str) {
+    match path.split_once('/') {
+        Some((host, rest)) if host.contains('.') && rest.contains('/') => (host, rest),
+        _ => ("gitlab.com", path),
+    }
+}
+
+/// Path of a project in routes, from its cache key: without the host on
+/// gitlab.com, unless the path would then be taken for a self-hosted one.
+pub fn route_path(cache_key: &str) -> &str {
+    match cache_key.strip_prefix("gitlab.com/") {
+        Some(path) if split_host(path).0 == "gitlab.com" => path,
+        _ => cache_key,
+    }
+}
+
+pub async fn fetch_releases(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Release>> {
     let encoded_path = urlencoding::encode(&format!("{}/{}", owner, repo));
-    let url = format!(
-        "https://gitlab.com/api/v4/projects/{}/releases",
-        encoded_path
+    let url = limits.paged_url(
+        format!(
+            "{}/api/v4/projects/{}/releases",
+            base_url(host),
+            encoded_path
+        ),
+        "per_page",
     );
 
-    let response = client
-        .get(&url)
+    debug::request("GET", &url);
+    let response = authorize(credentials, limits.request(client.get(&url)))
         .header("Accept", "application/json")
         .header("User-Agent", "checkup/0.1.0")
         .send()
         .await?;
+    debug::response(&response);
 
     if !response.status().is_success() {
-        anyhow::bail!("GitLab API returned status: {}", response.status());
+        return Err(UpstreamStatusError {
+            service: format!("GitLab API ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
     }
 
     let gitlab_releases: Vec<GitLabRelease> = response.json().await?;
+    debug::parsed("releases", gitlab_releases.len());
+    if gitlab_releases.is_empty() {
+        return tag_releases(client, host, owner, repo, limits, credentials).await;
+    }
 
-    Ok(gitlab_releases
+    let mut releases: Vec<Release> = gitlab_releases
         .into_iter()
         .map(|r| {
             let mut assets: Vec<Asset> = Vec::new();
@@ -94,6 +135,12 @@ pub async fn fetch_releases(client: &Client, owner: &str, repo: &str) -> Result<
                     content_type: Some(format!("application/{}", source.format.to_lowercase())),
                     size: 0,
                     download_count: 0,
+                    sha256: None,
+                    sri: None,
+                    file_type: None,
+                    type_label: None,
+                    updated_at: None,
+                    cid: None,
                 });
             }
 
@@ -104,6 +151,12 @@ pub async fn fetch_releases(client: &Client, owner: &str, repo: &str) -> Result<
                     content_type: None,
                     size: 0,
                     download_count: 0,
+                    sha256: None,
+                    sri: None,
+                    file_type: None,
+                    type_label: None,
+                    updated_at: None,
+                    cid: None,
                 });
             }
 
@@ -112,225 +165,312 @@ pub async fn fetch_releases(client: &Client, owner: &str, repo: &str) -> Result<
                 name: Some(r.name),
                 published_at: r.released_at,
                 html_url: r._links.self_url,
+                changes: changelog::parse(&r.description),
                 body: Some(r.description),
                 prerelease: false,
                 draft: false,
                 assets,
                 source_tarball: None,
                 source_zipball: None,
+                version: None,
+                container_images: Vec::new(),
+                signature: None,
+                install: Vec::new(),
+                license: None,
+                body_truncated: false,
+                updated_at: None,
+                first_seen_at: None,
             }
         })
-        .collect())
-}
+        .collect();
+    limits.truncate(&mut releases);
 
-pub async fn handler(
-    Path(repo_path): Path<String>,
-    State(state): State<Arc<AppState>>,
-) -> Result<Response, (StatusCode, String)> {
-    if let Some(pos) = repo_path.rfind('/') {
-        let last_segment = &repo_path[pos + 1..];
-        if last_segment.starts_with("latest") {
-            let repo_part = &repo_path[..pos];
-            let repo = parse_gitlab_path(repo_part)?;
-            let releases = fetch_blocking(&state, &repo).await?;
-
-            if let Some(latest) = releases.first() {
-                for asset in &latest.assets {
-                    if rename_to_latest(&asset.name) == last_segment {
-                        return Ok(Redirect::temporary(&asset.url).into_response());
-                    }
-                }
-            }
-            return Err((
-                StatusCode::NOT_FOUND,
-                format!("No asset matching '{}' found", last_segment),
-            ));
-        }
+    // Hashes for the latest release's assets, from published checksum files
+    if let Some(latest) = releases.first_mut() {
+        checksums::fill_from_checksum_files(client, &mut latest.assets).await;
     }
+    classify_assets(&mut releases);
 
-    let (path_str, want_json) = if repo_path.ends_with("/+json") {
-        (repo_path.trim_end_matches("/+json").to_string(), true)
-    } else {
-        (repo_path.clone(), false)
-    };
-
-    let repo = parse_gitlab_path(&path_str)?;
-    let cache_key = repo.cache_key();
-
-    if want_json {
-        if let Some(json_content) = state
-            .cache
-            .read_json_raw(&repo.host, &repo.owner, &repo.repo)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        {
-            return Ok(Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, "application/json")
-                .body(Body::from(json_content))
-                .unwrap());
-        }
-        return Err((
-            StatusCode::NOT_FOUND,
-            "No cached data available".to_string(),
-        ));
-    }
+    Ok(releases)
+}
 
-    match get_or_spawn_fetch(&state, &repo).await {
-        Ok(FetchResult::Cached) => {
-            if let Some(html) = state
-                .cache
-                .read_html(&repo.host, &repo.owner, &repo.repo)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            {
-                return Ok(Html(html).into_response());
-            }
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to read cached HTML".to_string(),
-            ))
-        }
-        Ok(FetchResult::Processing) => {
-            let html = format_processing_html(&cache_key, "gitlab");
-            Ok(Html(html).into_response())
-        }
-        Ok(FetchResult::Error(err)) => {
-            let html = format_error_html(&cache_key, &err, "gitlab");
-            Ok(Html(html).into_response())
-        }
-        Err(e) => Err(e),
-    }
+/// Projects that only push tags have a release per tag, with the archives
+/// GitLab generates for it.
+async fn tag_releases(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Release>> {
+    let tags = fetch_tags(client, host, owner, repo, limits, credentials).await?;
+    let project_url = format!("{}/{}/{}", base_url(host), owner, repo);
+    let mut releases = releases_from_tags(
+        tags,
+        |tag| format!("{}/-/tags/{}", project_url, tag),
+        |tag| {
+            ["tar.gz", "zip"]
+                .map(|ext| {
+                    let url = format!(
+                        "{}/-/archive/{}/{}-{}.{}",
+                        project_url,
+                        tag,
+                        repo,
+                        tag.replace('/', "-"),
+                        ext
+                    );
+                    (ext, url)
+                })
+                .to_vec()
+        },
+    );
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
 }
 
-fn parse_gitlab_path(path: &str) -> Result<RepoPath, (StatusCode, String)> {
-    let parts: Vec<&str> = path.splitn(2, '/').collect();
-    if parts.len() != 2 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Invalid path format. Use: /gitlab/{owner}/{repo}".to_string(),
-        ));
-    }
-    Ok(RepoPath {
-        host: "gitlab.com".to_string(),
-        owner: parts[0].to_string(),
-        repo: parts[1].to_string(),
-    })
+#[derive(Debug, Deserialize)]
+struct GitLabTag {
+    name: String,
+    commit: Option<GitLabTagCommit>,
 }
 
-pub enum FetchResult {
-    Cached,
-    Processing,
-    Error(String),
+#[derive(Debug, Deserialize)]
+struct GitLabTagCommit {
+    id: String,
+    created_at: Option<DateTime<Utc>>,
 }
 
-pub async fn get_or_spawn_fetch(
-    state: &Arc<AppState>,
-    repo: &RepoPath,
-) -> Result<FetchResult, (StatusCode, String)> {
-    if let Ok(Some(cached_at)) = state
-        .cache
-        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
-    {
-        if !state.cache.is_expired(cached_at) {
-            return Ok(FetchResult::Cached);
-        }
-    }
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Tag>> {
+    let encoded_path = urlencoding::encode(&format!("{}/{}", owner, repo));
+    let url = format!(
+        "{}/api/v4/projects/{}/repository/tags?per_page=100",
+        base_url(host),
+        encoded_path
+    );
 
-    let cache_key = repo.cache_key();
+    let max_pages = limits.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+    let mut gitlab_tags: Vec<GitLabTag> = Vec::new();
+    let (mut page, mut pages) = (1, 1);
+    while page <= pages {
+        let url = if page == 1 {
+            url.clone()
+        } else {
+            page_url(&url, page)
+        };
+        let response = authorize(credentials, limits.request(client.get(&url)))
+            .header("Accept", "application/json")
+            .header("User-Agent", "checkup/0.1.0")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(UpstreamStatusError {
+                service: format!("GitLab API ({})", host),
+                status: response.status(),
+                rate_limit: None,
+            }
+            .into());
+        }
 
-    if let Some(error) = state.failed_repos.get(&cache_key) {
-        return Ok(FetchResult::Error(error.clone()));
+        // The first page says how many there are
+        if page == 1 {
+            pages = last_page(response.headers()).unwrap_or(1).min(max_pages);
+        }
+        gitlab_tags.extend(response.json::<Vec<GitLabTag>>().await?);
+        page += 1;
     }
 
-    if state.pending_repos.contains(&cache_key) {
-        return Ok(FetchResult::Processing);
-    }
+    Ok(gitlab_tags
+        .into_iter()
+        .map(|t| Tag {
+            name: t.name,
+            date: t.commit.as_ref().and_then(|c| c.created_at),
+            commit: t.commit.map(|c| c.id),
+        })
+        .collect())
+}
 
-    state.pending_repos.insert(cache_key.clone());
+#[derive(Debug, Deserialize)]
+struct GitLabProject {
+    default_branch: Option<String>,
+    /// Web URL of the README, e.g.
+    /// "https://gitlab.com/owner/repo/-/blob/main/README.md"
+    readme_url: Option<String>,
+}
 
-    let state = state.clone();
-    let repo = repo.clone();
-    tokio::spawn(async move {
-        let result = fetch_and_cache(&state, &repo).await;
-        state.pending_repos.remove(&cache_key);
-        if let Err(e) = result {
-            state.failed_repos.insert(cache_key.clone(), e.to_string());
+/// The project's README, found through the project's `readme_url`; none
+/// when it has no README.
+pub async fn fetch_readme(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Option<String>> {
+    let encoded_path = urlencoding::encode(&format!("{}/{}", owner, repo));
+    let get = |url: String| async move {
+        debug::request("GET", &url);
+        let response = authorize(credentials, limits.request(client.get(&url)))
+            .header("User-Agent", "checkup/0.1.0")
+            .send()
+            .await?;
+        debug::response(&response);
+        if !response.status().is_success() {
+            return Err(anyhow::Error::from(UpstreamStatusError {
+                service: format!("GitLab API ({})", host),
+                status: response.status(),
+                rate_limit: None,
+            }));
         }
-    });
+        Ok(response)
+    };
 
-    Ok(FetchResult::Processing)
+    let project_url = format!("{}/api/v4/projects/{}", base_url(host), encoded_path);
+    let project: GitLabProject = get(project_url).await?.json().await?;
+    let (Some(branch), Some(readme_url)) = (project.default_branch, project.readme_url) else {
+        return Ok(None);
+    };
+    let Some(file) = readme_url
+        .split_once("/-/blob/")
+        .and_then(|(_, path)| path.strip_prefix(&format!("{}/", branch)))
+    else {
+        return Ok(None);
+    };
+    let file_url = format!(
+        "{}/api/v4/projects/{}/repository/files/{}/raw?ref={}",
+        base_url(host),
+        encoded_path,
+        urlencoding::encode(file),
+        urlencoding::encode(&branch)
+    );
+    match get(file_url).await {
+        Ok(response) => Ok(Some(response.text().await?)),
+        Err(e) if UpstreamStatusError::is_not_found(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
 }
 
-async fn fetch_and_cache(state: &Arc<AppState>, repo: &RepoPath) -> Result<()> {
-    let releases = fetch_releases(&state.client, &repo.owner, &repo.repo).await?;
-    let cached_at = Utc::now();
-    let cache_key = repo.cache_key();
+#[derive(Debug, Deserialize)]
+struct GitLabProjectLicense {
+    license: Option<GitLabLicense>,
+}
 
-    let cached = CachedReleases {
-        releases: releases.clone(),
-        cached_at,
-        repo_path: cache_key.clone(),
-    };
+#[derive(Debug, Deserialize)]
+struct GitLabLicense {
+    name: String,
+}
 
-    let html = format_releases_html(&releases, &cache_key, "gitlab", Some(cached_at));
+/// The project's license from the project with `license=true`; none when
+/// GitLab detects none. GitLab's license keys aren't SPDX identifiers, so
+/// only the name is kept.
+pub async fn fetch_license(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Option<License>> {
+    let url = format!(
+        "{}/api/v4/projects/{}?license=true",
+        base_url(host),
+        urlencoding::encode(&format!("{}/{}", owner, repo))
+    );
 
-    state
-        .cache
-        .write_timestamp(&repo.host, &repo.owner, &repo.repo)?;
-    state
-        .cache
-        .write_json(&repo.host, &repo.owner, &repo.repo, &cached)?;
-    state
-        .cache
-        .write_html(&repo.host, &repo.owner, &repo.repo, &html)?;
+    debug::request("GET", &url);
+    let response = authorize(credentials, limits.request(client.get(&url)))
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
 
-    state.failed_repos.remove(&cache_key);
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("GitLab API ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
+    }
 
-    Ok(())
+    let project: GitLabProjectLicense = response.json().await?;
+    Ok(project.license.map(|license| License {
+        spdx_id: None,
+        name: Some(license.name),
+        files: Vec::new(),
+    }))
 }
 
-async fn fetch_blocking(
-    state: &Arc<AppState>,
-    repo: &RepoPath,
-) -> Result<Vec<Release>, (StatusCode, String)> {
-    if let Ok(Some(cached_at)) = state
-        .cache
-        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
-    {
-        if !state.cache.is_expired(cached_at) {
-            if let Some(cached) = state
-                .cache
-                .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-            {
-                return Ok(cached.releases);
-            }
-        }
-    }
+pub struct GitLab;
 
-    let releases = fetch_releases(&state.client, &repo.owner, &repo.repo)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+impl Provider for GitLab {
+    const PREFIX: &'static str = "gitlab";
 
-    let cached_at = Utc::now();
-    let cache_key = repo.cache_key();
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /gitlab/{owner}/{repo} or /gitlab/{host}/{owner}/{repo}"
+                    .to_string(),
+            )
+        })
+    }
 
-    let cached = CachedReleases {
-        releases: releases.clone(),
-        cached_at,
-        repo_path: cache_key.clone(),
-    };
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.gitlab,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
 
-    let html = format_releases_html(&releases, &cache_key, "gitlab", Some(cached_at));
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.gitlab,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
 
-    let _ = state
-        .cache
-        .write_timestamp(&repo.host, &repo.owner, &repo.repo);
-    let _ = state
-        .cache
-        .write_json(&repo.host, &repo.owner, &repo.repo, &cached);
-    let _ = state
-        .cache
-        .write_html(&repo.host, &repo.owner, &repo.repo, &html);
+    async fn fetch_readme(state: &AppState, repo: &RepoPath) -> Result<Option<String>> {
+        fetch_readme(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.gitlab,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
 
-    Ok(releases)
+    async fn fetch_license(state: &AppState, repo: &RepoPath) -> Result<Option<License>> {
+        fetch_license(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.gitlab,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
 }
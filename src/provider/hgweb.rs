@@ -0,0 +1,217 @@
+use super::{Asset, Provider, Release, Tag, base_url, classify_assets};
+use crate::{
+    AppState, RepoPath, config::ProviderLimits, debug, error::AppError, routes,
+    upstream::UpstreamStatusError,
+};
+use anyhow::Result;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Response};
+use serde::Deserialize;
+
+/// Pseudo-tag of the newest changeset, which every repo has
+const TIP: &str = "tip";
+
+/// hgweb commands, the first path segment after the repo path
+const COMMANDS: [&str; 12] = [
+    "rev",
+    "file",
+    "log",
+    "shortlog",
+    "graph",
+    "tags",
+    "branches",
+    "bookmarks",
+    "archive",
+    "raw-file",
+    "raw-rev",
+    "summary",
+];
+
+/// Hosts taken to be hgweb when resolving repo links, e.g. "hg.mozilla.org"
+pub const HOST_PREFIX: &str = "hg.";
+
+/// The repo path of a page path: "releases/mozilla-beta/rev/abc" ->
+/// "releases/mozilla-beta".
+pub fn page_repo_path(path: &str) -> String {
+    path.split('/')
+        .take_while(|segment| !COMMANDS.contains(segment))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `json-tags`, served by hgweb since Mercurial 3.6
+#[derive(Debug, Deserialize)]
+struct HgTags {
+    tags: Vec<HgTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HgTag {
+    tag: String,
+    node: String,
+    /// Seconds since the epoch and the committer's UTC offset
+    date: (f64, i64),
+}
+
+async fn get(client: &Client, host: &str, url: &str, limits: &ProviderLimits) -> Result<Response> {
+    debug::request("GET", url);
+    let response = limits
+        .request(client.get(url))
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("hgweb ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
+    }
+    Ok(response)
+}
+
+/// Tags from `json-tags`, newest first, or `None` when the hgweb is too old
+/// to serve it.
+async fn fetch_json_tags(
+    client: &Client,
+    host: &str,
+    repo_url: &str,
+    limits: &ProviderLimits,
+) -> Option<Vec<Tag>> {
+    let response = get(client, host, &format!("{}/json-tags", repo_url), limits)
+        .await
+        .ok()?;
+    let listed: HgTags = response.json().await.ok()?;
+    Some(
+        listed
+            .tags
+            .into_iter()
+            .map(|t| Tag {
+                name: t.tag,
+                date: DateTime::<Utc>::from_timestamp(t.date.0 as i64, 0),
+                commit: Some(t.node),
+            })
+            .collect(),
+    )
+}
+
+/// Tags from `raw-tags`, "{tag}\t{node}" lines newest first, which have
+/// no dates.
+async fn fetch_raw_tags(
+    client: &Client,
+    host: &str,
+    repo_url: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Tag>> {
+    let response = get(client, host, &format!("{}/raw-tags", repo_url), limits).await?;
+    let text = response.text().await?;
+    Ok(text
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, node)| Tag {
+            name: name.to_string(),
+            date: None,
+            commit: Some(node.trim().to_string()),
+        })
+        .collect())
+}
+
+/// Tags newest first, from `json-tags` or, on hgweb without it, `raw-tags`.
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    repo_path: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Tag>> {
+    let repo_url = format!("{}/{}", base_url(host), repo_path);
+    let mut tags = match fetch_json_tags(client, host, &repo_url, limits).await {
+        Some(tags) => tags,
+        None => fetch_raw_tags(client, host, &repo_url, limits).await?,
+    };
+    tags.retain(|t| t.name != TIP);
+    debug::parsed("tags", tags.len());
+    // Stable, so undated tags keep hgweb's order
+    tags.sort_by_key(|t| std::cmp::Reverse(t.date));
+    Ok(tags)
+}
+
+/// hgweb has no releases; each tag is one, with the tarball hgweb generates
+/// for it where the repo allows archives.
+pub async fn fetch_releases(
+    client: &Client,
+    host: &str,
+    repo_path: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Release>> {
+    let tags = fetch_tags(client, host, repo_path, limits).await?;
+    let repo_url = format!("{}/{}", base_url(host), repo_path);
+    let name = repo_path.rsplit('/').next().unwrap_or(repo_path);
+
+    let mut releases: Vec<Release> = tags
+        .into_iter()
+        .map(|tag| Release {
+            name: Some(tag.name.clone()),
+            published_at: tag.date.unwrap_or_else(Utc::now),
+            html_url: format!("{}/rev/{}", repo_url, tag.name),
+            body: None,
+            changes: Default::default(),
+            prerelease: false,
+            draft: false,
+            assets: vec![Asset {
+                name: format!("{}-{}.tar.gz", name, tag.name.replace('/', "-")),
+                url: format!("{}/archive/{}.tar.gz", repo_url, tag.name),
+                content_type: Some("application/gzip".to_string()),
+                size: 0,
+                download_count: 0,
+                sha256: None,
+                sri: None,
+                file_type: None,
+                type_label: None,
+                updated_at: None,
+                cid: None,
+            }],
+            source_tarball: None,
+            source_zipball: None,
+            version: None,
+            container_images: Vec::new(),
+            signature: None,
+            install: Vec::new(),
+            license: None,
+            body_truncated: false,
+            updated_at: None,
+            first_seen_at: None,
+            tag_name: tag.name,
+        })
+        .collect();
+
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+pub struct Hgweb;
+
+impl Provider for Hgweb {
+    const PREFIX: &'static str = "hg";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /hg/{host}/{repo_path}".to_string(),
+            )
+        })
+    }
+
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(&state.client, &repo.host, &repo.repo, &state.providers.hg).await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(&state.client, &repo.host, &repo.repo, &state.providers.hg).await
+    }
+}
@@ -0,0 +1,336 @@
+use super::{Asset, Provider, Release, Tag, authorize, base_url, classify_assets, sri_from_sha256};
+use crate::{
+    AppState, RepoPath,
+    config::{HostCredentials, ProviderLimits},
+    debug,
+    error::AppError,
+    routes,
+    upstream::UpstreamStatusError,
+    versions,
+};
+use anyhow::Result;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt, stream};
+use reqwest::Client;
+use serde::{Deserialize, de::DeserializeOwned};
+
+pub const HOST: &str = "huggingface.co";
+
+/// Owner prefix of dataset repos, as in their URLs: "datasets/{owner}"
+pub const DATASETS: &str = "datasets/";
+/// Tags dated at least, the highest versions; refs come without dates, so
+/// each one takes a request for its revision.
+const MIN_DATED_TAGS: usize = 100;
+/// Revision lookups running at once
+const REVISION_REQUESTS: usize = 4;
+/// Branch whose head stands in for a release in repos without tags
+const DEFAULT_BRANCH: &str = "main";
+/// Length of the commit ids naming those releases
+const SHORT_COMMIT: usize = 7;
+
+#[derive(Debug, Deserialize)]
+struct HfRefs {
+    #[serde(default)]
+    branches: Vec<HfRef>,
+    #[serde(default)]
+    tags: Vec<HfRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HfRef {
+    name: String,
+    #[serde(rename = "targetCommit")]
+    target_commit: String,
+}
+
+/// `/api/{kind}/{repo}/revision/{commit}`: the repo as of a commit
+#[derive(Debug, Deserialize)]
+struct HfRevision {
+    /// Date of the commit
+    #[serde(rename = "lastModified")]
+    last_modified: DateTime<Utc>,
+    /// The repo's files, with their LFS pointers when `blobs=true`
+    #[serde(default)]
+    siblings: Vec<HfSibling>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HfSibling {
+    rfilename: String,
+    lfs: Option<HfLfs>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HfLfs {
+    sha256: String,
+    size: u64,
+}
+
+/// The repo path of a page path: "owner/repo/tree/main" -> "owner/repo",
+/// "datasets/owner/repo/blob/main/README.md" -> "datasets/owner/repo".
+pub fn page_repo_path(path: &str) -> String {
+    let segments = if path.starts_with(DATASETS) { 3 } else { 2 };
+    path.split('/').take(segments).collect::<Vec<_>>().join("/")
+}
+
+/// "models/{owner}/{repo}" or "datasets/{owner}/{repo}", as the API names
+/// repos.
+fn api_repo(owner: &str, repo: &str) -> String {
+    match owner.strip_prefix(DATASETS) {
+        Some(owner) => format!("datasets/{}/{}", owner, repo),
+        None => format!("models/{}/{}", owner, repo),
+    }
+}
+
+async fn get_json<T: DeserializeOwned>(
+    client: &Client,
+    host: &str,
+    url: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<T> {
+    debug::request("GET", url);
+    let response = authorize(credentials, limits.request(client.get(url)))
+        .header("Accept", "application/json")
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if !response.status().is_success() {
+        // Without a token, the Hub answers 401 for repos that don't exist,
+        // as it does for private ones
+        let status = match response.status() {
+            reqwest::StatusCode::UNAUTHORIZED if credentials.is_none() => {
+                reqwest::StatusCode::NOT_FOUND
+            }
+            status => status,
+        };
+        return Err(UpstreamStatusError {
+            service: format!("Hugging Face Hub API ({})", host),
+            status,
+            rate_limit: None,
+        }
+        .into());
+    }
+    Ok(response.json().await?)
+}
+
+/// The Hub API of one repo.
+struct HfApi<'a> {
+    client: &'a Client,
+    host: &'a str,
+    /// "{base}/api/{kind}/{owner}/{repo}"
+    url: String,
+    limits: &'a ProviderLimits,
+    credentials: Option<&'a HostCredentials>,
+}
+
+impl<'a> HfApi<'a> {
+    fn new(
+        client: &'a Client,
+        host: &'a str,
+        owner: &str,
+        repo: &str,
+        limits: &'a ProviderLimits,
+        credentials: Option<&'a HostCredentials>,
+    ) -> Self {
+        Self {
+            client,
+            host,
+            url: format!("{}/api/{}", base_url(host), api_repo(owner, repo)),
+            limits,
+            credentials,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let url = format!("{}{}", self.url, path);
+        get_json(self.client, self.host, &url, self.limits, self.credentials).await
+    }
+
+    async fn refs(&self) -> Result<HfRefs> {
+        let refs: HfRefs = self.get("/refs").await?;
+        debug::parsed("tags", refs.tags.len());
+        Ok(refs)
+    }
+
+    /// The repo at `commit`, with the LFS pointers of its files if `blobs`.
+    async fn revision(&self, commit: &str, blobs: bool) -> Result<HfRevision> {
+        let query = if blobs { "?blobs=true" } else { "" };
+        self.get(&format!("/revision/{}{}", commit, query)).await
+    }
+
+    /// Tags with their commit dates, newest first. Only the 100 highest
+    /// versions, or `max_releases` if that is more, are dated and kept.
+    async fn dated_tags(&self, mut listed: Vec<HfRef>) -> Result<Vec<Tag>> {
+        listed.sort_by(|a, b| versions::compare_versions(&b.name, &a.name));
+        listed.truncate(MIN_DATED_TAGS.max(self.limits.max_releases.unwrap_or(0)));
+
+        let lookups: Vec<_> = listed
+            .iter()
+            .map(|r| self.revision(&r.target_commit, false))
+            .collect();
+        let revisions: Vec<HfRevision> = stream::iter(lookups)
+            .buffered(REVISION_REQUESTS)
+            .try_collect()
+            .await?;
+
+        let mut tags: Vec<Tag> = listed
+            .into_iter()
+            .zip(revisions)
+            .map(|(r, revision)| Tag {
+                name: r.name,
+                commit: Some(r.target_commit),
+                date: Some(revision.last_modified),
+            })
+            .collect();
+        // Stable, so tags of one commit stay in version order
+        tags.sort_by_key(|t| std::cmp::Reverse(t.date));
+        Ok(tags)
+    }
+}
+
+/// The LFS files of a revision, downloaded through `resolve/{commit}`; files
+/// in folders are named with their path, "/" turned into "-".
+fn lfs_assets(repo_url: &str, commit: &str, revision: HfRevision) -> Vec<Asset> {
+    revision
+        .siblings
+        .into_iter()
+        .filter_map(|file| {
+            let lfs = file.lfs?;
+            Some(Asset {
+                name: file.rfilename.replace('/', "-"),
+                url: format!("{}/resolve/{}/{}", repo_url, commit, file.rfilename),
+                content_type: None,
+                size: lfs.size,
+                download_count: 0,
+                sri: sri_from_sha256(&lfs.sha256),
+                sha256: Some(lfs.sha256),
+                file_type: None,
+                type_label: None,
+                updated_at: None,
+                cid: None,
+            })
+        })
+        .collect()
+}
+
+/// Each tag is a release; repos without tags have one, the head of `main`,
+/// named by its commit. The latest release has the LFS files of its
+/// revision as assets, as weights and data files are kept in LFS.
+pub async fn fetch_releases(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Release>> {
+    let api = HfApi::new(client, host, owner, repo, limits, credentials);
+    let refs = api.refs().await?;
+    let mut tags = api.dated_tags(refs.tags).await?;
+    if tags.is_empty()
+        && let Some(head) = refs.branches.into_iter().find(|b| b.name == DEFAULT_BRANCH)
+    {
+        tags.push(Tag {
+            name: head.target_commit.chars().take(SHORT_COMMIT).collect(),
+            commit: Some(head.target_commit),
+            date: None,
+        });
+    }
+    let Some(latest) = tags.first_mut() else {
+        return Ok(Vec::new());
+    };
+    let latest_commit = latest.commit.clone().unwrap_or_default();
+    let latest_revision = api.revision(&latest_commit, true).await?;
+    latest.date = Some(latest_revision.last_modified);
+
+    let repo_url = format!("{}/{}/{}", base_url(host), owner, repo);
+    let mut releases: Vec<Release> = tags
+        .into_iter()
+        .map(|tag| {
+            let commit = tag.commit.unwrap_or_default();
+            Release {
+                name: Some(tag.name.clone()),
+                published_at: tag.date.unwrap_or_else(Utc::now),
+                html_url: format!("{}/tree/{}", repo_url, commit),
+                body: None,
+                changes: Default::default(),
+                prerelease: false,
+                draft: false,
+                assets: Vec::new(),
+                source_tarball: None,
+                source_zipball: None,
+                version: None,
+                container_images: Vec::new(),
+                signature: None,
+                install: Vec::new(),
+                license: None,
+                body_truncated: false,
+                updated_at: None,
+                first_seen_at: None,
+                tag_name: tag.name,
+            }
+        })
+        .collect();
+    releases[0].assets = lfs_assets(&repo_url, &latest_commit, latest_revision);
+
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Tag>> {
+    let api = HfApi::new(client, host, owner, repo, limits, credentials);
+    let refs = api.refs().await?;
+    api.dated_tags(refs.tags).await
+}
+
+pub struct HuggingFace;
+
+impl Provider for HuggingFace {
+    const PREFIX: &'static str = "huggingface";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /huggingface/{owner}/{repo} or /huggingface/datasets/{owner}/{repo}"
+                    .to_string(),
+            )
+        })
+    }
+
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.huggingface,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.huggingface,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
+}
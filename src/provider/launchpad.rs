@@ -0,0 +1,253 @@
+use super::{Asset, Provider, Release, Tag, base_url, classify_assets};
+use crate::{
+    AppState, RepoPath, changelog, config::ProviderLimits, debug, error::AppError, routes,
+    upstream::UpstreamStatusError,
+};
+use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt, stream};
+use percent_encoding::percent_decode_str;
+use reqwest::Client;
+use serde::{Deserialize, de::DeserializeOwned};
+
+pub const HOST: &str = "launchpad.net";
+pub const API_HOST: &str = "api.launchpad.net";
+
+/// Release pages followed by default
+const DEFAULT_MAX_PAGES: usize = 10;
+/// File lists requested at once, one per release
+const FILE_REQUESTS: usize = 4;
+
+/// A page of a collection; `next_collection_link` is absent on the last one.
+#[derive(Debug, Deserialize)]
+struct Collection<T> {
+    entries: Vec<T>,
+    next_collection_link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LaunchpadRelease {
+    version: String,
+    /// e.g. "Bazaar 2.7.0 \"Final\""
+    title: Option<String>,
+    date_released: Option<DateTime<Utc>>,
+    date_created: DateTime<Utc>,
+    /// Page of the release on launchpad.net
+    web_link: String,
+    release_notes: Option<String>,
+    changelog: Option<String>,
+    files_collection_link: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LaunchpadFile {
+    /// ".../+file/{name}", the name percent-encoded
+    self_link: String,
+}
+
+/// An API link with the host swapped for `api_host`, so links in responses
+/// are followed on the host the first request went to.
+fn api_url(api_host: &str, link: &str) -> Result<String> {
+    let url = url::Url::parse(link).with_context(|| format!("Invalid API link {}", link))?;
+    Ok(match url.query() {
+        Some(query) => format!("{}{}?{}", base_url(api_host), url.path(), query),
+        None => format!("{}{}", base_url(api_host), url.path()),
+    })
+}
+
+async fn get_json<T: DeserializeOwned>(
+    client: &Client,
+    api_host: &str,
+    url: &str,
+    limits: &ProviderLimits,
+) -> Result<T> {
+    debug::request("GET", url);
+    let response = limits
+        .request(client.get(url))
+        .header("Accept", "application/json")
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("Launchpad API ({})", api_host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
+    }
+    Ok(response.json().await?)
+}
+
+/// A project's releases, newest first, following up to `max_pages` pages.
+async fn fetch_project_releases(
+    client: &Client,
+    api_host: &str,
+    project: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<LaunchpadRelease>> {
+    let mut url = limits.paged_url(
+        format!("{}/1.0/{}/releases", base_url(api_host), project),
+        "ws.size",
+    );
+    let mut releases = Vec::new();
+    for _ in 0..limits.max_pages.unwrap_or(DEFAULT_MAX_PAGES) {
+        let page: Collection<LaunchpadRelease> = get_json(client, api_host, &url, limits).await?;
+        releases.extend(page.entries);
+        match page.next_collection_link {
+            Some(next) => url = api_url(api_host, &next)?,
+            None => break,
+        }
+    }
+    debug::parsed("releases", releases.len());
+
+    // The API lists releases by series, not by date
+    releases.sort_by_key(|r| std::cmp::Reverse(r.date_released.unwrap_or(r.date_created)));
+    Ok(releases)
+}
+
+/// The release's files, downloaded from its page's `+download/` directory.
+async fn fetch_files(
+    client: &Client,
+    api_host: &str,
+    release: &LaunchpadRelease,
+    limits: &ProviderLimits,
+) -> Result<Vec<Asset>> {
+    let url = api_url(api_host, &release.files_collection_link)?;
+    let files: Collection<LaunchpadFile> = get_json(client, api_host, &url, limits).await?;
+    Ok(files
+        .entries
+        .into_iter()
+        .filter_map(|f| {
+            let (_, encoded) = f.self_link.rsplit_once("/+file/")?;
+            Some(Asset {
+                name: percent_decode_str(encoded).decode_utf8_lossy().into_owned(),
+                url: format!("{}/+download/{}", release.web_link, encoded),
+                content_type: None,
+                size: 0,
+                download_count: 0,
+                sha256: None,
+                sri: None,
+                file_type: None,
+                type_label: None,
+                updated_at: None,
+                cid: None,
+            })
+        })
+        .collect())
+}
+
+/// Releases with their files; release notes fall back to the changelog.
+pub async fn fetch_releases(
+    client: &Client,
+    api_host: &str,
+    project: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Release>> {
+    let mut lp_releases = fetch_project_releases(client, api_host, project, limits).await?;
+    if let Some(max) = limits.max_releases {
+        lp_releases.truncate(max);
+    }
+
+    let requests: Vec<_> = lp_releases
+        .iter()
+        .map(|r| fetch_files(client, api_host, r, limits))
+        .collect();
+    let files: Vec<Vec<Asset>> = stream::iter(requests)
+        .buffered(FILE_REQUESTS)
+        .try_collect()
+        .await?;
+
+    let mut releases: Vec<Release> = lp_releases
+        .into_iter()
+        .zip(files)
+        .map(|(r, assets)| {
+            let body = r
+                .release_notes
+                .filter(|n| !n.trim().is_empty())
+                .or(r.changelog)
+                .filter(|n| !n.trim().is_empty());
+            Release {
+                name: r.title.filter(|t| !t.trim().is_empty()),
+                published_at: r.date_released.unwrap_or(r.date_created),
+                html_url: r.web_link,
+                changes: body.as_deref().map(changelog::parse).unwrap_or_default(),
+                body,
+                prerelease: false,
+                draft: false,
+                assets,
+                source_tarball: None,
+                source_zipball: None,
+                version: None,
+                container_images: Vec::new(),
+                signature: None,
+                install: Vec::new(),
+                license: None,
+                body_truncated: false,
+                updated_at: None,
+                first_seen_at: None,
+                tag_name: r.version,
+            }
+        })
+        .collect();
+
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+/// Launchpad projects have no tags of their own; release versions stand in.
+pub async fn fetch_tags(
+    client: &Client,
+    api_host: &str,
+    project: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Tag>> {
+    let releases = fetch_project_releases(client, api_host, project, limits).await?;
+    Ok(releases
+        .into_iter()
+        .map(|r| Tag {
+            date: Some(r.date_released.unwrap_or(r.date_created)),
+            name: r.version,
+            commit: None,
+        })
+        .collect())
+}
+
+pub struct Launchpad;
+
+impl Provider for Launchpad {
+    const PREFIX: &'static str = "launchpad";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /launchpad/{project}".to_string(),
+            )
+        })
+    }
+
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            API_HOST,
+            &repo.repo,
+            &state.providers.launchpad,
+        )
+        .await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            API_HOST,
+            &repo.repo,
+            &state.providers.launchpad,
+        )
+        .await
+    }
+}
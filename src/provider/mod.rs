@@ -1,37 +1,1552 @@
+pub mod autoindex;
 pub mod cgit;
 pub mod forgejo;
 pub mod github;
+pub mod gitiles;
 pub mod gitlab;
+pub mod hgweb;
+pub mod huggingface;
+pub mod launchpad;
+pub mod pagure;
+pub mod snap;
+pub mod sourcehut;
 
+#[cfg(test)]
+mod conformance;
+
+use autoindex::Autoindex;
+use cgit::Cgit;
+use forgejo::{Codeberg, Forgejo, Gitea};
+use github::GitHub;
+use gitiles::Gitiles;
+use gitlab::GitLab;
+use hgweb::Hgweb;
+use huggingface::HuggingFace;
+use launchpad::Launchpad;
+use pagure::Pagure;
+use snap::Snap;
+use sourcehut::SourceHut;
+
+use crate::{
+    AppState, RepoPath, badge,
+    cache_control::{self, RouteKind},
+    calendar,
+    catalog::{self, CatalogRepo},
+    changelog::Changelog,
+    channels, checksums,
+    config::{AuthScheme, HostCredentials, ProviderLimits},
+    enrich,
+    error::{AppError, ErrorCode},
+    filetype, format_html,
+    install::{self, InstallCommand},
+    ipfs,
+    latest_names::LatestNames,
+    license::{self, License},
+    notes, pins, proxy,
+    publishers::SignatureCheck,
+    quota::ClientIp,
+    retention, rolling,
+    routes::{self, Target},
+    sbom, shedding, signing, since, subscriptions, translate, update_info,
+};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderValue, Method, StatusCode, header},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use base64::Engine;
 use chrono::{DateTime, Utc};
+use reqwest::RequestBuilder;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::io::AsyncReadExt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Asset {
     pub name: String,
     pub url: String,
     pub content_type: Option<String>,
     pub size: u64,
     pub download_count: u64,
+    /// Hex-encoded SHA-256 of the asset, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Subresource-integrity value (`sha256-<base64>`) matching `sha256`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sri: Option<String>,
+    /// File type id from `filetype::classify`, e.g. "appimage"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<String>,
+    /// Human-readable file type, e.g. "AppImage"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_label: Option<String>,
+    /// When upstream last replaced the file, where its API tells
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// IPFS CID of the file, once pinned after a download through the proxy
+    /// (`ipfs.rs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Release {
     pub tag_name: String,
     pub name: Option<String>,
     pub published_at: DateTime<Utc>,
     pub html_url: String,
     pub body: Option<String>,
+    /// Whether `body` was cut short (`notes.rs`); the full notes are served
+    /// by `/{tag}/notes`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub body_truncated: bool,
+    /// When the release's assets last changed under the same tag, as happens
+    /// with rolling tags like "nightly" (`rolling.rs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// When the release was first cached, for releases that appeared after
+    /// the repo's first fetch (`since.rs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_seen_at: Option<DateTime<Utc>>,
+    /// Sections parsed from `body` (added, fixed, breaking, ...)
+    #[serde(default, skip_serializing_if = "Changelog::is_empty")]
+    pub changes: Changelog,
     pub prerelease: bool,
     pub draft: bool,
     pub assets: Vec<Asset>,
     pub source_tarball: Option<String>,
     pub source_zipball: Option<String>,
+    /// Version from the tag name, per the repo's tag rules (`versions.rs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Container images published with the release, from the repo's image
+    /// templates (`images.rs`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub container_images: Vec<String>,
+    /// Outcome of checking the release's signature against the repo's
+    /// expected signing keys (`publishers.rs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureCheck>,
+    /// Install commands from the notes and README, on the latest release
+    /// only (`install.rs`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub install: Vec<InstallCommand>,
+    /// License and notice files among the assets, and on the latest release
+    /// the forge's repo license (`license.rs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Tag {
+    pub name: String,
+    /// Commit the tag points to
+    pub commit: Option<String>,
+    pub date: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Contents of a repo's tags.json, cached separately from releases.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CachedTags {
+    pub tags: Vec<Tag>,
+    pub cached_at: DateTime<Utc>,
+    pub repo_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CachedReleases {
     pub releases: Vec<Release>,
     pub cached_at: DateTime<Utc>,
     pub repo_path: String,
+    /// Route prefix of the provider, e.g. "gitlab"; missing in caches
+    /// written before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Whether the latest release is signed by one of the repo's expected
+    /// signing keys; only for repos that have some
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_publisher: Option<bool>,
+}
+
+/// Convert a hex SHA-256 digest into a subresource-integrity value.
+/// e.g., "e3b0c442...b855" -> "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+pub fn sri_from_sha256(hex: &str) -> Option<String> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(format!(
+        "sha256-{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// Fill in each asset's file type from its name and content type.
+pub fn classify_assets(releases: &mut [Release]) {
+    for asset in releases.iter_mut().flat_map(|r| r.assets.iter_mut()) {
+        let file_type = filetype::classify(&asset.name, asset.content_type.as_deref());
+        asset.file_type = Some(file_type.id().to_string());
+        asset.type_label = Some(file_type.label().to_string());
+    }
+}
+
+/// Releases standing in for the tags of a repo that publishes none, newest
+/// commit first: each tag is linked with `tag_url` and gets the source
+/// archives the forge generates for it, as (extension, URL) pairs from
+/// `archives`, named "{tag}.{extension}" like the sources of real releases.
+pub fn releases_from_tags(
+    mut tags: Vec<Tag>,
+    tag_url: impl Fn(&str) -> String,
+    archives: impl Fn(&str) -> Vec<(&'static str, String)>,
+) -> Vec<Release> {
+    // Stable, so tags of one commit keep the forge's order
+    tags.sort_by_key(|t| std::cmp::Reverse(t.date));
+    tags.into_iter()
+        .map(|tag| Release {
+            name: Some(tag.name.clone()),
+            published_at: tag.date.unwrap_or_else(Utc::now),
+            html_url: tag_url(&tag.name),
+            body: None,
+            changes: Default::default(),
+            prerelease: false,
+            draft: false,
+            assets: archives(&tag.name)
+                .into_iter()
+                .map(|(extension, url)| Asset {
+                    name: format!("{}.{}", tag.name.replace('/', "-"), extension),
+                    url,
+                    content_type: Some(
+                        match extension {
+                            "zip" => "application/zip",
+                            _ => "application/gzip",
+                        }
+                        .to_string(),
+                    ),
+                    size: 0,
+                    download_count: 0,
+                    sha256: None,
+                    sri: None,
+                    file_type: None,
+                    type_label: None,
+                    updated_at: None,
+                    cid: None,
+                })
+                .collect(),
+            source_tarball: None,
+            source_zipball: None,
+            version: None,
+            container_images: Vec::new(),
+            signature: None,
+            install: Vec::new(),
+            license: None,
+            body_truncated: false,
+            updated_at: None,
+            first_seen_at: None,
+            tag_name: tag.name,
+        })
+        .collect()
+}
+
+/// Base of every URL on a forge host: "https://{host}". Under test, loopback
+/// hosts are spoken to over plain HTTP, where the conformance suite serves
+/// its fixtures.
+pub fn base_url(host: &str) -> String {
+    if cfg!(test) && host.starts_with("127.0.0.1:") {
+        return format!("http://{}", host);
+    }
+    format!("https://{}", host)
+}
+
+/// Listing pages fetched when `max_pages` is not configured
+pub const DEFAULT_MAX_PAGES: usize = 10;
+
+/// Number of the last page, from the `rel="last"` link of a `Link` header as
+/// GitHub, GitLab and Forgejo send it:
+/// `<https://api.github.com/...?per_page=100&page=5>; rel="last"`. None when
+/// everything fit on one page.
+pub fn last_page(headers: &reqwest::header::HeaderMap) -> Option<usize> {
+    let links = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    let last = links.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|p| p.trim() == r#"rel="last""#)
+            .then(|| target.trim().trim_start_matches('<').trim_end_matches('>'))
+    })?;
+    let last = url::Url::parse(last).ok()?;
+    last.query_pairs()
+        .find(|(key, _)| key == "page")
+        .and_then(|(_, page)| page.parse().ok())
+}
+
+/// `url` with a `page` parameter added.
+pub fn page_url(url: &str, page: usize) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}page={}", url, separator, page)
+}
+
+/// Add a host's token, if one is configured, to an API request.
+pub fn authorize(credentials: Option<&HostCredentials>, request: RequestBuilder) -> RequestBuilder {
+    let Some(credentials) = credentials else {
+        return request;
+    };
+    match credentials.scheme {
+        AuthScheme::Bearer => request.bearer_auth(&credentials.token),
+        AuthScheme::Token => {
+            request.header("Authorization", format!("token {}", credentials.token))
+        }
+        AuthScheme::PrivateToken => request.header("PRIVATE-TOKEN", &credentials.token),
+    }
+}
+
+impl ProviderLimits {
+    /// Apply the request timeout, if one is set.
+    pub fn request(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.timeout_secs {
+            Some(secs) => request.timeout(Duration::from_secs(secs)),
+            None => request,
+        }
+    }
+
+    /// `url` with the page size as `param`, if one is set.
+    pub fn paged_url(&self, url: String, param: &str) -> String {
+        match self.page_size {
+            Some(size) => format!("{}?{}={}", url, param, size),
+            None => url,
+        }
+    }
+
+    /// Drop releases (newest first) and assets past the maxima.
+    pub fn truncate(&self, releases: &mut Vec<Release>) {
+        if let Some(max) = self.max_releases {
+            releases.truncate(max);
+        }
+        if let Some(max) = self.max_assets {
+            for release in releases.iter_mut() {
+                release.assets.truncate(max);
+            }
+        }
+    }
+
+    /// The first `max_html_bytes` of `html`, cut at a character boundary.
+    pub fn truncate_html<'a>(&self, html: &'a str) -> &'a str {
+        match self.max_html_bytes {
+            Some(max) if html.len() > max => {
+                let end = (0..=max)
+                    .rev()
+                    .find(|&i| html.is_char_boundary(i))
+                    .unwrap_or(0);
+                &html[..end]
+            }
+            _ => html,
+        }
+    }
+}
+
+/// Query parameters accepted on release pages.
+#[derive(Debug, Default, Deserialize)]
+pub struct ReleaseQuery {
+    pub page: Option<usize>,
+    /// Version the client is running, for `/+check`
+    pub current: Option<String>,
+    /// On `latest*` redirects, how many older releases to search when the
+    /// newest has no matching asset
+    pub fallback: Option<usize>,
+    /// On `latest` redirects, the extension to pick, e.g. "tar.xz" for a cgit
+    /// snapshot; on `/pin`, the pin format
+    pub format: Option<String>,
+    /// Monorepo component whose releases to show, e.g. "cli" for "cli-v1.2.0"
+    pub component: Option<String>,
+    /// On `badge.svg`, the deployed version to compare with the latest release
+    pub compare: Option<String>,
+    /// On `+json`, how long to hold the request while the repo is being
+    /// fetched, e.g. "30s"
+    pub wait: Option<String>,
+    /// On release pages and `+json`, the language to translate notes into
+    pub lang: Option<String>,
+    /// On `latest*` redirects, the release channel to pick from, e.g.
+    /// "nightly"
+    pub channel: Option<String>,
+    /// On `/releases`, the RFC 3339 time after which releases count as new
+    pub since: Option<String>,
+}
+
+/// Most releases a `latest*` redirect may fall back through.
+const MAX_FALLBACK: usize = 10;
+
+/// Find the asset a `latest*` name refers to in the newest release, or in
+/// one of the `fallback` releases before it.
+pub fn find_latest_asset<'a>(
+    latest_names: &LatestNames,
+    releases: &'a [Release],
+    latest_name: &str,
+    fallback: Option<usize>,
+) -> Option<&'a Asset> {
+    let searched = 1 + fallback.unwrap_or(0).min(MAX_FALLBACK);
+    releases
+        .iter()
+        .take(searched)
+        .flat_map(|r| r.assets.iter())
+        .find(|asset| latest_names.rename(&asset.name) == latest_name)
+}
+
+/// Redirect to a `latest*` asset. Answers to HEAD also carry the asset's
+/// size as `Content-Length`, when known, so scripts can check for a new
+/// artifact without following the download.
+pub fn latest_redirect(state: &AppState, method: &Method, asset: &Asset) -> Response {
+    let mut redirect = Redirect::temporary(&asset.url).into_response();
+    if method == Method::HEAD && asset.size > 0 {
+        redirect
+            .headers_mut()
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(asset.size));
+    }
+    state.cache_policy.apply(RouteKind::Latest, redirect)
+}
+
+/// Narrow releases to those of a monorepo component (`?component=`);
+/// unchanged without one.
+pub fn component_releases(
+    state: &AppState,
+    repo: &RepoPath,
+    releases: Vec<Release>,
+    component: Option<&str>,
+) -> Result<Vec<Release>, AppError> {
+    let Some(component) = component else {
+        return Ok(releases);
+    };
+    let releases = state
+        .tag_rules
+        .component_releases(&repo.cache_key(), component, releases);
+    if releases.is_empty() {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("No releases found for component '{}'", component),
+        ));
+    }
+    Ok(releases)
+}
+
+fn read_cached(state: &AppState, repo: &RepoPath) -> Result<CachedReleases, AppError> {
+    state
+        .cache
+        .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?
+        .ok_or(AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to read cached releases".to_string(),
+        ))
+}
+
+/// Render page `page` of a repo's releases, or of one of its components,
+/// from the cached JSON, with notes translated into `lang` if given. Page 1
+/// of the whole repo is pre-rendered to index.html when the cache is written.
+pub async fn render_cached_page(
+    state: &AppState,
+    repo: &RepoPath,
+    route_prefix: &str,
+    page: usize,
+    filter: &format_html::PageFilter<'_>,
+    client: ClientIp,
+) -> Result<String, AppError> {
+    let cached = read_cached(state, repo)?;
+    let mut releases = component_releases(state, repo, cached.releases, filter.component)?;
+
+    if page > format_html::page_count(releases.len(), state.render.page_size) {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("Page {} not found", page),
+        ));
+    }
+    if let Some(lang) = filter.lang {
+        // Only the notes shown on this page
+        let start = (page - 1) * state.render.page_size;
+        let end = (start + state.render.page_size).min(releases.len());
+        translate::translate_releases(state, repo, &mut releases[start..end], lang, client).await?;
+    }
+
+    Ok(format_html::format_filtered_releases_html(
+        &releases,
+        &cached.repo_path,
+        route_prefix,
+        Some(cached.cached_at),
+        &state.render,
+        page,
+        filter,
+    ))
+}
+
+/// Error for a repo with nothing cached yet: `pending` while a background
+/// fetch is running, `not_cached` otherwise.
+pub fn not_cached(state: &AppState, repo: &RepoPath) -> AppError {
+    if state.fetches.is_pending(&repo.cache_key()) {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            "Repository is being fetched; try again shortly".to_string(),
+        )
+        .with_code(ErrorCode::Pending)
+    } else {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            "No cached data available".to_string(),
+        )
+        .with_code(ErrorCode::NotCached)
+    }
+}
+
+/// Seconds API clients are asked to wait before polling a pending fetch
+const PENDING_RETRY_AFTER_SECS: u64 = 2;
+
+#[derive(Serialize)]
+struct PendingBody<'a> {
+    status: &'static str,
+    repo: &'a str,
+    retry_after: u64,
+}
+
+/// `202 Accepted` with `Retry-After` for an API request whose repo is still
+/// being fetched, so clients can poll instead of parsing the HTML page.
+pub fn pending_response(state: &AppState, repo: &RepoPath) -> Result<Response, AppError> {
+    shedding::set_retry_after(PENDING_RETRY_AFTER_SECS);
+    let body = serde_json::to_string_pretty(&PendingBody {
+        status: "pending",
+        repo: &repo.cache_key(),
+        retry_after: PENDING_RETRY_AFTER_SECS,
+    })
+    .map_err(AppError::internal)?;
+    Ok(state.cache_policy.apply(
+        RouteKind::Transient,
+        signing::json_response(state, StatusCode::ACCEPTED, body),
+    ))
+}
+
+/// Longest `?wait=` a client may ask for.
+const MAX_WAIT_SECS: u64 = 60;
+
+/// How often a waiting request checks whether the fetch is done.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Parse `?wait=`: seconds, optionally suffixed with `s` or `m` ("30s",
+/// "1m"), capped at `MAX_WAIT_SECS`.
+fn parse_wait(wait: &str) -> Result<Duration, AppError> {
+    let wait = wait.trim();
+    let (number, unit) = match wait.strip_suffix('m') {
+        Some(minutes) => (minutes, 60),
+        None => (wait.strip_suffix('s').unwrap_or(wait), 1),
+    };
+    let secs: u64 = number.parse().map_err(|_| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid wait '{}', expected e.g. 30s", wait),
+        )
+    })?;
+    Ok(Duration::from_secs(
+        secs.saturating_mul(unit).min(MAX_WAIT_SECS),
+    ))
+}
+
+/// For `+json?wait=`: hold the request until the repo's pending fetch is
+/// done or the wait is over, then return the freshly cached JSON. `None`
+/// when no wait was asked for or the fetch is still running.
+pub async fn wait_for_fetch(
+    state: &AppState,
+    repo: &RepoPath,
+    wait: Option<&str>,
+) -> Result<Option<String>, AppError> {
+    let Some(wait) = wait else {
+        return Ok(None);
+    };
+    let deadline = Instant::now() + parse_wait(wait)?;
+    let cache_key = repo.cache_key();
+    while state.fetches.is_pending(&cache_key) && Instant::now() < deadline {
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+    if let Some(error) = state.fetches.failure(&cache_key) {
+        return Err(AppError::new(StatusCode::BAD_GATEWAY, error));
+    }
+    state
+        .cache
+        .read_json_raw(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)
+}
+
+/// `+json?component=`, `+json?lang=` and `/releases?since=`: the cached
+/// JSON narrowed to one component and/or to releases new since a time,
+/// and/or with translated notes.
+pub async fn filtered_json_response(
+    state: &AppState,
+    repo: &RepoPath,
+    query: &ReleaseQuery,
+    new_since: Option<DateTime<Utc>>,
+    client: ClientIp,
+) -> Result<Response, AppError> {
+    let mut cached = state
+        .cache
+        .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?
+        .ok_or_else(|| not_cached(state, repo))?;
+    cached.releases = component_releases(state, repo, cached.releases, query.component.as_deref())?;
+    if let Some(new_since) = new_since {
+        cached.releases = since::releases_since(cached.releases, new_since);
+    }
+    if let Some(lang) = &query.lang {
+        translate::translate_releases(state, repo, &mut cached.releases, lang, client).await?;
+    }
+    let body = serde_json::to_string_pretty(&cached).map_err(AppError::internal)?;
+    Ok(state.cache_policy.apply(
+        RouteKind::Json,
+        signing::json_response(state, StatusCode::OK, body),
+    ))
+}
+
+/// Releases newer than the client's version, as returned by `/+check`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UpdateCheck {
+    pub current: String,
+    pub latest: Option<String>,
+    pub update_available: bool,
+    pub releases_behind: usize,
+    /// Whether any newer release lists breaking changes
+    pub breaking: bool,
+    /// Whether any newer release lists security fixes
+    pub security: bool,
+    pub breaking_changes: Vec<String>,
+    pub security_fixes: Vec<String>,
+    pub newer_releases: Vec<NewerRelease>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct NewerRelease {
+    pub tag_name: String,
+    pub published_at: DateTime<Utc>,
+    pub html_url: String,
+    pub breaking: bool,
+    pub security: bool,
+}
+
+/// Compare tags ignoring a leading "v", so "1.2.0" matches "v1.2.0". The
+/// client may also send the release's normalized version.
+fn same_version(release: &Release, current: &str) -> bool {
+    let strip = |s: &str| s.trim().trim_start_matches(['v', 'V']).to_string();
+    strip(&release.tag_name) == strip(current)
+        || release
+            .version
+            .as_deref()
+            .is_some_and(|v| v == strip(current))
+}
+
+/// Answer `/+check?current=<tag>` from a repo's releases (newest first).
+/// Drafts and prereleases are not offered as updates.
+pub fn update_check_response(
+    state: &AppState,
+    releases: &[Release],
+    current: Option<&str>,
+) -> Result<Response, AppError> {
+    let current = current
+        .filter(|c| !c.trim().is_empty())
+        .ok_or(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Missing ?current=<version> parameter".to_string(),
+        ))?;
+    let position = releases
+        .iter()
+        .position(|r| same_version(r, current))
+        .ok_or(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("No release matching '{}' found", current),
+        ))?;
+
+    let newer: Vec<&Release> = releases[..position]
+        .iter()
+        .filter(|r| !r.draft && !r.prerelease)
+        .collect();
+
+    let check = UpdateCheck {
+        current: current.to_string(),
+        latest: newer
+            .first()
+            .map(|r| r.tag_name.clone())
+            .or_else(|| Some(releases[position].tag_name.clone())),
+        update_available: !newer.is_empty(),
+        releases_behind: newer.len(),
+        breaking: newer.iter().any(|r| r.changes.has_breaking()),
+        security: newer.iter().any(|r| r.changes.has_security()),
+        breaking_changes: newer
+            .iter()
+            .flat_map(|r| r.changes.breaking.iter().cloned())
+            .collect(),
+        security_fixes: newer
+            .iter()
+            .flat_map(|r| r.changes.security.iter().cloned())
+            .collect(),
+        newer_releases: newer
+            .iter()
+            .map(|r| NewerRelease {
+                tag_name: r.tag_name.clone(),
+                published_at: r.published_at,
+                html_url: r.html_url.clone(),
+                breaking: r.changes.has_breaking(),
+                security: r.changes.has_security(),
+            })
+            .collect(),
+    };
+
+    let body = serde_json::to_string_pretty(&check).map_err(AppError::internal)?;
+    Ok(state.cache_policy.apply(
+        RouteKind::Json,
+        signing::json_response(state, StatusCode::OK, body),
+    ))
+}
+
+/// Serve `/{tag}/SHA256SUMS` (GNU `sha256sum` format) for the release's assets
+/// with a known hash, or with `signature` its `.sig`: the base64 ed25519
+/// signature of that file by the instance's signing key.
+pub fn sha256sums_response(
+    state: &AppState,
+    releases: &[Release],
+    tag: &str,
+    signature: bool,
+) -> Result<Response, AppError> {
+    let release = releases
+        .iter()
+        .find(|r| r.tag_name == tag)
+        .ok_or(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("No release tagged '{}' found", tag),
+        ))?;
+
+    let sums: String = release
+        .assets
+        .iter()
+        .filter_map(|a| Some(format!("{}  {}\n", a.sha256.as_ref()?, a.name)))
+        .collect();
+    if sums.is_empty() {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("No asset hashes known for release '{}'", tag),
+        ));
+    }
+
+    let body = if signature {
+        let signer = state.signer.as_ref().ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                "Response signing is not enabled on this instance".to_string(),
+            )
+            .with_code(ErrorCode::Disabled)
+        })?;
+        format!("{}\n", signer.sign(sums.as_bytes()))
+    } else {
+        sums
+    };
+
+    let response = ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response();
+    Ok(state.cache_policy.apply(RouteKind::Json, response))
+}
+
+/// Repo route naming a provider this instance doesn't know.
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown provider in repo route: {0}")]
+pub struct UnknownProviderError(pub String);
+
+/// Route prefixes of all providers
+pub const PREFIXES: [&str; 14] = [
+    GitHub::PREFIX,
+    GitLab::PREFIX,
+    Forgejo::PREFIX,
+    Gitea::PREFIX,
+    Codeberg::PREFIX,
+    SourceHut::PREFIX,
+    Launchpad::PREFIX,
+    Snap::PREFIX,
+    HuggingFace::PREFIX,
+    Pagure::PREFIX,
+    Gitiles::PREFIX,
+    Hgweb::PREFIX,
+    Cgit::PREFIX,
+    Autoindex::PREFIX,
+];
+
+/// Resolve a repo route as used in URLs, e.g. "github/owner/repo",
+/// "forgejo/codeberg.org/owner/repo" or "cgit/git.kernel.org/path/repo.git",
+/// into its provider prefix and repo.
+pub fn parse_route(route: &str) -> anyhow::Result<(&'static str, RepoPath)> {
+    let route = route.trim_matches('/');
+    let (prefix, path) = split_prefix(route)?;
+    let repo = routes::split_repo(prefix, path)
+        .ok_or_else(|| anyhow::anyhow!("Invalid repo route: {}", route))?;
+    Ok((prefix, repo))
+}
+
+/// Split a route into its provider prefix and the path after it.
+pub fn split_prefix(route: &str) -> anyhow::Result<(&'static str, &str)> {
+    let (prefix, path) = route
+        .split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("Invalid repo route: {}", route))?;
+    let prefix = PREFIXES
+        .into_iter()
+        .find(|p| *p == prefix)
+        .ok_or_else(|| UnknownProviderError(route.to_string()))?;
+    Ok((prefix, path))
+}
+
+/// Resolve a repo given as a route ("github/owner/repo"), a web URL
+/// ("https://codeberg.org/owner/repo/releases") or "host/owner/repo". Hosts
+/// other than github.com and gitlab.com are taken to be Forgejo instances,
+/// unless the URL has GitLab's "/-/" page separator.
+pub fn resolve_repo(input: &str) -> anyhow::Result<(&'static str, RepoPath)> {
+    let input = input.trim();
+    let input = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+        .unwrap_or(input)
+        .trim_matches('/');
+    let Some((host, path)) = input.split_once('/').filter(|(host, _)| host.contains('.')) else {
+        return parse_route(input);
+    };
+
+    // Drop trailing pages like "/releases" or "/tree/main"
+    let owner_repo = |path: &str| path.splitn(3, '/').take(2).collect::<Vec<_>>().join("/");
+    let route = match host {
+        "github.com" => format!("github/{}", owner_repo(path)),
+        sourcehut::HOST => format!("sourcehut/{}", owner_repo(path)),
+        launchpad::HOST => format!("launchpad/{}", path.split('/').next().unwrap_or(path)),
+        snap::HOST => format!("snap/{}", path.split('/').next().unwrap_or(path)),
+        huggingface::HOST => format!("huggingface/{}", huggingface::page_repo_path(path)),
+        pagure::HOST => format!("pagure/{}/{}", host, path.split('/').next().unwrap_or(path)),
+        _ if host.starts_with(hgweb::HOST_PREFIX) => {
+            format!("hg/{}/{}", host, hgweb::page_repo_path(path))
+        }
+        // Gitiles pages start at "/+", e.g. "/+refs" or "/+/refs/heads/main"
+        _ if host.ends_with(gitiles::HOST_SUFFIX) || path.contains("/+") => {
+            format!(
+                "gitiles/{}/{}",
+                host,
+                path.split("/+").next().unwrap_or(path)
+            )
+        }
+        // GitLab repos can sit in subgroups; pages start after "/-/"
+        "gitlab.com" => format!("gitlab/{}", path.split("/-/").next().unwrap_or(path)),
+        _ if path.contains("/-/") => {
+            format!(
+                "gitlab/{}/{}",
+                host,
+                path.split("/-/").next().unwrap_or(path)
+            )
+        }
+        _ => format!("forgejo/{}/{}", host, owner_repo(path)),
+    };
+    parse_route(route.trim_end_matches(".git"))
+}
+
+/// Route of a repo's release page, e.g. "/forgejo/codeberg.org/owner/repo".
+/// Self-hosted GitLab repos keep their host, like Forgejo ones.
+pub fn repo_route(prefix: &str, repo: &RepoPath) -> String {
+    match prefix {
+        "github" | "codeberg" | "sourcehut" | "huggingface" => {
+            format!("/{}/{}/{}", prefix, repo.owner, repo.repo)
+        }
+        "gitlab" => format!("/gitlab/{}", gitlab::route_path(&repo.cache_key())),
+        "cgit" | "gitiles" | "hg" | "autoindex" => {
+            format!("/{}/{}/{}", prefix, repo.host, repo.repo)
+        }
+        "pagure" if repo.owner.is_empty() => format!("/pagure/{}/{}", repo.host, repo.repo),
+        "launchpad" | "snap" => format!("/{}/{}", prefix, repo.repo),
+        _ => format!("/{}/{}/{}/{}", prefix, repo.host, repo.owner, repo.repo),
+    }
+}
+
+/// A forge releases are fetched from. Each provider is a unit struct
+/// implementing this trait; routing `/{prefix}/*path` to [`handler`] gives it
+/// release pages, `latest*` redirects and every API suffix, and the release
+/// fetch gets the same post-processing and caching as every other forge.
+pub trait Provider: Send + Sync + 'static {
+    /// Route prefix, e.g. "github" for `/github/...`
+    const PREFIX: &'static str;
+
+    /// Parse the part of a route after the prefix into a repo.
+    fn parse_path(path: &str) -> Result<RepoPath, AppError>;
+
+    /// A repo's releases, newest first, with assets classified.
+    fn fetch_releases(
+        state: &AppState,
+        repo: &RepoPath,
+    ) -> impl Future<Output = anyhow::Result<Vec<Release>>> + Send;
+
+    fn fetch_tags(
+        state: &AppState,
+        repo: &RepoPath,
+    ) -> impl Future<Output = anyhow::Result<Vec<Tag>>> + Send;
+
+    /// Whether `/{prefix}/{host}` lists the repos of an instance; an error
+    /// rejects the request.
+    fn has_catalog(_state: &AppState, _host: &str) -> Result<bool, AppError> {
+        Ok(false)
+    }
+
+    /// The repos of the instance at `host`, for providers with a catalog.
+    fn fetch_catalog(
+        _state: &AppState,
+        _host: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<CatalogRepo>>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// The repo's README, scanned for install commands; `None` when the repo
+    /// has none or the provider can't get it.
+    fn fetch_readme(
+        _state: &AppState,
+        _repo: &RepoPath,
+    ) -> impl Future<Output = anyhow::Result<Option<String>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The repo's license as the forge reports it, without `files`; `None`
+    /// when it reports none or the provider can't ask.
+    fn fetch_license(
+        _state: &AppState,
+        _repo: &RepoPath,
+    ) -> impl Future<Output = anyhow::Result<Option<License>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The latest release for `latest*` redirects, when the provider can get
+    /// it cheaper than the full release list; `None` uses the full list.
+    fn latest_release(
+        _state: &AppState,
+        _repo: &RepoPath,
+        _query: &ReleaseQuery,
+        _client: ClientIp,
+    ) -> impl Future<Output = Result<Option<Release>, AppError>> + Send {
+        async { Ok(None) }
+    }
+}
+
+/// Route handler for `/{prefix}/*path` of provider `P`.
+pub async fn handler<P: Provider>(
+    method: Method,
+    Path(path): Path<String>,
+    Query(query): Query<ReleaseQuery>,
+    client: ClientIp,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, AppError> {
+    serve::<P>(&state, &method, &path, &query, client)
+        .await
+        .map_err(|e| AppError::from_provider(P::PREFIX, &path, e))
+}
+
+async fn serve<P: Provider>(
+    state: &Arc<AppState>,
+    method: &Method,
+    path: &str,
+    query: &ReleaseQuery,
+    client: ClientIp,
+) -> Result<Response, AppError> {
+    // `/{prefix}/{host}` lists the instance's repos, where supported
+    let host = path.trim_end_matches('/');
+    if !host.is_empty() && !host.contains('/') && P::has_catalog(state, host)? {
+        return catalog::catalog_response(
+            state,
+            P::PREFIX,
+            host,
+            query.page.unwrap_or(1).max(1),
+            client,
+            P::fetch_catalog(state, host),
+        )
+        .await;
+    }
+
+    let routes::Route { repo: path, target } = routes::parse(P::PREFIX, path);
+    if let Target::Latest(latest_name) = target {
+        return latest_response::<P>(state, method, path, latest_name, query, client).await;
+    }
+
+    let repo = P::parse_path(path)?;
+    let cache_key = repo.cache_key();
+    let route = format!("/{}/{}", P::PREFIX, path);
+    state.stats.record(&route);
+
+    if let Target::Sha256Sums { tag, signature } = target {
+        let releases = fetch_blocking::<P>(state, &repo, client).await?;
+        return sha256sums_response(state, &releases, tag, signature);
+    }
+
+    if let Target::Sbom(tag) = target {
+        let releases = fetch_blocking::<P>(state, &repo, client).await?;
+        return sbom::sbom_response(state, &repo, &releases, tag, client).await;
+    }
+
+    if let Target::Channel { channel, feed } = target {
+        let releases = component_releases(
+            state,
+            &repo,
+            fetch_blocking::<P>(state, &repo, client).await?,
+            query.component.as_deref(),
+        )?;
+        return channels::channel_response(state, &repo, P::PREFIX, releases, channel, feed, query);
+    }
+
+    if let Target::Notes { tag, markdown } = target {
+        let releases = fetch_blocking::<P>(state, &repo, client).await?;
+        return notes::notes_response(state, &repo, P::PREFIX, &releases, tag, markdown);
+    }
+
+    if target == Target::Tags {
+        return tags_response(state, &repo, client, P::fetch_tags(state, &repo)).await;
+    }
+
+    if matches!(
+        target,
+        Target::Check | Target::Badge | Target::Calendar | Target::UpdateInfo | Target::Pin
+    ) {
+        let releases = component_releases(
+            state,
+            &repo,
+            fetch_blocking::<P>(state, &repo, client).await?,
+            query.component.as_deref(),
+        )?;
+        return match target {
+            Target::Check => update_check_response(state, &releases, query.current.as_deref()),
+            Target::Badge => {
+                badge::badge_response(state, &repo, &releases, query.compare.as_deref())
+            }
+            Target::UpdateInfo => update_info::update_info_response(
+                state,
+                &route,
+                &cache_key,
+                &releases,
+                query.component.as_deref(),
+            ),
+            Target::Pin => {
+                pins::pin_response(state, P::PREFIX, &repo, &releases, query.format.as_deref())
+            }
+            _ => Ok(calendar::repo_ics_response(&route, &releases)),
+        };
+    }
+
+    if matches!(target, Target::Json | Target::Releases) {
+        // Checked before fetching, so a typo doesn't cost a fetch
+        let new_since = match target {
+            Target::Releases => since::parse_since(query.since.as_deref())?,
+            _ => None,
+        };
+        let cached = state
+            .cache
+            .read_json_raw(&repo.host, &repo.owner, &repo.repo)
+            .map_err(AppError::internal)?;
+        let hit = cached.is_some();
+        let json_content = match cached {
+            Some(json_content) => json_content,
+            None => {
+                if let FetchResult::Error(err) = get_or_spawn_fetch::<P>(state, &repo, client)? {
+                    return Err(AppError::new(StatusCode::BAD_GATEWAY, err));
+                }
+                match wait_for_fetch(state, &repo, query.wait.as_deref()).await? {
+                    Some(json_content) => json_content,
+                    None => return pending_response(state, &repo),
+                }
+            }
+        };
+        if new_since.is_some() || query.component.is_some() || query.lang.is_some() {
+            return filtered_json_response(state, &repo, query, new_since, client)
+                .await
+                .map(|response| cache_control::with_cache_status(response, hit));
+        }
+        let response = signing::json_response(state, StatusCode::OK, json_content);
+        let response = state.cache_policy.apply(RouteKind::Json, response);
+        return Ok(cache_control::with_cache_status(response, hit));
+    }
+
+    match get_or_spawn_fetch::<P>(state, &repo, client)? {
+        FetchResult::Cached => {
+            if query.component.is_some()
+                || query.lang.is_some()
+                || query.page.is_some_and(|p| p > 1)
+            {
+                let page = query.page.unwrap_or(1).max(1);
+                let filter = format_html::PageFilter {
+                    component: query.component.as_deref(),
+                    lang: query.lang.as_deref(),
+                    channel: None,
+                };
+                let html =
+                    render_cached_page(state, &repo, P::PREFIX, page, &filter, client).await?;
+                let response = state
+                    .cache_policy
+                    .apply(RouteKind::Html, Html(html).into_response());
+                return Ok(cache_control::with_cache_status(response, true));
+            }
+            let file = state
+                .cache
+                .open_html(&repo.host, &repo.owner, &repo.repo)
+                .await
+                .map_err(AppError::internal)?
+                .ok_or_else(|| AppError::internal("Failed to read cached HTML"))?;
+            let response = (
+                [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+                Body::from_stream(file_chunks(file)),
+            )
+                .into_response();
+            let response = state.cache_policy.apply(RouteKind::Html, response);
+            Ok(cache_control::with_cache_status(response, true))
+        }
+        FetchResult::Processing => {
+            let html = format_html::format_processing_html(&cache_key, P::PREFIX);
+            let response = state
+                .cache_policy
+                .apply(RouteKind::Transient, Html(html).into_response());
+            Ok(cache_control::with_cache_status(response, false))
+        }
+        FetchResult::Error(err) => {
+            let html = format_html::format_error_html(&cache_key, &err, P::PREFIX);
+            Ok(state
+                .cache_policy
+                .apply(RouteKind::Transient, Html(html).into_response()))
+        }
+    }
+}
+
+/// `{repo}/latest*`: redirect to the latest release's matching asset.
+/// `latest?format=tar.xz` is the same as `latest.tar.xz`.
+async fn latest_response<P: Provider>(
+    state: &Arc<AppState>,
+    method: &Method,
+    repo_part: &str,
+    latest_name: &str,
+    query: &ReleaseQuery,
+    client: ClientIp,
+) -> Result<Response, AppError> {
+    let repo = P::parse_path(repo_part)?;
+    state.stats.record(&format!("/{}/{}", P::PREFIX, repo_part));
+    let latest_name = match &query.format {
+        Some(format) => format!("latest.{}", format.trim_start_matches('.')),
+        None => latest_name.to_string(),
+    };
+
+    if let Some(release) = P::latest_release(state, &repo, query, client).await?
+        && let Some(asset) = find_latest_asset(
+            &state.render.latest_names,
+            std::slice::from_ref(&release),
+            &latest_name,
+            None,
+        )
+    {
+        return Ok(latest_redirect(state, method, asset));
+    }
+    let releases = component_releases(
+        state,
+        &repo,
+        fetch_blocking::<P>(state, &repo, client).await?,
+        query.component.as_deref(),
+    )?;
+    let releases = channels::query_releases(state, &repo, releases, query.channel.as_deref())?;
+    if let Some(asset) = find_latest_asset(
+        &state.render.latest_names,
+        &releases,
+        &latest_name,
+        query.fallback,
+    ) {
+        return Ok(latest_redirect(state, method, asset));
+    }
+    Err(AppError::new(
+        StatusCode::NOT_FOUND,
+        format!("No asset matching '{}' found", latest_name),
+    ))
+}
+
+/// A file read in chunks, for streaming responses.
+pub(crate) fn file_chunks(
+    file: tokio::fs::File,
+) -> impl futures_util::Stream<Item = std::io::Result<Vec<u8>>> + Send {
+    const CHUNK_SIZE: usize = 64 * 1024;
+    futures_util::stream::try_unfold(file, |mut file| async move {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let read = file.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        chunk.truncate(read);
+        Ok(Some((chunk, file)))
+    })
+}
+
+pub enum FetchResult {
+    Cached,
+    Processing,
+    Error(String),
+}
+
+/// Whether a repo's releases are cached, and if not, start fetching them in
+/// the background unless a fetch is already running or has failed.
+pub fn get_or_spawn_fetch<P: Provider>(
+    state: &Arc<AppState>,
+    repo: &RepoPath,
+    client: ClientIp,
+) -> Result<FetchResult, AppError> {
+    if let Ok(Some(cached_at)) = state
+        .cache
+        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
+        && !state
+            .cache
+            .is_repo_expired(&repo.host, &repo.owner, &repo.repo, cached_at)
+    {
+        return Ok(FetchResult::Cached);
+    }
+
+    let cache_key = repo.cache_key();
+
+    if let Some(error) = state.fetches.failure(&cache_key) {
+        return Ok(FetchResult::Error(error));
+    }
+
+    if state.fetches.is_pending(&cache_key) {
+        return Ok(FetchResult::Processing);
+    }
+
+    shedding::admit_fetch(state, client)?;
+    state.fetches.start(P::PREFIX, repo);
+
+    let state = state.clone();
+    let repo = repo.clone();
+    tokio::spawn(async move {
+        let result = cache_releases::<P>(&state, &repo).await;
+        state
+            .fetches
+            .finish(P::PREFIX, &repo, result.err().map(|e| e.to_string()));
+    });
+
+    Ok(FetchResult::Processing)
+}
+
+/// Fetch a repo's releases, fill in asset metadata and run the post-fetch
+/// steps with the repo's README and license. Returns the releases and those
+/// subscribers haven't heard of, to notify once the releases are cached.
+async fn fetch_processed<P: Provider>(
+    state: &AppState,
+    repo: &RepoPath,
+) -> anyhow::Result<(Vec<Release>, Vec<Release>)> {
+    let mut releases = state
+        .upstream
+        .track(&repo.host, P::fetch_releases(state, repo))
+        .await?;
+    enrich::fill_asset_metadata(state, &mut releases).await;
+    // A missing README only costs its commands
+    let readme = async {
+        let readme = P::fetch_readme(state, repo).await.ok().flatten();
+        readme.map_or_else(Vec::new, |r| {
+            install::extract(&r, install::InstallSource::Readme)
+        })
+    };
+    let forge_license = async { P::fetch_license(state, repo).await.ok().flatten() };
+    let news =
+        process_releases(state, P::PREFIX, repo, &mut releases, readme, forge_license).await?;
+    Ok((releases, news))
+}
+
+/// The steps between getting a repo's releases and caching them, for
+/// fetches and for releases a federation peer fetched: URL rewrites,
+/// recorded hashes, asset retention, recorded CIDs, tag rules and container
+/// images, in that order, then sort assets, check signatures, detect
+/// replaced assets, set install commands and licenses and truncate long
+/// notes. `readme` gives the README's install commands and `forge_license`
+/// the forge's license; they are only awaited when the releases changed.
+/// Returns the releases subscribers haven't heard of.
+async fn process_releases(
+    state: &AppState,
+    prefix: &'static str,
+    repo: &RepoPath,
+    releases: &mut [Release],
+    readme: impl Future<Output = Vec<InstallCommand>>,
+    forge_license: impl Future<Output = Option<License>>,
+) -> anyhow::Result<Vec<Release>> {
+    let cache_key = repo.cache_key();
+    state.url_rewriter.apply(releases);
+    proxy::apply_recorded_hashes(state, repo, releases);
+    retention::apply(state, repo, releases)?;
+    ipfs::apply_recorded_cids(state, repo, releases);
+    state.tag_rules.apply(&cache_key, releases);
+    state.image_templates.apply(&cache_key, releases);
+    // Upstreams don't promise an order, so two fetches of the same
+    // releases would otherwise cache and render differently
+    for release in releases.iter_mut() {
+        release.assets.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    // Compared with the cache before it is replaced
+    let cached = state
+        .cache
+        .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        .ok()
+        .flatten();
+    let previous = cached.as_ref().map_or(&[][..], |c| c.releases.as_slice());
+    state
+        .publishers
+        .check(&state.client, &cache_key, previous, releases)
+        .await;
+    let mut news = Vec::new();
+    if let Some(cached) = &cached {
+        let now = Utc::now();
+        let replaced = rolling::detect_updates(&cached.releases, releases, now);
+        since::record_first_seen(&cached.releases, releases, now);
+        if !replaced.is_empty() {
+            proxy::forget_hashes(state, repo, &replaced)?;
+            ipfs::forget_cids(state, repo, &replaced)?;
+        }
+        news = subscriptions::news(&cached.releases, releases);
+    }
+    // READMEs and licenses rarely change without a release, so what they
+    // gave is carried over until the releases change
+    let unchanged = cached
+        .as_ref()
+        .filter(|cached| same_releases(&cached.releases, releases));
+    let (readme, forge_license) = match unchanged {
+        Some(cached) => (
+            install::readme_commands(&cached.releases),
+            license::forge_license(&cached.releases),
+        ),
+        None => (readme.await, forge_license.await),
+    };
+    // Scanned before the notes are truncated
+    install::apply(releases, readme);
+    license::apply(releases, forge_license);
+    // Full notes are written first, so no marker links to missing notes
+    let route_url = format!("{}{}", state.render.public_url, repo_route(prefix, repo));
+    let notes = notes::truncate_notes(releases, state.max_notes_bytes, &route_url);
+    state
+        .cache
+        .write_notes(&repo.host, &repo.owner, &repo.repo, &notes)?;
+    Ok(news)
+}
+
+/// Whether `releases` are the `previous` ones: the same tags, drafts and
+/// publication times, in the same order.
+fn same_releases(previous: &[Release], releases: &[Release]) -> bool {
+    previous.len() == releases.len()
+        && previous.iter().zip(releases).all(|(a, b)| {
+            a.tag_name == b.tag_name && a.draft == b.draft && a.published_at == b.published_at
+        })
+}
+
+/// Write cache.json, the first page of HTML and the timestamp; the
+/// timestamp goes last so a fresh timestamp always has data behind it.
+/// Subscribers hear of `news` once all are written.
+fn write_releases<P: Provider>(
+    state: &AppState,
+    repo: &RepoPath,
+    releases: &[Release],
+    news: Vec<Release>,
+) -> anyhow::Result<()> {
+    write_cached(state, P::PREFIX, repo, releases, Utc::now())?;
+    let route = repo_route(P::PREFIX, repo);
+    subscriptions::notify(state, route.trim_start_matches('/'), news);
+    Ok(())
+}
+
+fn write_cached(
+    state: &AppState,
+    prefix: &'static str,
+    repo: &RepoPath,
+    releases: &[Release],
+    cached_at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let cache_key = repo.cache_key();
+    let html = format_html::format_releases_html(
+        releases,
+        &cache_key,
+        prefix,
+        Some(cached_at),
+        &state.render,
+        1,
+    );
+    let cached = CachedReleases {
+        releases: releases.to_vec(),
+        cached_at,
+        trusted_publisher: state.publishers.trusted(&cache_key, releases),
+        repo_path: cache_key.clone(),
+        provider: Some(prefix.to_string()),
+    };
+
+    let cache = &state.cache;
+    cache.with_repo_lock(&cache_key, || {
+        cache.write_json(&repo.host, &repo.owner, &repo.repo, &cached)?;
+        cache.write_html(&repo.host, &repo.owner, &repo.repo, &html)?;
+        cache.write_timestamp_at(&repo.host, &repo.owner, &repo.repo, cached_at)
+    })
+}
+
+/// Cache releases a federation peer fetched, through the same post-fetch
+/// steps as a fetch here, with the README's install commands and the
+/// license the peer found. The peer's `cached_at` is kept, so the cache
+/// ages from the peer's fetch and instances federating both ways don't take
+/// it back from each other; one in the future is taken as now.
+pub async fn store_federated(
+    state: &AppState,
+    prefix: &'static str,
+    repo: &RepoPath,
+    mut releases: Vec<Release>,
+    cached_at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    checksums::drop_invalid_hashes(&mut releases);
+    let readme = install::readme_commands(&releases);
+    let forge_license = license::forge_license(&releases);
+    let news = process_releases(
+        state,
+        prefix,
+        repo,
+        &mut releases,
+        std::future::ready(readme),
+        std::future::ready(forge_license),
+    )
+    .await?;
+    write_cached(state, prefix, repo, &releases, cached_at.min(Utc::now()))?;
+    state.fetches.clear_failure(&repo.cache_key());
+    let route = repo_route(prefix, repo);
+    subscriptions::notify(state, route.trim_start_matches('/'), news);
+    Ok(())
+}
+
+/// Fetch and cache a repo's releases, clearing an earlier failure.
+pub async fn cache_releases<P: Provider>(state: &AppState, repo: &RepoPath) -> anyhow::Result<()> {
+    let (releases, news) = fetch_processed::<P>(state, repo).await?;
+    write_releases::<P>(state, repo, &releases, news)?;
+    state.fetches.clear_failure(&repo.cache_key());
+    Ok(())
+}
+
+/// A repo's releases for routes that answer right away: from the cache
+/// while it is fresh, otherwise fetched (and cached) before answering.
+async fn fetch_blocking<P: Provider>(
+    state: &AppState,
+    repo: &RepoPath,
+    client: ClientIp,
+) -> Result<Vec<Release>, AppError> {
+    if let Ok(Some(cached_at)) = state
+        .cache
+        .read_timestamp(&repo.host, &repo.owner, &repo.repo)
+        && !state
+            .cache
+            .is_repo_expired(&repo.host, &repo.owner, &repo.repo, cached_at)
+        && let Some(cached) = state
+            .cache
+            .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+            .map_err(AppError::internal)?
+    {
+        return Ok(cached.releases);
+    }
+
+    shedding::admit_fetch(state, client)?;
+    let (releases, news) = fetch_processed::<P>(state, repo)
+        .await
+        .map_err(|e| AppError::from_upstream(&e))?;
+    // The fetched releases still answer this request
+    if let Err(e) = write_releases::<P>(state, repo, &releases, news) {
+        eprintln!("Caching {} failed: {}", repo.cache_key(), e);
+    }
+    Ok(releases)
+}
+
+/// Fetch and cache a repo's releases through its provider.
+pub async fn fetch_and_cache(
+    state: &Arc<AppState>,
+    prefix: &str,
+    repo: &RepoPath,
+) -> anyhow::Result<()> {
+    match prefix {
+        "github" => cache_releases::<GitHub>(state, repo).await,
+        "gitlab" => cache_releases::<GitLab>(state, repo).await,
+        "forgejo" => cache_releases::<Forgejo>(state, repo).await,
+        "gitea" => cache_releases::<Gitea>(state, repo).await,
+        "codeberg" => cache_releases::<Codeberg>(state, repo).await,
+        "sourcehut" => cache_releases::<SourceHut>(state, repo).await,
+        "launchpad" => cache_releases::<Launchpad>(state, repo).await,
+        "snap" => cache_releases::<Snap>(state, repo).await,
+        "huggingface" => cache_releases::<HuggingFace>(state, repo).await,
+        "pagure" => cache_releases::<Pagure>(state, repo).await,
+        "gitiles" => cache_releases::<Gitiles>(state, repo).await,
+        "hg" => cache_releases::<Hgweb>(state, repo).await,
+        "cgit" => cache_releases::<Cgit>(state, repo).await,
+        "autoindex" => cache_releases::<Autoindex>(state, repo).await,
+        _ => anyhow::bail!("Unknown provider: {}", prefix),
+    }
+}
+
+/// Fetch a repo's releases through its provider, without caching them.
+pub async fn fetch_releases(
+    state: &AppState,
+    prefix: &str,
+    repo: &RepoPath,
+) -> anyhow::Result<Vec<Release>> {
+    match prefix {
+        "github" => GitHub::fetch_releases(state, repo).await,
+        "gitlab" => GitLab::fetch_releases(state, repo).await,
+        "forgejo" => Forgejo::fetch_releases(state, repo).await,
+        "gitea" => Gitea::fetch_releases(state, repo).await,
+        "codeberg" => Codeberg::fetch_releases(state, repo).await,
+        "sourcehut" => SourceHut::fetch_releases(state, repo).await,
+        "launchpad" => Launchpad::fetch_releases(state, repo).await,
+        "snap" => Snap::fetch_releases(state, repo).await,
+        "huggingface" => HuggingFace::fetch_releases(state, repo).await,
+        "pagure" => Pagure::fetch_releases(state, repo).await,
+        "gitiles" => Gitiles::fetch_releases(state, repo).await,
+        "hg" => Hgweb::fetch_releases(state, repo).await,
+        "cgit" => Cgit::fetch_releases(state, repo).await,
+        "autoindex" => Autoindex::fetch_releases(state, repo).await,
+        _ => anyhow::bail!("Unknown provider: {}", prefix),
+    }
+}
+
+/// Answer `/tags.json` from the tag cache, fetching the tags with `fetch`
+/// when they are missing or expired.
+pub async fn tags_response(
+    state: &AppState,
+    repo: &RepoPath,
+    client: ClientIp,
+    fetch: impl Future<Output = anyhow::Result<Vec<Tag>>>,
+) -> Result<Response, AppError> {
+    let cached = match state
+        .cache
+        .read_tags::<CachedTags>(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?
+    {
+        Some(cached)
+            if !state.cache.is_repo_expired(
+                &repo.host,
+                &repo.owner,
+                &repo.repo,
+                cached.cached_at,
+            ) =>
+        {
+            cached
+        }
+        _ => {
+            shedding::admit_fetch(state, client)?;
+            let tags = state
+                .upstream
+                .track(&repo.host, fetch)
+                .await
+                .map_err(|e| AppError::from_upstream(&e))?;
+            let cached = CachedTags {
+                tags,
+                cached_at: Utc::now(),
+                repo_path: repo.cache_key(),
+            };
+            let _ = state
+                .cache
+                .write_tags(&repo.host, &repo.owner, &repo.repo, &cached);
+            cached
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&cached).map_err(AppError::internal)?;
+    Ok(state.cache_policy.apply(
+        RouteKind::Json,
+        signing::json_response(state, StatusCode::OK, body),
+    ))
 }
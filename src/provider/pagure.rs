@@ -0,0 +1,229 @@
+use super::{Asset, Provider, Release, Tag, authorize, base_url, classify_assets};
+use crate::{
+    AppState, RepoPath,
+    config::{HostCredentials, ProviderLimits},
+    debug,
+    error::AppError,
+    routes,
+    upstream::UpstreamStatusError,
+    versions,
+};
+use anyhow::Result;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt, stream};
+use reqwest::Client;
+use serde::{Deserialize, de::DeserializeOwned};
+use std::collections::HashMap;
+
+pub const HOST: &str = "pagure.io";
+
+/// Tags dated at least, the highest versions; the API lists tags without
+/// dates, so each one takes a request for its commit.
+const MIN_DATED_TAGS: usize = 100;
+/// Commit lookups running at once
+const COMMIT_REQUESTS: usize = 4;
+
+#[derive(Debug, Deserialize)]
+struct PagureTags {
+    /// Tag name to commit hash, with `with_commits=true`
+    tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PagureCommit {
+    /// Seconds since the epoch
+    commit_time: i64,
+}
+
+/// "{namespace}/{repo}", or just the repo when it has no namespace.
+fn project_path(namespace: &str, repo: &str) -> String {
+    if namespace.is_empty() {
+        repo.to_string()
+    } else {
+        format!("{}/{}", namespace, repo)
+    }
+}
+
+async fn get_json<T: DeserializeOwned>(
+    client: &Client,
+    host: &str,
+    url: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<T> {
+    debug::request("GET", url);
+    let response = authorize(credentials, limits.request(client.get(url)))
+        .header("Accept", "application/json")
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("Pagure API ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
+    }
+    Ok(response.json().await?)
+}
+
+/// Tags with their commit dates, newest first. Only the 100 highest
+/// versions, or `max_releases` if that is more, are dated and kept.
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    namespace: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Tag>> {
+    let api = format!("{}/api/0/{}", base_url(host), project_path(namespace, repo));
+    let listed: PagureTags = get_json(
+        client,
+        host,
+        &format!("{}/git/tags?with_commits=true", api),
+        limits,
+        credentials,
+    )
+    .await?;
+    debug::parsed("tags", listed.tags.len());
+
+    let mut listed: Vec<(String, String)> = listed.tags.into_iter().collect();
+    listed.sort_by(|(a, _), (b, _)| versions::compare_versions(b, a));
+    listed.truncate(MIN_DATED_TAGS.max(limits.max_releases.unwrap_or(0)));
+
+    let lookups: Vec<_> = listed
+        .iter()
+        .map(|(_, commit)| {
+            let url = format!("{}/c/{}/info", api, commit);
+            async move {
+                let info: PagureCommit = get_json(client, host, &url, limits, credentials).await?;
+                anyhow::Ok(DateTime::<Utc>::from_timestamp(info.commit_time, 0))
+            }
+        })
+        .collect();
+    let dates: Vec<Option<DateTime<Utc>>> = stream::iter(lookups)
+        .buffered(COMMIT_REQUESTS)
+        .try_collect()
+        .await?;
+
+    let mut tags: Vec<Tag> = listed
+        .into_iter()
+        .zip(dates)
+        .map(|((name, commit), date)| Tag {
+            name,
+            date,
+            commit: Some(commit),
+        })
+        .collect();
+    // Stable, so tags of one commit stay in version order
+    tags.sort_by_key(|t| std::cmp::Reverse(t.date));
+    Ok(tags)
+}
+
+/// Pagure has no releases; each tag is one, with the archives Pagure
+/// generates for it.
+pub async fn fetch_releases(
+    client: &Client,
+    host: &str,
+    namespace: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+    credentials: Option<&HostCredentials>,
+) -> Result<Vec<Release>> {
+    let tags = fetch_tags(client, host, namespace, repo, limits, credentials).await?;
+    let repo_url = format!("{}/{}", base_url(host), project_path(namespace, repo));
+
+    let mut releases: Vec<Release> = tags
+        .into_iter()
+        .map(|tag| {
+            let archive = |ext: &str, content_type: &str| Asset {
+                name: format!("{}-{}.{}", repo, tag.name.replace('/', "-"), ext),
+                url: format!(
+                    "{}/archive/{}/{}-{}.{}",
+                    repo_url, tag.name, repo, tag.name, ext
+                ),
+                content_type: Some(content_type.to_string()),
+                size: 0,
+                download_count: 0,
+                sha256: None,
+                sri: None,
+                file_type: None,
+                type_label: None,
+                updated_at: None,
+                cid: None,
+            };
+            Release {
+                name: Some(tag.name.clone()),
+                published_at: tag.date.unwrap_or_else(Utc::now),
+                html_url: format!("{}/tree/{}", repo_url, tag.name),
+                body: None,
+                changes: Default::default(),
+                prerelease: false,
+                draft: false,
+                assets: vec![
+                    archive("tar.gz", "application/gzip"),
+                    archive("zip", "application/zip"),
+                ],
+                source_tarball: None,
+                source_zipball: None,
+                version: None,
+                container_images: Vec::new(),
+                signature: None,
+                install: Vec::new(),
+                license: None,
+                body_truncated: false,
+                updated_at: None,
+                first_seen_at: None,
+                tag_name: tag.name,
+            }
+        })
+        .collect();
+
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+pub struct Pagure;
+
+impl Provider for Pagure {
+    const PREFIX: &'static str = "pagure";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /pagure/{host}/[{namespace}/]{repo}".to_string(),
+            )
+        })
+    }
+
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.pagure,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.pagure,
+            state.credentials.get(&repo.host),
+        )
+        .await
+    }
+}
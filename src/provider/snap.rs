@@ -0,0 +1,256 @@
+use super::{Asset, Provider, Release, Tag, base_url, classify_assets};
+use crate::{
+    AppState, RepoPath, config::ProviderLimits, debug, error::AppError, routes,
+    upstream::UpstreamStatusError,
+};
+use anyhow::Result;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+
+pub const HOST: &str = "snapcraft.io";
+pub const API_HOST: &str = "api.snapcraft.io";
+
+/// Fields requested of each channel's revision; `channel` always comes along
+const FIELDS: &str = "created-at,download,revision,version";
+
+/// `/v2/snaps/info/{name}`: the revision released to each channel, per
+/// architecture
+#[derive(Debug, Deserialize)]
+struct SnapInfo {
+    #[serde(rename = "channel-map")]
+    channel_map: Vec<ChannelRevision>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelRevision {
+    channel: Channel,
+    /// When the revision was uploaded
+    #[serde(rename = "created-at")]
+    created_at: DateTime<Utc>,
+    download: Download,
+    revision: u64,
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Channel {
+    architecture: String,
+    /// "stable", "candidate", "beta" or "edge"
+    risk: String,
+    /// "latest" unless the snap publishes tracks, e.g. "2.x"
+    track: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Download {
+    url: String,
+    size: u64,
+}
+
+/// The snap's channel map, requested as series 16 devices do.
+async fn fetch_channel_map(
+    client: &Client,
+    api_host: &str,
+    name: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<ChannelRevision>> {
+    let url = format!(
+        "{}/v2/snaps/info/{}?fields={}",
+        base_url(api_host),
+        name,
+        FIELDS
+    );
+    debug::request("GET", &url);
+    let response = limits
+        .request(client.get(&url))
+        .header("Snap-Device-Series", "16")
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("Snap Store API ({})", api_host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
+    }
+    let info: SnapInfo = response.json().await?;
+    debug::parsed("channel revisions", info.channel_map.len());
+    Ok(info.channel_map)
+}
+
+/// A snap version and the channel revisions that carry it.
+struct SnapVersion<'a> {
+    version: &'a str,
+    revisions: Vec<&'a ChannelRevision>,
+}
+
+impl SnapVersion<'_> {
+    /// When the version's first revision was uploaded
+    fn date(&self) -> DateTime<Utc> {
+        self.revisions
+            .iter()
+            .map(|r| r.created_at)
+            .min()
+            .unwrap_or_else(Utc::now)
+    }
+
+    /// Only versions released to a stable channel are stable.
+    fn prerelease(&self) -> bool {
+        !self.revisions.iter().any(|r| r.channel.risk == "stable")
+    }
+}
+
+/// The channel map grouped by version, newest first.
+fn versions(channel_map: &[ChannelRevision]) -> Vec<SnapVersion<'_>> {
+    let mut versions: Vec<SnapVersion> = Vec::new();
+    for revision in channel_map {
+        match versions.iter_mut().find(|v| v.version == revision.version) {
+            Some(version) => version.revisions.push(revision),
+            None => versions.push(SnapVersion {
+                version: &revision.version,
+                revisions: vec![revision],
+            }),
+        }
+    }
+    versions.sort_by_key(|v| std::cmp::Reverse(v.date()));
+    versions
+}
+
+/// Release notes listing the channels the version is released to.
+fn channels_body(version: &SnapVersion) -> String {
+    version
+        .revisions
+        .iter()
+        .map(|r| {
+            format!(
+                "- `{}/{}` ({}): revision {}",
+                r.channel.track, r.channel.risk, r.channel.architecture, r.revision
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One `.snap` per architecture and revision; channels sharing a revision
+/// share the download.
+fn snap_assets(name: &str, version: &SnapVersion) -> Vec<Asset> {
+    let mut assets: Vec<Asset> = Vec::new();
+    for r in &version.revisions {
+        if assets.iter().any(|a| a.url == r.download.url) {
+            continue;
+        }
+        let stem = format!(
+            "{}_{}_{}",
+            name,
+            version.version.replace('/', "-"),
+            r.channel.architecture
+        );
+        // Rebuilds of a version on one architecture get their revision added
+        let asset_name = if assets.iter().any(|a| a.name == format!("{}.snap", stem)) {
+            format!("{}_{}.snap", stem, r.revision)
+        } else {
+            format!("{}.snap", stem)
+        };
+        assets.push(Asset {
+            name: asset_name,
+            url: r.download.url.clone(),
+            content_type: None,
+            size: r.download.size,
+            download_count: 0,
+            sha256: None,
+            sri: None,
+            file_type: None,
+            type_label: None,
+            updated_at: Some(r.created_at),
+            cid: None,
+        });
+    }
+    assets
+}
+
+/// Each version in the channel map is a release, with the revisions of
+/// every architecture as assets; versions only on candidate, beta or edge
+/// channels are prereleases.
+pub async fn fetch_releases(
+    client: &Client,
+    api_host: &str,
+    name: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Release>> {
+    let channel_map = fetch_channel_map(client, api_host, name, limits).await?;
+    let mut releases: Vec<Release> = versions(&channel_map)
+        .into_iter()
+        .map(|v| Release {
+            name: Some(v.version.to_string()),
+            published_at: v.date(),
+            html_url: format!("https://{}/{}", HOST, name),
+            body: Some(channels_body(&v)),
+            changes: Default::default(),
+            prerelease: v.prerelease(),
+            draft: false,
+            assets: snap_assets(name, &v),
+            source_tarball: None,
+            source_zipball: None,
+            version: None,
+            container_images: Vec::new(),
+            signature: None,
+            install: Vec::new(),
+            license: None,
+            body_truncated: false,
+            updated_at: None,
+            first_seen_at: None,
+            tag_name: v.version.to_string(),
+        })
+        .collect();
+
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+/// Snaps have no tags; the versions in the channel map stand in.
+pub async fn fetch_tags(
+    client: &Client,
+    api_host: &str,
+    name: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Tag>> {
+    let channel_map = fetch_channel_map(client, api_host, name, limits).await?;
+    Ok(versions(&channel_map)
+        .into_iter()
+        .map(|v| Tag {
+            date: Some(v.date()),
+            name: v.version.to_string(),
+            commit: None,
+        })
+        .collect())
+}
+
+pub struct Snap;
+
+impl Provider for Snap {
+    const PREFIX: &'static str = "snap";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /snap/{name}".to_string(),
+            )
+        })
+    }
+
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(&state.client, API_HOST, &repo.repo, &state.providers.snap).await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(&state.client, API_HOST, &repo.repo, &state.providers.snap).await
+    }
+}
@@ -0,0 +1,195 @@
+use super::{Asset, Provider, Release, Tag, base_url, classify_assets};
+use crate::{
+    AppState, RepoPath, changelog, config::ProviderLimits, debug, error::AppError, routes,
+    upstream::UpstreamStatusError,
+};
+use anyhow::Result;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+
+pub const HOST: &str = "git.sr.ht";
+
+/// The refs feed, `/{~owner}/{repo}/refs/rss.xml`: one item per tag, newest
+/// first. Unlike the GraphQL API it needs no token.
+#[derive(Debug, Deserialize)]
+struct Feed {
+    channel: Channel,
+}
+
+#[derive(Debug, Deserialize)]
+struct Channel {
+    #[serde(default, rename = "item")]
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    /// The tag name
+    title: String,
+    link: Option<String>,
+    /// Message of annotated tags
+    description: Option<String>,
+    #[serde(rename = "pubDate")]
+    pub_date: Option<String>,
+}
+
+async fn fetch_feed(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Item>> {
+    let url = format!("{}/{}/{}/refs/rss.xml", base_url(host), owner, repo);
+
+    debug::request("GET", &url);
+    let response = limits
+        .request(client.get(&url))
+        .header("Accept", "application/rss+xml")
+        .header("User-Agent", "checkup/0.1.0")
+        .send()
+        .await?;
+    debug::response(&response);
+
+    if !response.status().is_success() {
+        return Err(UpstreamStatusError {
+            service: format!("SourceHut ({})", host),
+            status: response.status(),
+            rate_limit: None,
+        }
+        .into());
+    }
+
+    let xml = response.text().await?;
+    let feed: Feed = quick_xml::de::from_str(&xml)?;
+    debug::parsed("tags", feed.channel.items.len());
+    Ok(feed.channel.items)
+}
+
+fn parse_date(item: &Item) -> Option<DateTime<Utc>> {
+    item.pub_date
+        .as_deref()
+        .and_then(|d| DateTime::parse_from_rfc2822(d.trim()).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Releases are the repo's tags, each with its `.tar.gz` archive.
+pub async fn fetch_releases(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Release>> {
+    let items = fetch_feed(client, host, owner, repo, limits).await?;
+    let repo_url = format!("{}/{}/{}", base_url(host), owner, repo);
+
+    let mut releases: Vec<Release> = items
+        .into_iter()
+        .filter(|item| !item.title.trim().is_empty())
+        .map(|item| {
+            let tag_name = item.title.trim().to_string();
+            let published_at = parse_date(&item).unwrap_or_else(Utc::now);
+            let body = item.description.filter(|d| !d.trim().is_empty());
+            let archive = Asset {
+                name: format!("{}-{}.tar.gz", repo, tag_name.replace('/', "-")),
+                url: format!("{}/archive/{}.tar.gz", repo_url, tag_name),
+                content_type: Some("application/gzip".to_string()),
+                size: 0,
+                download_count: 0,
+                sha256: None,
+                sri: None,
+                file_type: None,
+                type_label: None,
+                updated_at: None,
+                cid: None,
+            };
+            Release {
+                name: Some(tag_name.clone()),
+                published_at,
+                html_url: item
+                    .link
+                    .unwrap_or_else(|| format!("{}/refs/{}", repo_url, tag_name)),
+                changes: body.as_deref().map(changelog::parse).unwrap_or_default(),
+                body,
+                prerelease: false,
+                draft: false,
+                assets: vec![archive],
+                source_tarball: None,
+                source_zipball: None,
+                version: None,
+                container_images: Vec::new(),
+                signature: None,
+                install: Vec::new(),
+                license: None,
+                body_truncated: false,
+                updated_at: None,
+                first_seen_at: None,
+                tag_name,
+            }
+        })
+        .collect();
+
+    limits.truncate(&mut releases);
+    classify_assets(&mut releases);
+    Ok(releases)
+}
+
+/// Tags come from the same feed as releases.
+pub async fn fetch_tags(
+    client: &Client,
+    host: &str,
+    owner: &str,
+    repo: &str,
+    limits: &ProviderLimits,
+) -> Result<Vec<Tag>> {
+    let items = fetch_feed(client, host, owner, repo, limits).await?;
+    Ok(items
+        .into_iter()
+        .filter(|item| !item.title.trim().is_empty())
+        .map(|item| Tag {
+            date: parse_date(&item),
+            name: item.title.trim().to_string(),
+            commit: None,
+        })
+        .collect())
+}
+
+pub struct SourceHut;
+
+impl Provider for SourceHut {
+    const PREFIX: &'static str = "sourcehut";
+
+    fn parse_path(path: &str) -> Result<RepoPath, AppError> {
+        routes::split_repo(Self::PREFIX, path).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid path format. Use: /sourcehut/{~owner}/{repo}".to_string(),
+            )
+        })
+    }
+
+    async fn fetch_releases(state: &AppState, repo: &RepoPath) -> Result<Vec<Release>> {
+        fetch_releases(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.sourcehut,
+        )
+        .await
+    }
+
+    async fn fetch_tags(state: &AppState, repo: &RepoPath) -> Result<Vec<Tag>> {
+        fetch_tags(
+            &state.client,
+            &repo.host,
+            &repo.owner,
+            &repo.repo,
+            &state.providers.sourcehut,
+        )
+        .await
+    }
+}
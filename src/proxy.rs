@@ -0,0 +1,325 @@
+//! `/proxy/{provider}/{...}/{tag}/{asset}`: stream a cached release asset from
+//! upstream while hashing it, and record its SHA-256 so the asset shows a
+//! checksum from then on. Only the hash is stored, never the file, unless
+//! the file is pinned to IPFS (`ipfs.rs`), for the releases `retention.rs`
+//! keeps. An asset whose hash is known already must match it: otherwise the
+//! download ends in an error, and nothing is recorded or pinned.
+
+use crate::{
+    AppState, RepoPath,
+    error::AppError,
+    format_html::format_releases_html,
+    ipfs::{self, Spool},
+    provider::{self, CachedReleases, Release, sri_from_sha256},
+};
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, sync::Arc};
+
+pub async fn proxy_asset(
+    Path(path): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, AppError> {
+    let invalid = || {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Use /proxy/{provider}/{...}/{tag}/{asset}",
+        )
+    };
+    let (rest, asset_name) = path.rsplit_once('/').ok_or_else(invalid)?;
+    let (route, tag) = rest.rsplit_once('/').ok_or_else(invalid)?;
+    let (prefix, repo) = provider::parse_route(route).map_err(|e| AppError::from_route(&e))?;
+    state.maintenance.check("downloading through the proxy")?;
+
+    // Only assets of cached releases are proxied, so this is no open proxy
+    let cached = state
+        .cache
+        .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Repository is not cached; open {} first",
+                    provider::repo_route(prefix, &repo)
+                ),
+            )
+        })?;
+    let index = cached.releases.iter().position(|r| r.tag_name == tag);
+    let asset = index
+        .and_then(|i| {
+            cached.releases[i]
+                .assets
+                .iter()
+                .find(|a| a.name == asset_name)
+        })
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                format!("No asset '{}' in release '{}'", asset_name, tag),
+            )
+        })?;
+
+    let upstream = state
+        .client
+        .get(&asset.url)
+        .send()
+        .await
+        .map_err(|e| AppError::new(StatusCode::BAD_GATEWAY, e.to_string()))?;
+    if !upstream.status().is_success() {
+        return Err(AppError::new(
+            StatusCode::BAD_GATEWAY,
+            format!("Upstream returned {} for {}", upstream.status(), asset.url),
+        ));
+    }
+
+    let content_type = upstream
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| asset.content_type.clone())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let expected_len = upstream.content_length();
+
+    // Assets of releases past `keep_releases` aren't pinned any more
+    let spool = match index {
+        Some(i)
+            if state
+                .retention
+                .keeps(&repo.cache_key(), &cached.releases, i) =>
+        {
+            ipfs::spool(&state, &repo, &asset.url, asset_name).await
+        }
+        _ => None,
+    };
+    let recorder = HashRecorder {
+        state: state.clone(),
+        prefix,
+        repo,
+        url: asset.url.clone(),
+        expected: asset.sha256.clone(),
+    };
+    let stream = futures_util::stream::unfold(
+        Some((upstream, Sha256::new(), 0u64, recorder, spool)),
+        move |download| async move {
+            let (mut upstream, mut hasher, mut len, recorder, mut spool) = download?;
+            match upstream.chunk().await {
+                Ok(Some(chunk)) => {
+                    hasher.update(&chunk);
+                    len += chunk.len() as u64;
+                    if let Some(copy) = spool.as_mut()
+                        && let Err(e) = copy.write(&chunk).await
+                    {
+                        eprintln!("Spooling {} for IPFS failed: {}", recorder.url, e);
+                        spool = None;
+                    }
+                    // With a Content-Length the body isn't polled past its end,
+                    // so a mismatch takes the place of the last chunk
+                    if expected_len == Some(len)
+                        && let Err(e) = recorder
+                            .complete(&format!("{:x}", hasher.clone().finalize()), spool.take())
+                    {
+                        return Some((Err(e), None));
+                    }
+                    Some((Ok(chunk), Some((upstream, hasher, len, recorder, spool))))
+                }
+                Ok(None) => {
+                    // Without a length, only a completed stream is known to be whole
+                    if expected_len.is_none()
+                        && let Err(e) =
+                            recorder.complete(&format!("{:x}", hasher.finalize()), spool)
+                    {
+                        return Some((Err(e), None));
+                    }
+                    None
+                }
+                Err(e) => Some((Err(std::io::Error::other(e)), None)),
+            }
+        },
+    );
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", asset_name.replace('"', "")),
+        );
+    if let Some(len) = expected_len {
+        response = response.header(header::CONTENT_LENGTH, len);
+    }
+    response
+        .body(Body::from_stream(stream))
+        .map_err(AppError::internal)
+}
+
+/// Check the SHA-256 of a whole download of `url` against the hash known
+/// for it, if any. A mismatch means upstream served another file than the
+/// one listed, which is neither recorded nor passed on.
+pub fn check_sha256(url: &str, expected: Option<&str>, sha256: &str) -> Result<(), String> {
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(sha256) => Err(format!(
+            "SHA-256 of {} is {}, not the {} listed for it",
+            url, sha256, expected
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Record the SHA-256 of an asset downloaded whole elsewhere, as the proxy
+/// does for the downloads it streams.
+pub fn record_hash(
+    state: &Arc<AppState>,
+    prefix: &'static str,
+    repo: &RepoPath,
+    url: &str,
+    sha256: &str,
+) {
+    HashRecorder {
+        state: state.clone(),
+        prefix,
+        repo: repo.clone(),
+        url: url.to_string(),
+        expected: None,
+    }
+    .record(sha256);
+}
+
+#[derive(Clone)]
+struct HashRecorder {
+    state: Arc<AppState>,
+    prefix: &'static str,
+    repo: RepoPath,
+    url: String,
+    /// Hash the download must have, when one is known
+    expected: Option<String>,
+}
+
+impl HashRecorder {
+    /// Record the hash of a whole download, and pin its spooled copy, unless
+    /// the hash isn't the one expected.
+    fn complete(&self, sha256: &str, spool: Option<Spool>) -> std::io::Result<()> {
+        if let Err(e) = check_sha256(&self.url, self.expected.as_deref(), sha256) {
+            eprintln!("WARNING: {}", e);
+            return Err(std::io::Error::other(e));
+        }
+        self.record(sha256);
+        if let Some(spool) = spool {
+            spool.pin(
+                self.state.clone(),
+                self.prefix,
+                self.repo.clone(),
+                self.url.clone(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Record the hash on a blocking thread, one write of the repo at a time.
+    fn record(&self, sha256: &str) {
+        let recorder = self.clone();
+        let sha256 = sha256.to_string();
+        tokio::task::spawn_blocking(move || {
+            let cache = &recorder.state.cache;
+            let recorded =
+                cache.with_repo_lock(&recorder.repo.cache_key(), || recorder.try_record(&sha256));
+            if let Err(e) = recorded {
+                eprintln!("Recording hash of {} failed: {}", recorder.url, e);
+            }
+        });
+    }
+
+    /// Add the hash to hashes.json, and to the cached releases and page so it
+    /// shows right away. Hashes already known are never replaced.
+    fn try_record(&self, sha256: &str) -> Result<()> {
+        let (cache, repo) = (&self.state.cache, &self.repo);
+        let mut hashes = cache.read_hashes(&repo.host, &repo.owner, &repo.repo)?;
+        if hashes.contains_key(&self.url) {
+            return Ok(());
+        }
+        hashes.insert(self.url.clone(), sha256.to_string());
+        cache.write_hashes(&repo.host, &repo.owner, &repo.repo, &hashes)?;
+        update_cached(&self.state, self.prefix, repo, |releases| {
+            fill_hashes(releases, &hashes)
+        })
+    }
+}
+
+/// Apply `fill` to the cached releases, and write them and their page again
+/// when it changed any. Callers hold the repo's lock
+/// (`CacheManager::with_repo_lock`).
+pub fn update_cached(
+    state: &AppState,
+    prefix: &str,
+    repo: &RepoPath,
+    fill: impl FnOnce(&mut [Release]) -> bool,
+) -> Result<()> {
+    let cache = &state.cache;
+    let Some(mut cached) =
+        cache.read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)?
+    else {
+        return Ok(());
+    };
+    if !fill(&mut cached.releases) {
+        return Ok(());
+    }
+    let html = format_releases_html(
+        &cached.releases,
+        &cached.repo_path,
+        prefix,
+        Some(cached.cached_at),
+        &state.render,
+        1,
+    );
+    cache.write_json(&repo.host, &repo.owner, &repo.repo, &cached)?;
+    cache.write_html(&repo.host, &repo.owner, &repo.repo, &html)?;
+    Ok(())
+}
+
+/// Set hashes recorded by the proxy on assets that have none; returns whether
+/// any asset changed.
+fn fill_hashes(releases: &mut [Release], hashes: &BTreeMap<String, String>) -> bool {
+    let mut changed = false;
+    for asset in releases
+        .iter_mut()
+        .flat_map(|r| r.assets.iter_mut())
+        .filter(|a| a.sha256.is_none())
+    {
+        if let Some(sha256) = hashes.get(&asset.url) {
+            asset.sri = sri_from_sha256(sha256);
+            asset.sha256 = Some(sha256.clone());
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Apply hashes recorded by the proxy to freshly fetched releases.
+pub fn apply_recorded_hashes(state: &AppState, repo: &RepoPath, releases: &mut [Release]) {
+    if let Ok(hashes) = state.cache.read_hashes(&repo.host, &repo.owner, &repo.repo)
+        && !hashes.is_empty()
+    {
+        fill_hashes(releases, &hashes);
+    }
+}
+
+/// Drop recorded hashes of assets whose file was replaced under the same URL
+/// (`rolling.rs`); the next download through the proxy records the new one.
+pub fn forget_hashes(state: &AppState, repo: &RepoPath, urls: &[String]) -> Result<()> {
+    let cache = &state.cache;
+    cache.with_repo_lock(&repo.cache_key(), || {
+        let mut hashes = cache.read_hashes(&repo.host, &repo.owner, &repo.repo)?;
+        let before = hashes.len();
+        hashes.retain(|url, _| !urls.contains(url));
+        if hashes.len() != before {
+            cache.write_hashes(&repo.host, &repo.owner, &repo.repo, &hashes)?;
+        }
+        Ok(())
+    })
+}
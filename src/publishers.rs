@@ -0,0 +1,533 @@
+//! Trusted publishers: `signing_keys` on a `[[repo]]` entry lists the keys
+//! the repo's releases are expected to be signed with, as minisign public
+//! keys ("RWQ...") or OpenSSH ed25519 keys ("ssh-ed25519 AAAA..."). When
+//! releases are fetched, the newest ones get their signature checked: a
+//! `.minisig`, or an SSH signature (`ssh-keygen -Y sign -n file`) in a
+//! `.sig`, published next to the asset it signs. The smallest signed asset
+//! is downloaded and verified, which usually is a checksum file covering the
+//! others.
+//!
+//! Pages of a repo whose latest release is signed by an expected key show a
+//! "Trusted publisher" badge; a latest release that is unsigned, signed by
+//! another key or whose signature doesn't verify gets a warning instead, and
+//! so does the log when such a release is new. `+json` has the outcome per
+//! release in `signature`, and for the repo in `trusted_publisher`.
+//! OpenPGP signatures (`.asc`, binary `.sig`) can't be verified here and
+//! count as unverifiable.
+
+use crate::{
+    config::RepoConfig,
+    debug, provider,
+    provider::{Asset, Release},
+};
+use anyhow::{Context, Result};
+use base64::Engine;
+use ed25519_dalek::{Signature as Ed25519Signature, Verifier, VerifyingKey};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+
+/// Newest releases whose signature is checked per fetch; older ones keep
+/// the outcome of earlier fetches
+const MAX_CHECKED_RELEASES: usize = 3;
+/// Signature files tried per release
+const MAX_SIGNATURE_FILES: usize = 4;
+const MAX_SIGNATURE_BYTES: u64 = 64 * 1024;
+/// Signed assets larger than this aren't downloaded to be verified
+const MAX_SIGNED_BYTES: u64 = 128 * 1024 * 1024;
+/// Namespace of SSH signatures made for files
+const SSH_NAMESPACE: &str = "file";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    /// Signed by an expected key
+    Trusted,
+    /// Signed by a key that isn't expected
+    OtherKey,
+    /// Signed by an expected key, but the signature doesn't verify
+    Invalid,
+    /// Only signed in formats that can't be verified here
+    Unverifiable,
+    Unsigned,
+}
+
+impl SignatureStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Trusted => "Signed",
+            Self::OtherKey => "Signed by another key",
+            Self::Invalid => "Bad signature",
+            Self::Unverifiable => "Unverified signature",
+            Self::Unsigned => "Unsigned",
+        }
+    }
+}
+
+/// Outcome of checking a release's signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SignatureCheck {
+    pub status: SignatureStatus,
+    /// Signature file checked, e.g. "SHA256SUMS.minisig"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Key it is signed with, e.g. "minisign 8A2F6C9D41E0B375" or
+    /// "SHA256:..." for SSH keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl SignatureCheck {
+    fn new(status: SignatureStatus, file: Option<&str>, key: Option<String>) -> Self {
+        Self {
+            status,
+            file: file.map(String::from),
+            key,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PublicKey {
+    Minisign {
+        id: [u8; 8],
+        key: VerifyingKey,
+    },
+    /// With its wire encoding, which SSH signatures embed
+    Ssh {
+        blob: Vec<u8>,
+        key: VerifyingKey,
+    },
+}
+
+impl PublicKey {
+    /// A minisign public key, its `.pub` file whole or only the key line,
+    /// or an OpenSSH "ssh-ed25519" public key line.
+    fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if let Some(rest) = text.strip_prefix("ssh-ed25519 ") {
+            let encoded = rest.split_whitespace().next().unwrap_or_default();
+            let blob = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .context("invalid base64")?;
+            let key = ssh_ed25519_key(&blob).context("not an ssh-ed25519 key")?;
+            return Ok(Self::Ssh { blob, key });
+        }
+        let line = text.lines().last().unwrap_or_default().trim();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(line)
+            .context("expected a minisign or ssh-ed25519 public key")?;
+        if bytes.len() != 42 || &bytes[..2] != b"Ed" {
+            anyhow::bail!("expected a minisign or ssh-ed25519 public key");
+        }
+        let key = VerifyingKey::from_bytes(bytes[10..].try_into()?)?;
+        Ok(Self::Minisign {
+            id: bytes[2..10].try_into()?,
+            key,
+        })
+    }
+
+    fn identity(&self) -> String {
+        match self {
+            Self::Minisign { id, .. } => minisign_identity(id),
+            Self::Ssh { blob, .. } => ssh_identity(blob),
+        }
+    }
+}
+
+fn minisign_identity(id: &[u8; 8]) -> String {
+    format!("minisign {:016X}", u64::from_le_bytes(*id))
+}
+
+/// The fingerprint `ssh-keygen -l` shows.
+fn ssh_identity(blob: &[u8]) -> String {
+    format!(
+        "SHA256:{}",
+        base64::engine::general_purpose::STANDARD_NO_PAD.encode(Sha256::digest(blob))
+    )
+}
+
+/// Reads the length-prefixed fields of the SSH wire format.
+struct Wire<'a>(&'a [u8]);
+
+impl<'a> Wire<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+fn wire_string(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn ssh_ed25519_key(blob: &[u8]) -> Option<VerifyingKey> {
+    let mut wire = Wire(blob);
+    if wire.string()? != b"ssh-ed25519" {
+        return None;
+    }
+    VerifyingKey::from_bytes(wire.string()?.try_into().ok()?).ok()
+}
+
+enum Signature {
+    Minisign {
+        /// Signs the BLAKE2b-512 hash of the file rather than the file
+        prehashed: bool,
+        key_id: [u8; 8],
+        signature: [u8; 64],
+        trusted_comment: String,
+        /// Signs `signature` and the trusted comment
+        global: [u8; 64],
+    },
+    Ssh {
+        key_blob: Vec<u8>,
+        reserved: Vec<u8>,
+        hash: String,
+        signature: [u8; 64],
+    },
+}
+
+impl Signature {
+    /// A signature file's content, or none in a format not verified here.
+    fn parse(name: &str, content: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(content).ok()?;
+        if name.ends_with(".minisig") {
+            return Self::parse_minisign(text);
+        }
+        if text
+            .trim_start()
+            .starts_with("-----BEGIN SSH SIGNATURE-----")
+        {
+            return Self::parse_ssh(text);
+        }
+        None
+    }
+
+    fn parse_minisign(text: &str) -> Option<Self> {
+        let mut lines = text.lines().map(str::trim);
+        let decode = |line: &str| base64::engine::general_purpose::STANDARD.decode(line).ok();
+        lines.next()?.strip_prefix("untrusted comment:")?;
+        let bytes = decode(lines.next()?)?;
+        let trusted_comment = lines.next()?.strip_prefix("trusted comment: ")?;
+        let global = decode(lines.next()?)?;
+        if bytes.len() != 74 {
+            return None;
+        }
+        let prehashed = match &bytes[..2] {
+            b"Ed" => false,
+            b"ED" => true,
+            _ => return None,
+        };
+        Some(Self::Minisign {
+            prehashed,
+            key_id: bytes[2..10].try_into().ok()?,
+            signature: bytes[10..].try_into().ok()?,
+            trusted_comment: trusted_comment.to_string(),
+            global: global.as_slice().try_into().ok()?,
+        })
+    }
+
+    fn parse_ssh(text: &str) -> Option<Self> {
+        let encoded: String = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let blob = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .ok()?;
+        let mut wire = Wire(&blob);
+        if wire.take(6)? != b"SSHSIG" || wire.u32()? != 1 {
+            return None;
+        }
+        let key_blob = wire.string()?.to_vec();
+        if wire.string()? != SSH_NAMESPACE.as_bytes() {
+            return None;
+        }
+        let reserved = wire.string()?.to_vec();
+        let hash = String::from_utf8(wire.string()?.to_vec()).ok()?;
+        let mut signature = Wire(wire.string()?);
+        if signature.string()? != b"ssh-ed25519" {
+            return None;
+        }
+        Some(Self::Ssh {
+            key_blob,
+            reserved,
+            hash,
+            signature: signature.string()?.try_into().ok()?,
+        })
+    }
+
+    /// The key that made it, as named in [`SignatureCheck::key`].
+    fn identity(&self) -> String {
+        match self {
+            Self::Minisign { key_id, .. } => minisign_identity(key_id),
+            Self::Ssh { key_blob, .. } => ssh_identity(key_blob),
+        }
+    }
+
+    fn made_by(&self, key: &PublicKey) -> bool {
+        match (self, key) {
+            (Self::Minisign { key_id, .. }, PublicKey::Minisign { id, .. }) => key_id == id,
+            (Self::Ssh { key_blob, .. }, PublicKey::Ssh { blob, .. }) => key_blob == blob,
+            _ => false,
+        }
+    }
+
+    /// Whether it is `key`'s valid signature of `data`.
+    fn verify(&self, key: &PublicKey, data: &[u8]) -> bool {
+        match (self, key) {
+            (
+                Self::Minisign {
+                    prehashed,
+                    signature,
+                    trusted_comment,
+                    global,
+                    ..
+                },
+                PublicKey::Minisign { key, .. },
+            ) => {
+                let message = if *prehashed {
+                    let Some(hash) = blake2b512(data) else {
+                        return false;
+                    };
+                    hash
+                } else {
+                    data.to_vec()
+                };
+                let mut signed_comment = signature.to_vec();
+                signed_comment.extend_from_slice(trusted_comment.as_bytes());
+                key.verify(&message, &Ed25519Signature::from_bytes(signature))
+                    .is_ok()
+                    && key
+                        .verify(&signed_comment, &Ed25519Signature::from_bytes(global))
+                        .is_ok()
+            }
+            (
+                Self::Ssh {
+                    reserved,
+                    hash,
+                    signature,
+                    ..
+                },
+                PublicKey::Ssh { key, .. },
+            ) => {
+                let digest = match hash.as_str() {
+                    "sha512" => Sha512::digest(data).to_vec(),
+                    "sha256" => Sha256::digest(data).to_vec(),
+                    _ => return false,
+                };
+                let mut message = b"SSHSIG".to_vec();
+                wire_string(&mut message, SSH_NAMESPACE.as_bytes());
+                wire_string(&mut message, reserved);
+                wire_string(&mut message, hash.as_bytes());
+                wire_string(&mut message, &digest);
+                key.verify(&message, &Ed25519Signature::from_bytes(signature))
+                    .is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+fn blake2b512(data: &[u8]) -> Option<Vec<u8>> {
+    let digest = openssl::hash::MessageDigest::from_name("BLAKE2b512")?;
+    openssl::hash::hash(digest, data).ok().map(|d| d.to_vec())
+}
+
+/// Signature files among `assets`, each with the asset it signs, smallest
+/// signed asset first.
+fn signed_assets(assets: &[Asset]) -> Vec<(&Asset, &Asset)> {
+    let mut signed: Vec<(&Asset, &Asset)> = assets
+        .iter()
+        .filter_map(|signature| {
+            let target = [".minisig", ".sig", ".asc"]
+                .iter()
+                .find_map(|suffix| signature.name.strip_suffix(suffix))?;
+            let target = assets.iter().find(|a| a.name == target)?;
+            Some((signature, target))
+        })
+        .collect();
+    signed.sort_by_key(|(_, target)| target.size);
+    signed
+}
+
+async fn download(client: &Client, url: &str, limit: u64) -> Result<Vec<u8>> {
+    debug::request("GET", url);
+    let response = client.get(url).send().await?;
+    debug::response(&response);
+    let mut response = response.error_for_status()?;
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        data.extend_from_slice(&chunk);
+        if data.len() as u64 > limit {
+            anyhow::bail!("{} is larger than {} bytes", url, limit);
+        }
+    }
+    Ok(data)
+}
+
+/// Check a release's signature against `keys`; none when no signature
+/// could be downloaded, so the next fetch tries again.
+async fn check_release(
+    client: &Client,
+    keys: &[PublicKey],
+    assets: &[Asset],
+) -> Option<SignatureCheck> {
+    let signed = signed_assets(assets);
+    if signed.is_empty() {
+        return Some(SignatureCheck::new(SignatureStatus::Unsigned, None, None));
+    }
+    let mut unverifiable = None;
+    for (signature_file, target) in signed.into_iter().take(MAX_SIGNATURE_FILES) {
+        let file = Some(signature_file.name.as_str());
+        if signature_file.size > MAX_SIGNATURE_BYTES || target.size > MAX_SIGNED_BYTES {
+            unverifiable.get_or_insert(file);
+            continue;
+        }
+        let Ok(content) = download(client, &signature_file.url, MAX_SIGNATURE_BYTES).await else {
+            continue;
+        };
+        let Some(signature) = Signature::parse(&signature_file.name, &content) else {
+            unverifiable.get_or_insert(file);
+            continue;
+        };
+        let Some(key) = keys.iter().find(|k| signature.made_by(k)) else {
+            let other = Some(signature.identity());
+            return Some(SignatureCheck::new(SignatureStatus::OtherKey, file, other));
+        };
+        let Ok(data) = download(client, &target.url, MAX_SIGNED_BYTES).await else {
+            continue;
+        };
+        let status = if signature.verify(key, &data) {
+            SignatureStatus::Trusted
+        } else {
+            SignatureStatus::Invalid
+        };
+        return Some(SignatureCheck::new(status, file, Some(key.identity())));
+    }
+    let file = unverifiable?;
+    Some(SignatureCheck::new(
+        SignatureStatus::Unverifiable,
+        file,
+        None,
+    ))
+}
+
+/// Whether two fetches of a release have the same assets, so a signature
+/// check of one holds for the other.
+fn same_assets(a: &Release, b: &Release) -> bool {
+    a.assets.len() == b.assets.len()
+        && a.assets
+            .iter()
+            .zip(&b.assets)
+            .all(|(a, b)| a.name == b.name && a.url == b.url && a.size == b.size)
+}
+
+#[derive(Debug)]
+pub struct TrustedPublishers {
+    /// Expected keys by cache key
+    by_repo: HashMap<String, Vec<PublicKey>>,
+}
+
+impl TrustedPublishers {
+    /// Fails on routes that don't name a repo and on keys that are neither
+    /// minisign nor ssh-ed25519 public keys.
+    pub fn from_config(repos: &[RepoConfig]) -> Result<Self> {
+        let mut by_repo = HashMap::new();
+        for entry in repos.iter().filter(|e| !e.signing_keys.is_empty()) {
+            let (_, repo) = provider::parse_route(&entry.repo)?;
+            let keys = entry
+                .signing_keys
+                .iter()
+                .map(|key| {
+                    PublicKey::parse(key).map_err(|e| {
+                        anyhow::anyhow!("Invalid signing key {:?} for {}: {}", key, entry.repo, e)
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            by_repo.insert(repo.cache_key(), keys);
+        }
+        Ok(Self { by_repo })
+    }
+
+    /// Set each release's `signature`: checked anew for the newest releases
+    /// whose assets changed since `previous`, carried over for the others.
+    /// New releases not signed by an expected key are logged.
+    pub async fn check(
+        &self,
+        client: &Client,
+        cache_key: &str,
+        previous: &[Release],
+        releases: &mut [Release],
+    ) {
+        let Some(keys) = self.by_repo.get(cache_key) else {
+            return;
+        };
+        let mut checked = 0;
+        for release in releases.iter_mut().filter(|r| !r.draft) {
+            let before = previous.iter().find(|p| p.tag_name == release.tag_name);
+            if let Some(before) = before
+                && same_assets(before, release)
+                && before.signature.is_some()
+            {
+                release.signature = before.signature.clone();
+                continue;
+            }
+            if checked == MAX_CHECKED_RELEASES {
+                continue;
+            }
+            checked += 1;
+            release.signature = check_release(client, keys, &release.assets).await;
+            if let Some(check) = &release.signature
+                && check.status != SignatureStatus::Trusted
+                && before.is_none()
+            {
+                eprintln!(
+                    "WARNING: new release {} of {} is not signed by a trusted publisher: {}{}",
+                    release.tag_name,
+                    cache_key,
+                    check.status.label().to_lowercase(),
+                    check
+                        .key
+                        .as_deref()
+                        .map(|key| format!(" ({})", key))
+                        .unwrap_or_default()
+                );
+            }
+        }
+    }
+
+    /// For repos with expected keys, whether the latest release is signed by
+    /// one of them.
+    pub fn trusted(&self, cache_key: &str, releases: &[Release]) -> Option<bool> {
+        let keys = self.by_repo.get(cache_key)?;
+        let latest = releases.iter().find(|r| !r.draft);
+        Some(
+            latest
+                .and_then(|r| r.signature.as_ref())
+                .is_some_and(|check| {
+                    check.status == SignatureStatus::Trusted
+                        && keys
+                            .iter()
+                            .any(|key| check.key.as_deref() == Some(key.identity().as_str()))
+                }),
+        )
+    }
+}
@@ -0,0 +1,46 @@
+// Service worker for the installable dashboard: serves the dashboard and the
+// release pages opened from it from cache when offline (network first).
+const CACHE = "checkup-v1";
+const SHELL = ["/dashboard", "/manifest.webmanifest", "/icon.svg", "/icon-maskable.svg"];
+const CACHED_PREFIXES = ["/dashboard", "/github/", "/gitlab/", "/forgejo/", "/cgit/"];
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(caches.open(CACHE).then((cache) => cache.addAll(SHELL)));
+  self.skipWaiting();
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches.keys().then((keys) =>
+      Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key)))
+    )
+  );
+  self.clients.claim();
+});
+
+self.addEventListener("fetch", (event) => {
+  const url = new URL(event.request.url);
+  if (
+    event.request.method !== "GET" ||
+    url.origin !== self.location.origin ||
+    url.pathname.includes("/latest") ||
+    !(SHELL.includes(url.pathname) || CACHED_PREFIXES.some((p) => url.pathname.startsWith(p)))
+  ) {
+    return;
+  }
+
+  event.respondWith(
+    fetch(event.request)
+      .then((response) => {
+        // Don't keep "Processing" and error pages around
+        if (response.ok && !response.headers.get("cache-control")?.includes("no-store")) {
+          const copy = response.clone();
+          caches.open(CACHE).then((cache) => cache.put(event.request, copy));
+        }
+        return response;
+      })
+      .catch(() =>
+        caches.match(event.request).then((cached) => cached || caches.match("/dashboard"))
+      )
+  );
+});
@@ -0,0 +1,109 @@
+//! Per-client quotas on upstream fetches triggered by requests for uncached repos,
+//! so a single crawler can't use a public instance to enumerate thousands of repos.
+
+use crate::{AppState, error::AppError, shedding};
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{StatusCode, request::Parts},
+};
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use std::{
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+/// Length of the window the quota applies to.
+const WINDOW_SECS: i64 = 3600;
+/// Cap on tracked clients; windows that already ended are pruned past this.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Address of the requesting client. Taken from the first `X-Forwarded-For`
+/// entry when running behind a trusted proxy, otherwise from the socket.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        if state.fetch_quota.trust_proxy
+            && let Some(ip) = parts
+                .headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .and_then(|v| v.trim().parse().ok())
+        {
+            return Ok(ClientIp(ip));
+        }
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        Ok(ClientIp(ip))
+    }
+}
+
+pub struct FetchQuota {
+    /// Uncached fetches allowed per client per hour; `None` disables the quota
+    limit: Option<u32>,
+    allowlist: Vec<IpAddr>,
+    trust_proxy: bool,
+    windows: DashMap<IpAddr, (DateTime<Utc>, u32)>,
+}
+
+impl FetchQuota {
+    pub fn new(limit: Option<u32>, allowlist: Vec<IpAddr>, trust_proxy: bool) -> Self {
+        Self {
+            limit,
+            allowlist,
+            trust_proxy,
+            windows: DashMap::new(),
+        }
+    }
+
+    /// Count one upstream fetch against `client`, rejecting it with 429 once the
+    /// client has used up its hourly quota.
+    pub fn acquire(&self, client: ClientIp) -> Result<(), AppError> {
+        let Some(limit) = self.limit else {
+            return Ok(());
+        };
+        if self.allowlist.contains(&client.0) {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let window = Duration::seconds(WINDOW_SECS);
+        if self.windows.len() >= MAX_TRACKED_CLIENTS {
+            self.windows.retain(|_, (start, _)| now - *start < window);
+        }
+
+        let mut entry = self.windows.entry(client.0).or_insert((now, 0));
+        let (start, used) = entry.value_mut();
+        if now - *start >= window {
+            *start = now;
+            *used = 0;
+        }
+        if *used >= limit {
+            shedding::set_retry_after((*start + window - now).num_seconds().max(1) as u64);
+            return Err(AppError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "Fetch quota exceeded: at most {} uncached repos per hour; try again after {}",
+                    limit,
+                    (*start + window).format("%H:%M:%S UTC")
+                ),
+            ));
+        }
+        *used += 1;
+        Ok(())
+    }
+}
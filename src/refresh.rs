@@ -0,0 +1,219 @@
+//! Background refresher for the repos on the watchlist: the config file's
+//! `[[watch]]` entries, and the repos added through `/api/v2/import`, which
+//! are kept in watchlist.json. Repos with email subscribers and those on
+//! users' watchlists are refreshed too, without being listed as watched.
+//!
+//! Each watched repo is refreshed once its cache is older than its own
+//! interval. Refreshes start after a random jitter and only `max_concurrent`
+//! run at once, so repos that were cached together don't all hit the same
+//! upstream in one burst when they come due.
+
+use crate::{
+    AppState, RepoPath, accounts,
+    cache::CacheManager,
+    config::{Config, WatchEntry},
+    provider, subscriptions,
+};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::{DashMap, DashSet};
+use std::sync::{Arc, RwLock};
+use tokio::sync::Semaphore;
+
+/// How often the watchlist is checked for repos that are due.
+const CHECK_INTERVAL_SECS: u64 = 60;
+/// Minimum wait before retrying a repo whose last refresh failed.
+const RETRY_AFTER_SECS: i64 = 900;
+
+#[derive(Debug, Clone)]
+pub struct WatchedRepo {
+    /// Route of the repo page, e.g. "/github/owner/repo"
+    pub route: String,
+    /// Provider prefix, e.g. "github"
+    pub provider: &'static str,
+    pub repo: RepoPath,
+    pub interval: Duration,
+    pub category: Option<String>,
+}
+
+impl WatchedRepo {
+    /// Fails on routes that don't name a repo.
+    fn resolve(entry: &WatchEntry, default_hours: i64) -> Result<Self> {
+        let (provider, repo) = provider::parse_route(&entry.repo)?;
+        Ok(Self {
+            route: format!("/{}", entry.repo.trim_matches('/')),
+            provider,
+            repo,
+            interval: Duration::hours(entry.interval_hours.unwrap_or(default_hours)),
+            category: entry.category.clone(),
+        })
+    }
+}
+
+pub struct Refresher {
+    /// From the config's `[[watch]]` entries
+    configured: Vec<WatchedRepo>,
+    /// Added through the API, as stored in watchlist.json
+    imported: RwLock<Vec<WatchEntry>>,
+    default_hours: i64,
+    cache: CacheManager,
+    jitter_secs: u64,
+    permits: Arc<Semaphore>,
+    /// Repos with a refresh waiting on jitter or a permit, or running
+    scheduled: Arc<DashSet<String>>,
+    /// When each repo's last refresh started, to space out retries after failures
+    attempts: Arc<DashMap<String, DateTime<Utc>>>,
+}
+
+impl Refresher {
+    /// Resolve the config's watchlist and load the imported one; fails on
+    /// routes that don't name a repo and on an unreadable watchlist.json.
+    pub fn from_config(config: &Config, default_hours: i64, cache: CacheManager) -> Result<Self> {
+        let default_hours = config.refresh.interval_hours.unwrap_or(default_hours);
+        let configured = config
+            .watch
+            .iter()
+            .map(|entry| WatchedRepo::resolve(entry, default_hours))
+            .collect::<Result<Vec<_>>>()?;
+        let imported: Vec<WatchEntry> = cache.read_watchlist()?;
+        for entry in &imported {
+            WatchedRepo::resolve(entry, default_hours)?;
+        }
+
+        Ok(Self {
+            configured,
+            imported: RwLock::new(imported),
+            default_hours,
+            cache,
+            jitter_secs: config.refresh.jitter_secs,
+            permits: Arc::new(Semaphore::new(config.refresh.max_concurrent.max(1))),
+            scheduled: Arc::new(DashSet::new()),
+            attempts: Arc::new(DashMap::new()),
+        })
+    }
+
+    /// Every watched repo, configured ones first.
+    pub fn repos(&self) -> Vec<WatchedRepo> {
+        let imported = self.imported.read().unwrap();
+        let imported = imported
+            .iter()
+            .filter_map(|entry| WatchedRepo::resolve(entry, self.default_hours).ok());
+        self.configured.iter().cloned().chain(imported).collect()
+    }
+
+    /// Add a repo to the watchlist and save it to watchlist.json. Returns
+    /// false, leaving the watchlist as it is, when the repo is watched already.
+    pub fn watch(&self, entry: WatchEntry) -> Result<bool> {
+        let cache_key = WatchedRepo::resolve(&entry, self.default_hours)?
+            .repo
+            .cache_key();
+        let mut imported = self.imported.write().unwrap();
+        let watched = self
+            .configured
+            .iter()
+            .any(|w| w.repo.cache_key() == cache_key)
+            || imported.iter().any(|e| {
+                provider::parse_route(&e.repo).is_ok_and(|(_, repo)| repo.cache_key() == cache_key)
+            });
+        if watched {
+            return Ok(false);
+        }
+        imported.push(entry);
+        self.cache.write_watchlist(&*imported)?;
+        Ok(true)
+    }
+
+    /// Check the watchlist every minute, for as long as the server runs.
+    /// Runs with an empty watchlist too, since repos can be added later.
+    pub fn spawn(self: Arc<Self>, state: Arc<AppState>) {
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+            loop {
+                ticker.tick().await;
+                self.schedule_due(&state);
+            }
+        });
+    }
+
+    /// Watched repos, then subscribed ones and those on users' watchlists
+    /// nobody watches at the default interval. Those a federation peer
+    /// watches are left to it, and archived repos aren't refreshed at all.
+    fn refreshed(&self, state: &AppState) -> Vec<WatchedRepo> {
+        let mut repos = self.repos();
+        let entries = subscriptions::watch_entries(state)
+            .into_iter()
+            .chain(accounts::watch_entries(state));
+        for entry in entries {
+            let Ok(subscribed) = WatchedRepo::resolve(&entry, self.default_hours) else {
+                continue;
+            };
+            let cache_key = subscribed.repo.cache_key();
+            let federated = state
+                .federation
+                .as_ref()
+                .is_some_and(|f| f.covers(&cache_key));
+            if !federated && !repos.iter().any(|w| w.repo.cache_key() == cache_key) {
+                repos.push(subscribed);
+            }
+        }
+        repos.retain(|w| !state.cache.is_archived(&w.repo.cache_key()));
+        repos
+    }
+
+    fn schedule_due(&self, state: &Arc<AppState>) {
+        if state.maintenance.active() {
+            return;
+        }
+        let now = Utc::now();
+        for watched in self.refreshed(state) {
+            let repo = &watched.repo;
+            let cache_key = repo.cache_key();
+            let due = match state
+                .cache
+                .read_timestamp(&repo.host, &repo.owner, &repo.repo)
+            {
+                Ok(Some(cached_at)) => now - cached_at >= watched.interval,
+                _ => true,
+            };
+            let retry_after = watched.interval.min(Duration::seconds(RETRY_AFTER_SECS));
+            let recently_tried = self
+                .attempts
+                .get(&cache_key)
+                .is_some_and(|at| now - *at < retry_after);
+            if !due || recently_tried || state.fetches.is_pending(&cache_key) {
+                continue;
+            }
+            if !self.scheduled.insert(cache_key.clone()) {
+                continue;
+            }
+
+            let state = state.clone();
+            let watched = watched.clone();
+            let permits = self.permits.clone();
+            let scheduled = self.scheduled.clone();
+            let attempts = self.attempts.clone();
+            let jitter = fastrand::u64(0..=self.jitter_secs);
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(jitter)).await;
+                let Ok(_permit) = permits.acquire().await else {
+                    return;
+                };
+                if state.fetches.start(watched.provider, &watched.repo) {
+                    attempts.insert(cache_key.clone(), Utc::now());
+                    let result =
+                        provider::fetch_and_cache(&state, watched.provider, &watched.repo).await;
+                    if let Err(e) = &result {
+                        eprintln!("Refresh of {} failed: {}", cache_key, e);
+                    }
+                    state.fetches.finish(
+                        watched.provider,
+                        &watched.repo,
+                        result.err().map(|e| e.to_string()),
+                    );
+                }
+                scheduled.remove(&cache_key);
+            });
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! `/reports/weekly`: everything the watched repos released in the last seven
+//! days, grouped by the watchlist's categories, as HTML or Markdown, followed
+//! by the deployments that are behind.
+
+use crate::{
+    AppState,
+    drift::{self, Drift},
+    format_html,
+    provider::CachedReleases,
+};
+use axum::{
+    extract::State,
+    http::header,
+    response::{Html, IntoResponse},
+};
+use chrono::{DateTime, Duration, Utc};
+use std::{collections::BTreeMap, fmt::Write, sync::Arc};
+
+const REPORT_DAYS: i64 = 7;
+/// Length of the release notes excerpt, in characters
+const EXCERPT_CHARS: usize = 200;
+/// Section for watched repos without a category
+const UNCATEGORIZED: &str = "Other";
+
+pub struct WeeklyReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Sorted by category, releases newest first
+    pub groups: Vec<ReportGroup>,
+    /// Deployments with newer releases available
+    pub drift: Vec<Drift>,
+}
+
+pub struct ReportGroup {
+    pub category: String,
+    pub releases: Vec<ReportRelease>,
+}
+
+pub struct ReportRelease {
+    /// Route of the repo page, e.g. "/github/owner/repo"
+    pub route: String,
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub published_at: DateTime<Utc>,
+    /// When a rolling tag's assets were last rebuilt
+    pub updated_at: Option<DateTime<Utc>>,
+    pub html_url: String,
+    pub prerelease: bool,
+    pub excerpt: Option<String>,
+}
+
+impl ReportRelease {
+    /// When the release was published, or rebuilt last
+    pub fn last_changed(&self) -> DateTime<Utc> {
+        self.updated_at.unwrap_or(self.published_at)
+    }
+
+    /// "2024-05-01", or "2024-05-01, rebuilt 2024-05-06" for rolling tags
+    pub fn date_label(&self) -> String {
+        let published = self.published_at.format("%Y-%m-%d");
+        match self.updated_at {
+            Some(updated_at) => format!("{}, rebuilt {}", published, updated_at.format("%Y-%m-%d")),
+            None => published.to_string(),
+        }
+    }
+}
+
+/// Build the report from the watched repos' cached releases.
+pub fn weekly_report(state: &AppState) -> WeeklyReport {
+    let to = Utc::now();
+    let from = to - Duration::days(REPORT_DAYS);
+
+    let mut groups: BTreeMap<String, Vec<ReportRelease>> = BTreeMap::new();
+    for watched in state.refresher.repos() {
+        let repo = &watched.repo;
+        let Ok(Some(cached)) =
+            state
+                .cache
+                .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        else {
+            continue;
+        };
+        let category = watched
+            .category
+            .clone()
+            .unwrap_or_else(|| UNCATEGORIZED.to_string());
+        groups.entry(category).or_default().extend(
+            cached
+                .releases
+                .into_iter()
+                .filter(|r| {
+                    let in_week = |t: DateTime<Utc>| t >= from && t <= to;
+                    !r.draft && (in_week(r.published_at) || r.updated_at.is_some_and(in_week))
+                })
+                .map(|r| ReportRelease {
+                    route: watched.route.clone(),
+                    excerpt: r.body.as_deref().and_then(excerpt),
+                    tag_name: r.tag_name,
+                    name: r.name,
+                    published_at: r.published_at,
+                    updated_at: r.updated_at,
+                    html_url: r.html_url,
+                    prerelease: r.prerelease,
+                }),
+        );
+    }
+
+    let groups = groups
+        .into_iter()
+        .filter(|(_, releases)| !releases.is_empty())
+        .map(|(category, mut releases)| {
+            releases.sort_by_key(|r| std::cmp::Reverse(r.last_changed()));
+            ReportGroup { category, releases }
+        })
+        .collect();
+
+    let drift = state
+        .deployments
+        .all()
+        .iter()
+        .map(|d| drift::drift(state, d))
+        .filter(|d| d.releases_behind > 0)
+        .collect();
+
+    WeeklyReport {
+        from,
+        to,
+        groups,
+        drift,
+    }
+}
+
+/// First lines of release notes as plain text, without headings, list
+/// markers or emphasis, cut at `EXCERPT_CHARS`.
+fn excerpt(notes: &str) -> Option<String> {
+    let text = notes
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            l.trim_start_matches(['-', '*', '+', ' '])
+                .replace(['*', '`'], "")
+        })
+        .collect::<Vec<_>>()
+        .join(" · ");
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= EXCERPT_CHARS {
+        return Some(text);
+    }
+    let cut: String = text.chars().take(EXCERPT_CHARS).collect();
+    Some(format!("{}…", cut.trim_end()))
+}
+
+pub fn format_markdown(report: &WeeklyReport) -> String {
+    let mut md = format!(
+        "# Weekly Release Report\n\n{} to {}\n",
+        report.from.format("%Y-%m-%d"),
+        report.to.format("%Y-%m-%d")
+    );
+    if report.groups.is_empty() {
+        md.push_str("\nNo watched repository published a release this week.\n");
+    }
+    for group in &report.groups {
+        let _ = write!(md, "\n## {}\n\n", group.category);
+        for r in &group.releases {
+            let _ = writeln!(
+                md,
+                "- **{}** [{}]({}){} — {}",
+                r.route.trim_start_matches('/'),
+                r.tag_name,
+                r.html_url,
+                if r.prerelease { " (pre-release)" } else { "" },
+                r.date_label()
+            );
+            if let Some(excerpt) = &r.excerpt {
+                let _ = writeln!(md, "  > {}", excerpt);
+            }
+        }
+    }
+    if !report.drift.is_empty() {
+        md.push_str("\n## Deployment drift\n\n");
+        for d in &report.drift {
+            let _ = writeln!(
+                md,
+                "- **{}** {} → {}: {}",
+                d.route,
+                d.deployed,
+                d.latest.as_deref().unwrap_or_default(),
+                drift_summary(d)
+            );
+        }
+    }
+    md
+}
+
+/// "3 releases behind, for 45 days"
+pub fn drift_summary(drift: &Drift) -> String {
+    let releases = match drift.releases_behind {
+        1 => "1 release behind".to_string(),
+        n => format!("{} releases behind", n),
+    };
+    match drift.days_behind {
+        Some(days) => format!("{}, for {} days", releases, days),
+        None => releases,
+    }
+}
+
+pub async fn weekly_html(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Html(format_html::format_weekly_report_html(&weekly_report(
+        &state,
+    )))
+}
+
+pub async fn weekly_markdown(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        format_markdown(&weekly_report(&state)),
+    )
+}
@@ -0,0 +1,111 @@
+//! `/api/v2/resolve?url=`: the Checkup page of an upstream URL, with the
+//! latest cached release, for browser extensions and bookmarklets that jump
+//! from a forge page to its mirror here.
+
+use crate::{
+    AppState,
+    cache_control::RouteKind,
+    error::{ApiError, AppError},
+    provider::{self, CachedReleases, Release},
+    signing::json_response,
+};
+use axum::{
+    extract::{Query, State},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveQuery {
+    url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Resolved {
+    provider: &'static str,
+    /// Route of the release page, e.g. "/github/owner/repo"
+    route: String,
+    page_url: String,
+    json_url: String,
+    /// When the releases were fetched; `null` while the repo isn't cached
+    cached_at: Option<DateTime<Utc>>,
+    /// Whether a fetch of the repo is running
+    fetching: bool,
+    latest: Option<LatestRelease>,
+}
+
+#[derive(Debug, Serialize)]
+struct LatestRelease {
+    tag_name: String,
+    name: Option<String>,
+    published_at: DateTime<Utc>,
+    prerelease: bool,
+    html_url: String,
+}
+
+impl From<&Release> for LatestRelease {
+    fn from(release: &Release) -> Self {
+        Self {
+            tag_name: release.tag_name.clone(),
+            name: release.name.clone(),
+            published_at: release.published_at,
+            prerelease: release.prerelease,
+            html_url: release.html_url.clone(),
+        }
+    }
+}
+
+/// Answered from the cache only: repos that aren't cached are fetched once
+/// their `page_url` is visited. Any origin may call it, errors included.
+pub async fn resolve_handler(
+    Query(query): Query<ResolveQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let mut response = resolve(query, &state).into_response();
+    response.headers_mut().insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    response
+}
+
+fn resolve(query: ResolveQuery, state: &AppState) -> Result<Response, ApiError> {
+    let url = query.url.filter(|u| !u.trim().is_empty()).ok_or_else(|| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Missing ?url=<repo or release page URL> parameter".to_string(),
+        )
+    })?;
+    let (prefix, repo) = provider::resolve_repo(&url).map_err(|e| AppError::from_route(&e))?;
+
+    let route = provider::repo_route(prefix, &repo);
+    let cached = state
+        .cache
+        .read_json::<CachedReleases>(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?;
+    // The latest stable release, or the latest one when all are prereleases
+    let latest = cached.as_ref().and_then(|c| {
+        let published = || c.releases.iter().filter(|r| !r.draft);
+        published()
+            .find(|r| !r.prerelease)
+            .or_else(|| published().next())
+            .map(LatestRelease::from)
+    });
+    let resolved = Resolved {
+        provider: prefix,
+        page_url: format!("{}{}", state.render.public_url, route),
+        json_url: format!("{}{}/+json", state.render.public_url, route),
+        cached_at: cached.as_ref().map(|c| c.cached_at),
+        fetching: state.fetches.is_pending(&repo.cache_key()),
+        latest,
+        route,
+    };
+
+    let body = serde_json::to_string_pretty(&resolved).map_err(AppError::internal)?;
+    Ok(state
+        .cache_policy
+        .apply(RouteKind::Json, json_response(state, StatusCode::OK, body)))
+}
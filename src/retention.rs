@@ -0,0 +1,120 @@
+//! How long stored assets are kept: with `[assets] keep_releases`, or
+//! `keep_releases` in a `[[repo]]` entry, only the assets of a repo's newest
+//! releases are pinned to IPFS (`ipfs.rs`) and get delta patches
+//! (`deltas.rs`). After each fetch, the pins and patches of releases that
+//! fell behind are removed, so the storage stays bounded while the
+//! downloads people actually make are still served. Recorded hashes are
+//! small and kept for every release.
+
+use crate::{AppState, RepoPath, config::RepoConfig, deltas, ipfs, provider, provider::Release};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Default)]
+pub struct Retention {
+    /// Releases kept of every repo (`[assets] keep_releases`)
+    global: Option<usize>,
+    /// Per repo (`[[repo]] keep_releases`), by cache key
+    by_repo: HashMap<String, usize>,
+}
+
+impl Retention {
+    pub fn from_config(global: Option<usize>, repos: &[RepoConfig]) -> Result<Self> {
+        let mut by_repo = HashMap::new();
+        for entry in repos {
+            if let Some(keep) = entry.keep_releases {
+                let (_, repo) = provider::parse_route(&entry.repo)?;
+                by_repo.insert(repo.cache_key(), keep);
+            }
+        }
+        Ok(Self { global, by_repo })
+    }
+
+    /// Releases of the repo whose assets are stored; None keeps all.
+    pub fn keep(&self, cache_key: &str) -> Option<usize> {
+        self.by_repo.get(cache_key).copied().or(self.global)
+    }
+
+    /// Tags of the releases whose assets are stored: the newest ones, drafts
+    /// aside. None when all are.
+    fn kept_tags<'a>(&self, cache_key: &str, releases: &'a [Release]) -> Option<HashSet<&'a str>> {
+        let keep = self.keep(cache_key)?;
+        Some(
+            releases
+                .iter()
+                .filter(|r| !r.draft)
+                .take(keep)
+                .map(|r| r.tag_name.as_str())
+                .collect(),
+        )
+    }
+
+    /// Whether the assets of `releases[index]` (newest first) are stored.
+    pub fn keeps(&self, cache_key: &str, releases: &[Release], index: usize) -> bool {
+        self.kept_tags(cache_key, releases)
+            .is_none_or(|kept| kept.contains(releases[index].tag_name.as_str()))
+    }
+}
+
+/// Remove the pins and patches of assets of releases that are no longer
+/// among the newest, after a fetch. Runs before recorded CIDs are applied,
+/// so the releases no longer show the removed ones.
+pub fn apply(state: &AppState, repo: &RepoPath, releases: &[Release]) -> Result<()> {
+    let Some(kept) = state.retention.kept_tags(&repo.cache_key(), releases) else {
+        return Ok(());
+    };
+    let urls: HashSet<&str> = releases
+        .iter()
+        .filter(|r| kept.contains(r.tag_name.as_str()))
+        .flat_map(|r| &r.assets)
+        .map(|a| a.url.as_str())
+        .collect();
+    ipfs::release_pins(state, repo, &urls)?;
+    deltas::remove_patches(state, repo, releases, &kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn releases(tags: &[(&str, bool)]) -> Vec<Release> {
+        tags.iter()
+            .map(|(tag, draft)| {
+                serde_json::from_value(serde_json::json!({
+                    "tag_name": tag,
+                    "name": null,
+                    "published_at": "2024-01-01T00:00:00Z",
+                    "html_url": "",
+                    "body": null,
+                    "prerelease": false,
+                    "draft": draft,
+                    "assets": [],
+                    "source_tarball": null,
+                    "source_zipball": null,
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn newest_releases_are_kept_drafts_aside() {
+        let releases = releases(&[("v4", true), ("v3", false), ("v2", false), ("v1", false)]);
+        let mut retention = Retention {
+            global: Some(2),
+            by_repo: HashMap::new(),
+        };
+        let kept: Vec<bool> = (0..4)
+            .map(|i| retention.keeps("github.com/o/r", &releases, i))
+            .collect();
+        assert_eq!(kept, [false, true, true, false]);
+
+        retention.by_repo.insert("github.com/o/r".to_string(), 1);
+        assert!(retention.keeps("github.com/o/r", &releases, 1));
+        assert!(!retention.keeps("github.com/o/r", &releases, 2));
+        assert!(retention.keeps("github.com/o/other", &releases, 2));
+
+        let all = Retention::default();
+        assert!((0..4).all(|i| all.keeps("github.com/o/r", &releases, i)));
+    }
+}
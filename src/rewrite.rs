@@ -0,0 +1,52 @@
+//! Config-driven rewriting of asset URLs (`[[rewrite]]`), e.g. to point
+//! downloads at an internal Artifactory or Nexus mirror for air-gapped
+//! networks. Rewritten URLs are what gets cached, so pages, JSON and
+//! `latest*` redirects all use them.
+
+use crate::{config::RewriteConfig, provider::Release};
+use anyhow::{Context, Result};
+use regex::Regex;
+
+#[derive(Debug, Default)]
+pub struct UrlRewriter {
+    /// Pattern and replacement (`$1`, `${name}`), first match wins
+    rules: Vec<(Regex, String)>,
+}
+
+impl UrlRewriter {
+    pub fn from_config(rules: &[RewriteConfig]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let pattern = Regex::new(&rule.pattern)
+                    .with_context(|| format!("Invalid rewrite pattern {:?}", rule.pattern))?;
+                Ok((pattern, rule.replace.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    fn rewrite(&self, url: &mut String) {
+        if let Some((pattern, replace)) = self.rules.iter().find(|(p, _)| p.is_match(url)) {
+            *url = pattern.replace(url, replace.as_str()).into_owned();
+        }
+    }
+
+    /// Rewrite the asset and source archive URLs of `releases`.
+    pub fn apply(&self, releases: &mut [Release]) {
+        if self.rules.is_empty() {
+            return;
+        }
+        for release in releases.iter_mut() {
+            for asset in release.assets.iter_mut() {
+                self.rewrite(&mut asset.url);
+            }
+            for url in [&mut release.source_tarball, &mut release.source_zipball]
+                .into_iter()
+                .flatten()
+            {
+                self.rewrite(url);
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Rolling tags: projects publishing builds under a fixed tag such as
+//! "nightly" or "continuous" replace its assets while the tag, and often the
+//! release date, stay the same. Each fetch compares the assets of every
+//! release with those cached for the same tag, by name, URL, size and
+//! upstream upload time. When they differ, the release gets `updated_at`,
+//! which release pages, the calendar feeds and the weekly report show as a
+//! rebuild, and hashes the download proxy recorded for the replaced files
+//! are dropped.
+
+use crate::provider::{Asset, Release};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Whether two assets of the same name are the same file.
+fn same_file(a: &Asset, b: &Asset) -> bool {
+    a.url == b.url && a.size == b.size && a.updated_at == b.updated_at
+}
+
+/// Set `updated_at` on releases whose assets changed since `previous` was
+/// cached, and carry it over on the others. The newest upstream upload time
+/// of the assets is used when there is one past the release date, else
+/// `now`. Returns the URLs of replaced assets; their hashes no longer hold,
+/// so hashes carried over from the cached asset are cleared.
+pub fn detect_updates(
+    previous: &[Release],
+    releases: &mut [Release],
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let previous: HashMap<&str, &Release> =
+        previous.iter().map(|r| (r.tag_name.as_str(), r)).collect();
+    let mut replaced = Vec::new();
+    for release in releases {
+        let Some(cached) = previous.get(release.tag_name.as_str()) else {
+            continue;
+        };
+        let cached_assets: HashMap<&str, &Asset> =
+            cached.assets.iter().map(|a| (a.name.as_str(), a)).collect();
+        let mut changed = cached_assets.len() != release.assets.len();
+        for asset in &mut release.assets {
+            let Some(old) = cached_assets.get(asset.name.as_str()) else {
+                changed = true;
+                continue;
+            };
+            if same_file(old, asset) {
+                continue;
+            }
+            changed = true;
+            replaced.push(asset.url.clone());
+            if asset.sha256.is_some() && asset.sha256 == old.sha256 {
+                asset.sha256 = None;
+                asset.sri = None;
+            }
+        }
+        if !changed {
+            release.updated_at = cached.updated_at;
+            continue;
+        }
+        let uploaded = release.assets.iter().filter_map(|a| a.updated_at).max();
+        release.updated_at = Some(uploaded.unwrap_or(now)).filter(|t| *t > release.published_at);
+    }
+    replaced
+}
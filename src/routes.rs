@@ -0,0 +1,496 @@
+//! Parsing of the paths under a provider prefix: which repo a path names and
+//! what is asked of it (`/+json`, `/latest*`, `/{tag}/notes`, ...). A suffix
+//! only counts when what precedes it is a whole repo of that provider, so a
+//! repo named "latest-cli", or a GitLab namespace with a dot in it, is taken
+//! as the repo rather than as a redirect or a host.
+
+use crate::{
+    RepoPath,
+    channels::{Channel, ChannelFeed},
+    provider::{gitlab, huggingface, launchpad, snap, sourcehut},
+};
+
+/// What a route asks of its repo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    /// The release page
+    Page,
+    /// `/+json`
+    Json,
+    /// `/releases`, the `+json` releases new since `?since=`
+    Releases,
+    /// `/tags.json`
+    Tags,
+    /// `/+check`
+    Check,
+    /// `/badge.svg`
+    Badge,
+    /// `/releases.ics`
+    Calendar,
+    /// `/update-info.json`
+    UpdateInfo,
+    /// `/pin`
+    Pin,
+    /// `/latest*`: redirect to the asset with this `latest` name
+    Latest(&'a str),
+    /// `/{tag}/SHA256SUMS`, or its `.sig` with `signature`
+    Sha256Sums { tag: &'a str, signature: bool },
+    /// `/{tag}/sbom`
+    Sbom(&'a str),
+    /// `/{tag}/notes`, or its Markdown source `/{tag}/notes.md`
+    Notes { tag: &'a str, markdown: bool },
+    /// `/channel/{name}`, or its `/+json` or `/releases.ics` feed
+    Channel { channel: Channel, feed: ChannelFeed },
+}
+
+/// Suffixes naming a [`Target`] on their own
+const SUFFIXES: [(&str, Target<'static>); 8] = [
+    ("tags.json", Target::Tags),
+    ("+check", Target::Check),
+    ("badge.svg", Target::Badge),
+    ("releases.ics", Target::Calendar),
+    ("update-info.json", Target::UpdateInfo),
+    ("pin", Target::Pin),
+    ("releases", Target::Releases),
+    ("+json", Target::Json),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route<'a> {
+    /// Path of the repo, as given to [`split_repo`]
+    pub repo: &'a str,
+    pub target: Target<'a>,
+}
+
+/// Split the path after `/{prefix}/` into the repo and its target. Paths
+/// without a suffix, or whose suffix doesn't follow a repo, are the release
+/// page of the whole path, which may well not name a repo either.
+pub fn parse<'a>(prefix: &str, path: &'a str) -> Route<'a> {
+    let is_repo = |repo: &str| split_repo(prefix, repo).is_some();
+    let route = |repo, target| Some(Route { repo, target });
+    let found = (|| {
+        if let Some((repo, channel, feed)) = channel_route(path)
+            && is_repo(repo)
+        {
+            return route(repo, Target::Channel { channel, feed });
+        }
+        let (rest, last) = path.rsplit_once('/')?;
+        if last.starts_with("latest") && is_repo(rest) {
+            return route(rest, Target::Latest(last));
+        }
+        if let Some(&(_, target)) = SUFFIXES.iter().find(|(s, _)| *s == last)
+            && is_repo(rest)
+        {
+            return route(rest, target);
+        }
+        let (repo, tag) = rest.rsplit_once('/')?;
+        if tag.is_empty() || !is_repo(repo) {
+            return None;
+        }
+        match last {
+            "SHA256SUMS" => route(
+                repo,
+                Target::Sha256Sums {
+                    tag,
+                    signature: false,
+                },
+            ),
+            "SHA256SUMS.sig" => route(
+                repo,
+                Target::Sha256Sums {
+                    tag,
+                    signature: true,
+                },
+            ),
+            "sbom" => route(repo, Target::Sbom(tag)),
+            "notes" => route(
+                repo,
+                Target::Notes {
+                    tag,
+                    markdown: false,
+                },
+            ),
+            "notes.md" => route(
+                repo,
+                Target::Notes {
+                    tag,
+                    markdown: true,
+                },
+            ),
+            _ => None,
+        }
+    })();
+    found.unwrap_or(Route {
+        repo: path,
+        target: Target::Page,
+    })
+}
+
+/// The ways "{repo}/{tag}/{file}" under `/{prefix}/` splits into a repo, a
+/// tag and a file name, longest repo first. Tags may hold slashes
+/// ("release/1.0") and so may repo paths, so which split is meant only shows
+/// in the repo's releases.
+pub fn split_tag_file<'a>(prefix: &str, path: &'a str) -> Vec<(RepoPath, &'a str, &'a str)> {
+    let Some((rest, file)) = path.rsplit_once('/') else {
+        return Vec::new();
+    };
+    if file.is_empty() {
+        return Vec::new();
+    }
+    rest.rmatch_indices('/')
+        .filter_map(|(i, _)| {
+            let tag = &rest[i + 1..];
+            let repo = split_repo(prefix, &rest[..i]).filter(|_| !tag.is_empty())?;
+            Some((repo, tag, file))
+        })
+        .collect()
+}
+
+/// Split "{repo}/channel/{name}[/+json|/releases.ics]" into its parts.
+fn channel_route(path: &str) -> Option<(&str, Channel, ChannelFeed)> {
+    let (path, feed) = match path.rsplit_once('/') {
+        Some((rest, "+json")) => (rest, ChannelFeed::Json),
+        Some((rest, "releases.ics")) => (rest, ChannelFeed::Calendar),
+        _ => (path, ChannelFeed::Page),
+    };
+    let (rest, name) = path.rsplit_once('/')?;
+    let repo = rest.strip_suffix("/channel")?;
+    Some((repo, Channel::from_name(name)?, feed))
+}
+
+/// The repo a path names under `/{prefix}/`, or `None` when it names none:
+/// - github: "{owner}/{repo}"
+/// - gitlab: "[{host}/]{namespace}/{project}", see [`gitlab::split_host`]
+/// - forgejo and gitea: "{host}/{owner}/{repo}"
+/// - codeberg: "{owner}/{repo}", on codeberg.org
+/// - sourcehut: "~{owner}/{repo}", on git.sr.ht; the "~" may be left out
+/// - launchpad: "{project}", on launchpad.net
+/// - snap: "{name}", on snapcraft.io
+/// - huggingface: "[datasets/]{owner}/{repo}", on huggingface.co
+/// - pagure: "{host}/[{namespace}/]{repo}"
+/// - gitiles, hg and cgit: "{host}/{repo path}", the repo path having any
+///   number of segments
+/// - autoindex: "{host}/{directory}", the directory likewise
+///
+/// The segments become directories of the cache (`cache.rs`), so empty, `.`
+/// and `..` segments, backslashes and NULs name no repo.
+pub fn split_repo(prefix: &str, path: &str) -> Option<RepoPath> {
+    if path.contains(['\\', '\0'])
+        || path
+            .split('/')
+            .any(|segment| matches!(segment, "" | "." | ".."))
+    {
+        return None;
+    }
+    let (host, owner, repo) = match prefix {
+        "github" => {
+            let (owner, repo) = path.split_once('/')?;
+            if repo.contains('/') {
+                return None;
+            }
+            ("github.com", owner, repo)
+        }
+        "gitlab" => {
+            let (host, path) = gitlab::split_host(path);
+            let (namespace, project) = path.split_once('/')?;
+            (host, namespace, project)
+        }
+        "codeberg" => {
+            let (owner, repo) = path.split_once('/')?;
+            if repo.contains('/') {
+                return None;
+            }
+            ("codeberg.org", owner, repo)
+        }
+        "sourcehut" => {
+            let (owner, repo) = path.split_once('/')?;
+            if repo.contains('/') || owner == "~" {
+                return None;
+            }
+            return Some(RepoPath {
+                host: sourcehut::HOST.to_string(),
+                owner: format!("~{}", owner.trim_start_matches('~')),
+                repo: repo.to_string(),
+            });
+        }
+        "launchpad" => {
+            if path.contains('/') {
+                return None;
+            }
+            (launchpad::HOST, "", path)
+        }
+        "snap" => {
+            if path.contains('/') {
+                return None;
+            }
+            (snap::HOST, "", path)
+        }
+        "huggingface" => {
+            let (kind, path) = match path.strip_prefix(huggingface::DATASETS) {
+                Some(path) => (huggingface::DATASETS, path),
+                None => ("", path),
+            };
+            let (owner, repo) = path.split_once('/')?;
+            if repo.contains('/') {
+                return None;
+            }
+            return Some(RepoPath {
+                host: huggingface::HOST.to_string(),
+                owner: format!("{}{}", kind, owner),
+                repo: repo.to_string(),
+            });
+        }
+        "pagure" => {
+            let (host, path) = path.split_once('/')?;
+            match path.split_once('/') {
+                Some((namespace, repo)) if !repo.contains('/') => (host, namespace, repo),
+                Some(_) => return None,
+                None => (host, "", path),
+            }
+        }
+        "forgejo" | "gitea" => {
+            let (host, path) = path.split_once('/')?;
+            let (owner, repo) = path.split_once('/')?;
+            if repo.contains('/') {
+                return None;
+            }
+            (host, owner, repo)
+        }
+        "cgit" | "gitiles" | "hg" | "autoindex" => {
+            let (host, repo) = path.split_once('/')?;
+            (host, "", repo)
+        }
+        _ => return None,
+    };
+    Some(RepoPath {
+        host: host.to_string(),
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::repo_route;
+    use proptest::prelude::*;
+
+    /// Owner, repo and tag names, including the troublesome ones
+    fn name() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[A-Za-z0-9_][A-Za-z0-9_.-]{0,12}",
+            "latest[a-z0-9.-]{0,8}",
+            "[a-z]{1,6}\\.(json|svg|ics|sig)",
+            Just("sbom".to_string()),
+            Just("notes".to_string()),
+            Just("SHA256SUMS".to_string()),
+            Just("+json".to_string()),
+        ]
+    }
+
+    fn host() -> impl Strategy<Value = String> {
+        "[a-z]{1,8}(\\.[a-z]{2,5}){1,2}"
+    }
+
+    /// A repo path for each prefix, with segments any repo may have
+    fn repo_path() -> impl Strategy<Value = (&'static str, String)> {
+        prop_oneof![
+            (name(), name()).prop_map(|(o, r)| ("github", format!("{}/{}", o, r))),
+            (name(), name()).prop_map(|(o, r)| ("gitlab", format!("{}/{}", o, r))),
+            (
+                host().prop_filter("self-hosted", |h| h != "gitlab.com"),
+                name(),
+                name()
+            )
+                .prop_map(|(h, o, r)| ("gitlab", format!("{}/{}/{}", h, o, r))),
+            (host(), name(), name())
+                .prop_map(|(h, o, r)| ("forgejo", format!("{}/{}/{}", h, o, r))),
+            (host(), name(), name()).prop_map(|(h, o, r)| ("gitea", format!("{}/{}/{}", h, o, r))),
+            (name(), name()).prop_map(|(o, r)| ("codeberg", format!("{}/{}", o, r))),
+            (name(), name()).prop_map(|(o, r)| ("sourcehut", format!("~{}/{}", o, r))),
+            name().prop_map(|p| ("launchpad", p)),
+            name().prop_map(|p| ("snap", p)),
+            (name(), name()).prop_map(|(o, r)| ("huggingface", format!("{}/{}", o, r))),
+            (name(), name()).prop_map(|(o, r)| ("huggingface", format!("datasets/{}/{}", o, r))),
+            (host(), name()).prop_map(|(h, r)| ("pagure", format!("{}/{}", h, r))),
+            (host(), name(), name()).prop_map(|(h, n, r)| ("pagure", format!("{}/{}/{}", h, n, r))),
+            (host(), name(), name())
+                .prop_map(|(h, p, r)| ("gitiles", format!("{}/{}/{}", h, p, r))),
+            (host(), name()).prop_map(|(h, r)| ("hg", format!("{}/{}", h, r))),
+            (host(), name()).prop_map(|(h, r)| ("cgit", format!("{}/{}", h, r))),
+            (host(), name()).prop_map(|(h, r)| ("autoindex", format!("{}/{}", h, r))),
+        ]
+    }
+
+    /// The targets with their suffix
+    fn target() -> impl Strategy<Value = (String, Target<'static>)> {
+        prop_oneof![
+            Just((String::new(), Target::Page)),
+            proptest::sample::select(SUFFIXES.to_vec()).prop_map(|(s, t)| (format!("/{}", s), t)),
+        ]
+    }
+
+    /// A repo named like a suffix can also be that suffix of a shorter repo,
+    /// which wins: a self-hosted GitLab project "{host}/{group}/latest" of
+    /// "{host}/{group}" on gitlab.com, a namespaced Pagure repo
+    /// "{host}/{namespace}/latest" of the repo "{host}/{namespace}", and
+    /// likewise a Gitiles repo path ending in one.
+    fn optional_segment(prefix: &str, path: &str) -> bool {
+        matches!(prefix, "gitlab" | "pagure" | "gitiles") && path.matches('/').count() == 2
+    }
+
+    proptest! {
+        #[test]
+        fn repos_round_trip((prefix, path) in repo_path()) {
+            let repo = split_repo(prefix, &path).unwrap();
+            prop_assert_eq!(repo_route(prefix, &repo), format!("/{}/{}", prefix, path));
+        }
+
+        #[test]
+        fn release_pages_keep_the_repo((prefix, path) in repo_path()) {
+            prop_assume!(!optional_segment(prefix, &path));
+            let route = parse(prefix, &path);
+            prop_assert_eq!(route, Route { repo: &path, target: Target::Page });
+        }
+
+        #[test]
+        fn suffixes_follow_the_repo(
+            (prefix, path) in repo_path(),
+            (suffix, target) in target(),
+        ) {
+            prop_assume!(target != Target::Page || !optional_segment(prefix, &path));
+            let full = format!("{}{}", path, suffix);
+            prop_assert_eq!(parse(prefix, &full), Route { repo: &path, target });
+        }
+
+        #[test]
+        fn latest_names((prefix, path) in repo_path(), ext in "(\\.[a-z0-9]{1,6}){0,2}") {
+            let latest = format!("latest{}", ext);
+            let full = format!("{}/{}", path, latest);
+            let route = parse(prefix, &full);
+            prop_assert_eq!(route, Route { repo: &path, target: Target::Latest(&latest) });
+        }
+
+        #[test]
+        fn tag_files(
+            (prefix, path) in repo_path(),
+            tag in name(),
+            signature in any::<bool>(),
+            markdown in any::<bool>(),
+        ) {
+            let file = if signature { "SHA256SUMS.sig" } else { "SHA256SUMS" };
+            let full = format!("{}/{}/{}", path, tag, file);
+            prop_assert_eq!(
+                parse(prefix, &full),
+                Route { repo: &path, target: Target::Sha256Sums { tag: &tag, signature } }
+            );
+            let full = format!("{}/{}/sbom", path, tag);
+            prop_assert_eq!(parse(prefix, &full), Route { repo: &path, target: Target::Sbom(&tag) });
+            let file = if markdown { "notes.md" } else { "notes" };
+            let full = format!("{}/{}/{}", path, tag, file);
+            prop_assert_eq!(
+                parse(prefix, &full),
+                Route { repo: &path, target: Target::Notes { tag: &tag, markdown } }
+            );
+        }
+
+        #[test]
+        fn tags_with_slashes(
+            (prefix, path) in repo_path(),
+            tag in "[a-z0-9.]{1,6}(/[a-z0-9.]{1,6}){0,2}",
+            file in name(),
+        ) {
+            let full = format!("{}/{}/{}", path, tag, file);
+            let key = split_repo(prefix, &path).unwrap().cache_key();
+            prop_assert!(
+                split_tag_file(prefix, &full)
+                    .iter()
+                    .any(|(repo, t, f)| repo.cache_key() == key && *t == tag && *f == file),
+                "{:?}",
+                split_tag_file(prefix, &full)
+            );
+        }
+
+        #[test]
+        fn channels(
+            (prefix, path) in repo_path(),
+            name in "stable|beta|nightly",
+            (suffix, feed) in proptest::sample::select(vec![
+                ("", ChannelFeed::Page),
+                ("/+json", ChannelFeed::Json),
+                ("/releases.ics", ChannelFeed::Calendar),
+            ]),
+        ) {
+            let channel = Channel::from_name(&name).unwrap();
+            let full = format!("{}/channel/{}{}", path, name, suffix);
+            prop_assert_eq!(
+                parse(prefix, &full),
+                Route { repo: &path, target: Target::Channel { channel, feed } }
+            );
+        }
+
+        #[test]
+        fn repos_stay_in_the_cache(
+            prefix in "github|gitlab|forgejo|gitea|codeberg|sourcehut|launchpad|snap|huggingface|pagure|gitiles|hg|cgit|autoindex",
+            segments in proptest::collection::vec(
+                prop_oneof![
+                    name(),
+                    host(),
+                    Just(".".to_string()),
+                    Just("..".to_string()),
+                    Just("~..".to_string()),
+                    Just("datasets".to_string()),
+                    "[a-z.]{0,3}[\\\\\\x00][a-z.]{0,3}",
+                ],
+                1..6,
+            ),
+        ) {
+            let path = segments.join("/");
+            if let Some(repo) = split_repo(&prefix, &path) {
+                for part in [&repo.host, &repo.owner, &repo.repo] {
+                    prop_assert!(!part.contains(['\\', '\0']), "{:?}", repo);
+                    prop_assert!(
+                        part.split('/').all(|s| s != "." && s != ".."),
+                        "{:?}",
+                        repo
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn any_path_parses(prefix in "github|gitlab|forgejo|gitea|codeberg|sourcehut|launchpad|snap|huggingface|pagure|gitiles|hg|cgit|autoindex", path in "[a-z./+-]{0,40}") {
+            let route = parse(&prefix, &path);
+            prop_assert!(path.starts_with(route.repo));
+        }
+    }
+
+    #[test]
+    fn codeberg_is_forgejo() {
+        let repo = split_repo("codeberg", "forgejo/forgejo").unwrap();
+        let forgejo = split_repo("forgejo", "codeberg.org/forgejo/forgejo").unwrap();
+        assert_eq!(repo.cache_key(), forgejo.cache_key());
+    }
+
+    #[test]
+    fn sourcehut_owners_have_a_tilde() {
+        let repo = split_repo("sourcehut", "sircmpwn/scdoc").unwrap();
+        assert_eq!(repo.cache_key(), "git.sr.ht/~sircmpwn/scdoc");
+        assert!(split_repo("sourcehut", "~/scdoc").is_none());
+    }
+
+    #[test]
+    fn repos_named_like_suffixes() {
+        let route = parse("github", "owner/latest-cli");
+        assert_eq!(route.target, Target::Page);
+        let route = parse("gitlab", "name.space/latest");
+        assert_eq!(route.target, Target::Page);
+        let repo = split_repo("gitlab", route.repo).unwrap();
+        assert_eq!(
+            (repo.host.as_str(), repo.owner.as_str()),
+            ("gitlab.com", "name.space")
+        );
+        let route = parse("forgejo", "codeberg.org/owner/sbom");
+        assert_eq!(route.target, Target::Page);
+    }
+}
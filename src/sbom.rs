@@ -0,0 +1,221 @@
+//! SBOMs published as release assets (`*.spdx.json`, `*.cdx.json`), and
+//! `/{provider}/{...}/{tag}/sbom`: a summary of the release's SBOM with its
+//! component count and licenses. Summaries are kept per tag in sboms.json,
+//! since a published SBOM doesn't change.
+
+use crate::{
+    AppState, RepoPath, cache_control::RouteKind, debug, error::AppError, format_html,
+    provider::Release, quota::ClientIp, shedding,
+};
+use anyhow::{Context, Result};
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// SBOMs larger than this are not downloaded.
+const MAX_SBOM_SIZE: u64 = 20 * 1024 * 1024;
+
+/// Whether an asset name is an SBOM document in SPDX or CycloneDX format.
+pub fn is_sbom(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    [".spdx.json", ".spdx", ".cdx.json", ".cdx.xml", ".bom.json"]
+        .iter()
+        .any(|ext| lower.ends_with(ext))
+}
+
+/// SBOMs that can be summarized; only the JSON encodings are parsed.
+pub fn is_json_sbom(name: &str) -> bool {
+    is_sbom(name) && name.to_lowercase().ends_with(".json")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SbomSummary {
+    /// Asset the summary was made from
+    pub asset: String,
+    pub url: String,
+    /// "SPDX" or "CycloneDX"
+    pub format: String,
+    /// e.g. "SPDX-2.3" or "1.5"
+    pub spec_version: Option<String>,
+    /// Name of the described document or component
+    pub name: Option<String>,
+    pub components: usize,
+    /// Components per license, most used first
+    pub licenses: Vec<LicenseCount>,
+    /// Components without a known license
+    pub unlicensed: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseCount {
+    pub license: String,
+    pub components: usize,
+}
+
+/// Summarize an SPDX or CycloneDX JSON document.
+fn summarize(asset: &str, url: &str, document: &Value) -> Result<SbomSummary> {
+    let text =
+        |value: &Value, key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+
+    let (format, spec_version, name, licenses) = if document.get("spdxVersion").is_some() {
+        let packages = document
+            .get("packages")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let licenses: Vec<Option<String>> = packages
+            .iter()
+            .map(|p| {
+                ["licenseConcluded", "licenseDeclared"]
+                    .iter()
+                    .filter_map(|key| text(p, key))
+                    .find(|l| l != "NOASSERTION" && l != "NONE")
+            })
+            .collect();
+        (
+            "SPDX",
+            text(document, "spdxVersion"),
+            text(document, "name"),
+            licenses,
+        )
+    } else if text(document, "bomFormat").as_deref() == Some("CycloneDX") {
+        let mut components = Vec::new();
+        collect_components(document, &mut components);
+        let licenses = components.iter().map(|c| cyclonedx_license(c)).collect();
+        let name = document
+            .pointer("/metadata/component/name")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        ("CycloneDX", text(document, "specVersion"), name, licenses)
+    } else {
+        anyhow::bail!("{} is neither an SPDX nor a CycloneDX document", asset);
+    };
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for license in licenses.iter().flatten() {
+        *counts.entry(license.clone()).or_default() += 1;
+    }
+    let mut counts: Vec<LicenseCount> = counts
+        .into_iter()
+        .map(|(license, components)| LicenseCount {
+            license,
+            components,
+        })
+        .collect();
+    counts.sort_by_key(|c| Reverse(c.components));
+
+    Ok(SbomSummary {
+        asset: asset.to_string(),
+        url: url.to_string(),
+        format: format.to_string(),
+        spec_version,
+        name,
+        components: licenses.len(),
+        unlicensed: licenses.iter().filter(|l| l.is_none()).count(),
+        licenses: counts,
+    })
+}
+
+/// CycloneDX components, including nested ones.
+fn collect_components<'a>(value: &'a Value, components: &mut Vec<&'a Value>) {
+    for component in value
+        .get("components")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        components.push(component);
+        collect_components(component, components);
+    }
+}
+
+/// A CycloneDX component's license: an SPDX expression, or its license ids
+/// and names joined with " AND ".
+fn cyclonedx_license(component: &Value) -> Option<String> {
+    let entries = component.get("licenses")?.as_array()?;
+    let names: Vec<&str> = entries
+        .iter()
+        .filter_map(|entry| {
+            entry
+                .get("expression")
+                .or_else(|| entry.pointer("/license/id"))
+                .or_else(|| entry.pointer("/license/name"))
+                .and_then(Value::as_str)
+        })
+        .collect();
+    (!names.is_empty()).then(|| names.join(" AND "))
+}
+
+async fn fetch_summary(state: &AppState, asset: &str, url: &str) -> Result<SbomSummary> {
+    debug::request("GET", url);
+    let response = state.client.get(url).send().await?.error_for_status()?;
+    debug::response(&response);
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_SBOM_SIZE)
+    {
+        anyhow::bail!("{} is larger than {} bytes", asset, MAX_SBOM_SIZE);
+    }
+    let document: Value = serde_json::from_slice(&response.bytes().await?)
+        .with_context(|| format!("{} is not valid JSON", asset))?;
+    summarize(asset, url, &document)
+}
+
+/// `/{tag}/sbom`: summary of the first JSON SBOM among the release's assets.
+pub async fn sbom_response(
+    state: &AppState,
+    repo: &RepoPath,
+    releases: &[Release],
+    tag: &str,
+    client: ClientIp,
+) -> Result<Response, AppError> {
+    let release = releases
+        .iter()
+        .find(|r| r.tag_name == tag)
+        .ok_or(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("No release tagged '{}' found", tag),
+        ))?;
+    let asset = release
+        .assets
+        .iter()
+        .find(|a| is_json_sbom(&a.name))
+        .ok_or(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!("Release '{}' has no SPDX or CycloneDX JSON asset", tag),
+        ))?;
+
+    let mut summaries: BTreeMap<String, SbomSummary> = state
+        .cache
+        .read_sboms(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?;
+    let summary = match summaries.get(tag) {
+        Some(summary) if summary.url == asset.url => summary.clone(),
+        _ => {
+            if asset.size > MAX_SBOM_SIZE {
+                return Err(AppError::new(
+                    StatusCode::BAD_GATEWAY,
+                    format!("{} is larger than {} bytes", asset.name, MAX_SBOM_SIZE),
+                ));
+            }
+            shedding::admit_fetch(state, client)?;
+            let summary = fetch_summary(state, &asset.name, &asset.url)
+                .await
+                .map_err(|e| AppError::from_upstream(&e))?;
+            summaries.insert(tag.to_string(), summary.clone());
+            let _ = state
+                .cache
+                .write_sboms(&repo.host, &repo.owner, &repo.repo, &summaries);
+            summary
+        }
+    };
+
+    let html = format_html::format_sbom_html(&repo.cache_key(), tag, &summary);
+    Ok(state
+        .cache_policy
+        .apply(RouteKind::Html, Html(html).into_response()))
+}
@@ -0,0 +1,119 @@
+//! `GET /schema.json`: JSON Schema of the API's responses, to generate typed
+//! clients from (quicktype, datamodel-code-generator, openapi-typescript,
+//! ...). Response types are listed under `$defs` by name and offered as
+//! alternatives at the root, so generators emit all of them.
+//!
+//! The same schema is checked in as schema.json at the repo root; the
+//! snapshot test below fails when a change to the types would change what
+//! clients see. Renaming or removing fields, or making them required, breaks
+//! generated clients; rerun the test with `UPDATE_SCHEMA=1` only for changes
+//! that are meant to ship.
+
+use crate::{
+    AppState,
+    cache_control::RouteKind,
+    error::ErrorBody,
+    firehose::FirehosePage,
+    instance::InstanceConfig,
+    provider::{CachedReleases, CachedTags, UpdateCheck},
+    signing,
+    update_info::UpdateInfo,
+};
+use axum::{extract::State, http::StatusCode, response::Response};
+use schemars::{JsonSchema, SchemaGenerator, generate::SchemaSettings};
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+/// Generate the schema of `T` into `generator`, and describe where it is served.
+fn response<T: JsonSchema>(generator: &mut SchemaGenerator, routes: &str) -> Value {
+    let mut schema = generator.subschema_for::<T>().to_value();
+    if let Some(schema) = schema.as_object_mut() {
+        schema.insert("description".into(), json!(routes));
+    }
+    schema
+}
+
+/// Schema of every JSON response, with the types they share in `$defs`.
+pub fn api_schema() -> Value {
+    let mut generator = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator();
+    let responses = vec![
+        response::<CachedReleases>(
+            &mut generator,
+            "/{provider}/{...}/+json and /{provider}/{...}/releases",
+        ),
+        response::<CachedTags>(&mut generator, "/{provider}/{...}/tags.json"),
+        response::<UpdateCheck>(&mut generator, "/{provider}/{...}/+check"),
+        response::<UpdateInfo>(&mut generator, "/{provider}/{...}/update-info.json"),
+        response::<InstanceConfig>(&mut generator, "/api/v2/config"),
+        response::<FirehosePage>(&mut generator, "/api/v2/firehose"),
+        response::<ErrorBody>(&mut generator, "Errors of API routes"),
+    ];
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "checkup API",
+        "anyOf": responses,
+        "$defs": generator.take_definitions(true),
+    })
+}
+
+/// `GET /schema.json`
+pub async fn schema_handler(State(state): State<Arc<AppState>>) -> Response {
+    let body = serde_json::to_string_pretty(&api_schema()).unwrap_or_default();
+    state.cache_policy.apply(
+        RouteKind::Json,
+        signing::json_response(&state, StatusCode::OK, body),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/schema.json");
+
+    /// Names of the `$defs` whose schema differs between `a` and `b`.
+    fn changed_defs(a: &Value, b: &Value) -> Vec<String> {
+        let empty = serde_json::Map::new();
+        let defs = |v: &Value| v["$defs"].as_object().unwrap_or(&empty).clone();
+        let (a, b) = (defs(a), defs(b));
+        let mut names: Vec<String> = a.keys().chain(b.keys()).cloned().collect();
+        names.sort();
+        names.dedup();
+        names.retain(|name| a.get(name) != b.get(name));
+        names
+    }
+
+    #[test]
+    fn schema_matches_snapshot() {
+        let schema = api_schema();
+        let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(SNAPSHOT, generated).unwrap();
+            return;
+        }
+        let snapshot = std::fs::read_to_string(SNAPSHOT).unwrap_or_default();
+        let expected: Value = serde_json::from_str(&snapshot).unwrap_or(Value::Null);
+        assert!(
+            schema == expected,
+            "API schema changed (definitions: {:?}, or the root); if clients are meant \
+             to see this, rerun with UPDATE_SCHEMA=1 and commit schema.json",
+            changed_defs(&expected, &schema)
+        );
+    }
+
+    #[test]
+    fn every_response_is_defined() {
+        let schema = api_schema();
+        for response in schema["anyOf"].as_array().unwrap() {
+            let reference = response["$ref"].as_str().unwrap();
+            let name = reference.strip_prefix("#/$defs/").unwrap();
+            assert!(
+                schema["$defs"].get(name).is_some(),
+                "{} is not defined",
+                name
+            );
+        }
+    }
+}
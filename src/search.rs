@@ -0,0 +1,223 @@
+//! `/search?q=`: find cached repos by path or release tag, so users of a shared
+//! instance can tell whether a project is already tracked.
+//!
+//! The index is built from the cache directory and rebuilt at most once a
+//! minute, when used. The homepage lists recent repos from it too.
+
+use crate::{
+    AppState, RepoPath, format_html,
+    provider::{self, CachedReleases},
+    signing,
+};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// How long a built index is reused before the cache directory is read again.
+const INDEX_TTL_SECS: i64 = 60;
+const MAX_RESULTS: usize = 50;
+/// Matching tags listed per repo
+const MAX_TAGS_PER_HIT: usize = 10;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+struct IndexedRepo {
+    route: String,
+    repo_path: String,
+    /// Newest first
+    tags: Vec<String>,
+    latest_published_at: Option<DateTime<Utc>>,
+    cached_at: DateTime<Utc>,
+}
+
+impl IndexedRepo {
+    fn hit(&self, matching_tags: Vec<String>) -> SearchHit {
+        SearchHit {
+            route: self.route.clone(),
+            repo_path: self.repo_path.clone(),
+            latest_tag: self.tags.first().cloned(),
+            latest_published_at: self.latest_published_at,
+            matching_tags,
+            cached_at: self.cached_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    /// Route of the repo page, e.g. "/github/owner/repo"
+    pub route: String,
+    pub repo_path: String,
+    pub latest_tag: Option<String>,
+    pub latest_published_at: Option<DateTime<Utc>>,
+    /// Tags containing the query, when it matched tags
+    pub matching_tags: Vec<String>,
+    pub cached_at: DateTime<Utc>,
+}
+
+struct BuiltIndex {
+    built_at: DateTime<Utc>,
+    repos: Arc<Vec<IndexedRepo>>,
+}
+
+#[derive(Default)]
+pub struct SearchIndex {
+    built: Mutex<Option<BuiltIndex>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn repos(&self, state: &AppState) -> Arc<Vec<IndexedRepo>> {
+        let mut built = self.built.lock().unwrap();
+        if let Some(index) = built.as_ref()
+            && Utc::now() - index.built_at < Duration::seconds(INDEX_TTL_SECS)
+        {
+            return index.repos.clone();
+        }
+
+        let repos: Arc<Vec<IndexedRepo>> = Arc::new(
+            state
+                .cache
+                .read_all_json::<CachedReleases>()
+                .into_iter()
+                .map(|cached| IndexedRepo {
+                    route: route_for(cached.provider.as_deref(), &cached.repo_path),
+                    tags: cached
+                        .releases
+                        .iter()
+                        .filter(|r| !r.draft)
+                        .map(|r| r.tag_name.clone())
+                        .collect(),
+                    latest_published_at: cached
+                        .releases
+                        .iter()
+                        .find(|r| !r.draft)
+                        .map(|r| r.published_at),
+                    repo_path: cached.repo_path,
+                    cached_at: cached.cached_at,
+                })
+                .collect(),
+        );
+        *built = Some(BuiltIndex {
+            built_at: Utc::now(),
+            repos: repos.clone(),
+        });
+        repos
+    }
+
+    /// Repos whose path contains every word of the query come first, then
+    /// repos with a tag containing the whole query. Case-insensitive.
+    pub fn search(&self, state: &AppState, query: &str) -> Vec<SearchHit> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let words: Vec<&str> = query.split_whitespace().collect();
+
+        let mut path_hits = Vec::new();
+        let mut tag_hits = Vec::new();
+        for repo in self.repos(state).iter() {
+            let path = repo.repo_path.to_lowercase();
+            let matching_tags: Vec<String> = repo
+                .tags
+                .iter()
+                .filter(|t| t.to_lowercase().contains(&query))
+                .take(MAX_TAGS_PER_HIT)
+                .cloned()
+                .collect();
+            if words.iter().all(|w| path.contains(w)) {
+                path_hits.push(repo.hit(matching_tags));
+            } else if !matching_tags.is_empty() {
+                tag_hits.push(repo.hit(matching_tags));
+            }
+        }
+
+        path_hits.sort_by(|a, b| a.repo_path.cmp(&b.repo_path));
+        tag_hits.sort_by(|a, b| a.repo_path.cmp(&b.repo_path));
+        path_hits.extend(tag_hits);
+        path_hits.truncate(MAX_RESULTS);
+        path_hits
+    }
+
+    /// The `limit` most recently cached repos.
+    pub fn recently_cached(&self, state: &AppState, limit: usize) -> Vec<SearchHit> {
+        let repos = self.repos(state);
+        let mut recent: Vec<&IndexedRepo> = repos.iter().collect();
+        recent.sort_by_key(|r| std::cmp::Reverse(r.cached_at));
+        recent
+            .iter()
+            .take(limit)
+            .map(|r| r.hit(Vec::new()))
+            .collect()
+    }
+
+    /// The `limit` repos with the most recently published latest release.
+    pub fn recently_released(&self, state: &AppState, limit: usize) -> Vec<SearchHit> {
+        let repos = self.repos(state);
+        let mut recent: Vec<&IndexedRepo> = repos
+            .iter()
+            .filter(|r| r.latest_published_at.is_some())
+            .collect();
+        recent.sort_by_key(|r| std::cmp::Reverse(r.latest_published_at));
+        recent
+            .iter()
+            .take(limit)
+            .map(|r| r.hit(Vec::new()))
+            .collect()
+    }
+}
+
+/// Route of a repo page from its provider and cache key ("host/owner/repo";
+/// cgit repos have no owner). Without a recorded provider it is guessed from
+/// the host.
+pub fn route_for(provider: Option<&str>, repo_path: &str) -> String {
+    let mut parts = repo_path.splitn(3, '/');
+    let (host, owner, repo) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+    if let Some(provider) = provider {
+        let repo = RepoPath {
+            host: host.to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+        };
+        return provider::repo_route(provider, &repo);
+    }
+    match host {
+        _ if owner.is_empty() => format!("/cgit/{}/{}", host, repo),
+        "github.com" => format!("/github/{}/{}", owner, repo),
+        "gitlab.com" => format!("/gitlab/{}/{}", owner, repo),
+        _ => format!("/forgejo/{}/{}/{}", host, owner, repo),
+    }
+}
+
+pub async fn search_page(
+    Query(query): Query<SearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let hits = state.search_index.search(&state, &query.q);
+    Html(format_html::format_search_html(&query.q, &hits))
+}
+
+pub async fn search_json(
+    Query(query): Query<SearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let hits = state.search_index.search(&state, &query.q);
+    let body = serde_json::to_string_pretty(&hits).unwrap_or_default();
+    signing::json_response(&state, StatusCode::OK, body)
+}
@@ -0,0 +1,91 @@
+//! Load shedding: while too many repos are being fetched, requests that would
+//! start another upstream fetch are turned away early with 429 and a
+//! `Retry-After`, while cached repos keep being served.
+//!
+//! Rejections set the `Retry-After` value for the current request; the
+//! [`retry_after_header`] middleware adds it to the response.
+
+use crate::{
+    AppState,
+    error::{AppError, ErrorCode},
+    quota::ClientIp,
+};
+use axum::{
+    extract::Request,
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+use std::cell::Cell;
+
+tokio::task_local! {
+    static RETRY_AFTER: Cell<Option<u64>>;
+}
+
+/// Retry-After sent while shedding load.
+const SHED_RETRY_AFTER_SECS: u64 = 30;
+
+pub struct LoadShedder {
+    /// Background fetches pending before new ones are refused
+    max_pending: Option<usize>,
+    /// Upstream fetches in flight before new ones are refused
+    max_in_flight: Option<usize>,
+}
+
+impl LoadShedder {
+    pub fn new(max_pending: Option<usize>, max_in_flight: Option<usize>) -> Self {
+        Self {
+            max_pending,
+            max_in_flight,
+        }
+    }
+
+    fn check(&self, pending: usize, in_flight: usize) -> Result<(), AppError> {
+        let overloaded = self.max_pending.is_some_and(|max| pending >= max)
+            || self.max_in_flight.is_some_and(|max| in_flight >= max);
+        if overloaded {
+            set_retry_after(SHED_RETRY_AFTER_SECS);
+            return Err(AppError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "Server is busy fetching other repositories; try again in {} seconds. Cached repositories are still available.",
+                    SHED_RETRY_AFTER_SECS
+                ),
+            )
+            .with_code(ErrorCode::Overloaded));
+        }
+        Ok(())
+    }
+}
+
+/// Admit an upstream fetch for an uncached or expired repo: refused in
+/// maintenance mode and while the server is overloaded, then counted
+/// against the client's quota.
+pub fn admit_fetch(state: &AppState, client: ClientIp) -> Result<(), AppError> {
+    state.maintenance.check("fetching from upstream")?;
+    state
+        .load_shedder
+        .check(state.fetches.pending_count(), state.upstream.in_flight())?;
+    state.fetch_quota.acquire(client)
+}
+
+/// Ask the client to retry after `secs` seconds; no-op outside of a request.
+pub fn set_retry_after(secs: u64) {
+    let _ = RETRY_AFTER.try_with(|retry_after| retry_after.set(Some(secs)));
+}
+
+/// Add the `Retry-After` set while handling the request, if any.
+pub async fn retry_after_header(request: Request, next: Next) -> Response {
+    let (mut response, retry_after) = RETRY_AFTER
+        .scope(Cell::new(None), async {
+            let response = next.run(request).await;
+            (response, RETRY_AFTER.with(Cell::get))
+        })
+        .await;
+    if let Some(secs) = retry_after {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    }
+    response
+}
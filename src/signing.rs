@@ -0,0 +1,81 @@
+//! Optional ed25519 signing of JSON responses.
+//!
+//! When a signing key is configured, every JSON document served carries an
+//! `X-Checkup-Signature` header with the base64 signature of the exact body
+//! bytes. The matching public key is published at `/.well-known/checkup-signing-key`.
+
+use anyhow::{Context, Result};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use base64::Engine;
+use ed25519_dalek::{
+    Signer as _, SigningKey,
+    pkcs8::{DecodePrivateKey, EncodePublicKey, spki::der::pem::LineEnding},
+};
+use std::{path::Path, sync::Arc};
+
+use crate::{
+    AppState,
+    error::{ApiError, AppError, ErrorCode},
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Checkup-Signature";
+
+#[derive(Clone)]
+pub struct ResponseSigner {
+    key: SigningKey,
+}
+
+impl ResponseSigner {
+    /// Load a PKCS#8 PEM ed25519 key, e.g. from `openssl genpkey -algorithm ed25519`.
+    pub fn from_pem_file(path: &Path) -> Result<Self> {
+        let pem = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read signing key {:?}", path))?;
+        let key = SigningKey::from_pkcs8_pem(&pem)
+            .map_err(|e| anyhow::anyhow!("Invalid ed25519 signing key {:?}: {}", path, e))?;
+        Ok(Self { key })
+    }
+
+    /// Base64 ed25519 signature over `data`.
+    pub fn sign(&self, data: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(self.key.sign(data).to_bytes())
+    }
+
+    pub fn public_key_pem(&self) -> String {
+        self.key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap_or_default()
+    }
+}
+
+/// Build a JSON response, signed when the instance has a signing key.
+pub fn json_response(state: &AppState, status: StatusCode, body: String) -> Response {
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(signer) = &state.signer {
+        builder = builder.header(SIGNATURE_HEADER, signer.sign(body.as_bytes()));
+    }
+    builder.body(Body::from(body)).unwrap()
+}
+
+pub async fn public_key_handler(State(state): State<Arc<AppState>>) -> Result<Response, ApiError> {
+    let signer = state.signer.as_ref().ok_or_else(|| {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            "Response signing is not enabled on this instance".to_string(),
+        )
+        .with_code(ErrorCode::Disabled)
+    })?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/x-pem-file")],
+        signer.public_key_pem(),
+    )
+        .into_response())
+}
@@ -0,0 +1,61 @@
+//! `/{provider}/{...}/releases?since=`: the releases that are new since a
+//! point in time, so integrations polling a repo fetch increments rather
+//! than the whole list. A release is new when it was published after
+//! `since`, or first seen after it: releases that turn up after a repo's
+//! first snapshot get `first_seen_at`, carried over from snapshot to
+//! snapshot, so one dated before `since` but only cached after it (a slow
+//! refresh, a backdated release, a tag pushed long after its commit) isn't
+//! missed. Releases of a repo's first snapshot have none.
+
+use crate::{error::AppError, provider::Release};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Set `first_seen_at` to `now` on releases that weren't in `previous`, and
+/// carry it over on the others.
+pub fn record_first_seen(previous: &[Release], releases: &mut [Release], now: DateTime<Utc>) {
+    let previous: HashMap<&str, &Release> =
+        previous.iter().map(|r| (r.tag_name.as_str(), r)).collect();
+    for release in releases {
+        release.first_seen_at = match previous.get(release.tag_name.as_str()) {
+            Some(cached) => cached.first_seen_at,
+            None => Some(now),
+        };
+    }
+}
+
+/// Parse `?since=` as an RFC 3339 timestamp. An unencoded "+" of the UTC
+/// offset arrives as a space, and is read as the "+" it was meant to be.
+pub fn parse_since(since: Option<&str>) -> Result<Option<DateTime<Utc>>, AppError> {
+    let Some(since) = since else {
+        return Ok(None);
+    };
+    DateTime::parse_from_rfc3339(&since.trim().replace(' ', "+"))
+        .map(|t| Some(t.with_timezone(&Utc)))
+        .map_err(|_| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Invalid since '{}'; use an RFC 3339 timestamp, e.g. 2024-05-01T12:00:00Z",
+                    since
+                ),
+            )
+        })
+}
+
+/// When a release became news: its publication, or its first sighting if
+/// that came later.
+pub fn observed_at(release: &Release) -> DateTime<Utc> {
+    release
+        .published_at
+        .max(release.first_seen_at.unwrap_or(release.published_at))
+}
+
+/// Releases published or first seen after `since`, in `releases` order.
+pub fn releases_since(releases: Vec<Release>, since: DateTime<Utc>) -> Vec<Release> {
+    releases
+        .into_iter()
+        .filter(|r| observed_at(r) > since)
+        .collect()
+}
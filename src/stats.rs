@@ -0,0 +1,52 @@
+//! In-memory request counters per repo page, used to rank the most requested repos.
+
+use dashmap::DashMap;
+use serde::Serialize;
+
+/// Cap on distinct routes tracked, so random paths can't grow the map without bound.
+const MAX_TRACKED_ROUTES: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoRequests {
+    /// Route of the repo page, e.g. "/github/owner/repo"
+    pub route: String,
+    pub requests: u64,
+}
+
+#[derive(Default)]
+pub struct RequestStats {
+    counts: DashMap<String, u64>,
+}
+
+impl RequestStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, route: &str) {
+        if let Some(mut count) = self.counts.get_mut(route) {
+            *count += 1;
+        } else if self.counts.len() < MAX_TRACKED_ROUTES {
+            self.counts.insert(route.to_string(), 1);
+        }
+    }
+
+    /// The `limit` most requested repos, most requested first.
+    pub fn top(&self, limit: usize) -> Vec<RepoRequests> {
+        let mut repos: Vec<RepoRequests> = self
+            .counts
+            .iter()
+            .map(|entry| RepoRequests {
+                route: entry.key().clone(),
+                requests: *entry.value(),
+            })
+            .collect();
+        repos.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.route.cmp(&b.route)));
+        repos.truncate(limit);
+        repos
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|entry| *entry.value()).sum()
+    }
+}
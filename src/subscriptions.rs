@@ -0,0 +1,536 @@
+//! Email subscriptions to single repo pages, for visitors of a public
+//! instance who don't control its watchlist. With `[email] from` set, release
+//! pages end in a form posting to `/subscribe`; the address gets a link to
+//! `/subscribe/confirm` and is only mailed again once it was followed. Each
+//! fetch that finds new releases, or rebuilt ones under a rolling tag, sends
+//! every confirmed subscriber of the repo one digest, which links to
+//! `/unsubscribe`. Subscribed repos are refreshed in the background like
+//! watched ones.
+//!
+//! Subscriptions are kept in subscriptions.json; unconfirmed ones expire
+//! after a week.
+
+use crate::{
+    AppState,
+    cache::CacheManager,
+    config::{EmailConfig, WatchEntry},
+    format_html,
+    mail::{self, Mailer, Message},
+    provider::{self, Release},
+    quota::ClientIp,
+};
+use anyhow::Result;
+use axum::{
+    Form,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, RwLock},
+};
+
+/// Days an unconfirmed subscription is kept
+const PENDING_DAYS: i64 = 7;
+/// Hours before a confirmation link is sent again for the same repo
+const RESEND_HOURS: i64 = 24;
+/// Unconfirmed subscriptions one address may have at once
+const MAX_PENDING: usize = 5;
+/// Releases listed in one digest; the rest are counted
+const DIGEST_RELEASES: usize = 10;
+/// Characters of release notes quoted per release in a digest
+const NOTES_EXCERPT_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub email: String,
+    /// Repo route as used in URLs, e.g. "github/owner/repo"
+    pub route: String,
+    /// Secret of the confirmation and unsubscribe links
+    token: String,
+    pub confirmed: bool,
+    /// When the subscription was asked for, or the confirmation last sent
+    pub created_at: DateTime<Utc>,
+}
+
+impl Subscription {
+    fn expired(&self, now: DateTime<Utc>) -> bool {
+        !self.confirmed && now - self.created_at >= Duration::days(PENDING_DAYS)
+    }
+}
+
+/// 32 random bytes as hex.
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    openssl::rand::rand_bytes(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub struct Subscriptions {
+    mailer: Mailer,
+    entries: RwLock<Vec<Subscription>>,
+    cache: CacheManager,
+    public_url: String,
+}
+
+impl Subscriptions {
+    /// None when email is off; fails on an unreadable subscriptions.json.
+    pub fn load(
+        config: &EmailConfig,
+        cache: CacheManager,
+        public_url: &str,
+    ) -> Result<Option<Self>> {
+        let Some(mailer) = Mailer::from_config(config) else {
+            return Ok(None);
+        };
+        let entries: Vec<Subscription> = cache.read_subscriptions()?;
+        Ok(Some(Self {
+            mailer,
+            entries: RwLock::new(entries),
+            cache,
+            public_url: public_url.to_string(),
+        }))
+    }
+
+    /// Routes of repos with a confirmed subscriber.
+    pub fn routes(&self) -> Vec<String> {
+        let entries = self.entries.read().unwrap();
+        let routes: BTreeSet<&str> = entries
+            .iter()
+            .filter(|s| s.confirmed)
+            .map(|s| s.route.as_str())
+            .collect();
+        routes.into_iter().map(String::from).collect()
+    }
+
+    fn subscribers(&self, route: &str) -> Vec<Subscription> {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|s| s.confirmed && s.route == route)
+            .cloned()
+            .collect()
+    }
+
+    /// Add or renew an unconfirmed subscription. Returns its token when a
+    /// confirmation link is to be sent: not for confirmed subscriptions, nor
+    /// within a day of the last link for the same repo. Fails once the
+    /// address has `MAX_PENDING` unconfirmed subscriptions.
+    fn subscribe(&self, route: &str, email: &str) -> Result<Option<String>> {
+        let now = Utc::now();
+        let mut entries = self.entries.write().unwrap();
+        entries.retain(|s| !s.expired(now));
+        let same_address = |s: &Subscription| s.email.eq_ignore_ascii_case(email);
+
+        let token = new_token()?;
+        if let Some(existing) = entries
+            .iter_mut()
+            .find(|s| same_address(s) && s.route == route)
+        {
+            if existing.confirmed || now - existing.created_at < Duration::hours(RESEND_HOURS) {
+                return Ok(None);
+            }
+            existing.token = token.clone();
+            existing.created_at = now;
+        } else {
+            let pending = entries
+                .iter()
+                .filter(|s| !s.confirmed && same_address(s))
+                .count();
+            if pending >= MAX_PENDING {
+                anyhow::bail!(
+                    "This address has {} unconfirmed subscriptions; confirm those first",
+                    pending
+                );
+            }
+            entries.push(Subscription {
+                email: email.to_string(),
+                route: route.to_string(),
+                token: token.clone(),
+                confirmed: false,
+                created_at: now,
+            });
+        }
+        self.cache.write_subscriptions(&*entries)?;
+        Ok(Some(token))
+    }
+
+    /// Confirm the unexpired subscription with `token`.
+    fn confirm(&self, token: &str) -> Result<Option<Subscription>> {
+        let now = Utc::now();
+        let mut entries = self.entries.write().unwrap();
+        let Some(subscription) = entries
+            .iter_mut()
+            .find(|s| s.token == token && !s.expired(now))
+        else {
+            return Ok(None);
+        };
+        subscription.confirmed = true;
+        let confirmed = subscription.clone();
+        self.cache.write_subscriptions(&*entries)?;
+        Ok(Some(confirmed))
+    }
+
+    fn unsubscribe(&self, token: &str) -> Result<Option<Subscription>> {
+        let mut entries = self.entries.write().unwrap();
+        let Some(index) = entries.iter().position(|s| s.token == token) else {
+            return Ok(None);
+        };
+        let removed = entries.remove(index);
+        self.cache.write_subscriptions(&*entries)?;
+        Ok(Some(removed))
+    }
+
+    fn unsubscribe_url(&self, token: &str) -> String {
+        format!("{}/unsubscribe?token={}", self.public_url, token)
+    }
+
+    async fn send_confirmation(&self, route: &str, email: &str, token: &str) -> Result<()> {
+        let body = format!(
+            "Someone, hopefully you, asked to be emailed about new releases of\n{route} at {url}/{route}\n\nTo confirm, open this link within {days} days:\n\n{url}/subscribe/confirm?token={token}\n\nIf it wasn't you, ignore this email; you won't hear from us again.\n",
+            route = route,
+            url = self.public_url,
+            days = PENDING_DAYS,
+            token = token
+        );
+        self.mailer
+            .send(&Message {
+                to: email.to_string(),
+                subject: format!("Confirm release emails for {}", route),
+                body,
+                unsubscribe: None,
+            })
+            .await
+    }
+
+    /// Mail every confirmed subscriber of `route` one digest of `releases`.
+    /// Failures are logged; subscribers who didn't get one aren't retried.
+    async fn send_digest(&self, route: &str, releases: &[Release]) {
+        let subscribers = self.subscribers(route);
+        if subscribers.is_empty() {
+            return;
+        }
+        let subject = match releases {
+            [release] if release.updated_at.is_some() => {
+                format!("{} {} was rebuilt", route, release.tag_name)
+            }
+            [release] => format!("{} {} released", route, release.tag_name),
+            _ => format!("{} new releases of {}", releases.len(), route),
+        };
+        let digest = format_digest(route, &self.public_url, releases);
+        for subscriber in subscribers {
+            let unsubscribe = self.unsubscribe_url(&subscriber.token);
+            let message = Message {
+                to: subscriber.email.clone(),
+                subject: subject.clone(),
+                body: format!(
+                    "{}\n-- \nYou subscribed to releases of {} at {}.\nUnsubscribe: {}\n",
+                    digest, route, self.public_url, unsubscribe
+                ),
+                unsubscribe: Some(unsubscribe),
+            };
+            if let Err(e) = self.mailer.send(&message).await {
+                eprintln!("Release email for {} failed: {}", route, e);
+            }
+        }
+    }
+}
+
+/// Plain-text digest of `releases`, newest first.
+fn format_digest(route: &str, public_url: &str, releases: &[Release]) -> String {
+    let mut text = format!("New releases of {}:\n\n", route);
+    for release in releases.iter().take(DIGEST_RELEASES) {
+        let name = release.name.as_deref().unwrap_or(&release.tag_name);
+        text.push_str(&release.tag_name);
+        if name != release.tag_name {
+            text.push_str(&format!(" - {}", name));
+        }
+        if release.prerelease {
+            text.push_str(" (pre-release)");
+        }
+        text.push('\n');
+        match release.updated_at {
+            Some(updated_at) => text.push_str(&format!(
+                "Rebuilt {}\n",
+                updated_at.format("%Y-%m-%d %H:%M UTC")
+            )),
+            None => text.push_str(&format!(
+                "Published {}\n",
+                release.published_at.format("%Y-%m-%d %H:%M UTC")
+            )),
+        }
+        text.push_str(&format!("{}\n", release.html_url));
+        if let Some(body) = release
+            .body
+            .as_deref()
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+        {
+            let mut excerpt: String = body.chars().take(NOTES_EXCERPT_CHARS).collect();
+            if excerpt.len() < body.len() {
+                excerpt.push_str(" [...]");
+            }
+            text.push_str(&format!("\n{}\n", excerpt));
+        }
+        text.push('\n');
+    }
+    if releases.len() > DIGEST_RELEASES {
+        text.push_str(&format!(
+            "...and {} more.\n\n",
+            releases.len() - DIGEST_RELEASES
+        ));
+    }
+    text.push_str(&format!("All releases: {}/{}\n", public_url, route));
+    text
+}
+
+/// Releases worth a digest: published ones that weren't cached before, and
+/// those rebuilt since, in `releases` order.
+pub fn news(previous: &[Release], releases: &[Release]) -> Vec<Release> {
+    let previous: HashMap<&str, &Release> =
+        previous.iter().map(|r| (r.tag_name.as_str(), r)).collect();
+    releases
+        .iter()
+        .filter(|r| !r.draft)
+        .filter(|r| match previous.get(r.tag_name.as_str()) {
+            None => true,
+            Some(cached) => r.updated_at.is_some() && r.updated_at != cached.updated_at,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Send the digest of `releases` to the subscribers of `route` in the
+/// background.
+pub fn notify(state: &AppState, route: &str, releases: Vec<Release>) {
+    let Some(subscriptions) = state.subscriptions.clone() else {
+        return;
+    };
+    if releases.is_empty() || subscriptions.subscribers(route).is_empty() {
+        return;
+    }
+    let route = route.to_string();
+    tokio::spawn(async move { subscriptions.send_digest(&route, &releases).await });
+}
+
+/// Subscribed repos, for the refresher to keep fresh like watched ones.
+pub fn watch_entries(state: &AppState) -> Vec<WatchEntry> {
+    let Some(subscriptions) = &state.subscriptions else {
+        return Vec::new();
+    };
+    subscriptions
+        .routes()
+        .into_iter()
+        .map(|repo| WatchEntry {
+            repo,
+            interval_hours: None,
+            category: None,
+        })
+        .collect()
+}
+
+fn page(status: StatusCode, title: &str, message: &str) -> Response {
+    (
+        status,
+        Html(format_html::format_notice_html(title, message)),
+    )
+        .into_response()
+}
+
+fn disabled() -> Response {
+    page(
+        StatusCode::NOT_FOUND,
+        "Not Found",
+        "Email subscriptions are not enabled on this instance.",
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubscribeForm {
+    /// Repo route, e.g. "github/owner/repo"
+    repo: String,
+    email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenQuery {
+    token: String,
+}
+
+/// `POST /subscribe` with `repo` and `email` form fields. Counts against the
+/// fetch quota, since the repo will be refreshed for the subscriber.
+pub async fn subscribe_handler(
+    State(state): State<Arc<AppState>>,
+    client: ClientIp,
+    Form(form): Form<SubscribeForm>,
+) -> Response {
+    let Some(subscriptions) = &state.subscriptions else {
+        return disabled();
+    };
+    let email = form.email.trim();
+    if !mail::valid_address(email) {
+        return page(
+            StatusCode::BAD_REQUEST,
+            "Invalid Address",
+            &format!("'{}' is not an email address.", email),
+        );
+    }
+    let Ok((prefix, repo)) = provider::parse_route(&form.repo) else {
+        return page(
+            StatusCode::BAD_REQUEST,
+            "Invalid Repository",
+            &format!("'{}' is not a repository route.", form.repo),
+        );
+    };
+    // Only repos that exist, as known from having been cached
+    if !matches!(
+        state
+            .cache
+            .read_timestamp(&repo.host, &repo.owner, &repo.repo),
+        Ok(Some(_))
+    ) {
+        return page(
+            StatusCode::NOT_FOUND,
+            "Not Found",
+            "Open the repository's page before subscribing to it.",
+        );
+    }
+    if let Err(e) = state.fetch_quota.acquire(client) {
+        return e.into_response();
+    }
+
+    let route = provider::repo_route(prefix, &repo)
+        .trim_start_matches('/')
+        .to_string();
+    let token = match subscriptions.subscribe(&route, email) {
+        Ok(token) => token,
+        Err(e) => {
+            return page(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too Many Requests",
+                &e.to_string(),
+            );
+        }
+    };
+    if let Some(token) = token
+        && let Err(e) = subscriptions.send_confirmation(&route, email, &token).await
+    {
+        eprintln!("Confirmation email for {} failed: {}", route, e);
+        return page(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something Went Wrong",
+            "The confirmation email could not be sent; try again later.",
+        );
+    }
+    // The same answer whether or not the address was subscribed already
+    page(
+        StatusCode::OK,
+        "Check Your Inbox",
+        &format!(
+            "Unless {} already gets release emails for {}, a confirmation link is on its way to it.",
+            email, route
+        ),
+    )
+}
+
+/// `GET /subscribe/confirm?token=`: a button posting back, so link scanners
+/// opening the link don't confirm on the owner's behalf.
+pub async fn confirm_page(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+) -> Response {
+    if state.subscriptions.is_none() {
+        return disabled();
+    }
+    Html(format_html::format_token_form_html(
+        "Confirm Subscription",
+        "Confirm that you want an email for each new release.",
+        "/subscribe/confirm",
+        &query.token,
+        "Confirm",
+    ))
+    .into_response()
+}
+
+/// `POST /subscribe/confirm?token=`
+pub async fn confirm_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+) -> Response {
+    let Some(subscriptions) = &state.subscriptions else {
+        return disabled();
+    };
+    match subscriptions.confirm(&query.token) {
+        Ok(Some(subscription)) => page(
+            StatusCode::OK,
+            "Subscribed",
+            &format!(
+                "{} will get an email for each new release of {}.",
+                subscription.email, subscription.route
+            ),
+        ),
+        Ok(None) => page(
+            StatusCode::NOT_FOUND,
+            "Link Expired",
+            "This confirmation link is unknown or has expired; subscribe again.",
+        ),
+        Err(e) => page(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something Went Wrong",
+            &e.to_string(),
+        ),
+    }
+}
+
+/// `GET /unsubscribe?token=`
+pub async fn unsubscribe_page(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+) -> Response {
+    if state.subscriptions.is_none() {
+        return disabled();
+    }
+    Html(format_html::format_token_form_html(
+        "Unsubscribe",
+        "Stop getting release emails for this repository.",
+        "/unsubscribe",
+        &query.token,
+        "Unsubscribe",
+    ))
+    .into_response()
+}
+
+/// `POST /unsubscribe?token=`, from the page or a mail client's one-click
+/// unsubscribe.
+pub async fn unsubscribe_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TokenQuery>,
+) -> Response {
+    let Some(subscriptions) = &state.subscriptions else {
+        return disabled();
+    };
+    match subscriptions.unsubscribe(&query.token) {
+        Ok(Some(subscription)) => page(
+            StatusCode::OK,
+            "Unsubscribed",
+            &format!(
+                "{} won't get release emails for {} anymore.",
+                subscription.email, subscription.route
+            ),
+        ),
+        Ok(None) => page(
+            StatusCode::NOT_FOUND,
+            "Not Subscribed",
+            "This link belongs to no subscription; it may have been removed already.",
+        ),
+        Err(e) => page(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something Went Wrong",
+            &e.to_string(),
+        ),
+    }
+}
@@ -0,0 +1,179 @@
+//! Opt-in usage statistics, to help prioritize provider development: with
+//! `[telemetry] endpoint` set, the instance POSTs aggregate numbers there
+//! every `interval_hours`: its version, uptime, requests per provider and
+//! how many release pages and `+json` responses came from the cache. No
+//! repos, URLs, addresses or other identifying data are part of it.
+//! `/about` shows the same numbers, and exactly what is sent, whether
+//! reporting is on or not.
+
+use crate::{
+    AppState, VERSION, cache_control::CACHE_STATUS_HEADER, config::TelemetryConfig, format_html,
+    provider::PREFIXES,
+};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// What is reported, counted since the server started
+#[derive(Debug, Serialize)]
+pub struct Usage {
+    pub version: &'static str,
+    pub uptime_hours: i64,
+    /// Requests by provider route prefix, e.g. "github"
+    pub providers: BTreeMap<&'static str, u64>,
+    pub cache: CacheUsage,
+}
+
+/// Release pages and `+json` responses, by whether they came from the cache
+#[derive(Debug, Serialize)]
+pub struct CacheUsage {
+    pub hits: u64,
+    pub misses: u64,
+    /// Share of hits; none before the first request
+    pub hit_ratio: Option<f64>,
+}
+
+/// Outcome of the last report
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub at: DateTime<Utc>,
+    pub error: Option<String>,
+}
+
+pub struct Telemetry {
+    pub endpoint: Option<String>,
+    pub interval_hours: u64,
+    started: DateTime<Utc>,
+    requests: DashMap<&'static str, u64>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    last_report: RwLock<Option<Report>>,
+}
+
+impl Telemetry {
+    pub fn from_config(config: &TelemetryConfig) -> Self {
+        Self {
+            endpoint: config
+                .endpoint
+                .clone()
+                .filter(|endpoint| !endpoint.trim().is_empty()),
+            interval_hours: config.interval_hours.max(1),
+            started: Utc::now(),
+            requests: DashMap::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            last_report: RwLock::new(None),
+        }
+    }
+
+    pub fn usage(&self) -> Usage {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        Usage {
+            version: VERSION,
+            uptime_hours: (Utc::now() - self.started).num_hours(),
+            providers: self
+                .requests
+                .iter()
+                .map(|e| (*e.key(), *e.value()))
+                .collect(),
+            cache: CacheUsage {
+                hits,
+                misses,
+                hit_ratio: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+            },
+        }
+    }
+
+    pub fn last_report(&self) -> Option<Report> {
+        self.last_report.read().unwrap().clone()
+    }
+
+    /// Report every interval, the first one interval after startup, for as
+    /// long as the server runs. Does nothing without an endpoint.
+    pub fn spawn(self: Arc<Self>, client: reqwest::Client) {
+        let Some(endpoint) = self.endpoint.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            let interval = Duration::from_secs(self.interval_hours * 3600);
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                ticker.tick().await;
+                let error = self.send(&client, &endpoint).await.err();
+                if let Some(e) = &error {
+                    eprintln!("Telemetry report to {} failed: {}", endpoint, e);
+                }
+                *self.last_report.write().unwrap() = Some(Report {
+                    at: Utc::now(),
+                    error: error.map(|e| e.to_string()),
+                });
+            }
+        });
+    }
+
+    async fn send(&self, client: &reqwest::Client, endpoint: &str) -> anyhow::Result<()> {
+        let response = client
+            .post(endpoint)
+            .json(&self.usage())
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("{} returned {}", endpoint, response.status());
+        }
+        Ok(())
+    }
+}
+
+/// Count requests per provider, and cache hits and misses as marked by
+/// `X-Checkup-Cache`.
+pub async fn count(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let prefix = request
+        .uri()
+        .path()
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    if let Some(prefix) = PREFIXES.into_iter().find(|p| *p == prefix) {
+        *state.telemetry.requests.entry(prefix).or_default() += 1;
+    }
+    let response = next.run(request).await;
+    let counter = match response
+        .headers()
+        .get(CACHE_STATUS_HEADER)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some("hit") => Some(&state.telemetry.hits),
+        Some("miss") => Some(&state.telemetry.misses),
+        _ => None,
+    };
+    if let Some(counter) = counter {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    response
+}
+
+/// `GET /about`: the instance's version and usage statistics, and whether
+/// they are reported.
+pub async fn about_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Html(format_html::format_about_html(
+        &state.telemetry.usage(),
+        &state.telemetry,
+    ))
+}
@@ -0,0 +1,216 @@
+//! Machine translation of release notes: `?lang=de` on release pages and
+//! `+json` shows the notes translated by a LibreTranslate-compatible backend
+//! configured under `[translate]`. Translations are kept per language and
+//! tag in translations.json, and redone when a release's notes change.
+
+use crate::{
+    AppState, RepoPath, config::TranslateConfig, debug, error::AppError, error::ErrorCode,
+    provider::Release, quota::ClientIp, shedding, upstream::UpstreamStatusError,
+};
+use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+pub struct Translator {
+    /// `/translate` endpoint of the backend
+    endpoint: String,
+    /// Backend host, as tracked on the status page
+    host: String,
+    api_key: Option<String>,
+    source: String,
+    languages: Vec<String>,
+}
+
+impl Translator {
+    /// `None` when no backend is configured.
+    pub fn from_config(config: &TranslateConfig) -> Result<Option<Self>> {
+        let Some(url) = &config.url else {
+            return Ok(None);
+        };
+        let parsed =
+            url::Url::parse(url).with_context(|| format!("Invalid [translate] url {}", url))?;
+        let host = parsed
+            .host_str()
+            .with_context(|| format!("No host in [translate] url {}", url))?
+            .to_string();
+        for lang in &config.languages {
+            if !is_language_code(lang) {
+                anyhow::bail!("Invalid language '{}' in [translate] languages", lang);
+            }
+        }
+        Ok(Some(Self {
+            endpoint: format!("{}/translate", url.trim_end_matches('/')),
+            host,
+            api_key: config.api_key.clone(),
+            source: config.source.clone().unwrap_or_else(|| "auto".to_string()),
+            languages: config.languages.clone(),
+        }))
+    }
+
+    fn check_language(&self, lang: &str) -> Result<(), AppError> {
+        if !is_language_code(lang) {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Invalid language '{}', expected a code like 'de' or 'pt-BR'",
+                    lang
+                ),
+            ));
+        }
+        if !self.languages.is_empty() && !self.languages.iter().any(|l| l == lang) {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Translation into '{}' is not offered; available: {}",
+                    lang,
+                    self.languages.join(", ")
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn translate(&self, state: &AppState, text: &str, target: &str) -> Result<String> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            q: &'a str,
+            source: &'a str,
+            target: &'a str,
+            format: &'static str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            api_key: Option<&'a str>,
+        }
+        #[derive(Deserialize)]
+        struct Translated {
+            #[serde(rename = "translatedText")]
+            translated_text: String,
+        }
+
+        debug::request("POST", &self.endpoint);
+        let response = state
+            .client
+            .post(&self.endpoint)
+            .json(&Request {
+                q: text,
+                source: &self.source,
+                target,
+                format: "text",
+                api_key: self.api_key.as_deref(),
+            })
+            .send()
+            .await?;
+        debug::response(&response);
+        if !response.status().is_success() {
+            return Err(UpstreamStatusError {
+                service: "Translation backend".to_string(),
+                status: response.status(),
+                rate_limit: None,
+            }
+            .into());
+        }
+        Ok(response.json::<Translated>().await?.translated_text)
+    }
+}
+
+/// e.g. "de", "pt-BR" or "zh-Hans"
+fn is_language_code(lang: &str) -> bool {
+    let (language, region) = match lang.split_once('-') {
+        Some((language, region)) => (language, Some(region)),
+        None => (lang, None),
+    };
+    (2..=3).contains(&language.len())
+        && language.bytes().all(|b| b.is_ascii_lowercase())
+        && region.is_none_or(|r| {
+            (2..=4).contains(&r.len()) && r.bytes().all(|b| b.is_ascii_alphabetic())
+        })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TranslatedNotes {
+    /// SHA-256 of the notes that were translated
+    source_sha256: String,
+    body: String,
+}
+
+/// Translations by language, then by tag
+type Translations = BTreeMap<String, BTreeMap<String, TranslatedNotes>>;
+
+fn notes_hash(body: &str) -> String {
+    format!("{:x}", Sha256::digest(body.as_bytes()))
+}
+
+/// Replace the notes of `releases` with their translation into `lang`,
+/// translating those not cached yet. Counts as one fetch against the
+/// client's quota when anything needs translating.
+pub async fn translate_releases(
+    state: &AppState,
+    repo: &RepoPath,
+    releases: &mut [Release],
+    lang: &str,
+    client: ClientIp,
+) -> Result<(), AppError> {
+    let translator = state.translator.as_ref().ok_or_else(|| {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            "Release note translation is not enabled on this instance",
+        )
+        .with_code(ErrorCode::Disabled)
+    })?;
+    translator.check_language(lang)?;
+
+    let mut translations: Translations = state
+        .cache
+        .read_translations(&repo.host, &repo.owner, &repo.repo)
+        .map_err(AppError::internal)?;
+    let cached = translations.entry(lang.to_string()).or_default();
+
+    let mut missing = Vec::new();
+    for (idx, release) in releases.iter_mut().enumerate() {
+        let Some(body) = release.body.as_deref().filter(|b| !b.trim().is_empty()) else {
+            continue;
+        };
+        match cached.get(&release.tag_name) {
+            Some(notes) if notes.source_sha256 == notes_hash(body) => {
+                release.body = Some(notes.body.clone())
+            }
+            _ => missing.push(idx),
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    shedding::admit_fetch(state, client)?;
+    let mut result = Ok(());
+    for idx in missing {
+        let release = &mut releases[idx];
+        let body = release.body.clone().unwrap_or_default();
+        match state
+            .upstream
+            .track(&translator.host, translator.translate(state, &body, lang))
+            .await
+        {
+            Ok(translated) => {
+                cached.insert(
+                    release.tag_name.clone(),
+                    TranslatedNotes {
+                        source_sha256: notes_hash(&body),
+                        body: translated.clone(),
+                    },
+                );
+                release.body = Some(translated);
+            }
+            Err(e) => {
+                result = Err(AppError::new(StatusCode::BAD_GATEWAY, e.to_string()));
+                break;
+            }
+        }
+    }
+    // Keep what was translated before a failure
+    let _ = state
+        .cache
+        .write_translations(&repo.host, &repo.owner, &repo.repo, &translations);
+    result
+}
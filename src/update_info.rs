@@ -0,0 +1,163 @@
+//! `/{provider}/{...}/update-info.json`: what desktop apps need to update
+//! themselves against this instance instead of the forge. AppImages get
+//! AppImageUpdate update information pointing at the `latest*` redirect of
+//! their `.zsync` file, and Flatpak `.flatpakref`/`.flatpakrepo` files are
+//! given through their `latest*` redirects with the command that uses them.
+
+use crate::{
+    AppState, cache_control::RouteKind, error::AppError, latest_names::LatestNames,
+    provider::Release, signing,
+};
+use axum::{http::StatusCode, response::Response};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UpdateInfo {
+    pub repo: String,
+    /// Tag of the release the files were found in
+    pub tag: String,
+    pub appimages: Vec<AppImageUpdate>,
+    pub flatpak: Vec<FlatpakFile>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AppImageUpdate {
+    pub asset: String,
+    /// `latest*` redirect to the newest build
+    pub latest_url: String,
+    /// `latest*` redirect to the build's zsync file, when one is published
+    pub zsync_url: Option<String>,
+    /// Update information to embed with `appimagetool -u`; none without a
+    /// zsync file, which AppImageUpdate needs
+    pub update_information: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FlatpakFile {
+    pub asset: String,
+    /// "ref" for `.flatpakref`, "repo" for `.flatpakrepo`
+    pub kind: &'static str,
+    pub latest_url: String,
+    /// Command that installs the app or adds the remote
+    pub command: String,
+}
+
+/// Update information for the AppImages and Flatpak files of the newest
+/// release. `route` is the repo's route, e.g. "/github/owner/repo", and
+/// `component` is kept in the generated links.
+fn update_info(
+    public_url: &str,
+    latest_names: &LatestNames,
+    route: &str,
+    cache_key: &str,
+    releases: &[Release],
+    component: Option<&str>,
+) -> Result<UpdateInfo, AppError> {
+    let release = releases.first().ok_or(AppError::new(
+        StatusCode::NOT_FOUND,
+        "Repository has no releases".to_string(),
+    ))?;
+    let query = component
+        .map(|c| {
+            format!(
+                "?component={}",
+                url::form_urlencoded::byte_serialize(c.as_bytes()).collect::<String>()
+            )
+        })
+        .unwrap_or_default();
+    let latest_url = |name: &str| {
+        format!(
+            "{}{}/{}{}",
+            public_url,
+            route,
+            latest_names.rename(name),
+            query
+        )
+    };
+
+    let appimages: Vec<AppImageUpdate> = release
+        .assets
+        .iter()
+        .filter(|a| a.name.to_lowercase().ends_with(".appimage"))
+        .map(|a| {
+            let zsync_name = format!("{}.zsync", a.name);
+            let zsync_url = release
+                .assets
+                .iter()
+                .any(|z| z.name == zsync_name)
+                .then(|| latest_url(&zsync_name));
+            AppImageUpdate {
+                asset: a.name.clone(),
+                latest_url: latest_url(&a.name),
+                update_information: zsync_url.as_ref().map(|url| format!("zsync|{}", url)),
+                zsync_url,
+            }
+        })
+        .collect();
+
+    let flatpak: Vec<FlatpakFile> = release
+        .assets
+        .iter()
+        .filter_map(|a| {
+            let lower = a.name.to_lowercase();
+            let url = latest_url(&a.name);
+            if lower.ends_with(".flatpakref") {
+                Some(FlatpakFile {
+                    asset: a.name.clone(),
+                    kind: "ref",
+                    command: format!("flatpak install --from {}", url),
+                    latest_url: url,
+                })
+            } else if lower.ends_with(".flatpakrepo") {
+                let remote = &a.name[..a.name.len() - ".flatpakrepo".len()];
+                Some(FlatpakFile {
+                    asset: a.name.clone(),
+                    kind: "repo",
+                    command: format!("flatpak remote-add --if-not-exists {} {}", remote, url),
+                    latest_url: url,
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if appimages.is_empty() && flatpak.is_empty() {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            format!(
+                "Release '{}' has no AppImage or Flatpak files",
+                release.tag_name
+            ),
+        ));
+    }
+    Ok(UpdateInfo {
+        repo: cache_key.to_string(),
+        tag: release.tag_name.clone(),
+        appimages,
+        flatpak,
+    })
+}
+
+pub fn update_info_response(
+    state: &AppState,
+    route: &str,
+    cache_key: &str,
+    releases: &[Release],
+    component: Option<&str>,
+) -> Result<Response, AppError> {
+    let info = update_info(
+        &state.render.public_url,
+        &state.render.latest_names,
+        route,
+        cache_key,
+        releases,
+        component,
+    )?;
+    let body = serde_json::to_string_pretty(&info).map_err(AppError::internal)?;
+    Ok(state.cache_policy.apply(
+        RouteKind::Json,
+        signing::json_response(state, StatusCode::OK, body),
+    ))
+}
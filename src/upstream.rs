@@ -0,0 +1,288 @@
+//! Health tracking for upstream forge hosts: fetch outcomes, rate limits and
+//! a per-host circuit breaker that stops hammering a host that keeps failing.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use dashmap::DashMap;
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Number of recent fetch outcomes kept per host for the error rate.
+const RECENT_WINDOW: usize = 50;
+/// Consecutive failures after which the breaker opens.
+const FAILURE_THRESHOLD: u32 = 5;
+/// How long an open breaker rejects fetches before allowing a trial request.
+/// Until the trial finishes, other fetches keep failing fast.
+const OPEN_DURATION_SECS: i64 = 300;
+
+/// Non-success HTTP status returned by an upstream API.
+#[derive(Debug, thiserror::Error)]
+#[error("{service} returned status: {status}{}", .rate_limit.as_ref().map(RateLimit::describe).unwrap_or_default())]
+pub struct UpstreamStatusError {
+    pub service: String,
+    pub status: reqwest::StatusCode,
+    /// Rate limit left when the API rejected the request for it
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl UpstreamStatusError {
+    /// Whether the status reflects a problem with the host rather than the request
+    /// (e.g. a 404 for a mistyped repo says nothing about the host's health).
+    fn is_host_failure(&self) -> bool {
+        self.status.is_server_error()
+            || self.status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || self.status == reqwest::StatusCode::FORBIDDEN
+    }
+
+    /// Whether `error` is a 404 from an upstream API.
+    pub fn is_not_found(error: &anyhow::Error) -> bool {
+        error
+            .downcast_ref::<Self>()
+            .is_some_and(|e| e.status == reqwest::StatusCode::NOT_FOUND)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: u64,
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    /// e.g. "; rate limit: 0 of 60 requests left, resets at 14:05 UTC"
+    fn describe(&self) -> String {
+        let limit = self
+            .limit
+            .map(|limit| format!(" of {}", limit))
+            .unwrap_or_default();
+        let reset = self
+            .reset_at
+            .map(|t| format!(", resets at {}", t.format("%H:%M UTC")))
+            .unwrap_or_default();
+        format!(
+            "; rate limit: {}{} requests left{}",
+            self.remaining, limit, reset
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct HostHealth {
+    last_success: Option<DateTime<Utc>>,
+    last_error: Option<(DateTime<Utc>, String)>,
+    recent: VecDeque<bool>,
+    consecutive_failures: u32,
+    open_until: Option<DateTime<Utc>>,
+    /// Whether a half-open breaker's trial request is running
+    trial_in_flight: bool,
+    rate_limit: Option<RateLimit>,
+}
+
+impl HostHealth {
+    fn breaker_state(&self) -> BreakerState {
+        match self.open_until {
+            Some(until) if Utc::now() < until => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+            None => BreakerState::Closed,
+        }
+    }
+
+    fn push_outcome(&mut self, ok: bool) {
+        self.recent.push_back(ok);
+        if self.recent.len() > RECENT_WINDOW {
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// Snapshot of one host's health, as shown on `/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HostStatus {
+    pub host: String,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub recent_requests: usize,
+    pub recent_error_rate: f64,
+    pub breaker: BreakerState,
+    pub rate_limit: Option<RateLimit>,
+}
+
+#[derive(Default)]
+pub struct UpstreamTracker {
+    hosts: DashMap<String, HostHealth>,
+    in_flight: AtomicUsize,
+}
+
+/// Counts a fetch as in flight until dropped, also when it is cancelled.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn start(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Marks a host's trial request as running until dropped, also when it is
+/// cancelled, so the next request can try again.
+struct Trial<'a> {
+    hosts: &'a DashMap<String, HostHealth>,
+    host: &'a str,
+}
+
+impl Drop for Trial<'_> {
+    fn drop(&mut self) {
+        if let Some(mut health) = self.hosts.get_mut(self.host) {
+            health.trial_in_flight = false;
+        }
+    }
+}
+
+impl UpstreamTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run an upstream fetch for `host`, recording its outcome. Fails fast
+    /// without calling upstream while the host's breaker is open, and once
+    /// it is half-open, lets one trial request through at a time.
+    pub async fn track<T>(&self, host: &str, fetch: impl Future<Output = Result<T>>) -> Result<T> {
+        let mut trial = None;
+        if let Some(mut health) = self.hosts.get_mut(host) {
+            match health.breaker_state() {
+                BreakerState::Open => anyhow::bail!(
+                    "{} is failing repeatedly; requests paused until {}",
+                    host,
+                    health
+                        .open_until
+                        .map(|t| t.format("%H:%M:%S UTC").to_string())
+                        .unwrap_or_default()
+                ),
+                BreakerState::HalfOpen if health.trial_in_flight => anyhow::bail!(
+                    "{} is failing repeatedly; requests paused while a trial request runs",
+                    host
+                ),
+                BreakerState::HalfOpen => {
+                    health.trial_in_flight = true;
+                    trial = Some(Trial {
+                        hosts: &self.hosts,
+                        host,
+                    });
+                }
+                BreakerState::Closed => {}
+            }
+        }
+
+        let in_flight = InFlight::start(&self.in_flight);
+        let result = fetch.await;
+        drop(in_flight);
+        match &result {
+            Ok(_) => self.record_success(host),
+            Err(e) => {
+                let host_failure = e
+                    .downcast_ref::<UpstreamStatusError>()
+                    .is_none_or(UpstreamStatusError::is_host_failure);
+                if host_failure {
+                    self.record_failure(host, &e.to_string());
+                } else {
+                    // The host answered; the request itself was bad
+                    self.record_success(host);
+                }
+            }
+        }
+        drop(trial);
+        result
+    }
+
+    /// Upstream fetches currently running.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    fn record_success(&self, host: &str) {
+        let mut health = self.hosts.entry(host.to_string()).or_default();
+        health.last_success = Some(Utc::now());
+        health.consecutive_failures = 0;
+        health.open_until = None;
+        health.push_outcome(true);
+    }
+
+    fn record_failure(&self, host: &str, error: &str) {
+        let mut health = self.hosts.entry(host.to_string()).or_default();
+        let now = Utc::now();
+        health.last_error = Some((now, error.to_string()));
+        health.consecutive_failures += 1;
+        health.push_outcome(false);
+        if health.consecutive_failures >= FAILURE_THRESHOLD {
+            health.open_until = Some(now + Duration::seconds(OPEN_DURATION_SECS));
+        }
+    }
+
+    /// Record rate-limit headers (`x-ratelimit-*` as sent by GitHub and
+    /// GitLab), returning what they say.
+    pub fn record_rate_limit(&self, host: &str, headers: &HeaderMap) -> Option<RateLimit> {
+        let get = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+        };
+        let remaining = get("x-ratelimit-remaining")?;
+        let rate_limit = RateLimit {
+            limit: get("x-ratelimit-limit"),
+            remaining,
+            reset_at: get("x-ratelimit-reset")
+                .and_then(|epoch| Utc.timestamp_opt(epoch as i64, 0).single()),
+        };
+        self.hosts.entry(host.to_string()).or_default().rate_limit = Some(rate_limit.clone());
+        Some(rate_limit)
+    }
+
+    pub fn snapshot(&self) -> Vec<HostStatus> {
+        let mut hosts: Vec<HostStatus> = self
+            .hosts
+            .iter()
+            .map(|entry| {
+                let health = entry.value();
+                let failures = health.recent.iter().filter(|ok| !**ok).count();
+                HostStatus {
+                    host: entry.key().clone(),
+                    last_success: health.last_success,
+                    last_error_at: health.last_error.as_ref().map(|(t, _)| *t),
+                    last_error: health.last_error.as_ref().map(|(_, e)| e.clone()),
+                    recent_requests: health.recent.len(),
+                    recent_error_rate: if health.recent.is_empty() {
+                        0.0
+                    } else {
+                        failures as f64 / health.recent.len() as f64
+                    },
+                    breaker: health.breaker_state(),
+                    rate_limit: health.rate_limit.clone(),
+                }
+            })
+            .collect();
+        hosts.sort_by(|a, b| a.host.cmp(&b.host));
+        hosts
+    }
+}
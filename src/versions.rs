@@ -0,0 +1,221 @@
+//! Version numbers from tag names. By default a leading "v" is dropped
+//! ("v1.2.0" → "1.2.0"); `[[repo]]` config entries add rules for unusual tag
+//! schemes, such as prefixes to strip ("release-1.2") or a regex capturing
+//! the version ("myapp_1_2_0"). Repos with rules have their releases ordered
+//! by version rather than by upstream date.
+//!
+//! Monorepos tag each component separately ("cli-v1.2.0", "lib-v0.9.1");
+//! `?component=` narrows releases to the tags of one component.
+
+use crate::{config::RepoConfig, provider, provider::Release};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::{cmp::Ordering, collections::HashMap};
+
+#[derive(Debug, Default)]
+pub struct TagRules {
+    strip_prefixes: Vec<String>,
+    /// Matches the version, in the `version` group or else the first group
+    pattern: Option<Regex>,
+    /// Tag prefix of each monorepo component
+    components: HashMap<String, String>,
+}
+
+impl TagRules {
+    pub fn normalize(&self, tag: &str) -> String {
+        let tag = tag.trim();
+        if let Some(captures) = self.pattern.as_ref().and_then(|p| p.captures(tag)) {
+            let version = captures
+                .name("version")
+                .or_else(|| captures.get(1))
+                .or_else(|| captures.get(0))
+                .map_or("", |m| m.as_str());
+            return strip_v(version).to_string();
+        }
+        let tag = self
+            .strip_prefixes
+            .iter()
+            .find_map(|prefix| tag.strip_prefix(prefix.as_str()))
+            .unwrap_or(tag);
+        strip_v(tag).to_string()
+    }
+
+    fn orders_by_version(&self) -> bool {
+        !self.strip_prefixes.is_empty() || self.pattern.is_some()
+    }
+
+    /// The part of `tag` after the component's prefix, when the tag belongs
+    /// to it. Unconfigured components use "{component}-" or "{component}/".
+    fn component_tag<'a>(&self, component: &str, tag: &'a str) -> Option<&'a str> {
+        match self.components.get(component) {
+            Some(prefix) => tag.strip_prefix(prefix.as_str()),
+            None => tag
+                .strip_prefix(component)
+                .and_then(|rest| rest.strip_prefix(['-', '/'])),
+        }
+    }
+}
+
+/// "v1.2" → "1.2"; a "v" not followed by a digit is part of the name.
+fn strip_v(tag: &str) -> &str {
+    match tag.strip_prefix(['v', 'V']) {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => tag,
+    }
+}
+
+/// Tag rules of every repo configured with some, by cache key.
+#[derive(Debug, Default)]
+pub struct TagRuleSet {
+    by_repo: HashMap<String, TagRules>,
+    default: TagRules,
+}
+
+impl TagRuleSet {
+    /// Fails on routes that don't name a repo and on invalid patterns.
+    pub fn from_config(repos: &[RepoConfig]) -> Result<Self> {
+        let mut by_repo = HashMap::new();
+        for entry in repos {
+            if entry.strip_prefixes.is_empty()
+                && entry.version_pattern.is_none()
+                && entry.components.is_empty()
+            {
+                continue;
+            }
+            let (_, repo) = provider::parse_route(&entry.repo)?;
+            let pattern = entry
+                .version_pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .with_context(|| format!("Invalid version_pattern for {}", entry.repo))?;
+            by_repo.insert(
+                repo.cache_key(),
+                TagRules {
+                    strip_prefixes: entry.strip_prefixes.clone(),
+                    pattern,
+                    components: entry.components.clone(),
+                },
+            );
+        }
+        Ok(Self {
+            by_repo,
+            default: TagRules::default(),
+        })
+    }
+
+    /// Whether the repo's releases are ordered by version.
+    pub fn has_rules(&self, cache_key: &str) -> bool {
+        self.by_repo
+            .get(cache_key)
+            .is_some_and(TagRules::orders_by_version)
+    }
+
+    pub fn for_repo(&self, cache_key: &str) -> &TagRules {
+        self.by_repo.get(cache_key).unwrap_or(&self.default)
+    }
+
+    /// Set each release's `version`, and order the releases by it, newest
+    /// first, when the repo has rules of its own.
+    pub fn apply(&self, cache_key: &str, releases: &mut [Release]) {
+        let rules = self.for_repo(cache_key);
+        for release in releases.iter_mut() {
+            release.version = Some(rules.normalize(&release.tag_name));
+        }
+        if self.has_rules(cache_key) {
+            sort_by_version(releases);
+        }
+    }
+
+    /// Releases of one monorepo component, with versions taken from the rest of
+    /// their tags.
+    pub fn component_releases(
+        &self,
+        cache_key: &str,
+        component: &str,
+        releases: Vec<Release>,
+    ) -> Vec<Release> {
+        let rules = self.for_repo(cache_key);
+        let mut releases: Vec<Release> = releases
+            .into_iter()
+            .filter_map(|mut release| {
+                let rest = rules.component_tag(component, &release.tag_name)?;
+                release.version = Some(rules.normalize(rest));
+                Some(release)
+            })
+            .collect();
+        if rules.orders_by_version() {
+            sort_by_version(&mut releases);
+        }
+        releases
+    }
+}
+
+/// Highest version first; releases without a version keep their order.
+fn sort_by_version(releases: &mut [Release]) {
+    releases.sort_by(|a, b| {
+        compare_versions(
+            b.version.as_deref().unwrap_or_default(),
+            a.version.as_deref().unwrap_or_default(),
+        )
+    });
+}
+
+/// Parse "1.2.3-rc.1" into ([1, 2, 3], Some("rc.1")). Dots, underscores and
+/// dashes between numbers all separate components; anything that doesn't
+/// start with a number is no version.
+fn parse_version(version: &str) -> Option<(Vec<u64>, Option<&str>)> {
+    let mut numbers = Vec::new();
+    let mut rest = version;
+    loop {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            break;
+        }
+        numbers.push(rest[..digits].parse().ok()?);
+        rest = &rest[digits..];
+        match rest.strip_prefix(['.', '_', '-']) {
+            Some(next) if next.starts_with(|c: char| c.is_ascii_digit()) => rest = next,
+            _ => break,
+        }
+    }
+    if numbers.is_empty() {
+        return None;
+    }
+    let pre = rest.trim_start_matches(['-', '.', '+', '_']);
+    Some((numbers, (!pre.is_empty()).then_some(pre)))
+}
+
+/// Prerelease part of a version, e.g. "rc.1" for "1.2.0-rc.1".
+pub fn prerelease(version: &str) -> Option<&str> {
+    parse_version(version).and_then(|(_, pre)| pre)
+}
+
+/// First number of a version, e.g. 1 for "1.4.0".
+pub fn major_version(version: &str) -> Option<u64> {
+    parse_version(version).map(|(numbers, _)| numbers[0])
+}
+
+/// Order versions numerically; a prerelease sorts before its release, and
+/// anything that isn't a version before all versions.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (parse_version(a), parse_version(b)) {
+        (Some((na, pa)), Some((nb, pb))) => {
+            let len = na.len().max(nb.len());
+            let component = |n: &[u64], i: usize| n.get(i).copied().unwrap_or(0);
+            (0..len)
+                .map(|i| component(&na, i).cmp(&component(&nb, i)))
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| match (pa, pb) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(_), None) => Ordering::Less,
+                    (Some(x), Some(y)) => x.cmp(y),
+                })
+        }
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
@@ -45,8 +45,8 @@ fn rename_to_latest(filename: &str) -> String {
     let version_idx = parts.iter().position(|p| {
         let p = p.strip_prefix('v').unwrap_or(p);
         let mut iter = p.split('.');
-        iter.next().map_or(false, |s| s.parse::<u64>().is_ok())
-            && iter.next().map_or(false, |s| s.parse::<u64>().is_ok())
+        iter.next().is_some_and(|s| s.parse::<u64>().is_ok())
+            && iter.next().is_some_and(|s| s.parse::<u64>().is_ok())
     });
     let suffix_parts = match version_idx {
         Some(idx) => &parts[idx + 1..],
@@ -29,6 +29,11 @@ declare -A ICONS=(
     [DOWNLOAD]="download-simple"
     [CALENDAR]="calendar-blank"
     [NOTE]="note"
+    [ANDROID]="android-logo"
+    [HASH]="hash"
+    [SEAL]="seal-check"
+    [CUBE]="cube"
+    [WHEEL]="steering-wheel"
 )
 
 # ── Doc comments for each constant ───────────────────────────────────────
@@ -45,10 +50,15 @@ declare -A DOCS=(
     [DOWNLOAD]="Download icon"
     [CALENDAR]="Calendar/Date icon"
     [NOTE]="Note/Release notes icon"
+    [ANDROID]="Android/APK icon"
+    [HASH]="Hash/Checksum icon"
+    [SEAL]="Seal/Signature icon"
+    [CUBE]="Cube/Snap and Flatpak icon"
+    [WHEEL]="Wheel/Python wheel icon"
 )
 
 # ── Ordered list (bash associative arrays have no guaranteed order) ───────
-ORDERED=(WINDOWS LINUX APPLE PACKAGE ARCHIVE COFFEE FILE PAPERCLIP STAR DOWNLOAD CALENDAR NOTE)
+ORDERED=(WINDOWS LINUX APPLE PACKAGE ARCHIVE COFFEE FILE PAPERCLIP STAR DOWNLOAD CALENDAR NOTE ANDROID HASH SEAL CUBE WHEEL)
 
 # ── Helper: transform a raw Phosphor SVG into a template with {size} placeholder ─
 transform_svg() {
@@ -117,34 +127,9 @@ HEADER
 
     # ── get_file_icon() function ─────────────────────────────────────────
     cat <<'FUNC'
-/// Returns the appropriate icon SVG based on file extension
-pub fn get_file_icon(filename: &str, size: u32) -> String {
-    if filename.ends_with(".exe") || filename.ends_with(".msi") {
-        WINDOWS(size)
-    } else if filename.ends_with(".deb") || filename.ends_with(".rpm") {
-        LINUX(size)
-    } else if filename.ends_with(".dmg")
-        || filename.contains("darwin")
-        || filename.contains("macos")
-    {
-        APPLE(size)
-    } else if filename.ends_with(".AppImage") {
-        PACKAGE(size)
-    } else if filename.ends_with(".tar.gz")
-        || filename.ends_with(".tgz")
-        || filename.ends_with(".tar.xz")
-        || filename.ends_with(".tar.bz2")
-    {
-        ARCHIVE(size)
-    } else if filename.ends_with(".zip") {
-        ARCHIVE(size)
-    } else if filename.ends_with(".jar") {
-        COFFEE(size)
-    } else if filename.contains("source") || filename.contains("src") {
-        FILE(size)
-    } else {
-        PAPERCLIP(size)
-    }
+/// Returns the icon SVG for an asset's file type (see filetype.rs)
+pub fn get_file_icon(filename: &str, content_type: Option<&str>, size: u32) -> String {
+    crate::filetype::classify(filename, content_type).icon(size)
 }
 FUNC
 } > "$OUTPUT"